@@ -1,5 +1,9 @@
 use std::fmt::{self, Display, Formatter};
+use std::net::SocketAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
 use clap::builder::ValueParser;
@@ -56,6 +60,25 @@ pub enum Command {
     /// Lists all discovered fonts in system and custom font paths
     Fonts(FontsCommand),
 
+    /// Formats Typst source files
+    Fmt(FmtCommand),
+
+    /// Checks an input file for likely mistakes
+    Lint(LintCommand),
+
+    /// Generates an API reference from a package's doc comments
+    Doc(DocCommand),
+
+    /// Runs a package's tests against reference images
+    Test(TestCommand),
+
+    /// Manages local and cached packages
+    #[command(subcommand)]
+    Package(PackageCommand),
+
+    /// Starts a language server that communicates over stdio
+    Lsp(LspCommand),
+
     /// Self update the Typst CLI
     #[cfg_attr(not(feature = "self-update"), doc = " (disabled)")]
     Update(UpdateCommand),
@@ -68,7 +91,8 @@ pub struct CompileCommand {
     #[clap(flatten)]
     pub common: SharedArgs,
 
-    /// Path to output file (PDF, PNG, or SVG), use `-` to write output to stdout
+    /// Path to output file (PDF, PNG, JPEG, WebP, SVG, or HTML), use `-` to
+    /// write output to stdout
     #[clap(required_if_eq("input", "-"), value_parser = ValueParser::new(output_value_parser))]
     pub output: Option<Output>,
 
@@ -85,10 +109,48 @@ pub struct CompileCommand {
     #[arg(long = "open")]
     pub open: Option<Option<String>>,
 
-    /// The PPI (pixels per inch) to use for PNG export
+    /// Which pages to export. When unspecified, all pages are exported.
+    ///
+    /// Pages are separated by commas and are either single page numbers
+    /// (e.g. '2,5' to export pages 2 and 5) or page ranges (e.g. '3-6,8-' to
+    /// export pages 3 to 6 and all pages from page 8 onwards). Page numbers
+    /// are one-indexed physical page numbers and thus not affected by the
+    /// document's page counter.
+    #[arg(long = "pages", value_delimiter = ',')]
+    pub pages: Option<Vec<PageRangeArgument>>,
+
+    /// The PPI (pixels per inch) to use for PNG, JPEG, and WebP export
     #[arg(long = "ppi", default_value_t = 144.0)]
     pub ppi: f32,
 
+    /// The width in pixels to scale each page to for PNG, JPEG, and WebP
+    /// export, instead of using the PPI
+    #[arg(long = "pixel-width", value_name = "PIXELS", conflicts_with = "ppi")]
+    pub pixel_width: Option<NonZeroU32>,
+
+    /// The background to render PNG, JPEG, and WebP images onto, unless the
+    /// page has a fill
+    ///
+    /// Either `transparent` or a color like `white` or `#1e1e2e`. JPEG images
+    /// cannot be transparent.
+    #[arg(long = "background", value_name = "COLOR", default_value = "white")]
+    pub background: BackgroundArgument,
+
+    /// The quality of JPEG and WebP export, from 1 (smallest) to 100 (best)
+    ///
+    /// WebP images are encoded losslessly at a quality of 100.
+    #[arg(
+        long = "quality",
+        default_value_t = 90,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+    )]
+    pub quality: u8,
+
+    /// One (or multiple comma-separated) PDF standards that Typst will enforce
+    /// conformance with
+    #[arg(long = "pdf-standard", value_delimiter = ',')]
+    pub pdf_standard: Vec<PdfStandard>,
+
     /// Produces performance timings of the compilation process (experimental)
     ///
     /// The resulting JSON file can be loaded into a tracing tool such as
@@ -96,6 +158,34 @@ pub struct CompileCommand {
     /// apart from file names and line numbers.
     #[arg(long = "timings", value_name = "OUTPUT_JSON")]
     pub timings: Option<Option<PathBuf>>,
+
+    /// Compiles the input once for each entry of a JSON or CSV manifest, in
+    /// parallel and in one process
+    ///
+    /// A JSON manifest is an array of objects with an `output` path and an
+    /// `inputs` dictionary, typed like the one of `--inputs-file`. A CSV
+    /// manifest has an `output` column and one string column per input. The
+    /// inputs of an entry are added to those given with `--input` and
+    /// `--inputs-file`.
+    #[arg(
+        long = "batch",
+        value_name = "MANIFEST",
+        conflicts_with_all = ["output", "open", "make_deps", "timings", "serve"],
+    )]
+    pub batch: Option<PathBuf>,
+
+    /// Serves a live preview of the document over HTTP (watch mode only)
+    ///
+    /// The preview is updated after each compilation and clicking into it
+    /// reports the corresponding source location. Listens on 127.0.0.1:3000
+    /// if no address is given.
+    #[arg(
+        long = "serve",
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = "127.0.0.1:3000",
+    )]
+    pub serve: Option<SocketAddr>,
 }
 
 /// Initializes a new project from a template
@@ -111,6 +201,22 @@ pub struct InitCommand {
 
     /// The project directory, defaults to the template's name
     pub dir: Option<String>,
+
+    /// Set a parameter of the template
+    ///
+    /// Parameters that are not set are asked for if the terminal is
+    /// interactive and take their default value otherwise.
+    #[clap(
+        long = "set",
+        value_name = "key=value",
+        action = ArgAction::Append,
+        value_parser = ValueParser::new(parse_input_pair),
+    )]
+    pub params: Vec<(String, String)>,
+
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
 }
 
 /// Processes an input file to extract provided metadata
@@ -143,7 +249,7 @@ pub enum SerializationFormat {
     Yaml,
 }
 
-/// Common arguments of compile, watch, and query.
+/// Common arguments of compile, watch, query, and lint.
 #[derive(Debug, Clone, Args)]
 pub struct SharedArgs {
     /// Path to input Typst file, use `-` to read input from stdin
@@ -163,6 +269,14 @@ pub struct SharedArgs {
     )]
     pub inputs: Vec<(String, String)>,
 
+    /// Path to a JSON, YAML, or TOML file with a dictionary of typed values
+    /// visible through `sys.inputs`, use `-` to read JSON or YAML from stdin
+    ///
+    /// Strings in ISO 8601 format are turned into datetimes. Pairs given with
+    /// `--input` take precedence over the file's entries.
+    #[clap(long = "inputs-file", value_name = "FILE", value_parser = input_value_parser)]
+    pub inputs_file: Option<Input>,
+
     /// Adds additional directories to search for fonts
     #[clap(
         long = "font-path",
@@ -172,6 +286,10 @@ pub struct SharedArgs {
     )]
     pub font_paths: Vec<PathBuf>,
 
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+
     /// The document's creation date formatted as a UNIX timestamp.
     ///
     /// For more information, see <https://reproducible-builds.org/specs/source-date-epoch/>.
@@ -190,6 +308,15 @@ pub struct SharedArgs {
         value_parser = clap::value_parser!(DiagnosticFormat)
     )]
     pub diagnostic_format: DiagnosticFormat,
+
+    /// Turns all warnings into errors
+    #[clap(long = "deny-warnings")]
+    pub deny_warnings: bool,
+
+    /// Turns warnings with the given identifiers (like `empty-emph` or the
+    /// name of a lint) into errors
+    #[clap(long = "deny", value_name = "ID", value_delimiter = ',')]
+    pub deny: Vec<String>,
 }
 
 /// Parses a UNIX timestamp according to <https://reproducible-builds.org/specs/source-date-epoch/>
@@ -267,6 +394,90 @@ fn parse_input_pair(raw: &str) -> Result<(String, String), String> {
     Ok((key, val))
 }
 
+/// A background color for raster export, as given on the command line.
+///
+/// Either `transparent`, `white`, `black`, or a hex color.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BackgroundArgument(pub [u8; 4]);
+
+impl FromStr for BackgroundArgument {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "transparent" => return Ok(Self([0; 4])),
+            "white" => return Ok(Self([255; 4])),
+            "black" => return Ok(Self([0, 0, 0, 255])),
+            _ => {}
+        }
+
+        let error = "expected `transparent`, `white`, `black`, or a hex color";
+        let digits = value
+            .strip_prefix('#')
+            .unwrap_or(value)
+            .chars()
+            .map(|c| c.to_digit(16).map(|digit| digit as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(error)?;
+
+        let mut rgba = [255; 4];
+        match digits.len() {
+            3 | 4 => {
+                for (channel, digit) in rgba.iter_mut().zip(&digits) {
+                    *channel = digit * 17;
+                }
+            }
+            6 | 8 => {
+                for (channel, pair) in rgba.iter_mut().zip(digits.chunks(2)) {
+                    *channel = pair[0] * 16 + pair[1];
+                }
+            }
+            _ => return Err(error),
+        }
+
+        Ok(Self(rgba))
+    }
+}
+
+/// A range of pages to export, as given on the command line.
+#[derive(Debug, Clone)]
+pub struct PageRangeArgument(pub RangeInclusive<Option<NonZeroUsize>>);
+
+impl FromStr for PageRangeArgument {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split('-').map(str::trim).collect::<Vec<_>>().as_slice() {
+            [] | [""] => Err("page export range must not be empty"),
+            [page] => {
+                let page = parse_page_number(page)?;
+                Ok(Self(Some(page)..=Some(page)))
+            }
+            ["", ""] => Err("page export range must have a start or an end"),
+            [start, ""] => Ok(Self(Some(parse_page_number(start)?)..=None)),
+            ["", end] => Ok(Self(None..=Some(parse_page_number(end)?))),
+            [start, end] => {
+                let start = parse_page_number(start)?;
+                let end = parse_page_number(end)?;
+                if start > end {
+                    return Err("page export range must not end before its start");
+                }
+                Ok(Self(Some(start)..=Some(end)))
+            }
+            [_, _, _, ..] => Err("page export range must contain at most one hyphen"),
+        }
+    }
+}
+
+/// Parses a one-indexed page number.
+fn parse_page_number(value: &str) -> Result<NonZeroUsize, &'static str> {
+    if value == "0" {
+        Err("page numbers start at one")
+    } else {
+        value.parse().map_err(|_| "not a valid page number")
+    }
+}
+
 /// Lists all discovered fonts in system and custom font paths
 #[derive(Debug, Clone, Parser)]
 pub struct FontsCommand {
@@ -284,11 +495,275 @@ pub struct FontsCommand {
     pub variants: bool,
 }
 
+/// Formats Typst source files
+#[derive(Debug, Clone, Parser)]
+pub struct FmtCommand {
+    /// Paths to files or directories to format, use `-` to read from stdin
+    /// and write to stdout
+    ///
+    /// Directories are searched recursively for `.typ` files.
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Checks that the files are formatted without changing them and fails
+    /// if any are not
+    #[arg(long)]
+    pub check: bool,
+
+    /// The number of spaces per indentation level in code
+    #[arg(long, default_value_t = 2)]
+    pub indent: usize,
+
+    /// The line width up to which code is kept on a single line
+    #[arg(long, default_value_t = 80)]
+    pub max_width: usize,
+}
+
+/// Checks an input file for likely mistakes
+///
+/// Lints can be configured in the `[lint]` table of the `typst.toml` file in
+/// the project root by setting their names to `allow`, `warn`, or `deny`.
+#[derive(Debug, Clone, Parser)]
+pub struct LintCommand {
+    /// Shared arguments
+    #[clap(flatten)]
+    pub common: SharedArgs,
+}
+
+/// Generates an API reference from a package's doc comments
+///
+/// Documents the `let` bindings of the package's entrypoint, using the `///`
+/// comments directly above them. Lines of the form `- name (type): text`
+/// document a parameter and a line of the form `-> type` the return type.
+#[derive(Debug, Clone, Parser)]
+pub struct DocCommand {
+    /// Path to the package directory or to a Typst file to document
+    #[clap(default_value = ".")]
+    pub path: PathBuf,
+
+    /// Path to output file (Markdown or HTML), defaults to stdout
+    #[clap(value_parser = ValueParser::new(output_value_parser))]
+    pub output: Option<Output>,
+
+    /// The format of the output, inferred from the extension by default and
+    /// Markdown otherwise
+    #[arg(long = "format", short = 'f')]
+    pub format: Option<DocFormat>,
+}
+
+/// Runs a package's tests against reference images
+///
+/// Every `.typ` file in the package's `tests` directory is a test. Tests can
+/// import the package under its name and version in any namespace. The pages
+/// of a test are rendered into one PNG and compared with the reference image
+/// at the same relative path in `tests/ref`. Renders that don't match are
+/// written to `tests/out`.
+///
+/// A test that is expected to fail contains annotations like
+/// `// Error: message` or `// Warning: message` for every diagnostic it
+/// emits. An annotation can also require the diagnostic to span a range of
+/// columns in the next line, like `// Error: 2-4 message`.
+#[derive(Debug, Clone, Parser)]
+pub struct TestCommand {
+    /// Only runs the tests whose names contain one of these strings
+    pub filter: Vec<String>,
+
+    /// Path to the package directory
+    #[clap(long = "package", value_name = "DIR", default_value = ".")]
+    pub package: PathBuf,
+
+    /// Writes the renders of failing tests as their new reference images
+    #[clap(long)]
+    pub update: bool,
+
+    /// The resolution to render the tests at, in pixels per inch
+    #[arg(long = "ppi", default_value_t = 144.0)]
+    pub ppi: f32,
+
+    /// By how much a color channel of a pixel may differ from the reference
+    /// image
+    #[arg(long = "max-deviation", default_value_t = 1)]
+    pub max_deviation: u8,
+
+    /// How many pixels may exceed the maximum deviation without failing the
+    /// test
+    #[arg(long = "max-pixels", default_value_t = 0)]
+    pub max_pixels: usize,
+
+    /// Adds additional directories to search for fonts
+    #[clap(
+        long = "font-path",
+        env = "TYPST_FONT_PATHS",
+        value_name = "DIR",
+        value_delimiter = ENV_PATH_SEP,
+    )]
+    pub font_paths: Vec<PathBuf>,
+
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Manages local and cached packages
+#[derive(Debug, Clone, Subcommand)]
+pub enum PackageCommand {
+    /// Lists the local and cached packages
+    List(PackageListCommand),
+
+    /// Removes a package from the cache and unlinks it from the local
+    /// packages
+    Remove(PackageRemoveCommand),
+
+    /// Removes all cached packages
+    Clean(PackageCleanCommand),
+
+    /// Links a package directory into the local packages so that it can be
+    /// imported without copying it
+    Link(PackageLinkCommand),
+}
+
+/// Lists the local and cached packages
+#[derive(Debug, Clone, Parser)]
+pub struct PackageListCommand {
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Removes a package from the cache and unlinks it from the local packages
+#[derive(Debug, Clone, Parser)]
+pub struct PackageRemoveCommand {
+    /// The package to remove, e.g. `@preview/example:0.1.0`
+    ///
+    /// All versions are removed if no version is given.
+    pub spec: String,
+
+    /// Also removes local packages that aren't links
+    ///
+    /// Without this, only cached packages and links to package directories
+    /// are removed.
+    #[clap(long)]
+    pub force: bool,
+
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Removes all cached packages
+#[derive(Debug, Clone, Parser)]
+pub struct PackageCleanCommand {
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Links a package directory into the local packages
+///
+/// The package is linked under its name and version from `typst.toml`, so
+/// that e.g. `@local/example:0.1.0` refers to the working copy.
+#[derive(Debug, Clone, Parser)]
+pub struct PackageLinkCommand {
+    /// The package directory, containing a `typst.toml`
+    #[clap(default_value = ".")]
+    pub dir: PathBuf,
+
+    /// The namespace to link the package into
+    #[clap(long = "namespace", default_value = "local")]
+    pub namespace: String,
+
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Which format to generate an API reference in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+/// Starts a language server that communicates over stdio
+#[derive(Debug, Clone, Parser)]
+pub struct LspCommand {
+    /// The project's main file, defaults to `main.typ` in the workspace root or
+    /// else the first opened document
+    #[clap(long = "main", value_name = "FILE")]
+    pub main: Option<PathBuf>,
+
+    /// Configures the project root (for absolute paths), defaults to the
+    /// workspace root reported by the editor
+    #[clap(long = "root", env = "TYPST_ROOT", value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Add a string key-value pair visible through `sys.inputs`
+    #[clap(
+        long = "input",
+        value_name = "key=value",
+        action = ArgAction::Append,
+        value_parser = ValueParser::new(parse_input_pair),
+    )]
+    pub inputs: Vec<(String, String)>,
+
+    /// Adds additional directories to search for fonts
+    #[clap(
+        long = "font-path",
+        env = "TYPST_FONT_PATHS",
+        value_name = "DIR",
+        value_delimiter = ENV_PATH_SEP,
+    )]
+    pub font_paths: Vec<PathBuf>,
+
+    /// Where packages are looked up
+    #[clap(flatten)]
+    pub packages: PackageStorageArgs,
+}
+
+/// Where packages are looked up and stored.
+#[derive(Debug, Clone, Default, Args)]
+pub struct PackageStorageArgs {
+    /// Adds directories with local packages, laid out as
+    /// `{namespace}/{name}/{version}` and searched before the default data
+    /// directory
+    #[clap(
+        long = "package-path",
+        env = "TYPST_PACKAGE_PATH",
+        value_name = "DIR",
+        value_delimiter = ENV_PATH_SEP,
+    )]
+    pub package_paths: Vec<PathBuf>,
+
+    /// Custom path to the cache for downloaded packages, defaults to a
+    /// system-dependent location
+    #[clap(
+        long = "package-cache-path",
+        env = "TYPST_PACKAGE_CACHE_PATH",
+        value_name = "DIR"
+    )]
+    pub package_cache_path: Option<PathBuf>,
+
+    /// A directory that mirrors the package registry, used instead of the
+    /// network
+    ///
+    /// It contains a directory per namespace with an `index.json` and the
+    /// package archives named `{name}-{version}.tar.gz`.
+    #[clap(long = "package-mirror", env = "TYPST_PACKAGE_MIRROR", value_name = "DIR")]
+    pub package_mirror: Option<PathBuf>,
+
+    /// Fails instead of recording the checksums of packages that aren't in
+    /// the project's `typst.lock` yet
+    #[clap(long = "locked")]
+    pub locked: bool,
+}
+
 /// Which format to use for diagnostics.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
 pub enum DiagnosticFormat {
     Human,
     Short,
+    Json,
+    Sarif,
 }
 
 impl Display for DiagnosticFormat {
@@ -321,7 +796,28 @@ pub struct UpdateCommand {
 pub enum OutputFormat {
     Pdf,
     Png,
+    Jpeg,
+    Webp,
     Svg,
+    Html,
+}
+
+/// A PDF standard that Typst can enforce conformance with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+#[allow(non_camel_case_types)]
+pub enum PdfStandard {
+    /// PDF 1.7.
+    #[value(name = "1.7")]
+    V_1_7,
+    /// PDF/A-2b (archival PDF).
+    #[value(name = "a-2b")]
+    A_2b,
+    /// PDF/A-3b (archival PDF with arbitrary embedded files).
+    #[value(name = "a-3b")]
+    A_3b,
+    /// PDF/UA-1 (tagged, accessible PDF).
+    #[value(name = "ua-1")]
+    Ua_1,
 }
 
 impl Display for OutputFormat {
@@ -332,3 +828,46 @@ impl Display for OutputFormat {
             .fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_background() {
+        let parse = |value: &str| value.parse::<BackgroundArgument>().map(|bg| bg.0);
+        assert_eq!(parse("transparent"), Ok([0, 0, 0, 0]));
+        assert_eq!(parse("white"), Ok([255, 255, 255, 255]));
+        assert_eq!(parse("black"), Ok([0, 0, 0, 255]));
+        assert_eq!(parse("#1e1e2e"), Ok([0x1e, 0x1e, 0x2e, 255]));
+        assert_eq!(parse("1E1E2E80"), Ok([0x1e, 0x1e, 0x2e, 0x80]));
+        assert_eq!(parse("#f80"), Ok([255, 136, 0, 255]));
+        assert_eq!(parse("#f808"), Ok([255, 136, 0, 136]));
+
+        let error = "expected `transparent`, `white`, `black`, or a hex color";
+        for invalid in ["", "#", "red", "#12345", "#1234567", "#ggg", "#1e1e2e "] {
+            assert_eq!(parse(invalid), Err(error), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        let page = |n| NonZeroUsize::new(n);
+        let parse = |value: &str| value.parse::<PageRangeArgument>().map(|range| range.0);
+        assert_eq!(parse("1"), Ok(page(1)..=page(1)));
+        assert_eq!(parse("3-5"), Ok(page(3)..=page(5)));
+        assert_eq!(parse(" 3 - 5 "), Ok(page(3)..=page(5)));
+        assert_eq!(parse("10-"), Ok(page(10)..=None));
+        assert_eq!(parse("-3"), Ok(None..=page(3)));
+        assert_eq!(parse("0"), Err("page numbers start at one"));
+        assert_eq!(parse("0-2"), Err("page numbers start at one"));
+        assert_eq!(parse("5-3"), Err("page export range must not end before its start"));
+        assert_eq!(parse(""), Err("page export range must not be empty"));
+        assert_eq!(parse("-"), Err("page export range must have a start or an end"));
+        assert_eq!(parse("a"), Err("not a valid page number"));
+        assert_eq!(
+            parse("1-2-3"),
+            Err("page export range must contain at most one hyphen")
+        );
+    }
+}
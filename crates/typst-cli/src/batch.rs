@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ecow::eco_format;
+use parking_lot::Mutex;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use typst::diag::{bail, At, FileError, StrResult};
+use typst::eval::Tracer;
+use typst::foundations::{Dict, IntoValue, Value};
+use typst::loading::{json, Readable};
+use typst::syntax::{Span, Spanned};
+use typst::World;
+
+use crate::args::{CompileCommand, DiagnosticFormat, Output};
+use crate::compile::{deny_warnings, export, print_diagnostics};
+use crate::set_failed;
+use crate::world::{with_datetimes, SystemWorld};
+
+/// Execute a batch compilation.
+///
+/// All entries share one world's fonts and files as well as the memoization
+/// caches, so that only the first compilation pays for the font search and
+/// the parsing of the template.
+pub fn batch(command: &CompileCommand) -> StrResult<()> {
+    let Some(manifest) = &command.batch else { return Ok(()) };
+    let entries = read_manifest(manifest)?;
+
+    let world = SystemWorld::new(&command.common)?;
+    world.source(world.main()).map_err(|err| err.to_string())?;
+
+    // Diagnostics are printed by one entry at a time so that they don't
+    // interleave.
+    let print = Mutex::new(());
+    let failed: usize = entries
+        .par_iter()
+        .map(|entry| {
+            let mut world = world.with_inputs(entry.inputs.clone());
+            let mut command = command.clone();
+            command.output = Some(Output::Path(entry.output.clone()));
+
+            let mut tracer = Tracer::new();
+            let result = typst::compile(&world, &mut tracer);
+            let (result, warnings) =
+                deny_warnings(&command.common, result, tracer.warnings());
+            let result = result.and_then(|document| {
+                if let Some(parent) = entry.output.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|err| eco_format!("failed to create directory ({err})"))
+                        .at(Span::detached())?;
+                }
+                export(&mut world, &document, &command, false)
+            });
+
+            let errors = result.err().unwrap_or_default();
+            if !errors.is_empty() || !warnings.is_empty() {
+                let _guard = print.lock();
+                let format = command.common.diagnostic_format;
+                if matches!(format, DiagnosticFormat::Human | DiagnosticFormat::Short) {
+                    eprintln!("{}:", entry.output.display());
+                }
+                print_diagnostics(
+                    &world,
+                    &errors,
+                    &warnings,
+                    command.common.diagnostic_format,
+                )
+                .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
+            }
+
+            Ok(usize::from(!errors.is_empty()))
+        })
+        .sum::<StrResult<usize>>()?;
+
+    if failed > 0 {
+        set_failed();
+        bail!("failed to compile {failed} of {} documents", entries.len());
+    }
+
+    Ok(())
+}
+
+/// An entry of a batch manifest.
+struct Entry {
+    /// Where to write the compiled document.
+    output: PathBuf,
+    /// The entry's additional `sys.inputs`.
+    inputs: Dict,
+}
+
+/// Read a JSON or CSV batch manifest.
+fn read_manifest(path: &Path) -> StrResult<Vec<Entry>> {
+    let data = fs::read(path).map_err(|err| {
+        eco_format!("failed to read batch manifest ({})", FileError::from_io(err, path))
+    })?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => read_json(data),
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => read_csv(&data),
+        _ => bail!("batch manifest must be a JSON or CSV file"),
+    }
+}
+
+/// Read the entries of a JSON manifest. Their inputs are typed like those of
+/// an inputs file.
+fn read_json(data: Vec<u8>) -> StrResult<Vec<Entry>> {
+    let data = Spanned::new(Readable::Bytes(data.into()), Span::detached());
+    let value = json::decode(data).map_err(|errors| {
+        let message = errors.into_iter().next().map(|error| error.message);
+        eco_format!("batch manifest is malformed ({})", message.unwrap_or_default())
+    })?;
+
+    let Value::Array(entries) = value else {
+        bail!("batch manifest must contain an array");
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let Value::Dict(mut entry) = entry else {
+                bail!("batch manifest entries must be dictionaries");
+            };
+            let Ok(Value::Str(output)) = entry.take("output") else {
+                bail!("batch manifest entries must have an `output` path");
+            };
+            let inputs = match entry.take("inputs").map(with_datetimes) {
+                Ok(Value::Dict(inputs)) => inputs,
+                Ok(_) => {
+                    bail!("the `inputs` of a batch manifest entry must be a dictionary")
+                }
+                Err(_) => Dict::new(),
+            };
+            Ok(Entry { output: output.as_str().into(), inputs })
+        })
+        .collect()
+}
+
+/// Read the entries of a CSV manifest.
+fn read_csv(data: &[u8]) -> StrResult<Vec<Entry>> {
+    let mut reader = csv::Reader::from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|err| eco_format!("batch manifest is malformed ({err})"))?
+        .clone();
+    let Some(output) = headers.iter().position(|header| header == "output") else {
+        bail!("batch manifest has no `output` column");
+    };
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record
+                .map_err(|err| eco_format!("batch manifest is malformed ({err})"))?;
+            let inputs = headers
+                .iter()
+                .zip(record.iter())
+                .enumerate()
+                .filter(|&(i, _)| i != output)
+                .map(|(_, (key, value))| (key.into(), value.into_value()))
+                .collect();
+            Ok(Entry { output: record[output].into(), inputs })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::foundations::Datetime;
+
+    use super::*;
+
+    #[test]
+    fn test_read_json() {
+        let entries = read_json(
+            br#"[
+                {"output": "a.pdf", "inputs": {"name": "A", "date": "2024-05-01", "n": 3}},
+                {"output": "b.pdf"}
+            ]"#
+            .to_vec(),
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].output, Path::new("a.pdf"));
+        assert_eq!(entries[0].inputs.get("name").unwrap(), &Value::Str("A".into()));
+        assert_eq!(entries[0].inputs.get("n").unwrap(), &Value::Int(3));
+        assert_eq!(
+            entries[0].inputs.get("date").unwrap(),
+            &Value::Datetime(Datetime::from_ymd(2024, 5, 1).unwrap()),
+        );
+        assert_eq!(entries[1].output, Path::new("b.pdf"));
+        assert!(entries[1].inputs.is_empty());
+    }
+
+    #[test]
+    fn test_read_json_invalid() {
+        let error = |data: &str| read_json(data.as_bytes().to_vec()).err().unwrap();
+        assert!(error("[").starts_with("batch manifest is malformed"));
+        assert_eq!(error("{}"), "batch manifest must contain an array");
+        assert_eq!(error("[1]"), "batch manifest entries must be dictionaries");
+        assert_eq!(error("[{}]"), "batch manifest entries must have an `output` path");
+        assert_eq!(
+            error(r#"[{"output": "a.pdf", "inputs": []}]"#),
+            "the `inputs` of a batch manifest entry must be a dictionary",
+        );
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let entries =
+            read_csv(b"name,output,date\nA,a.pdf,2024-05-01\nB,b.pdf,\n").unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].output, Path::new("a.pdf"));
+        assert_eq!(entries[1].output, Path::new("b.pdf"));
+
+        let keys: Vec<&str> =
+            entries[0].inputs.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["name", "date"]);
+        assert_eq!(entries[0].inputs.get("name").unwrap(), &Value::Str("A".into()));
+        assert_eq!(
+            entries[0].inputs.get("date").unwrap(),
+            &Value::Str("2024-05-01".into()),
+        );
+        assert_eq!(entries[1].inputs.get("date").unwrap(), &Value::Str("".into()));
+    }
+
+    #[test]
+    fn test_read_csv_invalid() {
+        let error = |data: &str| read_csv(data.as_bytes()).err().unwrap();
+        assert_eq!(
+            error("name,path\nA,a.pdf\n"),
+            "batch manifest has no `output` column"
+        );
+        assert!(error("name,output\nA,a.pdf,extra\n")
+            .starts_with("batch manifest is malformed"));
+    }
+}
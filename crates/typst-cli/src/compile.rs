@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
@@ -5,19 +6,28 @@ use std::path::{Path, PathBuf};
 use chrono::{Datelike, Timelike};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::term;
-use ecow::{eco_format, EcoString};
+use ecow::{eco_format, EcoString, EcoVec};
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, ImageEncoder};
 use parking_lot::RwLock;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use typst::diag::{bail, At, Severity, SourceDiagnostic, StrResult};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use serde_json::json;
+use typst::diag::{bail, At, Severity, SourceDiagnostic, SourceResult, StrResult};
 use typst::eval::Tracer;
 use typst::foundations::{Datetime, Smart};
-use typst::layout::Frame;
+use typst::layout::{Abs, Frame, Page, PageRanges};
 use typst::model::Document;
 use typst::syntax::{FileId, Source, Span};
 use typst::visualize::Color;
 use typst::{World, WorldExt};
+use typst_ide::Lint;
+use typst_pdf::{PdfOptions, PdfStandards};
 
-use crate::args::{CompileCommand, DiagnosticFormat, Input, Output, OutputFormat};
+use crate::args::{
+    CompileCommand, DiagnosticFormat, Input, Output, OutputFormat, PdfStandard,
+    SharedArgs,
+};
 use crate::timings::Timer;
 use crate::watch::Status;
 use crate::world::SystemWorld;
@@ -37,7 +47,10 @@ impl CompileCommand {
                 match self.output_format().unwrap_or(OutputFormat::Pdf) {
                     OutputFormat::Pdf => "pdf",
                     OutputFormat::Png => "png",
+                    OutputFormat::Jpeg => "jpg",
+                    OutputFormat::Webp => "webp",
                     OutputFormat::Svg => "svg",
+                    OutputFormat::Html => "html",
                 },
             ))
         })
@@ -53,17 +66,64 @@ impl CompileCommand {
             match output.extension() {
                 Some(ext) if ext.eq_ignore_ascii_case("pdf") => OutputFormat::Pdf,
                 Some(ext) if ext.eq_ignore_ascii_case("png") => OutputFormat::Png,
+                Some(ext)
+                    if ext.eq_ignore_ascii_case("jpg")
+                        || ext.eq_ignore_ascii_case("jpeg") =>
+                {
+                    OutputFormat::Jpeg
+                }
+                Some(ext) if ext.eq_ignore_ascii_case("webp") => OutputFormat::Webp,
                 Some(ext) if ext.eq_ignore_ascii_case("svg") => OutputFormat::Svg,
+                Some(ext)
+                    if ext.eq_ignore_ascii_case("html")
+                        || ext.eq_ignore_ascii_case("htm") =>
+                {
+                    OutputFormat::Html
+                }
                 _ => bail!("could not infer output format for path {}.\nconsider providing the format manually with `--format/-f`", output.display()),
             }
         } else {
             OutputFormat::Pdf
         })
     }
+
+    /// The PDF standards to apply for conformance.
+    pub fn pdf_standards(&self) -> StrResult<PdfStandards> {
+        let list = self
+            .pdf_standard
+            .iter()
+            .map(|standard| match standard {
+                PdfStandard::V_1_7 => typst_pdf::PdfStandard::V_1_7,
+                PdfStandard::A_2b => typst_pdf::PdfStandard::A_2b,
+                PdfStandard::A_3b => typst_pdf::PdfStandard::A_3b,
+                PdfStandard::Ua_1 => typst_pdf::PdfStandard::Ua_1,
+            })
+            .collect::<Vec<_>>();
+        PdfStandards::new(&list)
+    }
+
+    /// The background to render raster images onto.
+    pub fn background(&self) -> Color {
+        let [r, g, b, a] = self.background.0;
+        Color::from_u8(r, g, b, a)
+    }
+
+    /// The pages to export, if restricted.
+    pub fn exported_pages(&self) -> Option<PageRanges> {
+        self.pages.as_ref().map(|ranges| {
+            PageRanges::new(ranges.iter().map(|range| range.0.clone()).collect())
+        })
+    }
 }
 
 /// Execute a compilation command.
 pub fn compile(mut timer: Timer, mut command: CompileCommand) -> StrResult<()> {
+    if command.batch.is_some() {
+        return crate::batch::batch(&command);
+    } else if command.serve.is_some() {
+        bail!("a live preview can only be served in watch mode");
+    }
+
     let mut world =
         SystemWorld::new(&command.common).map_err(|err| eco_format!("{err}"))?;
     timer.record(&mut world, |world| compile_once(world, &mut command, false))??;
@@ -72,13 +132,13 @@ pub fn compile(mut timer: Timer, mut command: CompileCommand) -> StrResult<()> {
 
 /// Compile a single time.
 ///
-/// Returns whether it compiled without errors.
+/// Returns the document if it compiled and exported without errors.
 #[typst_macros::time(name = "compile once")]
 pub fn compile_once(
     world: &mut SystemWorld,
     command: &mut CompileCommand,
     watching: bool,
-) -> StrResult<()> {
+) -> StrResult<Option<Document>> {
     let start = std::time::Instant::now();
     if watching {
         Status::Compiling.print(command).unwrap();
@@ -94,17 +154,19 @@ pub fn compile_once(
         print_diagnostics(world, &errors, &[], command.common.diagnostic_format)
             .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
 
-        return Ok(());
+        return Ok(None);
     }
 
     let mut tracer = Tracer::new();
     let result = typst::compile(world, &mut tracer);
-    let warnings = tracer.warnings();
+    let (result, warnings) = deny_warnings(&command.common, result, tracer.warnings());
+    let result = result.and_then(|document| {
+        export(world, &document, command, watching).map(|()| document)
+    });
 
     match result {
-        // Export the PDF / PNG.
+        // Export the PDF / image.
         Ok(document) => {
-            export(world, &document, command, watching)?;
             let duration = start.elapsed();
 
             if watching {
@@ -125,6 +187,8 @@ pub fn compile_once(
                     open_file(open.as_deref(), &file)?;
                 }
             }
+
+            Ok(Some(document))
         }
 
         // Print diagnostics.
@@ -142,40 +206,75 @@ pub fn compile_once(
                 command.common.diagnostic_format,
             )
             .map_err(|err| eco_format!("failed to print diagnostics ({err})"))?;
+
+            Ok(None)
         }
     }
-
-    Ok(())
 }
 
 /// Export into the target format.
-fn export(
+pub fn export(
     world: &mut SystemWorld,
     document: &Document,
     command: &CompileCommand,
     watching: bool,
-) -> StrResult<()> {
-    match command.output_format()? {
+) -> SourceResult<()> {
+    match command.output_format().at(Span::detached())? {
         OutputFormat::Png => {
             export_image(world, document, command, watching, ImageExportFormat::Png)
+                .at(Span::detached())
+        }
+        OutputFormat::Jpeg => {
+            export_image(world, document, command, watching, ImageExportFormat::Jpeg)
+                .at(Span::detached())
+        }
+        OutputFormat::Webp => {
+            export_image(world, document, command, watching, ImageExportFormat::Webp)
+                .at(Span::detached())
         }
         OutputFormat::Svg => {
             export_image(world, document, command, watching, ImageExportFormat::Svg)
+                .at(Span::detached())
         }
         OutputFormat::Pdf => export_pdf(document, command),
+        OutputFormat::Html => export_html(world, document, command),
     }
 }
 
 /// Export to a PDF.
-fn export_pdf(document: &Document, command: &CompileCommand) -> StrResult<()> {
-    let timestamp = convert_datetime(
-        command.common.creation_timestamp.unwrap_or_else(chrono::Utc::now),
-    );
-    let buffer = typst_pdf::pdf(document, Smart::Auto, timestamp);
+fn export_pdf(document: &Document, command: &CompileCommand) -> SourceResult<()> {
+    let options = PdfOptions {
+        ident: Smart::Auto,
+        timestamp: convert_datetime(
+            command.common.creation_timestamp.unwrap_or_else(chrono::Utc::now),
+        ),
+        standards: command.pdf_standards().at(Span::detached())?,
+        page_ranges: command.exported_pages(),
+    };
+    validate_page_ranges(document, options.page_ranges.as_ref()).at(Span::detached())?;
+    let buffer = typst_pdf::pdf_with_options(document, &options)?;
     command
         .output()
         .write(&buffer)
-        .map_err(|err| eco_format!("failed to write PDF file ({err})"))?;
+        .map_err(|err| eco_format!("failed to write PDF file ({err})"))
+        .at(Span::detached())?;
+    Ok(())
+}
+
+/// Export to an HTML file.
+fn export_html(
+    world: &SystemWorld,
+    document: &Document,
+    command: &CompileCommand,
+) -> SourceResult<()> {
+    // Warnings emitted during the export duplicate those of the compilation,
+    // so they are discarded.
+    let html = typst_html::html(world, document, &mut Tracer::new())?;
+    command
+        .output()
+        .write(html.as_bytes())
+        .map_err(|err| eco_format!("failed to write HTML file ({err})"))
+        .at(Span::detached())?;
     Ok(())
 }
 
@@ -191,10 +290,43 @@ fn convert_datetime(date_time: chrono::DateTime<chrono::Utc>) -> Option<Datetime
     )
 }
 
+/// The pages of the document that are selected for export, together with
+/// their indices.
+///
+/// Fails if the selection does not contain any page of the document.
+fn exported_pages<'a>(
+    document: &'a Document,
+    command: &CompileCommand,
+) -> StrResult<Vec<(usize, &'a Page)>> {
+    let ranges = command.exported_pages();
+    validate_page_ranges(document, ranges.as_ref())?;
+    Ok(document
+        .pages
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| ranges.as_ref().map_or(true, |r| r.includes_page_index(*i)))
+        .collect())
+}
+
+/// Check that the selection of pages contains at least one page of the
+/// document.
+fn validate_page_ranges(
+    document: &Document,
+    ranges: Option<&PageRanges>,
+) -> StrResult<()> {
+    let count = document.pages.len();
+    if !(0..count).any(|i| ranges.map_or(true, |r| r.includes_page_index(i))) {
+        bail!("none of the document's {count} pages are selected for export");
+    }
+    Ok(())
+}
+
 /// An image format to export in.
 #[derive(Clone, Copy)]
 enum ImageExportFormat {
     Png,
+    Jpeg,
+    Webp,
     Svg,
 }
 
@@ -206,13 +338,18 @@ fn export_image(
     watching: bool,
     fmt: ImageExportFormat,
 ) -> StrResult<()> {
+    let pages = exported_pages(document, command)?;
+    if matches!(fmt, ImageExportFormat::Jpeg) && command.background.0[3] < 255 {
+        bail!("JPEG images cannot have a transparent background");
+    }
+
     // Determine whether we have a `{n}` numbering.
     let output = command.output();
     let can_handle_multiple = match output {
         Output::Stdout => false,
         Output::Path(ref output) => output.to_str().unwrap_or_default().contains("{n}"),
     };
-    if !can_handle_multiple && document.pages.len() > 1 {
+    if !can_handle_multiple && pages.len() > 1 {
         let s = match output {
             Output::Stdout => "to stdout",
             Output::Path(_) => "without `{n}` in output path",
@@ -227,12 +364,12 @@ fn export_image(
 
     let cache = world.export_cache();
 
-    // The results are collected in a `Vec<()>` which does not allocate.
-    document
-        .pages
+    // The results are collected in a `Vec<()>` which does not allocate. Pages
+    // keep their physical page number in the output path even if preceding
+    // pages are not exported.
+    pages
         .par_iter()
-        .enumerate()
-        .map(|(i, page)| {
+        .map(|&(i, page)| {
             // Use output with converted path.
             let output = match output {
                 Output::Path(ref path) => {
@@ -274,9 +411,11 @@ fn export_image_page(
     output: &Output,
     fmt: ImageExportFormat,
 ) -> StrResult<()> {
+    let pixel_per_pt = pixel_per_pt(command, frame.width());
+
     match fmt {
         ImageExportFormat::Png => {
-            let pixmap = typst_render::render(frame, command.ppi / 72.0, Color::WHITE);
+            let pixmap = typst_render::render(frame, pixel_per_pt, command.background());
             let buf = pixmap
                 .encode_png()
                 .map_err(|err| eco_format!("failed to encode PNG file ({err})"))?;
@@ -284,6 +423,41 @@ fn export_image_page(
                 .write(&buf)
                 .map_err(|err| eco_format!("failed to write PNG file ({err})"))?;
         }
+        ImageExportFormat::Jpeg => {
+            let pixmap = typst_render::render(frame, pixel_per_pt, command.background());
+            // The background is opaque, so the pixels don't need to be
+            // demultiplied.
+            let rgb: Vec<u8> = pixmap
+                .data()
+                .chunks_exact(4)
+                .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+                .collect();
+            let mut buf = vec![];
+            JpegEncoder::new_with_quality(&mut buf, command.quality)
+                .write_image(&rgb, pixmap.width(), pixmap.height(), ColorType::Rgb8)
+                .map_err(|err| eco_format!("failed to encode JPEG file ({err})"))?;
+            output
+                .write(&buf)
+                .map_err(|err| eco_format!("failed to write JPEG file ({err})"))?;
+        }
+        ImageExportFormat::Webp => {
+            let pixmap = typst_render::render(frame, pixel_per_pt, command.background());
+            let rgba: Vec<u8> = pixmap
+                .pixels()
+                .iter()
+                .flat_map(|pixel| {
+                    let color = pixel.demultiply();
+                    [color.red(), color.green(), color.blue(), color.alpha()]
+                })
+                .collect();
+            // At the highest quality, the image is encoded losslessly.
+            let buf = webp::Encoder::from_rgba(&rgba, pixmap.width(), pixmap.height())
+                .encode_simple(command.quality == 100, command.quality.into())
+                .map_err(|err| eco_format!("failed to encode WebP file ({err:?})"))?;
+            output
+                .write(&buf)
+                .map_err(|err| eco_format!("failed to write WebP file ({err})"))?;
+        }
         ImageExportFormat::Svg => {
             let svg = typst_svg::svg(frame);
             output
@@ -294,8 +468,17 @@ fn export_image_page(
     Ok(())
 }
 
+/// The scale at which a page of the given width is rendered, either to reach
+/// the requested pixel width or the requested PPI.
+fn pixel_per_pt(command: &CompileCommand, width: Abs) -> f32 {
+    match command.pixel_width {
+        Some(pixels) => (pixels.get() as f64 / width.to_pt().max(1.0)) as f32,
+        None => command.ppi / 72.0,
+    }
+}
+
 impl Output {
-    fn write(&self, buffer: &[u8]) -> StrResult<()> {
+    pub fn write(&self, buffer: &[u8]) -> StrResult<()> {
         match self {
             Output::Stdout => std::io::stdout().write_all(buffer),
             Output::Path(path) => fs::write(path, buffer),
@@ -329,7 +512,12 @@ impl ExportCache {
 
         let mut cache = self.cache.upgradable_read();
         if i >= cache.len() {
-            cache.with_upgraded(|cache| cache.push(hash));
+            // Pages may be exported sparsely and out of order, so the cache
+            // grows to fit the index, with unknown hashes in between.
+            cache.with_upgraded(|cache| {
+                cache.resize(i + 1, 0);
+                cache[i] = hash;
+            });
             return false;
         }
 
@@ -428,6 +616,58 @@ fn open_file(open: Option<&str>, path: &Path) -> StrResult<()> {
     Ok(())
 }
 
+/// The identifiers of the warnings emitted during compilation.
+const WARNINGS: &[&str] =
+    &["empty-strong", "empty-emph", "unnecessary-rename", "layout-convergence"];
+
+/// Whether a warning with the given identifier exists and can thus be denied.
+///
+/// Besides the compiler's warnings, these are the findings of the linter.
+pub fn is_warning(id: &str) -> bool {
+    WARNINGS.contains(&id) || id.parse::<Lint>().is_ok()
+}
+
+/// The identifiers of all warnings that can be denied.
+pub fn warnings() -> impl Iterator<Item = &'static str> {
+    WARNINGS.iter().copied().chain(Lint::ALL.into_iter().map(Lint::name))
+}
+
+/// Turn the warnings that are denied on the command line into errors, which
+/// fail the compilation.
+///
+/// Returns the new result and the remaining warnings.
+pub fn deny_warnings<T>(
+    args: &SharedArgs,
+    result: SourceResult<T>,
+    warnings: EcoVec<SourceDiagnostic>,
+) -> (SourceResult<T>, EcoVec<SourceDiagnostic>) {
+    let mut kept = EcoVec::new();
+    let mut denied = EcoVec::new();
+    for mut warning in warnings {
+        let listed = warning
+            .id
+            .as_ref()
+            .is_some_and(|id| args.deny.iter().any(|denied| denied == id));
+        if args.deny_warnings || listed {
+            warning.severity = Severity::Error;
+            denied.push(warning);
+        } else {
+            kept.push(warning);
+        }
+    }
+
+    let result = match result {
+        Ok(_) if !denied.is_empty() => Err(denied),
+        Ok(output) => Ok(output),
+        Err(mut errors) => {
+            errors.extend(denied);
+            Err(errors)
+        }
+    };
+
+    (result, kept)
+}
+
 /// Print diagnostic messages to the terminal.
 pub fn print_diagnostics(
     world: &SystemWorld,
@@ -436,12 +676,24 @@ pub fn print_diagnostics(
     diagnostic_format: DiagnosticFormat,
 ) -> Result<(), codespan_reporting::files::Error> {
     let mut config = term::Config { tab_width: 2, ..Default::default() };
-    if diagnostic_format == DiagnosticFormat::Short {
-        config.display_style = term::DisplayStyle::Short;
+    match diagnostic_format {
+        DiagnosticFormat::Human => {}
+        DiagnosticFormat::Short => config.display_style = term::DisplayStyle::Short,
+        DiagnosticFormat::Json => {
+            let diagnostics: Vec<_> = warnings
+                .iter()
+                .chain(errors)
+                .map(|diagnostic| JsonDiagnostic::new(world, diagnostic))
+                .collect();
+            return print_json(&diagnostics);
+        }
+        DiagnosticFormat::Sarif => {
+            return print_json(&sarif(world, warnings.iter().chain(errors)));
+        }
     }
 
     for diagnostic in warnings.iter().chain(errors) {
-        let diag = match diagnostic.severity {
+        let mut diag = match diagnostic.severity {
             Severity::Error => Diagnostic::error(),
             Severity::Warning => Diagnostic::warning(),
         }
@@ -455,6 +707,10 @@ pub fn print_diagnostics(
         )
         .with_labels(label(world, diagnostic.span).into_iter().collect());
 
+        if let Some(id) = &diagnostic.id {
+            diag = diag.with_code(id.as_str());
+        }
+
         term::emit(&mut terminal::out(), &config, world, &diag)?;
 
         // Stacktrace-like helper diagnostics.
@@ -476,6 +732,224 @@ fn label(world: &SystemWorld, span: Span) -> Option<Label<FileId>> {
     Some(Label::primary(span.id()?, world.range(span)?))
 }
 
+/// Print a value as a single line of JSON.
+fn print_json<T: Serialize>(value: &T) -> Result<(), CodespanError> {
+    let mut out = terminal::out();
+    serde_json::to_writer(&mut out, value).map_err(io::Error::from)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// A diagnostic in the JSON diagnostic format.
+#[derive(Serialize)]
+struct JsonDiagnostic {
+    severity: &'static str,
+    /// The identifier with which a warning can be allowed or denied.
+    id: Option<EcoString>,
+    message: EcoString,
+    hints: EcoVec<EcoString>,
+    /// Absent if the diagnostic isn't tied to a file.
+    location: Option<JsonLocation>,
+    trace: Vec<JsonTracepoint>,
+}
+
+impl JsonDiagnostic {
+    fn new(world: &SystemWorld, diagnostic: &SourceDiagnostic) -> Self {
+        Self {
+            severity: severity(diagnostic.severity),
+            id: diagnostic.id.clone(),
+            message: diagnostic.message.clone(),
+            hints: diagnostic.hints.clone(),
+            location: JsonLocation::new(world, diagnostic.span),
+            trace: diagnostic
+                .trace
+                .iter()
+                .map(|point| JsonTracepoint {
+                    message: point.v.to_string(),
+                    location: JsonLocation::new(world, point.span),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A step in the trace of a diagnostic.
+#[derive(Serialize)]
+struct JsonTracepoint {
+    message: String,
+    location: Option<JsonLocation>,
+}
+
+/// A range in a file. Lines and columns start at one and columns count
+/// Unicode code points, like in the human-readable format.
+#[derive(Serialize)]
+struct JsonLocation {
+    file: String,
+    range: std::ops::Range<usize>,
+    start: JsonPosition,
+    end: JsonPosition,
+}
+
+impl JsonLocation {
+    fn new(world: &SystemWorld, span: Span) -> Option<Self> {
+        let id = span.id()?;
+        let range = world.range(span)?;
+        let source = world.lookup(id);
+        let position = |offset| {
+            Some(JsonPosition {
+                line: source.byte_to_line(offset)? + 1,
+                column: source.byte_to_column(offset)? + 1,
+            })
+        };
+
+        Some(Self {
+            file: codespan_reporting::files::Files::name(world, id).ok()?,
+            start: position(range.start)?,
+            end: position(range.end)?,
+            range,
+        })
+    }
+}
+
+/// A position in a file.
+#[derive(Serialize)]
+struct JsonPosition {
+    line: usize,
+    column: usize,
+}
+
+/// The name of a severity in the structured formats.
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+/// Build a SARIF 2.1.0 log of the diagnostics, with traces as related
+/// locations.
+///
+/// Files of the project are referenced relative to the working directory.
+/// Files of packages are referenced relative to a base named after their
+/// package, like `@preview/example:0.1.0`, which is described in the log.
+fn sarif<'a>(
+    world: &SystemWorld,
+    diagnostics: impl Iterator<Item = &'a SourceDiagnostic>,
+) -> serde_json::Value {
+    let mut packages = BTreeSet::new();
+    let mut location = |span: Span| {
+        let location = JsonLocation::new(world, span)?;
+        let id = span.id()?;
+        let artifact = match id.package() {
+            Some(package) => {
+                let base = package.to_string();
+                let path = id.vpath().as_rootless_path().to_string_lossy();
+                let artifact = json!({ "uri": uri(&path), "uriBaseId": base });
+                packages.insert(base);
+                artifact
+            }
+            None => json!({ "uri": uri(&location.file) }),
+        };
+        Some(json!({
+            "physicalLocation": {
+                "artifactLocation": artifact,
+                "region": {
+                    "startLine": location.start.line,
+                    "startColumn": location.start.column,
+                    "endLine": location.end.line,
+                    "endColumn": location.end.column,
+                    "byteOffset": location.range.start,
+                    "byteLength": location.range.len(),
+                },
+            },
+        }))
+    };
+
+    let results: Vec<_> = diagnostics
+        .map(|diagnostic| {
+            let mut text = diagnostic.message.to_string();
+            for hint in &diagnostic.hints {
+                text.push_str("\nhint: ");
+                text.push_str(hint);
+            }
+
+            let related: Vec<_> = diagnostic
+                .trace
+                .iter()
+                .enumerate()
+                .filter_map(|(i, point)| {
+                    let mut related = location(point.span)?;
+                    related["id"] = json!(i);
+                    related["message"] = json!({ "text": point.v.to_string() });
+                    Some(related)
+                })
+                .collect();
+
+            let mut result = json!({
+                "level": severity(diagnostic.severity),
+                "message": { "text": text },
+                "locations": location(diagnostic.span).into_iter().collect::<Vec<_>>(),
+                "relatedLocations": related,
+                "properties": { "hints": diagnostic.hints },
+            });
+            if let Some(id) = &diagnostic.id {
+                result["ruleId"] = json!(id);
+            }
+            result
+        })
+        .collect();
+
+    let mut run = json!({
+        "tool": {
+            "driver": {
+                "name": "typst",
+                "version": crate::typst_version(),
+                "informationUri": "https://typst.app",
+            },
+        },
+        "columnKind": "unicodeCodePoints",
+        "results": results,
+    });
+
+    if !packages.is_empty() {
+        let bases: serde_json::Map<_, _> = packages
+            .into_iter()
+            .map(|package| {
+                let text = format!("The root directory of the package {package}.");
+                let base = json!({ "description": { "text": text } });
+                (package, base)
+            })
+            .collect();
+        run["originalUriBaseIds"] = bases.into();
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    })
+}
+
+/// Turn a relative file path into a relative URI reference by using forward
+/// slashes and percent-encoding all characters that aren't unreserved.
+fn uri(path: &str) -> String {
+    let mut uri = String::new();
+    for byte in path.replace('\\', "/").bytes() {
+        match byte {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~'
+            | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
 impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
     type FileId = FileId;
     type Name = String;
@@ -539,3 +1013,271 @@ impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use ecow::eco_vec;
+    use typst::diag::{error, warning};
+    use typst::layout::Size;
+
+    use super::*;
+
+    /// Parse the arguments of a compile command.
+    fn command(args: &[&str]) -> CompileCommand {
+        CompileCommand::try_parse_from(["compile", "main.typ"].iter().chain(args))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pixel_per_pt() {
+        let a4 = Abs::mm(210.0);
+        assert_eq!(pixel_per_pt(&command(&[]), a4), 2.0);
+        assert_eq!(pixel_per_pt(&command(&["--ppi", "300"]), a4), 300.0 / 72.0);
+
+        // A page rendered at the pixel width comes out that many pixels wide.
+        let scale = pixel_per_pt(&command(&["--pixel-width", "1200"]), a4);
+        assert_eq!((a4.to_pt() as f32 * scale).round(), 1200.0);
+
+        // The pixel width conflicts with the PPI.
+        let args = ["compile", "main.typ", "--ppi", "300", "--pixel-width", "1200"];
+        assert!(CompileCommand::try_parse_from(args).is_err());
+    }
+
+    /// The severities and messages of diagnostics.
+    fn messages(diags: &[SourceDiagnostic]) -> Vec<(Severity, &str)> {
+        diags
+            .iter()
+            .map(|diag| (diag.severity, diag.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let emph = || {
+            warning!(Span::detached(), "no text within underscores").with_id("empty-emph")
+        };
+        let layout = || warning!(Span::detached(), "layout did not converge");
+
+        // Without denied warnings, nothing changes.
+        let (result, kept) =
+            deny_warnings(&command(&[]).common, Ok(()), eco_vec![emph(), layout()]);
+        assert!(result.is_ok());
+        assert_eq!(kept.len(), 2);
+
+        // A denied warning turns a successful result into an error.
+        let args = command(&["--deny", "empty-emph,unused-binding"]).common;
+        let (result, kept) = deny_warnings(&args, Ok(()), eco_vec![emph(), layout()]);
+        assert_eq!(
+            messages(&result.unwrap_err()),
+            [(Severity::Error, "no text within underscores")]
+        );
+        assert_eq!(messages(&kept), [(Severity::Warning, "layout did not converge")]);
+
+        // Denied warnings are added to existing errors.
+        let error = error!(Span::detached(), "unknown variable: x");
+        let (result, kept) =
+            deny_warnings(&args, Err::<(), _>(eco_vec![error]), eco_vec![emph()]);
+        assert_eq!(
+            messages(&result.unwrap_err()),
+            [
+                (Severity::Error, "unknown variable: x"),
+                (Severity::Error, "no text within underscores"),
+            ]
+        );
+        assert!(kept.is_empty());
+
+        // All warnings can be denied at once.
+        let args = command(&["--deny-warnings"]).common;
+        let (result, kept) = deny_warnings(&args, Ok(()), eco_vec![emph(), layout()]);
+        assert_eq!(result.unwrap_err().len(), 2);
+        assert!(kept.is_empty());
+    }
+
+    #[test]
+    fn test_validate_page_ranges() {
+        let mut document = Document::default();
+        for number in 1..=5 {
+            document.pages.push(Page {
+                frame: Frame::soft(Size::zero()),
+                numbering: None,
+                number,
+            });
+        }
+
+        let ranges = |args: &[&str]| command(args).exported_pages();
+        assert!(validate_page_ranges(&document, None).is_ok());
+        assert!(
+            validate_page_ranges(&document, ranges(&["--pages", "5-"]).as_ref()).is_ok()
+        );
+        assert_eq!(
+            validate_page_ranges(&document, ranges(&["--pages", "6-,10"]).as_ref()),
+            Err("none of the document's 5 pages are selected for export".into())
+        );
+
+        let pages =
+            exported_pages(&document, &command(&["--pages", "1,3-4,5-9"])).unwrap();
+        let numbers: Vec<_> = pages.iter().map(|(i, page)| (*i, page.number)).collect();
+        assert_eq!(numbers, [(0, 1), (2, 3), (3, 4), (4, 5)]);
+    }
+
+    #[test]
+    fn test_export_pdf_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("main.typ");
+        let output = dir.path().join("main.pdf");
+        fs::write(
+            &input,
+            "#set page(height: 80pt)\n\
+             = One <one>\n#link(<two>)[Two] #link(<three>)[Three]\n\
+             #pagebreak()\n= Two <two>\n#pagebreak()\n= Three <three>\n",
+        )
+        .unwrap();
+
+        let mut args = vec![input.to_str().unwrap(), output.to_str().unwrap()];
+        args.extend(["--pages", "1-2"]);
+        let command =
+            CompileCommand::try_parse_from(["compile"].iter().chain(&args)).unwrap();
+        let world = SystemWorld::new(&command.common).unwrap();
+        let document = typst::compile(&world, &mut Tracer::new()).unwrap();
+        assert_eq!(document.pages.len(), 3);
+        export_pdf(&document, &command).unwrap();
+
+        // Named destinations, links, and outline entries that lead to the
+        // excluded third page are dropped.
+        let pdf = String::from_utf8_lossy(&fs::read(&output).unwrap()).into_owned();
+        let count = |pattern: &str| pdf.matches(pattern).count();
+        assert_eq!(count("/Type /Page\n"), 2);
+        assert_eq!(count("/Names [(one) "), 1);
+        assert!(pdf.contains("(two) "));
+        assert!(!pdf.contains("(three)"));
+        assert_eq!(count("/S /GoTo"), 1);
+        assert_eq!(count("/Subtype /Link"), 2);
+        assert!(pdf.contains("/Title (One)"));
+        assert!(pdf.contains("/Title (Two)"));
+        assert!(!pdf.contains("/Title (Three)"));
+    }
+
+    #[test]
+    fn test_structured_diagnostics() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = dir.path().join("local/preview/pkg/0.1.0");
+        fs::create_dir_all(&package).unwrap();
+        fs::write(
+            package.join("typst.toml"),
+            "[package]\nname = \"pkg\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+        )
+        .unwrap();
+        fs::write(package.join("lib.typ"), "#let f(x) = x + 1\n").unwrap();
+
+        let input = dir.path().join("my file.typ");
+        fs::write(&input, "#import \"@preview/pkg:0.1.0\": f\n#f(\"a\")\n").unwrap();
+
+        let local = dir.path().join("local");
+        let args = [input.to_str().unwrap(), "--package-path", local.to_str().unwrap()];
+        let command =
+            CompileCommand::try_parse_from(["compile"].iter().chain(&args)).unwrap();
+        let world = SystemWorld::new(&command.common).unwrap();
+        let mut errors = typst::compile(&world, &mut Tracer::new()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        errors.make_mut()[0]
+            .hints
+            .push("convert the string with `int(..)`".into());
+
+        let main = world.main();
+        let name = codespan_reporting::files::Files::name(&world, main).unwrap();
+        let json = serde_json::to_value(JsonDiagnostic::new(&world, &errors[0])).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "severity": "error",
+                "id": null,
+                "message": "cannot add string and integer",
+                "hints": ["convert the string with `int(..)`"],
+                "location": {
+                    "file": "@preview/pkg:0.1.0/lib.typ",
+                    "range": { "start": 12, "end": 17 },
+                    "start": { "line": 1, "column": 13 },
+                    "end": { "line": 1, "column": 18 },
+                },
+                "trace": [{
+                    "message": "error occurred in this call of function `f`",
+                    "location": {
+                        "file": name,
+                        "range": { "start": 33, "end": 39 },
+                        "start": { "line": 2, "column": 2 },
+                        "end": { "line": 2, "column": 8 },
+                    },
+                }],
+            })
+        );
+
+        let log = sarif(&world, errors.iter());
+        let region = |line, start, end, offset| {
+            json!({
+                "startLine": line,
+                "startColumn": start,
+                "endLine": line,
+                "endColumn": end,
+                "byteOffset": offset,
+                "byteLength": end - start,
+            })
+        };
+        assert_eq!(
+            log["runs"][0]["results"],
+            json!([{
+                "level": "error",
+                "message": {
+                    "text": "cannot add string and integer\n\
+                             hint: convert the string with `int(..)`",
+                },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": "lib.typ",
+                            "uriBaseId": "@preview/pkg:0.1.0",
+                        },
+                        "region": region(1, 13, 18, 12),
+                    },
+                }],
+                "relatedLocations": [{
+                    "id": 0,
+                    "message": { "text": "error occurred in this call of function `f`" },
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": uri(&name) },
+                        "region": region(2, 2, 8, 33),
+                    },
+                }],
+                "properties": { "hints": ["convert the string with `int(..)`"] },
+            }])
+        );
+        assert!(uri(&name).ends_with("my%20file.typ"));
+        assert_eq!(
+            log["runs"][0]["originalUriBaseIds"],
+            json!({
+                "@preview/pkg:0.1.0": {
+                    "description": {
+                        "text": "The root directory of the package @preview/pkg:0.1.0.",
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_uri() {
+        assert_eq!(uri("main.typ"), "main.typ");
+        assert_eq!(uri("../chapters/a b.typ"), "../chapters/a%20b.typ");
+        assert_eq!(uri("src\\100%#?.typ"), "src/100%25%23%3F.typ");
+        assert_eq!(uri("ü.typ"), "%C3%BC.typ");
+    }
+
+    #[test]
+    fn test_is_warning() {
+        assert!(is_warning("empty-emph"));
+        assert!(is_warning("layout-convergence"));
+        assert!(is_warning("unused-binding"));
+        assert!(!is_warning("empty-emphasis"));
+        assert!(warnings().any(|id| id == "duplicate-label"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use typst::diag::{bail, StrResult};
+use typst::World;
+use typst_html::escape;
+use typst_ide::{DocItem, DocItemKind, DocParam};
+
+use crate::args::{
+    DiagnosticFormat, DocCommand, DocFormat, Input, Output, PackageStorageArgs,
+    SharedArgs,
+};
+use crate::world::SystemWorld;
+
+/// Execute a documentation command.
+pub fn doc(command: &DocCommand) -> StrResult<()> {
+    let (root, entrypoint, title) = locate(&command.path)?;
+    let args = SharedArgs {
+        input: Input::Path(entrypoint),
+        root: Some(root),
+        inputs: vec![],
+        inputs_file: None,
+        font_paths: vec![],
+        packages: PackageStorageArgs::default(),
+        creation_timestamp: None,
+        deny_warnings: false,
+        deny: vec![],
+        diagnostic_format: DiagnosticFormat::Human,
+    };
+
+    let world = SystemWorld::new(&args)?;
+    let source = world.source(world.main()).map_err(|err| err.to_string())?;
+    let items = typst_ide::package_docs(&world, &source);
+
+    let output = command.output.clone().unwrap_or(Output::Stdout);
+    let format = command.format.unwrap_or_else(|| match &output {
+        Output::Path(path)
+            if path.extension().is_some_and(|ext| ext == "html" || ext == "htm") =>
+        {
+            DocFormat::Html
+        }
+        _ => DocFormat::Markdown,
+    });
+
+    let rendered = match format {
+        DocFormat::Markdown => markdown(&title, &items),
+        DocFormat::Html => html(&title, &items),
+    };
+
+    output
+        .write(rendered.as_bytes())
+        .map_err(|err| eco_format!("failed to write API reference ({err})"))
+}
+
+/// Determine the root directory, the entrypoint, and the title of the
+/// documentation for a package directory or a single file.
+fn locate(path: &Path) -> StrResult<(PathBuf, PathBuf, EcoString)> {
+    if path.is_dir() {
+        if !path.join("typst.toml").exists() {
+            bail!("{} does not contain a typst.toml", path.display());
+        }
+
+        let manifest = crate::package::parse_manifest(path)?;
+        let entrypoint = path.join(manifest.package.entrypoint.as_str());
+        let title = eco_format!("{} {}", manifest.package.name, manifest.package.version);
+        return Ok((path.to_path_buf(), entrypoint, title));
+    }
+
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+
+    let root = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into())
+        .unwrap_or_default();
+    Ok((root.to_path_buf(), path.to_path_buf(), title))
+}
+
+/// Render the API reference as Markdown.
+fn markdown(title: &str, items: &[DocItem]) -> String {
+    let mut out = String::new();
+    writeln!(out, "# {title}").unwrap();
+
+    for item in items {
+        writeln!(out, "\n## `{}`\n", item.name).unwrap();
+        writeln!(out, "```typc\n{}\n```", signature(item)).unwrap();
+        if !item.docs.is_empty() {
+            writeln!(out, "\n{}", item.docs).unwrap();
+        }
+
+        if !item.params.is_empty() {
+            writeln!(out, "\n**Parameters**\n").unwrap();
+            for param in &item.params {
+                write!(out, "- `{}`", param_name(param)).unwrap();
+                let details = param_details(param);
+                if !details.is_empty() {
+                    write!(out, " ({details})").unwrap();
+                }
+                if !param.docs.is_empty() {
+                    write!(out, ": {}", param.docs).unwrap();
+                }
+                out.push('\n');
+            }
+        }
+    }
+
+    out
+}
+
+/// Render the API reference as a standalone HTML page.
+fn html(title: &str, items: &[DocItem]) -> String {
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>").unwrap();
+    writeln!(out, "<html>\n<head>").unwrap();
+    writeln!(out, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(out, "<title>{}</title>", escape(title)).unwrap();
+    writeln!(out, "<style>{STYLE}</style>").unwrap();
+    writeln!(out, "</head>\n<body>").unwrap();
+    writeln!(out, "<h1>{}</h1>", escape(title)).unwrap();
+
+    for item in items {
+        writeln!(out, "<section id=\"{}\">", escape(&item.name)).unwrap();
+        writeln!(out, "<h2><code>{}</code></h2>", escape(&item.name)).unwrap();
+        writeln!(out, "<pre><code>{}</code></pre>", escape(&signature(item))).unwrap();
+        for paragraph in item.docs.split("\n\n").filter(|p| !p.trim().is_empty()) {
+            writeln!(out, "<p>{}</p>", inline(paragraph.trim())).unwrap();
+        }
+
+        if !item.params.is_empty() {
+            writeln!(out, "<h3>Parameters</h3>\n<dl>").unwrap();
+            for param in &item.params {
+                write!(out, "<dt><code>{}</code>", escape(&param_name(param))).unwrap();
+                let details = param_details(param);
+                if !details.is_empty() {
+                    write!(out, " <span>({})</span>", inline(&details)).unwrap();
+                }
+                writeln!(out, "</dt>\n<dd>{}</dd>", inline(&param.docs)).unwrap();
+            }
+            writeln!(out, "</dl>").unwrap();
+        }
+
+        writeln!(out, "</section>").unwrap();
+    }
+
+    writeln!(out, "</body>\n</html>").unwrap();
+    out
+}
+
+/// The styles of the HTML API reference.
+const STYLE: &str = "body { max-width: 48rem; margin: 2rem auto; padding: 0 1rem; \
+                     font-family: sans-serif; line-height: 1.5 } \
+                     pre { background: #f4f4f4; padding: 0.5rem 1rem; overflow-x: auto } \
+                     section { border-top: 1px solid #ddd; margin-top: 2rem } \
+                     dt span { color: #666 } dd { margin-bottom: 0.5rem }";
+
+/// How an item is used, e.g. `boxed(body, fill: red) -> content`.
+fn signature(item: &DocItem) -> String {
+    let mut out = item.name.to_string();
+    match item.kind {
+        DocItemKind::Function => {
+            let params: Vec<_> = item
+                .params
+                .iter()
+                .map(|param| match &param.default {
+                    Some(default) => format!("{}: {default}", param.name),
+                    None => param_name(param),
+                })
+                .collect();
+            write!(out, "({})", params.join(", ")).unwrap();
+        }
+        DocItemKind::Variable => {
+            if let Some(value) = &item.value {
+                write!(out, " = {value}").unwrap();
+            }
+        }
+    }
+
+    if let Some(returns) = &item.returns {
+        write!(out, " -> {returns}").unwrap();
+    }
+
+    out
+}
+
+/// The name of a parameter as it is written in the signature.
+fn param_name(param: &DocParam) -> String {
+    if param.variadic {
+        format!("..{}", param.name)
+    } else {
+        param.name.to_string()
+    }
+}
+
+/// The types and default value of a parameter.
+fn param_details(param: &DocParam) -> String {
+    let mut details = vec![];
+    if let Some(types) = &param.types {
+        details.push(types.to_string());
+    }
+    if let Some(default) = &param.default {
+        details.push(format!("default `{default}`"));
+    }
+    details.join(", ")
+}
+
+/// Escape text and turn backtick spans into code elements.
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 1 {
+                format!("<code>{}</code>", escape(part))
+            } else {
+                escape(part)
+            }
+        })
+        .collect()
+}
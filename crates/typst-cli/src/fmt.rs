@@ -0,0 +1,96 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use ecow::eco_format;
+use typst::diag::{bail, StrResult};
+use typst::syntax::Source;
+use typst_ide::FormatConfig;
+
+use crate::args::FmtCommand;
+use crate::{print_error, set_failed};
+
+/// Execute a formatting command.
+pub fn fmt(command: &FmtCommand) -> StrResult<()> {
+    let config = FormatConfig {
+        indent: command.indent,
+        max_width: command.max_width,
+    };
+
+    let mut unformatted = 0;
+    for path in collect(&command.paths)? {
+        let text = read(&path)?;
+        let formatted = match typst_ide::format(&Source::detached(text.clone()), &config)
+        {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                set_failed();
+                print_error(&format!("failed to format {} ({err})", path.display()))
+                    .map_err(|err| eco_format!("failed to print error ({err})"))?;
+                continue;
+            }
+        };
+
+        if command.check {
+            if formatted != text {
+                eprintln!("{} is not formatted", path.display());
+                unformatted += 1;
+            }
+        } else if path == Path::new("-") {
+            io::stdout()
+                .write_all(formatted.as_bytes())
+                .map_err(|err| eco_format!("failed to write to stdout ({err})"))?;
+        } else if formatted != text {
+            fs::write(&path, formatted).map_err(|err| {
+                eco_format!("failed to write {} ({err})", path.display())
+            })?;
+        }
+    }
+
+    if unformatted > 0 {
+        bail!(
+            "{unformatted} file{} would be reformatted",
+            if unformatted == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Expand directories into the Typst files they contain.
+fn collect(paths: &[PathBuf]) -> StrResult<Vec<PathBuf>> {
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+
+        for entry in walkdir::WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(|err| {
+                eco_format!("failed to read {} ({err})", path.display())
+            })?;
+            let path = entry.path();
+            if entry.file_type().is_file()
+                && path.extension().is_some_and(|ext| ext == "typ")
+            {
+                files.push(path.to_path_buf());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Read a file or, for `-`, stdin.
+fn read(path: &Path) -> StrResult<String> {
+    let mut text = String::new();
+    if path == Path::new("-") {
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|err| eco_format!("failed to read from stdin ({err})"))?;
+    } else {
+        text = fs::read_to_string(path)
+            .map_err(|err| eco_format!("failed to read {} ({err})", path.display()))?;
+    }
+    Ok(text)
+}
@@ -1,18 +1,24 @@
-use std::io::Write;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
 use std::path::Path;
 
 use codespan_reporting::term::termcolor::{Color, ColorSpec, WriteColor};
-use ecow::eco_format;
+use ecow::{eco_format, EcoString};
 use fs_extra::dir::CopyOptions;
-use typst::diag::{bail, FileError, StrResult};
+use typst::diag::{bail, StrResult};
 use typst::syntax::package::{
-    PackageManifest, PackageSpec, TemplateInfo, VersionlessPackageSpec,
+    PackageSpec, TemplateInfo, TemplateParam, VersionlessPackageSpec,
 };
+use typst::syntax::{LinkedNode, SyntaxKind};
 
 use crate::args::InitCommand;
+use crate::package::{parse_manifest, PackageStorage};
 
 /// Execute an initialization command.
 pub fn init(command: &InitCommand) -> StrResult<()> {
+    let storage = PackageStorage::new(&command.packages);
+
     // Parse the package specification. If the user didn't specify the version,
     // we try to figure it out automatically by downloading the package index
     // or searching the disk.
@@ -20,12 +26,12 @@ pub fn init(command: &InitCommand) -> StrResult<()> {
         // Try to parse without version, but prefer the error message of the
         // normal package spec parsing if it fails.
         let spec: VersionlessPackageSpec = command.template.parse().map_err(|_| err)?;
-        let version = crate::package::determine_latest_version(&spec)?;
+        let version = storage.determine_latest_version(&spec)?;
         StrResult::Ok(spec.at(version))
     })?;
 
     // Find or download the package.
-    let package_path = crate::package::prepare_package(&spec)?;
+    let package_path = storage.prepare_package(&spec)?;
 
     // Parse the manifest.
     let manifest = parse_manifest(&package_path)?;
@@ -38,9 +44,16 @@ pub fn init(command: &InitCommand) -> StrResult<()> {
 
     // Determine the directory at which we will create the project.
     let project_dir = Path::new(command.dir.as_deref().unwrap_or(&manifest.package.name));
+    if project_dir.exists() {
+        bail!("project directory already exists (at {})", project_dir.display());
+    }
+
+    // Determine the values of the template's parameters.
+    let values = determine_params(template, &command.params)?;
 
     // Set up the project.
     scaffold_project(project_dir, &package_path, template)?;
+    substitute_params(project_dir, template, &values)?;
 
     // Print the summary.
     print_summary(spec, project_dir, template).unwrap();
@@ -48,20 +61,6 @@ pub fn init(command: &InitCommand) -> StrResult<()> {
     Ok(())
 }
 
-/// Parses the manifest of the package located at `package_path`.
-fn parse_manifest(package_path: &Path) -> StrResult<PackageManifest> {
-    let toml_path = package_path.join("typst.toml");
-    let string = std::fs::read_to_string(&toml_path).map_err(|err| {
-        eco_format!(
-            "failed to read package manifest ({})",
-            FileError::from_io(err, &toml_path)
-        )
-    })?;
-
-    toml::from_str(&string)
-        .map_err(|err| eco_format!("package manifest is malformed ({})", err.message()))
-}
-
 /// Creates the project directory with the template's contents and returns the
 /// path at which it was created.
 fn scaffold_project(
@@ -69,10 +68,6 @@ fn scaffold_project(
     package_path: &Path,
     template: &TemplateInfo,
 ) -> StrResult<()> {
-    if project_dir.exists() {
-        bail!("project directory already exists (at {})", project_dir.display());
-    }
-
     let template_dir = package_path.join(template.path.as_str());
     if !template_dir.exists() {
         bail!("template directory does not exist (at {})", template_dir.display());
@@ -88,6 +83,154 @@ fn scaffold_project(
     Ok(())
 }
 
+/// Determines the value of each of the template's parameters, from the
+/// command line, by asking the user, or from the parameter's default.
+fn determine_params(
+    template: &TemplateInfo,
+    set: &[(String, String)],
+) -> StrResult<Vec<(EcoString, EcoString)>> {
+    for (key, _) in set {
+        if !template.params.iter().any(|param| param.name == key.as_str()) {
+            bail!("template has no parameter `{key}`");
+        }
+    }
+
+    let interactive = io::stdin().is_terminal() && io::stderr().is_terminal();
+    let mut values = vec![];
+    for param in &template.params {
+        let value = match set.iter().rev().find(|(key, _)| param.name == key.as_str()) {
+            Some((_, value)) => value.as_str().into(),
+            None if interactive => ask_param(param)
+                .map_err(|err| eco_format!("failed to read parameter ({err})"))?,
+            None => param.default.clone(),
+        };
+        values.push((param.name.clone(), value));
+    }
+
+    Ok(values)
+}
+
+/// Asks the user for the value of a parameter.
+fn ask_param(param: &TemplateParam) -> io::Result<EcoString> {
+    let mut out = crate::terminal::out();
+    match &param.description {
+        Some(description) => write!(out, "{} ({description})", param.name)?,
+        None => write!(out, "{}", param.name)?,
+    }
+    if !param.default.is_empty() {
+        write!(out, " [{}]", param.default)?;
+    }
+    write!(out, ": ")?;
+    out.flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let line = line.trim();
+    Ok(if line.is_empty() { param.default.clone() } else { line.into() })
+}
+
+/// Replaces the `{{name}}` placeholders in the project's entrypoint with the
+/// values of the template's parameters.
+fn substitute_params(
+    project_dir: &Path,
+    template: &TemplateInfo,
+    values: &[(EcoString, EcoString)],
+) -> StrResult<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let path = project_dir.join(template.entrypoint.as_str());
+    let text = fs::read_to_string(&path)
+        .map_err(|err| eco_format!("failed to read {} ({err})", path.display()))?;
+
+    fs::write(&path, substitute(&text, values))
+        .map_err(|err| eco_format!("failed to write {} ({err})", path.display()))
+}
+
+/// Replaces the `{{name}}` placeholders in some Typst source.
+///
+/// The values are escaped to appear verbatim: Within string literals, they are
+/// escaped as strings and everywhere else as markup.
+fn substitute(text: &str, values: &[(EcoString, EcoString)]) -> String {
+    let root = typst::syntax::parse(text);
+    let mut strings = vec![];
+    collect_strings(&LinkedNode::new(&root), &mut strings);
+
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    while let Some(start) = text[cursor..].find("{{").map(|i| cursor + i) {
+        let rest = &text[start + 2..];
+        let value = rest.find("}}").and_then(|end| {
+            let (_, value) = values.iter().find(|(name, _)| name == &rest[..end])?;
+            Some((value, start + 2 + end + 2))
+        });
+
+        let Some((value, end)) = value else {
+            out.push_str(&text[cursor..start + 2]);
+            cursor = start + 2;
+            continue;
+        };
+
+        out.push_str(&text[cursor..start]);
+        if strings.iter().any(|range| range.contains(&start)) {
+            out.push_str(&escape_string(value));
+        } else {
+            out.push_str(&escape_markup(value));
+        }
+        cursor = end;
+    }
+
+    out.push_str(&text[cursor..]);
+    out
+}
+
+/// Collects the ranges of the string literals in a syntax tree.
+fn collect_strings(node: &LinkedNode, strings: &mut Vec<Range<usize>>) {
+    if node.kind() == SyntaxKind::Str {
+        strings.push(node.range());
+    }
+    for child in node.children() {
+        collect_strings(&child, strings);
+    }
+}
+
+/// Escapes the characters that would end or escape within a string literal.
+fn escape_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes the characters that could start markup syntax. Quotes are left
+/// alone so that they still become smart quotes.
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '/'
+                | '['
+                | ']'
+                | '#'
+                | '~'
+                | '-'
+                | '.'
+                | '*'
+                | '_'
+                | '`'
+                | '$'
+                | '<'
+                | '>'
+                | '@'
+                | '='
+                | '+'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 /// Prints a summary after successful initialization.
 fn print_summary(
     spec: PackageSpec,
@@ -120,3 +263,37 @@ fn print_summary(
     writeln!(out)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_markup() {
+        assert_eq!(escape_markup("Jane's \"Notes\""), "Jane's \"Notes\"");
+        assert_eq!(escape_markup("*Report* #1"), "\\*Report\\* \\#1");
+        assert_eq!(escape_markup("a\\b <c> @d"), "a\\\\b \\<c\\> \\@d");
+        assert_eq!(escape_markup("$x$ = [y]"), "\\$x\\$ \\= \\[y\\]");
+    }
+
+    #[test]
+    fn test_substitute() {
+        let values = [
+            ("title".into(), "*A* \\ \"B\"".into()),
+            ("author".into(), "Jane #1".into()),
+        ];
+        let text = "#let title = \"{{title}}\"\n\
+                    #set document(author: \"{{author}}\")\n\
+                    = {{title}}\n\
+                    By {{author}}, {{unknown}} {{title\n\
+                    #[{{author}}] #(\"x {{author}} y\")\n";
+        assert_eq!(
+            substitute(text, &values),
+            "#let title = \"*A* \\\\ \\\"B\\\"\"\n\
+             #set document(author: \"Jane #1\")\n\
+             = \\*A\\* \\\\ \"B\"\n\
+             By Jane \\#1, {{unknown}} {{title\n\
+             #[Jane \\#1] #(\"x Jane #1 y\")\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use ecow::{eco_format, EcoVec};
+use typst::diag::{bail, FileError, Severity, StrResult};
+use typst::eval::Tracer;
+use typst::World;
+use typst_ide::Lint;
+
+use crate::args::LintCommand;
+use crate::compile::{deny_warnings, print_diagnostics};
+use crate::set_failed;
+use crate::world::SystemWorld;
+
+/// Execute a lint command.
+pub fn lint(command: &LintCommand) -> StrResult<()> {
+    let mut world = SystemWorld::new(&command.common)?;
+    let levels = levels(world.root())?;
+
+    // Reset everything and ensure that the main file is present.
+    world.reset();
+    world.source(world.main()).map_err(|err| err.to_string())?;
+
+    let mut tracer = Tracer::new();
+    let result = typst::compile(&world, &mut tracer);
+    let mut warnings = tracer.warnings();
+    let mut denied = EcoVec::new();
+
+    // Explain how to silence a lint the first time it is reported.
+    let mut explained = HashSet::new();
+    for finding in typst_ide::lint(&world, result.as_ref().ok()) {
+        let level = levels.get(&finding.lint).copied().unwrap_or(Level::Warn);
+        let mut diagnostic = finding.diagnostic.with_id(finding.lint.name());
+        if explained.insert(finding.lint) {
+            diagnostic.hint(eco_format!(
+                "this check can be disabled with `// typst: allow({})` or in the \
+                 `[lint]` table of `typst.toml`",
+                finding.lint,
+            ));
+        }
+
+        match level {
+            Level::Allow => {}
+            Level::Warn => warnings.push(diagnostic),
+            Level::Deny => {
+                diagnostic.severity = Severity::Error;
+                denied.push(diagnostic);
+            }
+        }
+    }
+
+    let (result, warnings) = deny_warnings(&command.common, result, warnings);
+    let mut errors = result.err().unwrap_or_default();
+    errors.extend(denied);
+
+    if !errors.is_empty() {
+        set_failed();
+    }
+
+    print_diagnostics(&world, &errors, &warnings, command.common.diagnostic_format)
+        .map_err(|err| eco_format!("failed to print diagnostics ({err})"))
+}
+
+/// How a lint's findings are reported.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Level {
+    /// The findings are left out.
+    Allow,
+    /// The findings are reported as warnings.
+    Warn,
+    /// The findings are reported as errors.
+    Deny,
+}
+
+/// Read the configured lint levels from the `[lint]` table of the project's
+/// `typst.toml`, if there is one.
+fn levels(root: &Path) -> StrResult<HashMap<Lint, Level>> {
+    let path = root.join("typst.toml");
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let string = fs::read_to_string(&path).map_err(|err| {
+        eco_format!("failed to read typst.toml ({})", FileError::from_io(err, &path))
+    })?;
+    let manifest: toml::Table = toml::from_str(&string)
+        .map_err(|err| eco_format!("typst.toml is malformed ({})", err.message()))?;
+    let Some(table) = manifest.get("lint") else { return Ok(HashMap::new()) };
+    let Some(table) = table.as_table() else {
+        bail!("the `lint` key in typst.toml must be a table");
+    };
+
+    let mut levels = HashMap::new();
+    for (name, value) in table {
+        let lint: Lint = name.parse()?;
+        let level = match value.as_str() {
+            Some("allow") => Level::Allow,
+            Some("warn") => Level::Warn,
+            Some("deny") => Level::Deny,
+            _ => bail!("lint `{name}` must be set to \"allow\", \"warn\", or \"deny\""),
+        };
+        levels.insert(lint, level);
+    }
+
+    Ok(levels)
+}
@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use typst::diag::{PackageError, PackageResult};
+use typst::syntax::package::PackageSpec;
+
+/// The name of a project's lockfile.
+const LOCKFILE: &str = "typst.lock";
+
+/// The comment at the top of a lockfile.
+const HEADER: &str = "\
+# This file is generated by Typst and records the checksums of the packages
+# the project uses. It should be committed.
+
+";
+
+/// Records the checksums of the packages a project uses and verifies them.
+///
+/// The checksum of a package is taken over its unpacked files, so that both
+/// tampered archives and changes to the package cache are detected.
+#[derive(Debug)]
+pub struct Lockfile {
+    /// Where the lockfile is stored.
+    path: PathBuf,
+    /// Whether packages that aren't in the lockfile yet are refused.
+    locked: bool,
+    /// The lazily loaded checksums.
+    state: Mutex<State>,
+}
+
+/// The mutable state of a lockfile.
+#[derive(Debug, Default)]
+struct State {
+    /// The recorded checksums by package, if already loaded.
+    checksums: Option<BTreeMap<String, String>>,
+    /// The packages that were already verified in this process, with the
+    /// fingerprints of their files at the time.
+    verified: HashMap<PackageSpec, u128>,
+}
+
+/// The serialized form of a lockfile.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Data {
+    /// The format version.
+    version: u32,
+    /// The locked packages.
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// A package in a lockfile.
+#[derive(Debug, Serialize, Deserialize)]
+struct LockedPackage {
+    /// The package specification, e.g. `@preview/example:0.1.0`.
+    spec: String,
+    /// The checksum of the package's files, e.g. `sha256:...`.
+    checksum: String,
+}
+
+impl Lockfile {
+    /// Create a lockfile for the project with the given root directory.
+    pub fn new(root: &Path, locked: bool) -> Self {
+        Self {
+            path: root.join(LOCKFILE),
+            locked,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Verify that a package matches its recorded checksum, or record its
+    /// checksum if it isn't locked yet.
+    ///
+    /// A package is only hashed again if its files were modified since it was
+    /// last verified.
+    pub fn verify(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+        let fingerprint = fingerprint(dir).map_err(|err| {
+            PackageError::Other(Some(eco_format!("failed to hash {spec} ({err})")))
+        })?;
+
+        let mut state = self.state.lock();
+        if state.verified.get(spec) == Some(&fingerprint) {
+            return Ok(());
+        }
+
+        if state.checksums.is_none() {
+            state.checksums = Some(self.load().map_err(|err| {
+                PackageError::Other(Some(eco_format!(
+                    "failed to read {LOCKFILE} ({err})"
+                )))
+            })?);
+        }
+
+        let actual = checksum(dir).map_err(|err| {
+            PackageError::Other(Some(eco_format!("failed to hash {spec} ({err})")))
+        })?;
+
+        let key = spec.to_string();
+        let checksums = state.checksums.as_mut().unwrap();
+        match checksums.get(&key) {
+            Some(expected) if *expected == actual => {}
+            Some(expected) => {
+                return Err(PackageError::Other(Some(eco_format!(
+                    "{spec} does not match its checksum in {LOCKFILE} (expected \
+                     {expected}, found {actual}); if the change is intended, \
+                     remove its entry from {LOCKFILE}"
+                ))));
+            }
+            None if self.locked => {
+                return Err(PackageError::Other(Some(eco_format!(
+                    "{spec} is not recorded in {LOCKFILE}, but packages are locked"
+                ))));
+            }
+            None => {
+                checksums.insert(key, actual);
+                self.save(checksums).map_err(|err| {
+                    PackageError::Other(Some(eco_format!(
+                        "failed to write {LOCKFILE} ({err})"
+                    )))
+                })?;
+            }
+        }
+
+        state.verified.insert(spec.clone(), fingerprint);
+        Ok(())
+    }
+
+    /// Read the recorded checksums.
+    fn load(&self) -> Result<BTreeMap<String, String>, EcoString> {
+        let string = match fs::read_to_string(&self.path) {
+            Ok(string) => string,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(BTreeMap::new())
+            }
+            Err(err) => return Err(eco_format!("{err}")),
+        };
+
+        let data: Data = toml::from_str(&string)
+            .map_err(|err| eco_format!("lockfile is malformed ({})", err.message()))?;
+        if data.version != 1 {
+            return Err(eco_format!("unsupported lockfile version {}", data.version));
+        }
+
+        Ok(data
+            .package
+            .into_iter()
+            .map(|package| (package.spec, package.checksum))
+            .collect())
+    }
+
+    /// Write the recorded checksums.
+    fn save(&self, checksums: &BTreeMap<String, String>) -> Result<(), EcoString> {
+        let data = Data {
+            version: 1,
+            package: checksums
+                .iter()
+                .map(|(spec, checksum)| LockedPackage {
+                    spec: spec.clone(),
+                    checksum: checksum.clone(),
+                })
+                .collect(),
+        };
+
+        let string = toml::to_string(&data).map_err(|err| eco_format!("{err}"))?;
+        fs::write(&self.path, format!("{HEADER}{string}"))
+            .map_err(|err| eco_format!("{err}"))
+    }
+}
+
+/// Compute a cheap fingerprint of a package's files from their paths, lengths
+/// and modification times.
+fn fingerprint(dir: &Path) -> io::Result<u128> {
+    let mut files = vec![];
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let metadata = entry.metadata()?;
+            files.push((entry.into_path(), metadata.len(), metadata.modified().ok()));
+        }
+    }
+    Ok(typst::util::hash128(&files))
+}
+
+/// Compute the checksum of a package's files.
+///
+/// The files are hashed in the order of their paths, each with its path
+/// relative to the package directory and its length, so that moving bytes
+/// between files changes the checksum.
+fn checksum(dir: &Path) -> io::Result<String> {
+    let mut files = vec![];
+    for entry in walkdir::WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(dir).unwrap();
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, entry.into_path()));
+        }
+    }
+
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (name, path) in files {
+        let data = fs::read(path)?;
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update((data.len() as u64).to_le_bytes());
+        hasher.update(&data);
+    }
+
+    Ok(format!("sha256:{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a small package to a directory.
+    fn write_package(dir: &Path) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("typst.toml"), "[package]\nname = \"a\"\n").unwrap();
+        fs::write(dir.join("src/lib.typ"), "#let x = 1").unwrap();
+    }
+
+    fn spec() -> PackageSpec {
+        "@preview/a:0.1.0".parse().unwrap()
+    }
+
+    #[test]
+    fn test_checksum() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("a");
+        write_package(&dir);
+
+        let sum = checksum(&dir).unwrap();
+        assert_eq!(
+            sum,
+            "sha256:d740d85602f44407413a211e479eb94951b9987c16718d98a34defe62a63d05f"
+        );
+
+        // Moving bytes between files changes the checksum.
+        fs::write(dir.join("src/lib.typ"), "#let x = 1]").unwrap();
+        fs::write(dir.join("typst.toml"), "[package\nname = \"a\"\n").unwrap();
+        assert_ne!(checksum(&dir).unwrap(), sum);
+    }
+
+    #[test]
+    fn test_verify() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("a");
+        write_package(&dir);
+
+        // The first use records the checksum.
+        let lockfile = Lockfile::new(root.path(), false);
+        lockfile.verify(&spec(), &dir).unwrap();
+        let checksums = lockfile.load().unwrap();
+        assert_eq!(checksums.get("@preview/a:0.1.0"), Some(&checksum(&dir).unwrap()));
+
+        // A modified package is rejected, also by a lockfile that verified it
+        // before.
+        fs::write(dir.join("src/lib.typ"), "#let x = 2 + 3").unwrap();
+        let err = lockfile.verify(&spec(), &dir).unwrap_err().to_string();
+        assert!(err.contains("does not match its checksum"), "{err}");
+        let err = Lockfile::new(root.path(), false)
+            .verify(&spec(), &dir)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not match its checksum"), "{err}");
+
+        // Restoring it makes it pass again.
+        fs::write(dir.join("src/lib.typ"), "#let x = 1").unwrap();
+        lockfile.verify(&spec(), &dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_locked() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("a");
+        write_package(&dir);
+
+        let err = Lockfile::new(root.path(), true)
+            .verify(&spec(), &dir)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("is not recorded in typst.lock"), "{err}");
+        assert!(!root.path().join(LOCKFILE).exists());
+
+        // Once recorded, the package passes.
+        Lockfile::new(root.path(), false).verify(&spec(), &dir).unwrap();
+        Lockfile::new(root.path(), true).verify(&spec(), &dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let lockfile = Lockfile::new(root.path(), false);
+        assert_eq!(lockfile.load().unwrap(), BTreeMap::new());
+
+        let checksums = BTreeMap::from([
+            ("@preview/a:0.1.0".to_string(), "sha256:00".to_string()),
+            ("@preview/b:1.2.3".to_string(), "sha256:ff".to_string()),
+        ]);
+        lockfile.save(&checksums).unwrap();
+        assert_eq!(lockfile.load().unwrap(), checksums);
+
+        let string = fs::read_to_string(root.path().join(LOCKFILE)).unwrap();
+        assert!(string.starts_with(HEADER));
+        assert!(string.contains("spec = \"@preview/b:1.2.3\""));
+
+        fs::write(root.path().join(LOCKFILE), "version = 2\n").unwrap();
+        assert_eq!(lockfile.load().unwrap_err(), "unsupported lockfile version 2");
+    }
+}
@@ -0,0 +1,1342 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use comemo::Prehashed;
+use ecow::{eco_format, EcoString};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use typst::diag::{bail, FileResult, Severity, SourceDiagnostic, StrResult};
+use typst::eval::Tracer;
+use typst::foundations::{Bytes, Datetime};
+use typst::model::Document;
+use typst::syntax::{FileId, Side, Source, Span, VirtualPath};
+use typst::text::{Font, FontBook};
+use typst::{Library, World};
+use typst_ide::{
+    Completion, CompletionKind, DocumentSymbol, DocumentSymbolKind, InlayHintKind,
+    SemanticTokenKind, SemanticTokenModifiers, Tooltip,
+};
+use url::Url;
+
+use crate::args::{DiagnosticFormat, Input, LspCommand, SharedArgs};
+use crate::world::SystemWorld;
+
+/// The JSON-RPC error code for a request that was sent before `initialize`.
+const SERVER_NOT_INITIALIZED: i32 = -32002;
+
+/// The JSON-RPC error code for an unknown method.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// The JSON-RPC error code for malformed parameters.
+const INVALID_PARAMS: i32 = -32602;
+
+/// The JSON-RPC error code for a valid request that couldn't be carried out.
+const REQUEST_FAILED: i32 = -32803;
+
+/// The maximum length of a message's content, which bounds the memory that a
+/// single message can claim.
+const MAX_MESSAGE_LEN: usize = 64 * 1024 * 1024;
+
+/// Execute a language server command.
+pub fn lsp(command: &LspCommand) -> StrResult<()> {
+    // Read messages on a separate thread so that we can compile once all
+    // messages that arrived in the meantime have been processed.
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        loop {
+            match read_message(&mut stdin) {
+                Ok(Some(message)) => {
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    eprintln!("failed to read message ({err})");
+                    break;
+                }
+            }
+        }
+    });
+
+    let mut server = Server::new(command);
+    loop {
+        let message = match rx.try_recv() {
+            Ok(message) => message,
+            Err(TryRecvError::Empty) => {
+                server.compile()?;
+                match rx.recv() {
+                    Ok(message) => message,
+                    Err(_) => break,
+                }
+            }
+            Err(TryRecvError::Disconnected) => break,
+        };
+
+        if message.method.as_deref() == Some("exit") {
+            if !server.shutdown {
+                bail!("language client exited without shutting down the server");
+            }
+            return Ok(());
+        }
+
+        server.handle(message)?;
+    }
+
+    if !server.shutdown {
+        bail!("language client disconnected without shutting down the server");
+    }
+
+    Ok(())
+}
+
+/// The state of the language server.
+struct Server<'a> {
+    /// The command line arguments.
+    command: &'a LspCommand,
+    /// The root directory of the project.
+    root: Option<PathBuf>,
+    /// How positions are encoded in messages.
+    encoding: PositionEncoding,
+    /// The world, created once the main file is known.
+    world: Option<LspWorld>,
+    /// The document of the last successful compilation.
+    document: Option<Document>,
+    /// The diagnostics of the last compilation.
+    diagnostics: Vec<SourceDiagnostic>,
+    /// The files for which diagnostics were last published.
+    published: HashSet<String>,
+    /// Whether anything changed since the last compilation.
+    dirty: bool,
+    /// Whether the client initialized the server.
+    initialized: bool,
+    /// Whether the client requested a shutdown.
+    shutdown: bool,
+}
+
+impl<'a> Server<'a> {
+    /// Create a server that is not yet initialized.
+    fn new(command: &'a LspCommand) -> Self {
+        Self {
+            command,
+            root: None,
+            encoding: PositionEncoding::Utf16,
+            world: None,
+            document: None,
+            diagnostics: vec![],
+            published: HashSet::new(),
+            dirty: false,
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// Handle a request or notification from the client.
+    fn handle(&mut self, message: Message) -> StrResult<()> {
+        let Some(method) = message.method else {
+            // A response to a request of ours. We don't send any.
+            return Ok(());
+        };
+
+        let Some(id) = message.id else {
+            return self.notify(&method, message.params);
+        };
+
+        let result = if method == "initialize" {
+            Ok(self.initialize(message.params))
+        } else if !self.initialized {
+            Err(ResponseError::new(SERVER_NOT_INITIALIZED, "server is not initialized"))
+        } else {
+            self.request(&method, message.params)
+        };
+
+        send(&match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        })
+    }
+
+    /// Answer a request.
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/completion" => Ok(self.completion(parse(params)?)),
+            "textDocument/hover" => Ok(self.hover(parse(params)?)),
+            "textDocument/definition" => Ok(self.definition(parse(params)?)),
+            "textDocument/references" => Ok(self.references(parse(params)?)),
+            "textDocument/prepareRename" => self.prepare_rename(parse(params)?),
+            "textDocument/rename" => self.rename(parse(params)?),
+            "textDocument/semanticTokens/full" => {
+                Ok(self.semantic_tokens(parse(params)?))
+            }
+            "textDocument/documentSymbol" => Ok(self.document_symbols(parse(params)?)),
+            "textDocument/signatureHelp" => Ok(self.signature_help(parse(params)?)),
+            "textDocument/inlayHint" => Ok(self.inlay_hints(parse(params)?)),
+            "textDocument/codeAction" => Ok(self.code_actions(parse(params)?)),
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                eco_format!("unsupported method `{method}`"),
+            )),
+        }
+    }
+
+    /// Process a notification.
+    fn notify(&mut self, method: &str, params: Value) -> StrResult<()> {
+        if !self.initialized {
+            return Ok(());
+        }
+
+        match method {
+            "textDocument/didOpen" => {
+                let Ok(params) = parse::<DidOpenParams>(params) else { return Ok(()) };
+                let item = params.text_document;
+                self.open(&item.uri, item.text)?;
+            }
+            "textDocument/didChange" => {
+                let Ok(params) = parse::<DidChangeParams>(params) else { return Ok(()) };
+                self.change(&params.text_document.uri, params.content_changes);
+            }
+            "textDocument/didClose" => {
+                let Ok(params) = parse::<DidCloseParams>(params) else { return Ok(()) };
+                if let Some(world) = &mut self.world {
+                    if let Some(id) = world.id(&params.text_document.uri) {
+                        world.documents.remove(&id);
+                    }
+                }
+                self.dirty = true;
+            }
+            // Files that aren't open in the editor may have changed on disk.
+            "textDocument/didSave" | "workspace/didChangeWatchedFiles" => {
+                self.dirty = true;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Negotiate the capabilities of the server.
+    fn initialize(&mut self, params: Value) -> Value {
+        self.initialized = true;
+        self.root = self.command.root.clone().or_else(|| {
+            let uri = params["rootUri"]
+                .as_str()
+                .or_else(|| params["workspaceFolders"][0]["uri"].as_str())?;
+            uri_to_path(uri)
+        });
+
+        let encodings = &params["capabilities"]["general"]["positionEncodings"];
+        if encodings
+            .as_array()
+            .is_some_and(|encodings| encodings.iter().any(|e| e == "utf-8"))
+        {
+            self.encoding = PositionEncoding::Utf8;
+        }
+
+        // The main file is picked once: the one given on the command line,
+        // the `main.typ` in the workspace root, or else the first opened
+        // Typst document.
+        let main = self.command.main.clone().or_else(|| {
+            let path = self.root.as_ref()?.join("main.typ");
+            path.is_file().then_some(path)
+        });
+        if let Some(main) = main {
+            if let Err(err) = self.create_world(&main) {
+                show_error(&err);
+            }
+        }
+
+        json!({
+            "capabilities": {
+                "positionEncoding": self.encoding.name(),
+                "textDocumentSync": {
+                    "openClose": true,
+                    // Incremental updates.
+                    "change": 2,
+                    "save": true,
+                },
+                "completionProvider": {
+                    "triggerCharacters": ["#", ".", "@", "(", ",", ":", "$", "<"],
+                },
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "referencesProvider": true,
+                "renameProvider": { "prepareProvider": true },
+                "semanticTokensProvider": {
+                    "legend": {
+                        "tokenTypes": SemanticTokenKind::LIST
+                            .iter()
+                            .map(|&kind| token_type(kind))
+                            .collect::<Vec<_>>(),
+                        "tokenModifiers": SemanticTokenModifiers::all()
+                            .iter()
+                            .map(token_modifier)
+                            .collect::<Vec<_>>(),
+                    },
+                    "full": true,
+                },
+                "documentSymbolProvider": true,
+                "signatureHelpProvider": {
+                    "triggerCharacters": ["(", ","],
+                    "retriggerCharacters": [":"],
+                },
+                "inlayHintProvider": true,
+                "codeActionProvider": { "codeActionKinds": ["quickfix"] },
+            },
+            "serverInfo": {
+                "name": "typst",
+                "version": crate::typst_version(),
+            },
+        })
+    }
+
+    /// Create the world with the given main file.
+    fn create_world(&mut self, main: &Path) -> StrResult<()> {
+        let args = SharedArgs {
+            input: Input::Path(main.into()),
+            root: self.root.clone(),
+            inputs: self.command.inputs.clone(),
+            inputs_file: None,
+            font_paths: self.command.font_paths.clone(),
+            packages: self.command.packages.clone(),
+            creation_timestamp: None,
+            deny_warnings: false,
+            deny: vec![],
+            diagnostic_format: DiagnosticFormat::Human,
+        };
+
+        let system = SystemWorld::new(&args)?;
+        self.world = Some(LspWorld {
+            main: system.main(),
+            system,
+            documents: HashMap::new(),
+        });
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Start tracking the contents of a document that was opened.
+    fn open(&mut self, uri: &str, text: String) -> StrResult<()> {
+        if self.world.is_none() {
+            let Some(path) = uri_to_path(uri) else { return Ok(()) };
+            if path.extension().map_or(true, |ext| ext != "typ") {
+                return Ok(());
+            }
+            if let Err(err) = self.create_world(&path) {
+                show_error(&err);
+                return Ok(());
+            }
+        }
+
+        let Some(world) = &mut self.world else { return Ok(()) };
+        let Some(id) = world.id(uri) else { return Ok(()) };
+        world.documents.insert(id, Source::new(id, text));
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Apply edits from the client to an open document.
+    fn change(&mut self, uri: &str, changes: Vec<ContentChange>) {
+        let Some(world) = &mut self.world else { return };
+        let Some(id) = world.id(uri) else { return };
+        let Some(source) = world.documents.get_mut(&id) else { return };
+
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let start = self.encoding.offset(source, range.start);
+                    let end = self.encoding.offset(source, range.end);
+                    source.edit(start..end.max(start), &change.text);
+                }
+                None => {
+                    source.replace(&change.text);
+                }
+            }
+        }
+
+        self.dirty = true;
+    }
+
+    /// Compile the document if anything changed and publish the diagnostics.
+    fn compile(&mut self) -> StrResult<()> {
+        if !std::mem::take(&mut self.dirty) {
+            return Ok(());
+        }
+
+        let Some(world) = &mut self.world else { return Ok(()) };
+        world.system.reset();
+
+        let mut tracer = Tracer::new();
+        let errors = match world.source(world.main) {
+            Ok(_) => match typst::compile(world, &mut tracer) {
+                Ok(document) => {
+                    self.document = Some(document);
+                    vec![]
+                }
+                Err(errors) => errors.to_vec(),
+            },
+            Err(err) => {
+                vec![SourceDiagnostic::error(Span::detached(), eco_format!("{err}"))]
+            }
+        };
+
+        let warnings = tracer.warnings();
+        self.publish(errors.iter().chain(&warnings))?;
+        self.diagnostics = errors.into_iter().chain(warnings).collect();
+
+        comemo::evict(10);
+
+        Ok(())
+    }
+
+    /// Publish diagnostics for all affected files and clear those of files
+    /// that are no longer affected.
+    fn publish<'d>(
+        &mut self,
+        diagnostics: impl Iterator<Item = &'d SourceDiagnostic>,
+    ) -> StrResult<()> {
+        let Some(world) = &self.world else { return Ok(()) };
+
+        let mut files: HashMap<String, Vec<Value>> = HashMap::new();
+        for diagnostic in diagnostics {
+            // Diagnostics without a location are shown at the start of the
+            // main file.
+            let id = diagnostic.span.id().unwrap_or(world.main);
+            let Some(uri) = world.uri(id) else { continue };
+            let range = world.range(diagnostic.span, self.encoding).unwrap_or_default();
+
+            let mut message = diagnostic.message.to_string();
+            for hint in &diagnostic.hints {
+                message.push_str("\nhint: ");
+                message.push_str(hint);
+            }
+
+            let related: Vec<_> = diagnostic
+                .trace
+                .iter()
+                .filter_map(|point| {
+                    let location = world.location(point.span, self.encoding)?;
+                    Some(json!({ "location": location, "message": point.v.to_string() }))
+                })
+                .collect();
+
+            let severity = match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            };
+
+            let mut published = json!({
+                "range": range,
+                "severity": severity,
+                "source": "typst",
+                "message": message,
+                "relatedInformation": related,
+            });
+            if let Some(id) = &diagnostic.id {
+                published["code"] = json!(id);
+            }
+
+            files.entry(uri).or_default().push(published);
+        }
+
+        for uri in self.published.drain() {
+            files.entry(uri).or_default();
+        }
+
+        for (uri, diagnostics) in files {
+            if !diagnostics.is_empty() {
+                self.published.insert(uri.clone());
+            }
+
+            send(&json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }))?;
+        }
+
+        Ok(())
+    }
+
+    /// Resolve a position in a document to the source and a byte offset.
+    fn locate(
+        &self,
+        params: &TextDocumentPositionParams,
+    ) -> Option<(&LspWorld, Source, usize)> {
+        let world = self.world.as_ref()?;
+        let id = world.id(&params.text_document.uri)?;
+        let source = world.source(id).ok()?;
+        let cursor = self.encoding.offset(&source, params.position);
+        Some((world, source, cursor))
+    }
+
+    /// Resolve a document to its source.
+    fn document(&self, document: &TextDocumentIdentifier) -> Option<Source> {
+        let world = self.world.as_ref()?;
+        world.source(world.id(&document.uri)?).ok()
+    }
+
+    /// Autocomplete at a position.
+    fn completion(&self, params: CompletionParams) -> Value {
+        let Some((world, source, cursor)) = self.locate(&params.position) else {
+            return Value::Null;
+        };
+
+        // Completion was invoked explicitly rather than by typing.
+        let explicit = params.context.map_or(true, |context| context.trigger_kind == 1);
+        let Some((from, completions)) = typst_ide::autocomplete(
+            world,
+            self.document.as_ref(),
+            &source,
+            cursor,
+            explicit,
+        ) else {
+            return Value::Null;
+        };
+
+        let range = Range {
+            start: self.encoding.position(&source, from),
+            end: self.encoding.position(&source, cursor),
+        };
+
+        let items: Vec<_> = completions
+            .iter()
+            .map(|completion| completion_item(completion, range))
+            .collect();
+
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    /// Describe the item under the cursor.
+    fn hover(&self, params: TextDocumentPositionParams) -> Value {
+        let Some((world, source, cursor)) = self.locate(&params) else {
+            return Value::Null;
+        };
+
+        let Some(tooltip) = typst_ide::tooltip(
+            world,
+            self.document.as_ref(),
+            &source,
+            cursor,
+            Side::After,
+        ) else {
+            return Value::Null;
+        };
+
+        let value = match tooltip {
+            Tooltip::Text(text) => text,
+            Tooltip::Code(code) => eco_format!("```typst\n{code}\n```"),
+        };
+
+        json!({ "contents": { "kind": "markdown", "value": value } })
+    }
+
+    /// Find the definition of the symbol under the cursor.
+    fn definition(&self, params: TextDocumentPositionParams) -> Value {
+        let Some((world, source, cursor)) = self.locate(&params) else {
+            return Value::Null;
+        };
+
+        let Some(definition) =
+            typst_ide::definition(world, self.document.as_ref(), &source, cursor)
+        else {
+            return Value::Null;
+        };
+
+        match world.location(definition.span, self.encoding) {
+            Some(location) => json!(location),
+            None => Value::Null,
+        }
+    }
+
+    /// Find all references to the symbol under the cursor.
+    fn references(&self, params: TextDocumentPositionParams) -> Value {
+        let Some((world, source, cursor)) = self.locate(&params) else {
+            return Value::Null;
+        };
+
+        // References are searched in all files reachable from the main file.
+        if world.source(world.main).is_err() {
+            return json!([]);
+        }
+
+        let locations: Vec<_> = typst_ide::references(world, &source, cursor)
+            .into_iter()
+            .filter_map(|span| world.location(span, self.encoding))
+            .collect();
+
+        json!(locations)
+    }
+
+    /// Check whether the symbol under the cursor can be renamed.
+    fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Value, ResponseError> {
+        let Some((world, source, cursor)) = self.locate(&params) else {
+            return Ok(Value::Null);
+        };
+
+        let range = typst_ide::prepare_rename(world, &source, cursor)
+            .map_err(|err| ResponseError::new(REQUEST_FAILED, err))?;
+
+        Ok(json!(self.encoding.range(&source, range)))
+    }
+
+    /// Rename the symbol under the cursor across the project.
+    fn rename(&self, params: RenameParams) -> Result<Value, ResponseError> {
+        let Some((world, source, cursor)) = self.locate(&params.position) else {
+            return Ok(Value::Null);
+        };
+
+        if let Err(err) = world.source(world.main) {
+            return Err(ResponseError::new(
+                REQUEST_FAILED,
+                eco_format!("cannot rename without the main file ({err})"),
+            ));
+        }
+
+        let edits = typst_ide::rename(world, &source, cursor, &params.new_name)
+            .map_err(|err| ResponseError::new(REQUEST_FAILED, err))?;
+
+        let mut changes: HashMap<String, Vec<TextEdit>> = HashMap::new();
+        for edit in edits {
+            let (Some(uri), Ok(source)) = (world.uri(edit.id), world.source(edit.id))
+            else {
+                continue;
+            };
+
+            changes.entry(uri).or_default().push(TextEdit {
+                range: self.encoding.range(&source, edit.range),
+                new_text: edit.text,
+            });
+        }
+
+        Ok(json!({ "changes": changes }))
+    }
+
+    /// Classify the tokens of a document.
+    fn semantic_tokens(&self, params: TextDocumentParams) -> Value {
+        let Some(source) = self.document(&params.text_document) else {
+            return Value::Null;
+        };
+
+        // Each token is encoded as five numbers, with its position relative to
+        // the previous token. Tokens must not span multiple lines.
+        let mut data = vec![];
+        let mut last = Position::default();
+        for token in typst_ide::semantic_tokens(&source) {
+            let mut start = token.range.start;
+            for line in source.text()[token.range].split_inclusive('\n') {
+                let text = line.trim_end_matches(['\n', '\r']);
+                if !text.is_empty() {
+                    let position = self.encoding.position(&source, start);
+                    let character = if position.line == last.line {
+                        position.character - last.character
+                    } else {
+                        position.character
+                    };
+                    data.extend([
+                        position.line - last.line,
+                        character,
+                        text.chars().map(|c| self.encoding.len(c)).sum(),
+                        token.kind as usize,
+                        token.modifiers.bits() as usize,
+                    ]);
+                    last = position;
+                }
+                start += line.len();
+            }
+        }
+
+        json!({ "data": data })
+    }
+
+    /// Build the outline of a document.
+    fn document_symbols(&self, params: TextDocumentParams) -> Value {
+        let Some(source) = self.document(&params.text_document) else {
+            return Value::Null;
+        };
+
+        json!(typst_ide::document_symbols(&source)
+            .iter()
+            .map(|symbol| document_symbol(symbol, &source, self.encoding))
+            .collect::<Vec<_>>())
+    }
+
+    /// Describe the signature of the function call around the cursor.
+    fn signature_help(&self, params: TextDocumentPositionParams) -> Value {
+        let Some((world, source, cursor)) = self.locate(&params) else {
+            return Value::Null;
+        };
+
+        let Some(help) = typst_ide::signature_help(world, &source, cursor) else {
+            return Value::Null;
+        };
+
+        // Parameters are given as offsets into the label, measured in the
+        // negotiated encoding.
+        let units =
+            |text: &str| -> usize { text.chars().map(|c| self.encoding.len(c)).sum() };
+        let parameters: Vec<_> = help
+            .params
+            .iter()
+            .map(|param| {
+                let start = units(&help.label[..param.range.start]);
+                let end = start + units(&help.label[param.range.clone()]);
+                let mut parameter = json!({ "label": [start, end] });
+                if let Some(docs) = &param.docs {
+                    parameter["documentation"] = docs.as_str().into();
+                }
+                parameter
+            })
+            .collect();
+
+        let mut signature = json!({
+            "label": help.label,
+            "parameters": parameters,
+        });
+        if let Some(docs) = &help.docs {
+            signature["documentation"] = docs.as_str().into();
+        }
+        if let Some(active) = help.active {
+            signature["activeParameter"] = active.into();
+        }
+
+        json!({ "signatures": [signature], "activeSignature": 0 })
+    }
+
+    /// Find the inlay hints in a range of a document.
+    fn inlay_hints(&self, params: InlayHintParams) -> Value {
+        let Some(world) = &self.world else { return Value::Null };
+        let Some(source) = self.document(&params.text_document) else {
+            return Value::Null;
+        };
+
+        let range = self.encoding.offset(&source, params.range.start)
+            ..self.encoding.offset(&source, params.range.end);
+        let hints: Vec<_> = typst_ide::inlay_hints(world, &source, range)
+            .into_iter()
+            .map(|hint| {
+                let (kind, left, right) = match hint.kind {
+                    InlayHintKind::Value => (1, true, false),
+                    InlayHintKind::Parameter => (2, false, true),
+                };
+                json!({
+                    "position": self.encoding.position(&source, hint.offset),
+                    "label": hint.label,
+                    "kind": kind,
+                    "paddingLeft": left,
+                    "paddingRight": right,
+                })
+            })
+            .collect();
+
+        json!(hints)
+    }
+
+    /// Offer the fixes of the diagnostics in a range of a document.
+    fn code_actions(&self, params: CodeActionParams) -> Value {
+        let Some(world) = &self.world else { return Value::Null };
+        let Some(source) = self.document(&params.text_document) else {
+            return Value::Null;
+        };
+
+        let range = self.encoding.offset(&source, params.range.start)
+            ..self.encoding.offset(&source, params.range.end);
+        let actions: Vec<_> = typst_ide::code_actions(&source, range, &self.diagnostics)
+            .into_iter()
+            .filter_map(|action| {
+                let uri = world.uri(action.edit.id)?;
+                let edit = TextEdit {
+                    range: self.encoding.range(&source, action.edit.range),
+                    new_text: action.edit.text,
+                };
+                Some(json!({
+                    "title": action.title,
+                    "kind": "quickfix",
+                    "edit": { "changes": { uri: [edit] } },
+                }))
+            })
+            .collect();
+
+        json!(actions)
+    }
+}
+
+/// A world that serves the contents of documents open in the editor and falls
+/// back to the file system for everything else.
+struct LspWorld {
+    /// The world that provides access to the operating system.
+    system: SystemWorld,
+    /// The main file of the project.
+    main: FileId,
+    /// The documents open in the editor, kept up to date with its edits.
+    documents: HashMap<FileId, Source>,
+}
+
+impl LspWorld {
+    /// The id of the file at a URI, if it lies within the project root.
+    fn id(&self, uri: &str) -> Option<FileId> {
+        let path = uri_to_path(uri)?;
+        let path = path.canonicalize().unwrap_or(path);
+        let vpath = VirtualPath::within_root(&path, self.system.root())?;
+        Some(FileId::new(None, vpath))
+    }
+
+    /// The URI of a file.
+    fn uri(&self, id: FileId) -> Option<String> {
+        let path = self.system.path(id).ok()?;
+        Some(Url::from_file_path(path).ok()?.into())
+    }
+
+    /// The range of a span in a file.
+    fn range(&self, span: Span, encoding: PositionEncoding) -> Option<Range> {
+        let source = self.source(span.id()?).ok()?;
+        let range = source.range(span)?;
+        Some(encoding.range(&source, range))
+    }
+
+    /// The location of a span.
+    fn location(&self, span: Span, encoding: PositionEncoding) -> Option<Location> {
+        Some(Location {
+            uri: self.uri(span.id()?)?,
+            range: self.range(span, encoding)?,
+        })
+    }
+}
+
+impl World for LspWorld {
+    fn library(&self) -> &Prehashed<Library> {
+        self.system.library()
+    }
+
+    fn book(&self) -> &Prehashed<FontBook> {
+        self.system.book()
+    }
+
+    fn main(&self) -> Source {
+        // The main file may have been deleted while the server is running.
+        // Callers check for it, but an empty file beats a crashed server.
+        self.source(self.main)
+            .unwrap_or_else(|_| Source::new(self.main, String::new()))
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        match self.documents.get(&id) {
+            Some(source) => Ok(source.clone()),
+            None => self.system.source(id),
+        }
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        match self.documents.get(&id) {
+            Some(source) => Ok(Bytes::from(source.text().as_bytes())),
+            None => self.system.file(id),
+        }
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.system.font(index)
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        self.system.today(offset)
+    }
+}
+
+/// How the character offset of a position is measured.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum PositionEncoding {
+    /// In UTF-8 code units, i.e. bytes.
+    Utf8,
+    /// In UTF-16 code units, the protocol's default.
+    Utf16,
+}
+
+impl PositionEncoding {
+    /// The name of the encoding in the protocol.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+        }
+    }
+
+    /// The length of a character in code units.
+    fn len(self, c: char) -> usize {
+        match self {
+            Self::Utf8 => c.len_utf8(),
+            Self::Utf16 => c.len_utf16(),
+        }
+    }
+
+    /// Convert a position to a byte offset, clamping it to the end of its
+    /// line.
+    fn offset(self, source: &Source, position: Position) -> usize {
+        let Some(range) = source.line_to_range(position.line) else {
+            return source.len_bytes();
+        };
+
+        let line = source.get(range.clone()).unwrap_or_default();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character {
+                return range.start + i;
+            }
+            units += self.len(c);
+        }
+
+        range.start + line.len()
+    }
+
+    /// Convert a byte offset to a position.
+    fn position(self, source: &Source, offset: usize) -> Position {
+        let offset = offset.min(source.len_bytes());
+        let line = source.byte_to_line(offset).unwrap_or_default();
+        let start = source.line_to_byte(line).unwrap_or_default();
+        let head = source.get(start..offset).unwrap_or_default();
+        Position {
+            line,
+            character: head.chars().map(|c| self.len(c)).sum(),
+        }
+    }
+
+    /// Convert a byte range to a range of positions.
+    fn range(self, source: &Source, range: std::ops::Range<usize>) -> Range {
+        Range {
+            start: self.position(source, range.start),
+            end: self.position(source, range.end),
+        }
+    }
+}
+
+/// Convert a completion into a completion item whose text replaces the given
+/// range.
+fn completion_item(completion: &Completion, range: Range) -> Value {
+    let kind = match completion.kind {
+        CompletionKind::Syntax => 15,
+        CompletionKind::Func => 3,
+        CompletionKind::Type => 7,
+        CompletionKind::Param => 6,
+        CompletionKind::Constant | CompletionKind::Symbol(_) => 21,
+    };
+
+    let apply = completion.apply.as_ref().unwrap_or(&completion.label);
+    let (text, format) =
+        if apply.contains("${") { (to_snippet(apply), 2) } else { (apply.clone(), 1) };
+
+    let mut item = json!({
+        "label": completion.label,
+        "kind": kind,
+        "textEdit": { "range": range, "newText": text },
+        "insertTextFormat": format,
+    });
+
+    let detail = match completion.kind {
+        CompletionKind::Symbol(c) => Some(eco_format!("{c}")),
+        _ => completion.detail.clone(),
+    };
+
+    if let Some(detail) = detail {
+        item["detail"] = detail.as_str().into();
+    }
+
+    item
+}
+
+/// The name of a semantic token kind in the legend.
+fn token_type(kind: SemanticTokenKind) -> &'static str {
+    match kind {
+        SemanticTokenKind::Comment => "comment",
+        SemanticTokenKind::Punctuation => "punctuation",
+        SemanticTokenKind::Escape => "escape",
+        SemanticTokenKind::Strong => "strong",
+        SemanticTokenKind::Emph => "emph",
+        SemanticTokenKind::Link => "link",
+        SemanticTokenKind::Raw => "raw",
+        SemanticTokenKind::Label => "label",
+        SemanticTokenKind::Ref => "ref",
+        SemanticTokenKind::Heading => "heading",
+        SemanticTokenKind::ListMarker => "marker",
+        SemanticTokenKind::ListTerm => "term",
+        SemanticTokenKind::Keyword => "keyword",
+        SemanticTokenKind::Operator => "operator",
+        SemanticTokenKind::Number => "number",
+        SemanticTokenKind::String => "string",
+        SemanticTokenKind::Function => "function",
+        SemanticTokenKind::Parameter => "parameter",
+        SemanticTokenKind::Variable => "variable",
+    }
+}
+
+/// The name of a semantic token modifier in the legend.
+fn token_modifier(modifier: SemanticTokenModifiers) -> &'static str {
+    match modifier {
+        SemanticTokenModifiers::DECLARATION => "declaration",
+        SemanticTokenModifiers::MATH => "math",
+        SemanticTokenModifiers::STRONG => "strong",
+        SemanticTokenModifiers::EMPH => "emph",
+        _ => unreachable!(),
+    }
+}
+
+/// Convert an outline entry and its children into a document symbol.
+fn document_symbol(
+    symbol: &DocumentSymbol,
+    source: &Source,
+    encoding: PositionEncoding,
+) -> Value {
+    let kind = match symbol.kind {
+        DocumentSymbolKind::Heading => 15,
+        DocumentSymbolKind::Function => 12,
+        DocumentSymbolKind::Variable => 13,
+    };
+
+    let children: Vec<_> = symbol
+        .children
+        .iter()
+        .map(|child| document_symbol(child, source, encoding))
+        .collect();
+
+    json!({
+        "name": symbol.name,
+        "kind": kind,
+        "range": encoding.range(source, symbol.range.clone()),
+        "selectionRange": encoding.range(source, symbol.selection.clone()),
+        "children": children,
+    })
+}
+
+/// Convert Typst's snippet syntax with `${label}` placeholders to the
+/// protocol's numbered `${1:label}` tab stops.
+fn to_snippet(apply: &str) -> EcoString {
+    let mut snippet = EcoString::new();
+    let mut stop = 0;
+    let mut rest = apply;
+    while let Some((text, tail)) = rest.split_once("${") {
+        escape_snippet(&mut snippet, text);
+        let (label, tail) = tail.split_once('}').unwrap_or((tail, ""));
+        stop += 1;
+        if label.is_empty() {
+            snippet.push_str(&eco_format!("${stop}"));
+        } else {
+            snippet.push_str(&eco_format!("${{{stop}:"));
+            escape_snippet(&mut snippet, label);
+            snippet.push('}');
+        }
+        rest = tail;
+    }
+    escape_snippet(&mut snippet, rest);
+    snippet
+}
+
+/// Push text to a snippet, escaping characters with a special meaning.
+fn escape_snippet(snippet: &mut EcoString, text: &str) {
+    for c in text.chars() {
+        if matches!(c, '$' | '}' | '\\') {
+            snippet.push('\\');
+        }
+        snippet.push(c);
+    }
+}
+
+/// Convert a `file:` URI to a path.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// Show an error message in the editor.
+fn show_error(message: &str) {
+    let notification = json!({
+        "jsonrpc": "2.0",
+        "method": "window/showMessage",
+        "params": { "type": 1, "message": message },
+    });
+
+    if let Err(err) = send(&notification) {
+        eprintln!("{message} ({err})");
+    }
+}
+
+/// Parse the parameters of a message.
+fn parse<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params).map_err(|err| {
+        ResponseError::new(INVALID_PARAMS, eco_format!("invalid parameters ({err})"))
+    })
+}
+
+/// Read a message from the client.
+///
+/// Returns `None` once the client closed the connection.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Message>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "missing content length"));
+    };
+
+    if length > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("content length of {length} bytes exceeds the maximum"),
+        ));
+    }
+
+    let mut buf = vec![0; length];
+    reader.read_exact(&mut buf)?;
+    serde_json::from_slice(&buf)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Send a message to the client.
+fn send(message: &Value) -> StrResult<()> {
+    write_message(&mut io::stdout().lock(), message)
+        .map_err(|err| eco_format!("failed to write message ({err})"))
+}
+
+/// Write a message with its header.
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+/// A request, notification or response from the client.
+#[derive(Deserialize)]
+struct Message {
+    id: Option<Value>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+}
+
+/// An error response to a request.
+#[derive(Serialize)]
+struct ResponseError {
+    code: i32,
+    message: EcoString,
+}
+
+impl ResponseError {
+    fn new(code: i32, message: impl Into<EcoString>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// A zero-based line and character offset in a document.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct Position {
+    line: usize,
+    character: usize,
+}
+
+/// A range between two positions in a document.
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+/// A range in a specific document.
+#[derive(Serialize)]
+struct Location {
+    uri: String,
+    range: Range,
+}
+
+/// A replacement of a range in a document.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TextEdit {
+    range: Range,
+    new_text: EcoString,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentParams {
+    text_document: TextDocumentIdentifier,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentPositionParams {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionParams {
+    #[serde(flatten)]
+    position: TextDocumentPositionParams,
+    context: Option<CompletionContext>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionContext {
+    trigger_kind: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InlayHintParams {
+    text_document: TextDocumentIdentifier,
+    range: Range,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CodeActionParams {
+    text_document: TextDocumentIdentifier,
+    range: Range,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameParams {
+    #[serde(flatten)]
+    position: TextDocumentPositionParams,
+    new_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpenParams {
+    text_document: TextDocumentItem,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidCloseParams {
+    text_document: TextDocumentIdentifier,
+}
+
+/// An edit to a document, replacing the whole text if there is no range.
+#[derive(Deserialize)]
+struct ContentChange {
+    range: Option<Range>,
+    text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_message() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let data = format!(
+            "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\n\
+             content-length: {}\r\n\r\n{body}{{\"trailing\"",
+            body.len(),
+        );
+
+        let mut reader = data.as_bytes();
+        let message = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(message.id, Some(json!(1)));
+        assert_eq!(message.method.as_deref(), Some("initialize"));
+        assert_eq!(reader, br#"{"trailing""#);
+    }
+
+    #[test]
+    fn test_read_message_invalid() {
+        assert!(read_message(&mut "".as_bytes()).unwrap().is_none());
+        assert!(read_message(&mut "\r\n{}".as_bytes()).is_err());
+        assert!(read_message(&mut "Content-Length: 10\r\n\r\n{}".as_bytes()).is_err());
+        assert!(read_message(&mut "Content-Length: 2\r\n\r\n[]".as_bytes()).is_err());
+
+        // Overly long messages are rejected before their content is read.
+        let header = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE_LEN + 1);
+        let err = read_message(&mut header.as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let header = format!("Content-Length: {MAX_MESSAGE_LEN}\r\n\r\n");
+        let err = read_message(&mut header.as_bytes()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_write_message() {
+        let mut buf = vec![];
+        let message = json!({ "jsonrpc": "2.0", "id": 1, "result": "ä" });
+        write_message(&mut buf, &message).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "Content-Length: 38\r\n\r\n{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":\"ä\"}",
+        );
+
+        let message = read_message(&mut buf.as_slice()).unwrap().unwrap();
+        assert_eq!(message.id, Some(json!(1)));
+    }
+
+    #[test]
+    fn test_position_encoding() {
+        // The emoji is four bytes and two UTF-16 code units long.
+        let source = Source::detached("a😀b\r\näx\n");
+        let at = |line, character| Position { line, character };
+
+        assert_eq!(PositionEncoding::Utf8.position(&source, 5), at(0, 5));
+        assert_eq!(PositionEncoding::Utf16.position(&source, 5), at(0, 3));
+        assert_eq!(PositionEncoding::Utf8.position(&source, 10), at(1, 2));
+        assert_eq!(PositionEncoding::Utf16.position(&source, 10), at(1, 1));
+        assert_eq!(PositionEncoding::Utf16.position(&source, 100), at(2, 0));
+
+        assert_eq!(PositionEncoding::Utf8.offset(&source, at(0, 5)), 5);
+        assert_eq!(PositionEncoding::Utf16.offset(&source, at(0, 3)), 5);
+        assert_eq!(PositionEncoding::Utf16.offset(&source, at(1, 1)), 10);
+
+        // Positions past the end of a line are clamped to it.
+        assert_eq!(PositionEncoding::Utf16.offset(&source, at(0, 50)), 6);
+        assert_eq!(PositionEncoding::Utf16.offset(&source, at(9, 0)), 12);
+    }
+}
@@ -1,11 +1,19 @@
 mod args;
+mod batch;
 mod compile;
+mod doc;
 mod download;
+mod fmt;
 mod fonts;
 mod init;
+mod lint;
+mod lock;
+mod lsp;
 mod package;
 mod query;
+mod server;
 mod terminal;
+mod test;
 mod timings;
 #[cfg(feature = "self-update")]
 mod update;
@@ -42,6 +50,12 @@ fn main() -> ExitCode {
         Command::Init(command) => crate::init::init(command),
         Command::Query(command) => crate::query::query(command),
         Command::Fonts(command) => crate::fonts::fonts(command),
+        Command::Fmt(command) => crate::fmt::fmt(command),
+        Command::Lint(command) => crate::lint::lint(command),
+        Command::Doc(command) => crate::doc::doc(command),
+        Command::Test(command) => crate::test::test(command),
+        Command::Package(command) => crate::package::package(command),
+        Command::Lsp(command) => crate::lsp::lsp(command),
         Command::Update(command) => crate::update::update(command),
     };
 
@@ -1,78 +1,229 @@
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use codespan_reporting::term::{self, termcolor};
-use ecow::eco_format;
+use ecow::{eco_format, EcoString};
 use termcolor::WriteColor;
-use typst::diag::{bail, PackageError, PackageResult, StrResult};
+use typst::diag::{bail, FileError, PackageError, PackageResult, StrResult};
 use typst::syntax::package::{
-    PackageInfo, PackageSpec, PackageVersion, VersionlessPackageSpec,
+    PackageInfo, PackageManifest, PackageSpec, PackageVersion, VersionlessPackageSpec,
 };
 
+use crate::args::{
+    PackageCleanCommand, PackageCommand, PackageLinkCommand, PackageListCommand,
+    PackageRemoveCommand, PackageStorageArgs,
+};
 use crate::download::{download, download_with_progress};
+use crate::lock::Lockfile;
 use crate::terminal;
 
 const HOST: &str = "https://packages.typst.org";
 
-/// Make a package available in the on-disk cache.
-pub fn prepare_package(spec: &PackageSpec) -> PackageResult<PathBuf> {
-    let subdir =
-        format!("typst/packages/{}/{}/{}", spec.namespace, spec.name, spec.version);
+/// Where packages are looked up and stored.
+#[derive(Debug, Clone, Default)]
+pub struct PackageStorage {
+    /// Directories with local packages, searched before the data directory.
+    package_paths: Vec<PathBuf>,
+    /// The directory to cache downloaded packages in, if not the default.
+    cache_path: Option<PathBuf>,
+    /// A directory that mirrors the registry, used instead of the network.
+    mirror: Option<PathBuf>,
+    /// Packages that are read from a local directory, no matter what, keyed
+    /// by name and version to apply to any namespace.
+    overrides: HashMap<(EcoString, PackageVersion), PathBuf>,
+    /// Whether packages that aren't in the lockfile yet are refused.
+    locked: bool,
+    /// The project's lockfile, if packages from the cache are verified.
+    lockfile: Option<Arc<Lockfile>>,
+}
+
+impl PackageStorage {
+    /// Configure the package storage from the command line arguments.
+    pub fn new(args: &PackageStorageArgs) -> Self {
+        Self {
+            package_paths: args.package_paths.clone(),
+            cache_path: args.package_cache_path.clone(),
+            mirror: args.package_mirror.clone(),
+            overrides: HashMap::new(),
+            locked: args.locked,
+            lockfile: None,
+        }
+    }
+
+    /// Verify the packages from the cache against the lockfile of the
+    /// project with the given root, recording those that aren't locked yet.
+    ///
+    /// Local packages are not verified as they are expected to change.
+    pub fn lock(&mut self, root: &Path) {
+        self.lockfile = Some(Arc::new(Lockfile::new(root, self.locked)));
+    }
 
-    if let Some(data_dir) = dirs::data_dir() {
-        let dir = data_dir.join(&subdir);
-        if dir.exists() {
-            return Ok(dir);
+    /// Verify a package from the cache against the lockfile.
+    fn verify(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+        match &self.lockfile {
+            Some(lockfile) => lockfile.verify(spec, dir),
+            None => Ok(()),
         }
     }
 
-    if let Some(cache_dir) = dirs::cache_dir() {
-        let dir = cache_dir.join(&subdir);
-        if dir.exists() {
-            return Ok(dir);
+    /// Read the files of the package with the given name and version from a
+    /// local directory instead of looking it up, no matter the namespace it
+    /// is imported from.
+    pub fn override_package(
+        &mut self,
+        name: EcoString,
+        version: PackageVersion,
+        dir: PathBuf,
+    ) {
+        self.overrides.insert((name, version), dir);
+    }
+
+    /// The directories with local packages, in the order they are searched.
+    pub fn local_dirs(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.package_paths
+            .iter()
+            .cloned()
+            .chain(dirs::data_dir().map(|dir| dir.join("typst/packages")))
+    }
+
+    /// The directory in which downloaded packages are cached.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_path
+            .clone()
+            .or_else(|| Some(dirs::cache_dir()?.join("typst/packages")))
+    }
+
+    /// Make a package available in the on-disk cache.
+    pub fn prepare_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        if let Some(dir) = self.overrides.get(&(spec.name.clone(), spec.version)) {
+            return Ok(dir.clone());
         }
 
-        // Download from network if it doesn't exist yet.
-        if spec.namespace == "preview" {
-            download_package(spec, &dir)?;
+        let subdir = format!("{}/{}/{}", spec.namespace, spec.name, spec.version);
+        for local in self.local_dirs() {
+            let dir = local.join(&subdir);
             if dir.exists() {
+                validate(spec, &dir)?;
                 return Ok(dir);
             }
         }
+
+        if let Some(cache_dir) = self.cache_dir() {
+            let dir = cache_dir.join(&subdir);
+            if dir.exists() {
+                self.verify(spec, &dir)?;
+                return Ok(dir);
+            }
+
+            // Unpack from the mirror or download from network if it doesn't
+            // exist yet.
+            if let Some(mirror) = &self.mirror {
+                unpack_from_mirror(spec, mirror, &dir)?;
+            } else if spec.namespace == "preview" {
+                download_package(spec, &dir)?;
+            }
+
+            if dir.exists() {
+                if let Err(err) =
+                    validate(spec, &dir).and_then(|_| self.verify(spec, &dir))
+                {
+                    fs::remove_dir_all(&dir).ok();
+                    return Err(err);
+                }
+                return Ok(dir);
+            }
+        }
+
+        Err(PackageError::NotFound(spec.clone()))
     }
 
-    Err(PackageError::NotFound(spec.clone()))
+    /// Try to determine the latest version of a package.
+    pub fn determine_latest_version(
+        &self,
+        spec: &VersionlessPackageSpec,
+    ) -> StrResult<PackageVersion> {
+        if let Some(mirror) = &self.mirror {
+            // A mirror has an index for every namespace it contains.
+            read_mirror_index(mirror, &spec.namespace)?
+                .iter()
+                .filter(|package| package.name == spec.name)
+                .map(|package| package.version)
+                .max()
+                .ok_or_else(|| eco_format!("failed to find package {spec}"))
+        } else if spec.namespace == "preview" {
+            // For `@preview`, download the package index and find the latest
+            // version.
+            download_index()?
+                .iter()
+                .filter(|package| package.name == spec.name)
+                .map(|package| package.version)
+                .max()
+                .ok_or_else(|| eco_format!("failed to find package {spec}"))
+        } else {
+            // For other namespaces, search locally. We only search in the
+            // local package directories and not the cache directory, because
+            // the latter is not intended for storage of local packages.
+            let subdir = format!("{}/{}", spec.namespace, spec.name);
+            self.local_dirs()
+                .flat_map(|dir| std::fs::read_dir(dir.join(&subdir)).ok())
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter_map(|path| path.file_name()?.to_string_lossy().parse().ok())
+                .max()
+                .ok_or_else(|| eco_format!("please specify the desired version"))
+        }
+    }
 }
 
-/// Try to determine the latest version of a package.
-pub fn determine_latest_version(
-    spec: &VersionlessPackageSpec,
-) -> StrResult<PackageVersion> {
-    if spec.namespace == "preview" {
-        // For `@preview`, download the package index and find the latest
-        // version.
-        download_index()?
-            .iter()
-            .filter(|package| package.name == spec.name)
-            .map(|package| package.version)
-            .max()
-            .ok_or_else(|| eco_format!("failed to find package {spec}"))
-    } else {
-        // For other namespaces, search locally. We only search in the data
-        // directory and not the cache directory, because the latter is not
-        // intended for storage of local packages.
-        let subdir = format!("typst/packages/{}/{}", spec.namespace, spec.name);
-        dirs::data_dir()
-            .into_iter()
-            .flat_map(|dir| std::fs::read_dir(dir.join(&subdir)).ok())
-            .flatten()
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter_map(|path| path.file_name()?.to_string_lossy().parse().ok())
-            .max()
-            .ok_or_else(|| eco_format!("please specify the desired version"))
-    }
+/// Parses the manifest of the package located at `package_path`.
+pub fn parse_manifest(package_path: &Path) -> StrResult<PackageManifest> {
+    let toml_path = package_path.join("typst.toml");
+    let string = std::fs::read_to_string(&toml_path).map_err(|err| {
+        eco_format!(
+            "failed to read package manifest ({})",
+            FileError::from_io(err, &toml_path)
+        )
+    })?;
+
+    toml::from_str(&string)
+        .map_err(|err| eco_format!("package manifest is malformed ({})", err.message()))
+}
+
+/// Ensure that the manifest of a package found on disk matches its
+/// specification.
+fn validate(spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+    parse_manifest(dir)
+        .and_then(|manifest| manifest.validate(spec))
+        .map_err(|err| PackageError::Other(Some(eco_format!("{spec}: {err}"))))
+}
+
+/// Unpack a package from a mirror of the registry.
+fn unpack_from_mirror(
+    spec: &PackageSpec,
+    mirror: &Path,
+    package_dir: &Path,
+) -> PackageResult<()> {
+    let path = mirror
+        .join(spec.namespace.as_str())
+        .join(format!("{}-{}.tar.gz", spec.name, spec.version));
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(PackageError::NotFound(spec.clone()))
+        }
+        Err(err) => {
+            return Err(PackageError::Other(Some(eco_format!(
+                "failed to read {} ({err})",
+                path.display()
+            ))))
+        }
+    };
+
+    unpack(&data, package_dir)
 }
 
 /// Download a package over the network.
@@ -93,13 +244,28 @@ fn download_package(spec: &PackageSpec, package_dir: &Path) -> PackageResult<()>
         Err(err) => return Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
     };
 
-    let decompressed = flate2::read::GzDecoder::new(data.as_slice());
+    unpack(&data, package_dir)
+}
+
+/// Unpack a package archive into a directory.
+fn unpack(data: &[u8], package_dir: &Path) -> PackageResult<()> {
+    let decompressed = flate2::read::GzDecoder::new(data);
     tar::Archive::new(decompressed).unpack(package_dir).map_err(|err| {
         fs::remove_dir_all(package_dir).ok();
         PackageError::MalformedArchive(Some(eco_format!("{err}")))
     })
 }
 
+/// Read the package index of a namespace from a mirror of the registry.
+fn read_mirror_index(mirror: &Path, namespace: &str) -> StrResult<Vec<PackageInfo>> {
+    let path = mirror.join(namespace).join("index.json");
+    let data = fs::read(&path).map_err(|err| {
+        eco_format!("failed to read package index ({})", FileError::from_io(err, &path))
+    })?;
+    serde_json::from_slice(&data)
+        .map_err(|err| eco_format!("failed to parse package index: {err}"))
+}
+
 /// Download the `@preview` package index.
 fn download_index() -> StrResult<Vec<PackageInfo>> {
     let url = format!("{HOST}/preview/index.json");
@@ -114,6 +280,258 @@ fn download_index() -> StrResult<Vec<PackageInfo>> {
     }
 }
 
+/// Execute a package management command.
+pub fn package(command: &PackageCommand) -> StrResult<()> {
+    match command {
+        PackageCommand::List(command) => list(command),
+        PackageCommand::Remove(command) => remove(command),
+        PackageCommand::Clean(command) => clean(command),
+        PackageCommand::Link(command) => link(command),
+    }
+}
+
+/// List the local and cached packages.
+fn list(command: &PackageListCommand) -> StrResult<()> {
+    let storage = PackageStorage::new(&command.packages);
+    let packages = packages(&storage)?;
+    let width = packages.iter().map(|(spec, ..)| spec.to_string().len()).max();
+    for (spec, path, kind) in &packages {
+        let spec = spec.to_string();
+        let width = width.unwrap_or_default();
+        match fs::read_link(path) {
+            Ok(target) => println!(
+                "{spec:width$}  {kind:6}  {} -> {}",
+                path.display(),
+                target.display()
+            ),
+            Err(_) => println!("{spec:width$}  {kind:6}  {}", path.display()),
+        }
+    }
+
+    Ok(())
+}
+
+/// The local and cached packages, along with their kind.
+fn packages(
+    storage: &PackageStorage,
+) -> StrResult<Vec<(PackageSpec, PathBuf, &'static str)>> {
+    let mut packages = vec![];
+    for dir in storage.local_dirs() {
+        packages.extend(installed(&dir)?.into_iter().map(|(s, p)| (s, p, "local")));
+    }
+    if let Some(dir) = storage.cache_dir() {
+        packages.extend(installed(&dir)?.into_iter().map(|(s, p)| (s, p, "cached")));
+    }
+    Ok(packages)
+}
+
+/// Remove a package from the cache and unlink it from the local packages.
+///
+/// Local packages that aren't links are only removed when forced, as they
+/// may be the only copy of a package.
+fn remove(command: &PackageRemoveCommand) -> StrResult<()> {
+    let storage = PackageStorage::new(&command.packages);
+    let (namespace, name, version) = match command.spec.parse::<PackageSpec>() {
+        Ok(spec) => (spec.namespace, spec.name, Some(spec.version)),
+        Err(err) => {
+            let spec: VersionlessPackageSpec = command.spec.parse().map_err(|_| err)?;
+            (spec.namespace, spec.name, None)
+        }
+    };
+
+    let mut removed = 0;
+    let mut skipped = vec![];
+    for (spec, path, kind) in packages(&storage)? {
+        if spec.namespace != namespace
+            || spec.name != name
+            || version.is_some_and(|version| version != spec.version)
+        {
+            continue;
+        }
+
+        if kind == "local" && !path.is_symlink() && !command.force {
+            skipped.push(path);
+            continue;
+        }
+
+        // Only remove the link of a linked package, not its working copy.
+        let result = if path.is_symlink() {
+            remove_link(&path)
+        } else {
+            fs::remove_dir_all(&path)
+        };
+        result
+            .map_err(|err| eco_format!("failed to remove {} ({err})", path.display()))?;
+
+        println!("removed {spec} ({})", path.display());
+        removed += 1;
+    }
+
+    if removed == 0 && !skipped.is_empty() {
+        bail!(
+            "package {} is only installed locally, pass `--force` to remove it",
+            command.spec
+        );
+    } else if removed == 0 {
+        bail!("package {} is not installed", command.spec);
+    }
+
+    for path in &skipped {
+        println!("kept local package at {}, pass `--force` to remove it", path.display());
+    }
+
+    Ok(())
+}
+
+/// Remove all cached packages.
+///
+/// Only the package directories are removed, along with the namespace and
+/// name directories they leave empty, so that the cache may also be used for
+/// other files.
+fn clean(command: &PackageCleanCommand) -> StrResult<()> {
+    let storage = PackageStorage::new(&command.packages);
+    let Some(dir) = storage.cache_dir() else {
+        bail!("failed to locate the package cache");
+    };
+
+    let packages = installed(&dir)?;
+    for (_, path) in &packages {
+        fs::remove_dir_all(path)
+            .map_err(|err| eco_format!("failed to remove {} ({err})", path.display()))?;
+
+        // Fails if the directories still contain something else.
+        let name = path.parent().unwrap_or(path);
+        if fs::remove_dir(name).is_ok() {
+            fs::remove_dir(name.parent().unwrap_or(name)).ok();
+        }
+    }
+
+    let count = packages.len();
+    println!(
+        "removed {count} cached package{} ({})",
+        if count == 1 { "" } else { "s" },
+        dir.display()
+    );
+
+    Ok(())
+}
+
+/// Link a package directory into the local packages.
+fn link(command: &PackageLinkCommand) -> StrResult<()> {
+    let storage = PackageStorage::new(&command.packages);
+    let dir = command.dir.canonicalize().map_err(|err| {
+        eco_format!("failed to locate {} ({err})", command.dir.display())
+    })?;
+
+    let manifest = parse_manifest(&dir)?;
+    let spec = PackageSpec {
+        namespace: command.namespace.as_str().into(),
+        name: manifest.package.name.clone(),
+        version: manifest.package.version,
+    };
+    manifest.validate(&spec)?;
+    if !dir.join(manifest.package.entrypoint.as_str()).is_file() {
+        bail!("package entrypoint `{}` does not exist", manifest.package.entrypoint);
+    }
+
+    let Some(local) = storage.local_dirs().next() else {
+        bail!("failed to locate the local package directory");
+    };
+
+    let path = local
+        .join(spec.namespace.as_str())
+        .join(spec.name.as_str())
+        .join(spec.version.to_string());
+    if fs::read_link(&path).is_ok_and(|target| target == dir) {
+        println!("{spec} is already linked to {}", dir.display());
+        return Ok(());
+    } else if path.exists() || path.is_symlink() {
+        bail!(
+            "{spec} already exists (at {}), remove it with `typst package remove {spec}`",
+            path.display()
+        );
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| {
+            eco_format!("failed to create {} ({err})", parent.display())
+        })?;
+    }
+
+    create_link(&dir, &path)
+        .map_err(|err| eco_format!("failed to link {} ({err})", path.display()))?;
+
+    println!("linked {spec} to {}", dir.display());
+    Ok(())
+}
+
+/// The packages in a directory laid out as `{namespace}/{name}/{version}`.
+fn installed(dir: &Path) -> StrResult<Vec<(PackageSpec, PathBuf)>> {
+    let mut packages = vec![];
+    for namespace in read_dir(dir)? {
+        for name in read_dir(&namespace)? {
+            for version in read_dir(&name)? {
+                let spec = PackageSpec {
+                    namespace: file_name(&namespace).into(),
+                    name: file_name(&name).into(),
+                    version: match file_name(&version).parse() {
+                        Ok(version) => version,
+                        Err(_) => continue,
+                    },
+                };
+                packages.push((spec, version));
+            }
+        }
+    }
+
+    packages.sort_by(|(a, _), (b, _)| {
+        (&a.namespace, &a.name, a.version).cmp(&(&b.namespace, &b.name, b.version))
+    });
+
+    Ok(packages)
+}
+
+/// The subdirectories of a directory, or nothing if it doesn't exist.
+fn read_dir(dir: &Path) -> StrResult<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => bail!("failed to read {} ({err})", dir.display()),
+    };
+
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+/// The last component of a path.
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into()
+}
+
+/// Create a symbolic link to a directory.
+fn create_link(target: &Path, link: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(target, link);
+    #[cfg(windows)]
+    return std::os::windows::fs::symlink_dir(target, link);
+    #[cfg(not(any(unix, windows)))]
+    return Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links are unsupported",
+    ));
+}
+
+/// Remove a symbolic link to a directory.
+fn remove_link(link: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    return fs::remove_dir(link);
+    #[cfg(not(windows))]
+    return fs::remove_file(link);
+}
+
 /// Print that a package downloading is happening.
 fn print_downloading(spec: &PackageSpec) -> io::Result<()> {
     let styles = term::Styles::default();
@@ -125,3 +543,243 @@ fn print_downloading(spec: &PackageSpec) -> io::Result<()> {
     out.reset()?;
     writeln!(out, " {spec}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a package with a manifest and an entrypoint to a directory.
+    fn write_package(dir: &Path, name: &str, version: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("typst.toml"),
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"{version}\"\n\
+                 entrypoint = \"lib.typ\"\n"
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("lib.typ"), "#let x = 1").unwrap();
+    }
+
+    /// Storage arguments with a local and a cache directory in a temporary
+    /// directory.
+    fn storage(root: &Path) -> PackageStorageArgs {
+        PackageStorageArgs {
+            package_paths: vec![root.join("local")],
+            package_cache_path: Some(root.join("cache")),
+            ..PackageStorageArgs::default()
+        }
+    }
+
+    /// The packages of the storage in the temporary directory.
+    fn listed(root: &Path) -> Vec<(String, &'static str)> {
+        let storage = PackageStorage::new(&storage(root));
+        packages(&storage)
+            .unwrap()
+            .into_iter()
+            .filter(|(_, path, _)| path.starts_with(root))
+            .map(|(spec, _, kind)| (spec.to_string(), kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_list() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write_package(&root.join("local/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("cache/test/b/0.2.0"), "b", "0.2.0");
+        write_package(&root.join("cache/test/b/0.1.0"), "b", "0.1.0");
+        fs::create_dir_all(root.join("cache/test/b/invalid")).unwrap();
+
+        assert_eq!(
+            listed(root),
+            [
+                ("@test/a:0.1.0".into(), "local"),
+                ("@test/b:0.1.0".into(), "cached"),
+                ("@test/b:0.2.0".into(), "cached"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write_package(&root.join("local/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("cache/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("cache/test/a/0.2.0"), "a", "0.2.0");
+        let remove = |spec: &str, force| {
+            super::remove(&PackageRemoveCommand {
+                spec: spec.into(),
+                force,
+                packages: storage(root),
+            })
+        };
+
+        // Only the cached copy is removed without `--force`.
+        remove("@test/a:0.1.0", false).unwrap();
+        assert!(root.join("local/test/a/0.1.0").exists());
+        assert!(!root.join("cache/test/a/0.1.0").exists());
+
+        let err = remove("@test/a:0.1.0", false).unwrap_err();
+        assert_eq!(
+            err,
+            "package @test/a:0.1.0 is only installed locally, pass `--force` to remove it"
+        );
+
+        // Without a version, all versions are removed.
+        remove("@test/a", true).unwrap();
+        assert!(listed(root).is_empty());
+
+        let err = remove("@test/a", false).unwrap_err();
+        assert_eq!(err, "package @test/a is not installed");
+    }
+
+    #[test]
+    fn test_clean() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write_package(&root.join("local/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("cache/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("cache/test/b/0.1.0"), "b", "0.1.0");
+        fs::write(root.join("cache/other.txt"), "keep").unwrap();
+        fs::create_dir_all(root.join("cache/test/b/notes")).unwrap();
+
+        clean(&PackageCleanCommand { packages: storage(root) }).unwrap();
+        assert_eq!(listed(root), [("@test/a:0.1.0".into(), "local")]);
+        assert!(root.join("cache/other.txt").exists());
+        assert!(root.join("cache/test/b/notes").exists());
+        assert!(!root.join("cache/test/a").exists());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_link() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let dir = root.join("work");
+        write_package(&dir, "a", "0.1.0");
+        let link = || {
+            super::link(&PackageLinkCommand {
+                dir: dir.clone(),
+                namespace: "local".into(),
+                packages: storage(root),
+            })
+        };
+
+        link().unwrap();
+        let path = root.join("local/local/a/0.1.0");
+        assert_eq!(fs::read_link(&path).unwrap(), dir.canonicalize().unwrap());
+
+        // Linking again is a no-op.
+        link().unwrap();
+        assert_eq!(listed(root), [("@local/a:0.1.0".into(), "local")]);
+
+        // Removing the link keeps the working copy, even without `--force`.
+        remove(&PackageRemoveCommand {
+            spec: "@local/a:0.1.0".into(),
+            force: false,
+            packages: storage(root),
+        })
+        .unwrap();
+        assert!(!path.is_symlink());
+        assert!(dir.join("lib.typ").exists());
+
+        // A package whose entrypoint is missing can't be linked.
+        fs::remove_file(dir.join("lib.typ")).unwrap();
+        assert_eq!(link().unwrap_err(), "package entrypoint `lib.typ` does not exist");
+    }
+
+    /// Pack a package directory into a gzipped archive, like those of the
+    /// registry.
+    fn pack(dir: &Path) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        builder.append_dir_all(".", dir).unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn test_prepare_local() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let args = PackageStorageArgs {
+            package_paths: vec![root.join("local"), root.join("other")],
+            ..storage(root)
+        };
+        let storage = PackageStorage::new(&args);
+        let prepare = |spec: &str| storage.prepare_package(&spec.parse().unwrap());
+
+        // Earlier local directories take precedence over later ones and all
+        // of them over the cache.
+        write_package(&root.join("local/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("other/test/a/0.1.0"), "a", "0.1.0");
+        write_package(&root.join("other/test/b/0.1.0"), "b", "0.1.0");
+        write_package(&root.join("cache/test/b/0.1.0"), "b", "0.1.0");
+        write_package(&root.join("cache/test/c/0.1.0"), "c", "0.1.0");
+        assert_eq!(prepare("@test/a:0.1.0").unwrap(), root.join("local/test/a/0.1.0"));
+        assert_eq!(prepare("@test/b:0.1.0").unwrap(), root.join("other/test/b/0.1.0"));
+        assert_eq!(prepare("@test/c:0.1.0").unwrap(), root.join("cache/test/c/0.1.0"));
+        assert_eq!(
+            prepare("@test/d:0.1.0").unwrap_err(),
+            PackageError::NotFound("@test/d:0.1.0".parse().unwrap())
+        );
+
+        // A local package must match its manifest, even if a valid copy is
+        // cached.
+        write_package(&root.join("local/test/c/0.1.0"), "c", "0.2.0");
+        let err = prepare("@test/c:0.1.0").unwrap_err().to_string();
+        assert!(
+            err.contains("package manifest contains mismatched version 0.2.0"),
+            "{err}"
+        );
+        assert!(root.join("local/test/c/0.1.0").exists());
+    }
+
+    #[test]
+    fn test_prepare_mirror() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let mirror = root.join("mirror");
+        let args = PackageStorageArgs {
+            package_mirror: Some(mirror.clone()),
+            ..storage(root)
+        };
+        let storage = PackageStorage::new(&args);
+        let prepare = |spec: &str| storage.prepare_package(&spec.parse().unwrap());
+
+        let source = root.join("source");
+        write_package(&source, "a", "0.1.0");
+        fs::create_dir_all(mirror.join("test")).unwrap();
+        fs::write(mirror.join("test/a-0.1.0.tar.gz"), pack(&source)).unwrap();
+
+        // The archive is unpacked into the cache.
+        let dir = prepare("@test/a:0.1.0").unwrap();
+        assert_eq!(dir, root.join("cache/test/a/0.1.0"));
+        assert_eq!(fs::read_to_string(dir.join("lib.typ")).unwrap(), "#let x = 1");
+        assert_eq!(listed(root), [("@test/a:0.1.0".into(), "cached")]);
+
+        // Packages that the mirror lacks are not found.
+        assert_eq!(
+            prepare("@test/b:0.1.0").unwrap_err(),
+            PackageError::NotFound("@test/b:0.1.0".parse().unwrap())
+        );
+
+        // An archive whose manifest doesn't match is rejected and not kept in
+        // the cache.
+        write_package(&source, "c", "0.1.0");
+        fs::write(mirror.join("test/b-0.1.0.tar.gz"), pack(&source)).unwrap();
+        let err = prepare("@test/b:0.1.0").unwrap_err().to_string();
+        assert!(err.contains("package manifest contains mismatched name `c`"), "{err}");
+        assert!(!root.join("cache/test/b/0.1.0").exists());
+
+        // So is a malformed archive.
+        fs::write(mirror.join("test/d-0.1.0.tar.gz"), b"not an archive").unwrap();
+        assert!(matches!(
+            prepare("@test/d:0.1.0").unwrap_err(),
+            PackageError::MalformedArchive(_)
+        ));
+        assert!(!root.join("cache/test/d/0.1.0").exists());
+    }
+}
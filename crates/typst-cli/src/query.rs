@@ -9,7 +9,7 @@ use typst::syntax::Span;
 use typst::World;
 
 use crate::args::{QueryCommand, SerializationFormat};
-use crate::compile::print_diagnostics;
+use crate::compile::{deny_warnings, print_diagnostics};
 use crate::set_failed;
 use crate::world::SystemWorld;
 
@@ -23,7 +23,7 @@ pub fn query(command: &QueryCommand) -> StrResult<()> {
 
     let mut tracer = Tracer::new();
     let result = typst::compile(&world, &mut tracer);
-    let warnings = tracer.warnings();
+    let (result, warnings) = deny_warnings(&command.common, result, tracer.warnings());
 
     match result {
         // Retrieve and print query results.
@@ -0,0 +1,609 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use codespan_reporting::files::Files;
+use ecow::eco_format;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use typst::diag::StrResult;
+use typst::layout::{Abs, Point};
+use typst::model::Document;
+use typst::World;
+use typst_ide::Jump;
+
+use crate::compile::ExportCache;
+use crate::terminal;
+use crate::world::SystemWorld;
+
+/// The GUID that the key of a WebSocket handshake is combined with.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The maximum size of the request line and headers of an HTTP request.
+const MAX_HEAD_LEN: u64 = 16 * 1024;
+
+/// The maximum size of a message a preview client may send.
+const MAX_MESSAGE_LEN: u64 = 64 * 1024;
+
+/// How many frames may be queued for a client before it is considered too
+/// slow and dropped.
+const MAX_QUEUED_FRAMES: usize = 16;
+
+/// How long writing to a client may block before it is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serves a live preview of the watched document.
+///
+/// The preview page connects through a WebSocket, over which the server
+/// pushes the pages that changed after each compilation as SVGs and the
+/// client reports clicks, which are resolved to source locations.
+pub struct Server {
+    shared: Arc<Shared>,
+}
+
+/// The state shared between the watch loop and the connections.
+struct Shared {
+    /// A world that shares its files with the watched one, used to resolve
+    /// clicks into source locations.
+    world: SystemWorld,
+    /// The preview's mutable state.
+    state: Mutex<State>,
+}
+
+/// The mutable state of a preview.
+struct State {
+    /// The last document that compiled successfully.
+    document: Option<Document>,
+    /// The SVGs of the document's pages.
+    pages: Vec<String>,
+    /// The hashes of the pages' frames, to only send pages that changed.
+    cache: ExportCache,
+    /// Whether the last compilation failed.
+    failed: bool,
+    /// The connected clients.
+    clients: Vec<Client>,
+}
+
+/// A connected preview client.
+///
+/// Frames are written on a separate thread per client, so that a slow client
+/// holds up neither the watch loop nor the other clients.
+struct Client {
+    /// Queues frames for the client's writer thread.
+    sender: SyncSender<Frame>,
+    /// The client's connection.
+    stream: TcpStream,
+}
+
+/// A WebSocket frame's opcode and payload.
+type Frame = (u8, Arc<[u8]>);
+
+/// A message from a preview client.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum ClientMessage {
+    /// The user clicked at a point (in pt) on a page (starting at zero).
+    Click { page: usize, x: f64, y: f64 },
+}
+
+impl Server {
+    /// Start serving a preview at the given address.
+    pub fn new(addr: SocketAddr, world: &SystemWorld) -> StrResult<Self> {
+        let listener = TcpListener::bind(addr)
+            .map_err(|err| eco_format!("failed to serve preview at {addr} ({err})"))?;
+
+        let shared = Arc::new(Shared {
+            world: world.snapshot(),
+            state: Mutex::new(State {
+                document: None,
+                pages: vec![],
+                cache: ExportCache::new(),
+                failed: false,
+                clients: vec![],
+            }),
+        });
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = accepting.clone();
+                thread::spawn(move || shared.handle(stream).ok());
+            }
+        });
+
+        Ok(Self { shared })
+    }
+
+    /// Push the pages that changed to the clients, or notify them that the
+    /// compilation failed if there is no new document.
+    pub fn update(&self, document: Option<Document>) {
+        let mut state = self.shared.state.lock();
+        let message = match document {
+            Some(document) => {
+                let changed = state.render(&document);
+                state.document = Some(document);
+                state.failed = false;
+                state.message(changed)
+            }
+            None => {
+                state.failed = true;
+                json!({ "type": "failed" })
+            }
+        };
+
+        state.broadcast(text_frame(&message));
+    }
+}
+
+impl Shared {
+    /// Serve a single HTTP request.
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (request, headers) = read_head(&mut reader)?;
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let mut parts = request.split_whitespace();
+        match (parts.next(), parts.next(), header("sec-websocket-key")) {
+            (Some("GET"), Some("/ws"), Some(key)) => {
+                // Only the preview page itself may connect, so that other
+                // websites can't read the document.
+                if !same_origin(header("origin"), header("host")) {
+                    return respond(
+                        &mut stream,
+                        "403 Forbidden",
+                        "text/plain",
+                        "forbidden",
+                    );
+                }
+                self.connect(stream, reader, key)
+            }
+            (Some("GET"), Some("/"), _) => {
+                respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE)
+            }
+            _ => respond(&mut stream, "404 Not Found", "text/plain", "not found"),
+        }
+    }
+
+    /// Complete a WebSocket handshake and handle the client's messages until
+    /// it disconnects.
+    fn connect(
+        &self,
+        mut stream: TcpStream,
+        mut reader: BufReader<TcpStream>,
+        key: &str,
+    ) -> io::Result<()> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let accept = accept_key(key);
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {accept}\r\n\r\n"
+        )?;
+
+        let client = Client::spawn(stream.try_clone()?)?;
+        let sender = client.sender.clone();
+
+        // Queue the current state and register the client for updates. This
+        // happens under the lock so that no broadcast is queued before it.
+        {
+            let mut state = self.state.lock();
+            let all = (0..state.pages.len()).collect();
+            let mut frames = vec![text_frame(&state.message(all))];
+            if state.failed {
+                frames.push(text_frame(&json!({ "type": "failed" })));
+            }
+            for frame in frames {
+                sender.send(frame).map_err(|_| disconnected())?;
+            }
+            state.clients.push(client);
+        }
+
+        let result = self.receive(&sender, &mut reader);
+
+        // Once the client is removed, its writer thread finishes writing the
+        // queued frames and closes the connection.
+        let peer = stream.peer_addr().ok();
+        self.state
+            .lock()
+            .clients
+            .retain(|client| client.stream.peer_addr().ok() != peer);
+
+        result
+    }
+
+    /// Handle a client's messages until it disconnects.
+    fn receive(
+        &self,
+        sender: &SyncSender<Frame>,
+        reader: &mut BufReader<TcpStream>,
+    ) -> io::Result<()> {
+        loop {
+            let (opcode, payload) = read_frame(reader)?;
+            let frame = match opcode {
+                OPCODE_TEXT => {
+                    let Ok(message) = serde_json::from_slice(&payload) else { continue };
+                    let Some(reply) = self.respond(message) else { continue };
+                    text_frame(&reply)
+                }
+                OPCODE_PING => (OPCODE_PONG, payload.into()),
+                OPCODE_CLOSE => {
+                    return sender
+                        .send((OPCODE_CLOSE, payload.into()))
+                        .map_err(|_| disconnected());
+                }
+                _ => continue,
+            };
+            sender.send(frame).map_err(|_| disconnected())?;
+        }
+    }
+
+    /// Determine the reply to a client's message.
+    fn respond(&self, message: ClientMessage) -> Option<Value> {
+        let ClientMessage::Click { page, x, y } = message;
+        let document = self.state.lock().document.clone()?;
+        let frame = &document.pages.get(page)?.frame;
+        let click = Point::new(Abs::pt(x), Abs::pt(y));
+
+        Some(match typst_ide::jump_from_click(&self.world, &document, frame, click)? {
+            Jump::Source(id, offset) => {
+                let source = World::source(&self.world, id).ok()?;
+                let name = self.world.name(id).ok()?;
+                let line = source.byte_to_line(offset)? + 1;
+                let column = source.byte_to_column(offset)? + 1;
+
+                let mut out = terminal::out();
+                writeln!(out, "clicked {name}:{line}:{column}").ok();
+
+                json!({ "type": "jump", "file": name, "line": line, "column": column })
+            }
+            Jump::Position(position) => json!({
+                "type": "scroll",
+                "page": position.page.get() - 1,
+                "y": position.point.y.to_pt(),
+            }),
+            Jump::Url(url) => json!({ "type": "url", "url": url.as_str() }),
+        })
+    }
+}
+
+impl State {
+    /// Render the pages whose frames changed and return their indices.
+    fn render(&mut self, document: &Document) -> Vec<usize> {
+        let mut changed = vec![];
+        for (i, page) in document.pages.iter().enumerate() {
+            // A page that was removed in between may still be in the cache.
+            let cached = self.cache.is_cached(i, &page.frame);
+            if cached && i < self.pages.len() {
+                continue;
+            }
+
+            let svg = typst_svg::svg(&page.frame);
+            if i < self.pages.len() {
+                self.pages[i] = svg;
+            } else {
+                self.pages.push(svg);
+            }
+            changed.push(i);
+        }
+
+        self.pages.truncate(document.pages.len());
+        changed
+    }
+
+    /// The update message with the given pages.
+    fn message(&self, changed: Vec<usize>) -> Value {
+        let changed: serde_json::Map<_, _> = changed
+            .into_iter()
+            .map(|i| (i.to_string(), self.pages[i].clone().into()))
+            .collect();
+        json!({ "type": "update", "pages": self.pages.len(), "changed": changed })
+    }
+
+    /// Send a frame to all clients, dropping those that disconnected or that
+    /// can't keep up.
+    fn broadcast(&mut self, frame: Frame) {
+        self.clients
+            .retain(|client| match client.sender.try_send(frame.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    client.stream.shutdown(Shutdown::Both).ok();
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+}
+
+impl Client {
+    /// Spawn a thread that writes the queued frames to the connection.
+    ///
+    /// The thread closes the connection once writing fails or the client's
+    /// senders are dropped, which also ends the client's read loop.
+    fn spawn(stream: TcpStream) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel::<Frame>(MAX_QUEUED_FRAMES);
+        let mut writer = stream.try_clone()?;
+        thread::spawn(move || {
+            for (opcode, payload) in receiver {
+                if write_frame(&mut writer, opcode, &payload).is_err() {
+                    break;
+                }
+            }
+            writer.shutdown(Shutdown::Both).ok();
+        });
+        Ok(Self { sender, stream })
+    }
+}
+
+/// A text frame with a JSON message.
+fn text_frame(message: &Value) -> Frame {
+    (OPCODE_TEXT, message.to_string().into_bytes().into())
+}
+
+/// The error for a client whose writer thread has stopped.
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected")
+}
+
+/// Write an HTTP response.
+fn respond(stream: &mut TcpStream, status: &str, ty: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {ty}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-cache\r\n\
+         Connection: close\r\n\r\n\
+         {body}",
+        body.len()
+    )
+}
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Read a WebSocket frame and return its opcode and unmasked payload.
+///
+/// Fragmented messages are not supported as the client only sends short
+/// messages.
+fn read_frame(reader: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0F;
+    let masked = head[1] & 0x80 != 0;
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut buf = [0; 2];
+            reader.read_exact(&mut buf)?;
+            u16::from_be_bytes(buf).into()
+        }
+        127 => {
+            let mut buf = [0; 8];
+            reader.read_exact(&mut buf)?;
+            u64::from_be_bytes(buf)
+        }
+        len => len.into(),
+    };
+
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message is too large"));
+    }
+
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok((opcode, payload))
+}
+
+/// Write an unfragmented, unmasked WebSocket frame.
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut head = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => head.push(len as u8),
+        len @ 126..=0xFFFF => {
+            head.push(126);
+            head.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            head.push(127);
+            head.extend((len as u64).to_be_bytes());
+        }
+    }
+
+    stream.write_all(&head)?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+/// Read the request line and headers of an HTTP request.
+fn read_head(reader: impl BufRead) -> io::Result<(String, Vec<(String, String)>)> {
+    let mut head = reader.take(MAX_HEAD_LEN);
+    let mut lines = vec![];
+    loop {
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request head is too large",
+            ));
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let mut lines = lines.into_iter();
+    let request = lines.next().unwrap_or_default();
+    let headers = lines
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect();
+
+    Ok((request, headers))
+}
+
+/// Whether a WebSocket handshake comes from a page served by the host it
+/// connects to.
+fn same_origin(origin: Option<&str>, host: Option<&str>) -> bool {
+    matches!(
+        (origin, host),
+        (Some(origin), Some(host)) if origin.strip_prefix("http://") == Some(host)
+    )
+}
+
+/// Compute the accept key of a WebSocket handshake from the client's key.
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::digest(format!("{key}{WEBSOCKET_GUID}").as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// The preview page.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Typst Preview</title>
+<style>
+  body { margin: 0; background: #e5e5e5; font-family: sans-serif }
+  #pages { display: flex; flex-direction: column; align-items: center; gap: 1rem; padding: 1rem }
+  .page { background: white; box-shadow: 0 1px 4px rgba(0, 0, 0, 0.25); cursor: crosshair; line-height: 0 }
+  .page svg { max-width: 100%; height: auto }
+  #status { position: fixed; bottom: 0; left: 0; right: 0; padding: 0.5rem 1rem; color: white; display: none }
+  #status.failed { display: block; background: #c33 }
+  #status.jump { display: block; background: #333 }
+</style>
+</head>
+<body>
+<div id="pages"></div>
+<div id="status"></div>
+<script>
+  const pages = document.getElementById("pages");
+  const status = document.getElementById("status");
+
+  function show(kind, text) {
+    status.className = kind;
+    status.textContent = text;
+  }
+
+  function connect() {
+    const socket = new WebSocket(`ws://${location.host}/ws`);
+    socket.onmessage = (event) => {
+      const message = JSON.parse(event.data);
+      if (message.type === "update") {
+        while (pages.children.length > message.pages) pages.lastChild.remove();
+        while (pages.children.length < message.pages) {
+          const page = document.createElement("div");
+          page.className = "page";
+          page.onclick = (event) => click(socket, page, event);
+          pages.appendChild(page);
+        }
+        for (const [i, svg] of Object.entries(message.changed)) {
+          pages.children[i].innerHTML = svg;
+        }
+        if (status.className === "failed") show("", "");
+      } else if (message.type === "failed") {
+        show("failed", "Compilation failed, showing the last successful version.");
+      } else if (message.type === "jump") {
+        show("jump", `${message.file}:${message.line}:${message.column}`);
+      } else if (message.type === "scroll") {
+        const page = pages.children[message.page];
+        const svg = page && page.querySelector("svg");
+        if (svg) {
+          const scale = svg.getBoundingClientRect().height / svg.viewBox.baseVal.height;
+          window.scrollTo(0, page.offsetTop + message.y * scale);
+        }
+      } else if (message.type === "url") {
+        window.open(message.url, "_blank");
+      }
+    };
+    socket.onclose = () => setTimeout(connect, 1000);
+  }
+
+  function click(socket, page, event) {
+    const svg = page.querySelector("svg");
+    if (!svg) return;
+    const rect = svg.getBoundingClientRect();
+    const box = svg.viewBox.baseVal;
+    socket.send(JSON.stringify({
+      type: "click",
+      page: Array.prototype.indexOf.call(pages.children, page),
+      x: (event.clientX - rect.left) / rect.width * box.width,
+      y: (event.clientY - rect.top) / rect.height * box.height,
+    }));
+  }
+
+  connect();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_accept() {
+        // The example from RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_read_head() {
+        let request =
+            "GET /ws HTTP/1.1\r\nHost: 127.0.0.1:3000\r\nOrigin:  http://x\r\n\r\nbody";
+        let (request, headers) = read_head(request.as_bytes()).unwrap();
+        assert_eq!(request.trim(), "GET /ws HTTP/1.1");
+        assert_eq!(
+            headers,
+            [
+                ("Host".into(), "127.0.0.1:3000".into()),
+                ("Origin".into(), "http://x".into()),
+            ]
+        );
+
+        let long = format!("GET / HTTP/1.1\r\nCookie: {}\r\n\r\n", "a".repeat(20000));
+        let err = read_head(long.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_same_origin() {
+        let host = Some("127.0.0.1:3000");
+        assert!(same_origin(Some("http://127.0.0.1:3000"), host));
+        assert!(!same_origin(Some("http://127.0.0.1:3001"), host));
+        assert!(!same_origin(Some("https://evil.example"), host));
+        assert!(!same_origin(Some("null"), host));
+        assert!(!same_origin(None, host));
+        assert!(!same_origin(Some("http://127.0.0.1:3000"), None));
+    }
+}
@@ -0,0 +1,485 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use ecow::{eco_format, EcoString};
+use tiny_skia as sk;
+use typst::diag::{bail, Severity, SourceDiagnostic, StrResult};
+use typst::eval::Tracer;
+use typst::layout::Abs;
+use typst::model::Document;
+use typst::syntax::{FileId, Source, VirtualPath};
+use typst::visualize::Color;
+use typst::{World, WorldExt};
+
+use crate::args::{DiagnosticFormat, Input, SharedArgs, TestCommand};
+use crate::set_failed;
+use crate::world::SystemWorld;
+
+/// Execute a test command.
+pub fn test(command: &TestCommand) -> StrResult<()> {
+    let package = &command.package;
+    let manifest = crate::package::parse_manifest(package)?;
+    let tests = collect(package, &command.filter)?;
+    if tests.is_empty() {
+        bail!("no tests found in {}", package.join("tests").display());
+    }
+
+    let args = SharedArgs {
+        input: Input::Path(tests[0].path.clone()),
+        root: Some(package.clone()),
+        inputs: vec![],
+        inputs_file: None,
+        font_paths: command.font_paths.clone(),
+        packages: command.packages.clone(),
+        creation_timestamp: None,
+        deny_warnings: false,
+        deny: vec![],
+        diagnostic_format: DiagnosticFormat::Human,
+    };
+
+    // Resolve imports of the package to its local directory, no matter the
+    // namespace the tests import it from.
+    let mut world = SystemWorld::new(&args)?;
+    let dir = world.root().to_path_buf();
+    world.override_package(manifest.package.name, manifest.package.version, dir);
+
+    println!("running {} test{}", tests.len(), if tests.len() == 1 { "" } else { "s" });
+
+    let mut failed = 0;
+    for test in &tests {
+        let problems = run(&mut world, command, test)?;
+        if problems.is_empty() {
+            println!("pass  {}", test.name);
+        } else {
+            failed += 1;
+            println!("FAIL  {}", test.name);
+            for problem in problems {
+                println!("  {problem}");
+            }
+        }
+    }
+
+    println!("{} passed, {failed} failed", tests.len() - failed);
+    if failed > 0 {
+        set_failed();
+    }
+
+    Ok(())
+}
+
+/// A test file of a package.
+struct Test {
+    /// The test's path relative to the tests directory, without extension.
+    name: EcoString,
+    /// The path of the test file.
+    path: PathBuf,
+    /// The path of the reference image.
+    reference: PathBuf,
+    /// Where to write the render if it doesn't match the reference.
+    live: PathBuf,
+}
+
+/// Find the tests of a package.
+fn collect(package: &Path, filter: &[String]) -> StrResult<Vec<Test>> {
+    let dir = package.join("tests");
+    if !dir.is_dir() {
+        bail!("{} does not exist", dir.display());
+    }
+
+    let mut tests = vec![];
+    let walker = walkdir::WalkDir::new(&dir).sort_by_file_name().into_iter();
+    for entry in walker.filter_entry(|entry| {
+        entry.depth() != 1 || !matches!(entry.file_name().to_str(), Some("ref" | "out"))
+    }) {
+        let entry = entry.map_err(|err| eco_format!("failed to read tests ({err})"))?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension() != Some("typ".as_ref()) {
+            continue;
+        }
+
+        let relative = path.strip_prefix(&dir).unwrap().with_extension("");
+        let name: EcoString = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+            .into();
+
+        if !filter.is_empty() && !filter.iter().any(|f| name.contains(f.as_str())) {
+            continue;
+        }
+
+        tests.push(Test {
+            name,
+            path: path.to_path_buf(),
+            reference: dir.join("ref").join(&relative).with_extension("png"),
+            live: dir.join("out").join(&relative).with_extension("png"),
+        });
+    }
+
+    Ok(tests)
+}
+
+/// Run a single test and return its problems.
+fn run(
+    world: &mut SystemWorld,
+    command: &TestCommand,
+    test: &Test,
+) -> StrResult<Vec<EcoString>> {
+    let path = test.path.canonicalize().map_err(|err| eco_format!("{err}"))?;
+    let vpath = VirtualPath::within_root(&path, world.root())
+        .ok_or("test is outside of the package")?;
+    world.set_main(FileId::new(None, vpath));
+    world.reset();
+
+    let mut problems = vec![];
+    let source = match world.source(world.main()) {
+        Ok(source) => source,
+        Err(err) => return Ok(vec![eco_format!("failed to load test ({err})")]),
+    };
+
+    let mut tracer = Tracer::new();
+    let result = typst::compile(world, &mut tracer);
+    let mut diagnostics = tracer.warnings();
+    let document = match result {
+        Ok(document) => Some(document),
+        Err(errors) => {
+            diagnostics.extend(errors);
+            None
+        }
+    };
+
+    // Match the emitted diagnostics against the annotations.
+    let mut annotations = match annotations(&source) {
+        Ok(annotations) => annotations,
+        Err(err) => return Ok(vec![err]),
+    };
+    let mut unexpected = vec![];
+    for diagnostic in diagnostics {
+        let range = diagnostic
+            .span
+            .id()
+            .filter(|&id| id == source.id())
+            .and_then(|_| source.range(diagnostic.span));
+        match annotations.iter().position(|annotation| {
+            annotation.severity == diagnostic.severity
+                && annotation.message == diagnostic.message
+                && annotation.range.as_ref().map_or(true, |r| Some(r) == range.as_ref())
+        }) {
+            Some(i) => {
+                annotations.remove(i);
+            }
+            None => unexpected.push(diagnostic),
+        }
+    }
+
+    for annotation in annotations {
+        let at = match &annotation.range {
+            Some(range) => position(&source, range.start),
+            None => EcoString::new(),
+        };
+        problems.push(eco_format!(
+            "expected {}: {}{at}",
+            severity_name(annotation.severity),
+            annotation.message,
+        ));
+    }
+
+    for diagnostic in unexpected {
+        problems.push(eco_format!(
+            "unexpected {}: {}{}",
+            severity_name(diagnostic.severity),
+            diagnostic.message,
+            location(world, &diagnostic),
+        ));
+    }
+
+    match document {
+        Some(document) => check(command, test, &document, &mut problems)?,
+        None if test.reference.exists() => {
+            problems.push(eco_format!("no document, but a reference image exists"));
+        }
+        None => {}
+    }
+
+    Ok(problems)
+}
+
+/// Where a diagnostic occurred, formatted as ` (at path:line:column)`.
+fn location(world: &SystemWorld, diagnostic: &SourceDiagnostic) -> EcoString {
+    let span = diagnostic.span;
+    let Some(source) = span.id().and_then(|id| world.source(id).ok()) else {
+        return EcoString::new();
+    };
+    let Some(range) = world.range(span) else { return EcoString::new() };
+    position(&source, range.start)
+}
+
+/// A position in a file, formatted as ` (at path:line:column)`.
+fn position(source: &Source, offset: usize) -> EcoString {
+    let line = source.byte_to_line(offset).unwrap_or(0);
+    let column = source.byte_to_column(offset).unwrap_or(0);
+    eco_format!(
+        " (at {}:{}:{})",
+        source.id().vpath().as_rootless_path().display(),
+        line + 1,
+        column + 1
+    )
+}
+
+/// Compare the render of a document with the test's reference image.
+fn check(
+    command: &TestCommand,
+    test: &Test,
+    document: &Document,
+    problems: &mut Vec<EcoString>,
+) -> StrResult<()> {
+    let pixmap = typst_render::render_merged(
+        document,
+        command.ppi / 72.0,
+        Color::WHITE,
+        Abs::pt(1.0),
+        Color::BLACK,
+    );
+
+    let reference = match fs::read(&test.reference) {
+        Ok(data) => Some(
+            sk::Pixmap::decode_png(&data)
+                .map_err(|err| eco_format!("failed to decode reference image ({err})"))?,
+        ),
+        Err(_) => None,
+    };
+
+    let matches = reference.as_ref().is_some_and(|reference| {
+        mismatched_pixels(&pixmap, reference, command.max_deviation)
+            .is_some_and(|count| count <= command.max_pixels)
+    });
+
+    if matches {
+        fs::remove_file(&test.live).ok();
+        return Ok(());
+    }
+
+    let data = pixmap
+        .encode_png()
+        .map_err(|err| eco_format!("failed to encode PNG file ({err})"))?;
+
+    if command.update {
+        write(&test.reference, &data)?;
+        fs::remove_file(&test.live).ok();
+        println!("  updated {}", test.reference.display());
+    } else {
+        write(&test.live, &data)?;
+        problems.push(match reference {
+            Some(_) => eco_format!("mismatched rendering ({})", test.live.display()),
+            None => eco_format!("missing reference image ({})", test.live.display()),
+        });
+    }
+
+    Ok(())
+}
+
+/// How many pixels of two images differ by more than the maximum deviation
+/// in any channel, or `None` if their sizes differ.
+fn mismatched_pixels(a: &sk::Pixmap, b: &sk::Pixmap, max_deviation: u8) -> Option<usize> {
+    if a.width() != b.width() || a.height() != b.height() {
+        return None;
+    }
+
+    Some(
+        a.data()
+            .chunks_exact(4)
+            .zip(b.data().chunks_exact(4))
+            .filter(|(a, b)| {
+                a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > max_deviation)
+            })
+            .count(),
+    )
+}
+
+/// Write a file, creating its parent directories.
+fn write(path: &Path, data: &[u8]) -> StrResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| {
+            eco_format!("failed to create directory {} ({err})", parent.display())
+        })?;
+    }
+
+    fs::write(path, data)
+        .map_err(|err| eco_format!("failed to write {} ({err})", path.display()))
+}
+
+/// A diagnostic a test expects.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Annotation {
+    /// Whether an error or a warning is expected.
+    severity: Severity,
+    /// The expected byte range of the diagnostic, if any.
+    range: Option<Range<usize>>,
+    /// The expected message.
+    message: EcoString,
+}
+
+/// Parse the `// Error: message` and `// Warning: message` annotations of a
+/// test.
+///
+/// Like in Typst's own test suite, the message may be preceded by a range
+/// like `6-7` or `2:3-2:20`. Its positions are `(line:)?column` pairs, with
+/// the line relative to the annotation block and defaulting to the first
+/// line after it, and with 1-based columns, counted from the back if they
+/// are negative. Without a range, the diagnostic may occur anywhere.
+fn annotations(source: &Source) -> StrResult<Vec<Annotation>> {
+    let lines: Vec<_> = source.text().lines().collect();
+    let mut annotations = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let Some(rest) = line.trim().strip_prefix("//") else { continue };
+        let Some((kind, rest)) = rest.trim().split_once(':') else { continue };
+        let severity = match kind {
+            "Error" => Severity::Error,
+            "Warning" => Severity::Warning,
+            _ => continue,
+        };
+
+        let mut rest = rest.trim();
+        let mut range = None;
+        if rest.starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            let (spec, message) = rest.split_once(' ').unwrap_or((rest, ""));
+            let block = lines[i + 1..]
+                .iter()
+                .take_while(|line| line.trim().starts_with("//"))
+                .count();
+            range = Some(parse_range(source, spec, i + block).ok_or_else(|| {
+                eco_format!(
+                    "malformed range in annotation (at {}:{})",
+                    source.id().vpath().as_rootless_path().display(),
+                    i + 1,
+                )
+            })?);
+            rest = message.trim();
+        }
+
+        annotations.push(Annotation { severity, range, message: rest.into() });
+    }
+
+    Ok(annotations)
+}
+
+/// Parse a `start-end` range or a single position in an annotation. Lines are
+/// relative to the given one.
+fn parse_range(source: &Source, text: &str, base: usize) -> Option<Range<usize>> {
+    let (start, rest) = parse_position(source, text, base)?;
+    let (end, rest) = match rest.strip_prefix('-') {
+        Some(rest) => parse_position(source, rest, base)?,
+        None => (start, rest),
+    };
+    rest.is_empty().then_some(start..end)
+}
+
+/// Parse a `(line:)?column` position into a byte offset and return it along
+/// with the unparsed rest of the text.
+fn parse_position<'a>(
+    source: &Source,
+    text: &'a str,
+    base: usize,
+) -> Option<(usize, &'a str)> {
+    let (first, rest) = parse_number(text)?;
+    let (delta, column, rest) = match rest.strip_prefix(':') {
+        Some(rest) => {
+            let (column, rest) = parse_number(rest)?;
+            (first, column, rest)
+        }
+        None => (1, first, rest),
+    };
+
+    let line = base.checked_add_signed(delta)?;
+    let column = if column < 0 {
+        let range = source.line_to_range(line)?;
+        source.text()[range].chars().count().saturating_add_signed(column)
+    } else {
+        usize::try_from(column).ok()?.checked_sub(1)?
+    };
+
+    Some((source.line_column_to_byte(line, column)?, rest))
+}
+
+/// Parse an optionally negative number at the start of the text.
+fn parse_number(text: &str) -> Option<(isize, &str)> {
+    let unsigned = text.strip_prefix('-').unwrap_or(text);
+    let digits = unsigned.chars().take_while(char::is_ascii_digit).count();
+    let len = text.len() - unsigned.len() + digits;
+    Some((text[..len].parse().ok()?, &text[len..]))
+}
+
+/// The name of a severity in the annotations.
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotations() {
+        let source = Source::detached(
+            "// Error: unknown variable: x\n\
+             // Warning: 2-4 a warning\n\
+             // Error: 4:-3-4:-1 an error\n\
+             #aa\n\
+             // Not: an annotation\n\
+             // Error: 1 empty\n\
+             #box[b]",
+        );
+
+        let parsed: Vec<_> = annotations(&source)
+            .unwrap()
+            .into_iter()
+            .map(|annotation| {
+                let range = annotation.range.map(|range| &source.text()[range]);
+                (annotation.severity, range, annotation.message)
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                (Severity::Error, None, "unknown variable: x".into()),
+                (Severity::Warning, Some("aa"), "a warning".into()),
+                (Severity::Error, Some("[b"), "an error".into()),
+                (Severity::Error, Some(""), "empty".into()),
+            ]
+        );
+
+        // An empty range at the start of the last line.
+        let start = source.text().len() - "#box[b]".len();
+        assert_eq!(annotations(&source).unwrap()[3].range, Some(start..start));
+    }
+
+    #[test]
+    fn test_annotations_malformed() {
+        let source = Source::detached("// Error: 2-x message\n#x");
+        assert!(annotations(&source).is_err());
+        let source = Source::detached("// Error: 5:1 beyond the end\n#x");
+        assert!(annotations(&source).is_err());
+    }
+
+    #[test]
+    fn test_mismatched_pixels() {
+        let mut a = sk::Pixmap::new(2, 2).unwrap();
+        a.fill(sk::Color::from_rgba8(100, 100, 100, 255));
+        let mut b = a.clone();
+        assert_eq!(mismatched_pixels(&a, &b, 0), Some(0));
+
+        b.pixels_mut()[0] = sk::ColorU8::from_rgba(101, 100, 100, 255).premultiply();
+        b.pixels_mut()[3] = sk::ColorU8::from_rgba(100, 100, 103, 255).premultiply();
+        assert_eq!(mismatched_pixels(&a, &b, 0), Some(2));
+        assert_eq!(mismatched_pixels(&a, &b, 1), Some(1));
+        assert_eq!(mismatched_pixels(&a, &b, 3), Some(0));
+
+        let c = sk::Pixmap::new(2, 3).unwrap();
+        assert_eq!(mismatched_pixels(&a, &c, 255), None);
+    }
+}
@@ -12,14 +12,19 @@ use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher as _};
 use same_file::is_same_file;
 use typst::diag::{bail, StrResult};
 
-use crate::args::{CompileCommand, Input, Output};
+use crate::args::{CompileCommand, DiagnosticFormat, Input, Output};
 use crate::compile::compile_once;
+use crate::server::Server;
 use crate::timings::Timer;
 use crate::world::{SystemWorld, WorldCreationError};
 use crate::{print_error, terminal};
 
 /// Execute a watching compilation command.
 pub fn watch(mut timer: Timer, mut command: CompileCommand) -> StrResult<()> {
+    if command.batch.is_some() {
+        bail!("cannot compile a batch in watch mode");
+    }
+
     let Output::Path(output) = command.output() else {
         bail!("cannot write document to stdout in watch mode");
     };
@@ -45,8 +50,15 @@ pub fn watch(mut timer: Timer, mut command: CompileCommand) -> StrResult<()> {
         }
     };
 
+    // Start serving the live preview, if requested.
+    let server = command.serve.map(|addr| Server::new(addr, &world)).transpose()?;
+
     // Perform initial compilation.
-    timer.record(&mut world, |world| compile_once(world, &mut command, true))??;
+    let document =
+        timer.record(&mut world, |world| compile_once(world, &mut command, true))??;
+    if let Some(server) = &server {
+        server.update(document);
+    }
 
     // Watch all dependencies of the initial compilation.
     watcher.update(world.dependencies())?;
@@ -60,7 +72,11 @@ pub fn watch(mut timer: Timer, mut command: CompileCommand) -> StrResult<()> {
         world.reset();
 
         // Recompile.
-        timer.record(&mut world, |world| compile_once(world, &mut command, true))??;
+        let document = timer
+            .record(&mut world, |world| compile_once(world, &mut command, true))??;
+        if let Some(server) = &server {
+            server.update(document);
+        }
 
         // Evict the cache.
         comemo::evict(10);
@@ -267,6 +283,14 @@ pub enum Status {
 impl Status {
     /// Clear the terminal and render the status message.
     pub fn print(&self, command: &CompileCommand) -> io::Result<()> {
+        // Keep the output parseable for machine-readable diagnostics.
+        if matches!(
+            command.common.diagnostic_format,
+            DiagnosticFormat::Json | DiagnosticFormat::Sarif
+        ) {
+            return Ok(());
+        }
+
         let output = command.output();
         let timestamp = chrono::offset::Local::now().format("%H:%M:%S");
         let color = self.color();
@@ -287,6 +311,13 @@ impl Status {
         out.reset()?;
         writeln!(out, " {output}")?;
 
+        if let Some(addr) = &command.serve {
+            out.set_color(&color)?;
+            write!(out, "serving at")?;
+            out.reset()?;
+            writeln!(out, " http://{addr}")?;
+        }
+
         writeln!(out)?;
         writeln!(out, "[{timestamp}] {}", self.message())?;
         writeln!(out)?;
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 use std::{fmt, fs, io, mem};
 
 use chrono::{DateTime, Datelike, FixedOffset, Local, Utc};
@@ -9,16 +9,19 @@ use comemo::Prehashed;
 use ecow::{eco_format, EcoString};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
-use typst::diag::{FileError, FileResult};
-use typst::foundations::{Bytes, Datetime, Dict, IntoValue};
-use typst::syntax::{FileId, Source, VirtualPath};
+use typst::diag::{bail, FileError, FileResult, StrResult};
+use typst::foundations::{Bytes, Datetime, Dict, IntoValue, Value};
+use typst::loading::{json, toml, yaml, Readable};
+use typst::syntax::package::PackageVersion;
+use typst::syntax::{FileId, Source, Span, Spanned, VirtualPath};
 use typst::text::{Font, FontBook};
 use typst::{Library, World};
 use typst_timing::{timed, TimingScope};
 
 use crate::args::{Input, SharedArgs};
-use crate::compile::ExportCache;
+use crate::compile::{is_warning, warnings, ExportCache};
 use crate::fonts::{FontSearcher, FontSlot};
+use crate::package::PackageStorage;
 
 /// Static `FileId` allocated for stdin.
 /// This is to ensure that a file is read in the correct way.
@@ -33,14 +36,18 @@ pub struct SystemWorld {
     root: PathBuf,
     /// The input path.
     main: FileId,
+    /// The values visible through `sys.inputs`.
+    inputs: Dict,
     /// Typst's standard library.
     library: Prehashed<Library>,
     /// Metadata about discovered fonts.
-    book: Prehashed<FontBook>,
+    book: Arc<Prehashed<FontBook>>,
     /// Locations of and storage for lazily loaded fonts.
-    fonts: Vec<FontSlot>,
+    fonts: Arc<Vec<FontSlot>>,
     /// Maps file ids to source files and buffers.
-    slots: Mutex<HashMap<FileId, FileSlot>>,
+    slots: Arc<Mutex<HashMap<FileId, FileSlot>>>,
+    /// Where packages are looked up.
+    packages: PackageStorage,
     /// The current datetime if requested. This is stored here to ensure it is
     /// always the same within one compilation.
     /// Reset between compilations if not [`Now::Fixed`].
@@ -53,6 +60,10 @@ pub struct SystemWorld {
 impl SystemWorld {
     /// Create a new system world.
     pub fn new(command: &SharedArgs) -> Result<Self, WorldCreationError> {
+        if let Some(id) = command.deny.iter().find(|id| !is_warning(id)) {
+            return Err(WorldCreationError::UnknownWarning(id.as_str().into()));
+        }
+
         // Resolve the system-global input path.
         let input = match &command.input {
             Input::Stdin => None,
@@ -91,16 +102,10 @@ impl SystemWorld {
             *STDIN_ID
         };
 
-        let library = {
-            // Convert the input pairs to a dictionary.
-            let inputs: Dict = command
-                .inputs
-                .iter()
-                .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
-                .collect();
-
-            Library::builder().with_inputs(inputs).build()
-        };
+        let inputs = load_inputs(command)?;
+        let mut packages = PackageStorage::new(&command.packages);
+        packages.lock(&root);
+        let library = Library::builder().with_inputs(inputs.clone()).build();
 
         let mut searcher = FontSearcher::new();
         searcher.search(&command.font_paths);
@@ -114,10 +119,12 @@ impl SystemWorld {
             workdir: std::env::current_dir().ok(),
             root,
             main,
+            inputs,
             library: Prehashed::new(library),
-            book: Prehashed::new(searcher.book),
-            fonts: searcher.fonts,
-            slots: Mutex::new(HashMap::new()),
+            book: Arc::new(Prehashed::new(searcher.book)),
+            fonts: Arc::new(searcher.fonts),
+            slots: Arc::new(Mutex::new(HashMap::new())),
+            packages,
             now,
             export_cache: ExportCache::new(),
         })
@@ -128,6 +135,59 @@ impl SystemWorld {
         self.main
     }
 
+    /// Create a world with additional `sys.inputs` that shares its fonts and
+    /// files with this one.
+    pub fn with_inputs(&self, additional: Dict) -> Self {
+        let mut inputs = self.inputs.clone();
+        for (key, value) in additional {
+            inputs.insert(key, value);
+        }
+
+        Self {
+            workdir: self.workdir.clone(),
+            root: self.root.clone(),
+            main: self.main,
+            library: Prehashed::new(
+                Library::builder().with_inputs(inputs.clone()).build(),
+            ),
+            inputs,
+            book: self.book.clone(),
+            fonts: self.fonts.clone(),
+            slots: self.slots.clone(),
+            packages: self.packages.clone(),
+            now: match &self.now {
+                Now::Fixed(time) => Now::Fixed(*time),
+                Now::System(_) => Now::System(OnceLock::new()),
+            },
+            export_cache: ExportCache::new(),
+        }
+    }
+
+    /// Create a world that shares its inputs, fonts, and files with this one,
+    /// e.g. to look up sources on another thread.
+    pub fn snapshot(&self) -> Self {
+        self.with_inputs(Dict::new())
+    }
+
+    /// Change the main source file, e.g. to compile several files with the
+    /// same fonts and caches.
+    pub fn set_main(&mut self, main: FileId) {
+        self.main = main;
+    }
+
+    /// Read the files of the package with the given name and version from a
+    /// local directory instead of looking it up, no matter the namespace it
+    /// is imported from.
+    pub fn override_package(
+        &mut self,
+        name: EcoString,
+        version: PackageVersion,
+        dir: PathBuf,
+    ) {
+        self.packages.override_package(name, version, dir);
+        self.slots.lock().clear();
+    }
+
     /// The root relative to which absolute paths are resolved.
     pub fn root(&self) -> &Path {
         &self.root
@@ -138,18 +198,27 @@ impl SystemWorld {
         self.workdir.as_deref().unwrap_or(Path::new("."))
     }
 
+    /// Resolves the path of a file on the system.
+    pub fn path(&self, id: FileId) -> FileResult<PathBuf> {
+        system_path(&self.root, &self.packages, id)
+    }
+
     /// Return all paths the last compilation depended on.
     pub fn dependencies(&mut self) -> impl Iterator<Item = PathBuf> + '_ {
-        self.slots
-            .get_mut()
+        let ids: Vec<_> = self
+            .slots
+            .lock()
             .values()
             .filter(|slot| slot.accessed())
-            .filter_map(|slot| system_path(&self.root, slot.id).ok())
+            .map(|slot| slot.id)
+            .collect();
+        ids.into_iter()
+            .filter_map(|id| system_path(&self.root, &self.packages, id).ok())
     }
 
     /// Reset the compilation state in preparation of a new compilation.
     pub fn reset(&mut self) {
-        for slot in self.slots.get_mut().values_mut() {
+        for slot in self.slots.lock().values_mut() {
             slot.reset();
         }
         if let Now::System(time_lock) = &mut self.now {
@@ -183,11 +252,11 @@ impl World for SystemWorld {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.slot(id, |slot| slot.source(&self.root))
+        self.slot(id, |slot| slot.source(&self.root, &self.packages))
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.slot(id, |slot| slot.file(&self.root))
+        self.slot(id, |slot| slot.file(&self.root, &self.packages))
     }
 
     fn font(&self, index: usize) -> Option<Font> {
@@ -259,9 +328,13 @@ impl FileSlot {
     }
 
     /// Retrieve the source for this file.
-    fn source(&mut self, project_root: &Path) -> FileResult<Source> {
+    fn source(
+        &mut self,
+        project_root: &Path,
+        packages: &PackageStorage,
+    ) -> FileResult<Source> {
         self.source.get_or_init(
-            || read(self.id, project_root),
+            || read(self.id, project_root, packages),
             |data, prev| {
                 let name = if prev.is_some() { "reparsing file" } else { "parsing file" };
                 let _scope = TimingScope::new(name, None);
@@ -277,9 +350,15 @@ impl FileSlot {
     }
 
     /// Retrieve the file's bytes.
-    fn file(&mut self, project_root: &Path) -> FileResult<Bytes> {
-        self.file
-            .get_or_init(|| read(self.id, project_root), |data, _| Ok(data.into()))
+    fn file(
+        &mut self,
+        project_root: &Path,
+        packages: &PackageStorage,
+    ) -> FileResult<Bytes> {
+        self.file.get_or_init(
+            || read(self.id, project_root, packages),
+            |data, _| Ok(data.into()),
+        )
     }
 }
 
@@ -344,13 +423,17 @@ impl<T: Clone> SlotCell<T> {
 
 /// Resolves the path of a file id on the system, downloading a package if
 /// necessary.
-fn system_path(project_root: &Path, id: FileId) -> FileResult<PathBuf> {
+fn system_path(
+    project_root: &Path,
+    packages: &PackageStorage,
+    id: FileId,
+) -> FileResult<PathBuf> {
     // Determine the root path relative to which the file path
     // will be resolved.
     let buf;
     let mut root = project_root;
     if let Some(spec) = id.package() {
-        buf = crate::package::prepare_package(spec)?;
+        buf = packages.prepare_package(spec)?;
         root = &buf;
     }
 
@@ -363,11 +446,15 @@ fn system_path(project_root: &Path, id: FileId) -> FileResult<PathBuf> {
 ///
 /// If the ID represents stdin it will read from standard input,
 /// otherwise it gets the file path of the ID and reads the file from disk.
-fn read(id: FileId, project_root: &Path) -> FileResult<Vec<u8>> {
+fn read(
+    id: FileId,
+    project_root: &Path,
+    packages: &PackageStorage,
+) -> FileResult<Vec<u8>> {
     if id == *STDIN_ID {
         read_from_stdin()
     } else {
-        read_from_disk(&system_path(project_root, id)?)
+        read_from_disk(&system_path(project_root, packages, id)?)
     }
 }
 
@@ -393,6 +480,80 @@ fn read_from_stdin() -> FileResult<Vec<u8>> {
     Ok(buf)
 }
 
+/// Load the values of `sys.inputs` from the inputs file and the input pairs.
+fn load_inputs(command: &SharedArgs) -> Result<Dict, WorldCreationError> {
+    let mut inputs = match &command.inputs_file {
+        Some(file) => load_inputs_file(command, file)
+            .map_err(|err| WorldCreationError::Inputs(eco_format!("{err}")))?,
+        None => Dict::new(),
+    };
+
+    for (key, value) in &command.inputs {
+        inputs.insert(key.as_str().into(), value.as_str().into_value());
+    }
+
+    Ok(inputs)
+}
+
+/// Read and decode the dictionary of an inputs file.
+fn load_inputs_file(command: &SharedArgs, file: &Input) -> StrResult<Dict> {
+    match file {
+        Input::Stdin if matches!(command.input, Input::Stdin) => {
+            bail!("cannot read both the input and the inputs file from stdin")
+        }
+        Input::Stdin => decode_inputs(read_from_stdin()?, None),
+        Input::Path(path) => {
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            decode_inputs(read_from_disk(path)?, extension.map(str::to_lowercase))
+        }
+    }
+}
+
+/// Decode the dictionary of an inputs file with the given extension.
+///
+/// Data without an extension, like that from stdin, is decoded as YAML, which
+/// is a superset of JSON.
+fn decode_inputs(data: Vec<u8>, extension: Option<String>) -> StrResult<Dict> {
+    let data = Spanned::new(Readable::Bytes(data.into()), Span::detached());
+    let value = match extension.as_deref() {
+        Some("json") => json::decode(data),
+        Some("yaml" | "yml") | None => yaml::decode(data),
+        Some("toml") => toml::decode(data),
+        Some(_) => bail!("inputs file must be a JSON, YAML, or TOML file"),
+    }
+    .map_err(|errors| {
+        errors
+            .into_iter()
+            .next()
+            .map(|error| error.message)
+            .unwrap_or_default()
+    })?;
+
+    match with_datetimes(value) {
+        Value::Dict(dict) => Ok(dict),
+        value => bail!("inputs file must contain a dictionary, not {}", value.ty()),
+    }
+}
+
+/// Turn strings in ISO 8601 format into datetimes.
+pub fn with_datetimes(value: Value) -> Value {
+    match value {
+        Value::Str(string) => match Datetime::from_iso(&string) {
+            Some(datetime) => Value::Datetime(datetime),
+            None => Value::Str(string),
+        },
+        Value::Array(array) => {
+            Value::Array(array.into_iter().map(with_datetimes).collect())
+        }
+        Value::Dict(dict) => Value::Dict(
+            dict.into_iter()
+                .map(|(key, value)| (key, with_datetimes(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
 /// Decode UTF-8 with an optional BOM.
 fn decode_utf8(buf: &[u8]) -> FileResult<&str> {
     // Remove UTF-8 BOM.
@@ -417,6 +578,10 @@ pub enum WorldCreationError {
     InputOutsideRoot,
     /// The root directory does not appear to exist.
     RootNotFound(PathBuf),
+    /// The inputs file could not be loaded.
+    Inputs(EcoString),
+    /// A warning that doesn't exist was denied.
+    UnknownWarning(EcoString),
     /// Another type of I/O error.
     Io(io::Error),
 }
@@ -433,6 +598,17 @@ impl fmt::Display for WorldCreationError {
             WorldCreationError::RootNotFound(path) => {
                 write!(f, "root directory not found (searched at {})", path.display())
             }
+            WorldCreationError::Inputs(err) => {
+                write!(f, "failed to load inputs file ({err})")
+            }
+            WorldCreationError::UnknownWarning(id) => {
+                let known: Vec<_> = warnings().collect();
+                write!(
+                    f,
+                    "cannot deny unknown warning `{id}` (known warnings are {})",
+                    known.join(", "),
+                )
+            }
             WorldCreationError::Io(err) => write!(f, "{err}"),
         }
     }
@@ -443,3 +619,98 @@ impl From<WorldCreationError> for EcoString {
         eco_format!("{err}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::args::CompileCommand;
+
+    /// Decode inputs with the given extension.
+    fn decode(data: &str, extension: Option<&str>) -> StrResult<Dict> {
+        decode_inputs(data.as_bytes().to_vec(), extension.map(Into::into))
+    }
+
+    #[test]
+    fn test_decode_inputs() {
+        let date = Value::Datetime(Datetime::from_ymd(2024, 5, 1).unwrap());
+        for (data, extension) in [
+            (r#"{"name": "A", "n": 3, "date": "2024-05-01"}"#, Some("json")),
+            ("name: A\nn: 3\ndate: 2024-05-01\n", Some("yaml")),
+            ("name: A\nn: 3\ndate: 2024-05-01\n", Some("yml")),
+            ("name = \"A\"\nn = 3\ndate = \"2024-05-01\"\n", Some("toml")),
+            // Data from stdin is decoded as YAML, which also covers JSON.
+            ("name: A\nn: 3\ndate: 2024-05-01\n", None),
+            (r#"{"name": "A", "n": 3, "date": "2024-05-01"}"#, None),
+        ] {
+            let inputs = decode(data, extension).unwrap();
+            assert_eq!(inputs.get("name").unwrap(), &Value::Str("A".into()));
+            assert_eq!(inputs.get("n").unwrap(), &Value::Int(3));
+            assert_eq!(inputs.get("date").unwrap(), &date, "{extension:?}");
+        }
+    }
+
+    #[test]
+    fn test_decode_inputs_datetimes() {
+        let inputs = decode(
+            r#"{"list": ["2024-05-01", "May"], "nested": {"at": "2024-05-01T12:30:00"}}"#,
+            Some("json"),
+        )
+        .unwrap();
+
+        let date = Value::Datetime(Datetime::from_ymd(2024, 5, 1).unwrap());
+        let Value::Array(list) = inputs.get("list").unwrap() else { panic!() };
+        assert_eq!(list.as_slice(), [date, Value::Str("May".into())]);
+
+        let Value::Dict(nested) = inputs.get("nested").unwrap() else { panic!() };
+        assert_eq!(
+            nested.get("at").unwrap(),
+            &Value::Datetime(Datetime::from_ymd_hms(2024, 5, 1, 12, 30, 0).unwrap()),
+        );
+    }
+
+    #[test]
+    fn test_decode_inputs_invalid() {
+        assert_eq!(
+            decode("[1, 2]", Some("json")).unwrap_err(),
+            "inputs file must contain a dictionary, not array"
+        );
+        assert_eq!(
+            decode("a,b", Some("csv")).unwrap_err(),
+            "inputs file must be a JSON, YAML, or TOML file"
+        );
+        assert!(decode("{", Some("json")).is_err());
+        assert!(decode("a = ", Some("toml")).is_err());
+    }
+
+    #[test]
+    fn test_load_inputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("inputs.JSON");
+        fs::write(&file, r#"{"name": "A", "n": 3}"#).unwrap();
+
+        // Pairs given on the command line take precedence.
+        let args = ["--inputs-file", file.to_str().unwrap(), "--input", "name=B"];
+        let command =
+            CompileCommand::try_parse_from(["compile", "main.typ"].iter().chain(&args))
+                .unwrap();
+        let inputs = load_inputs(&command.common).unwrap();
+        assert_eq!(inputs.get("name").unwrap(), &Value::Str("B".into()));
+        assert_eq!(inputs.get("n").unwrap(), &Value::Int(3));
+
+        // Stdin can only be read once.
+        let command = CompileCommand::try_parse_from([
+            "compile",
+            "-",
+            "out.pdf",
+            "--inputs-file",
+            "-",
+        ])
+        .unwrap();
+        assert_eq!(
+            load_inputs_file(&command.common, &Input::Stdin).unwrap_err(),
+            "cannot read both the input and the inputs file from stdin"
+        );
+    }
+}
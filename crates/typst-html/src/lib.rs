@@ -0,0 +1,752 @@
+//! Exporting of Typst documents into HTML.
+//!
+//! Unlike the other exporters, this one does not operate on the laid-out
+//! frames of a document, but on its content tree. Elements with a semantic
+//! counterpart in HTML are mapped to the corresponding tags. Everything else
+//! is laid out on its own and embedded as an inline SVG.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::mem;
+
+use comemo::Track;
+use ecow::EcoString;
+use typst::diag::{bail, SourceResult};
+use typst::engine::{Engine, Route};
+use typst::eval::Tracer;
+use typst::foundations::{
+    Content, Label, NativeElement, Packed, Selector, SequenceElem, Smart, StyleChain,
+    StyledElem,
+};
+use typst::introspection::{Locator, Meta, MetaElem};
+use typst::layout::{
+    Abs, Axes, BoxElem, Frame, FrameItem, HElem, LayoutMultiple, PageElem, PagebreakElem,
+    Regions, ResolvableCell, Size, VElem,
+};
+use typst::math::EquationElem;
+use typst::model::{
+    Destination, Document, EmphElem, EnumElem, EnumItem, FigureElem, FootnoteBody,
+    FootnoteElem, HeadingElem, LinkElem, LinkTarget, ListItem, ParElem, ParbreakElem,
+    StrongElem, TableChild, TableElem, TableItem, TermItem,
+};
+use typst::realize::process;
+use typst::text::{
+    LinebreakElem, RawContent, RawElem, SmartQuoteElem, SmartQuoter, SmartQuotes,
+    SpaceElem, TextElem,
+};
+use typst::util::Numeric;
+use typst::World;
+
+/// Export a document into an HTML file.
+///
+/// The content of the document's main file is evaluated once more (which is
+/// cheap because evaluation is memoized) and then exported. The `document`
+/// from a previous compilation is needed to resolve introspection, e.g. for
+/// references and counters.
+#[typst_macros::time(name = "html")]
+pub fn html(
+    world: &dyn World,
+    document: &Document,
+    tracer: &mut Tracer,
+) -> SourceResult<String> {
+    let world = world.track();
+    let module = typst::eval::eval(
+        world,
+        Route::default().track(),
+        tracer.track_mut(),
+        &world.main(),
+    )?;
+
+    let mut locator = Locator::new();
+    let mut engine = Engine {
+        world,
+        introspector: document.introspector.track(),
+        route: Route::default(),
+        locator: &mut locator,
+        tracer: tracer.track_mut(),
+    };
+
+    let library = world.library();
+    let styles = StyleChain::new(&library.styles);
+    let content = module.content();
+
+    let mut exporter = HtmlExporter::new(&mut engine);
+    exporter.accept(&content, styles)?;
+    exporter.finish_block();
+
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n",
+    );
+    if let Some(title) = &document.title {
+        writeln!(html, "<title>{}</title>", escape(title)).unwrap();
+    }
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&exporter.html);
+    if !exporter.footnotes.is_empty() {
+        html.push_str("<section class=\"footnotes\">\n<ol>\n");
+        for (i, note) in exporter.footnotes.iter().enumerate() {
+            let n = i + 1;
+            writeln!(
+                html,
+                "<li id=\"footnote-{n}\">{note} <a href=\"#footnote-ref-{n}\">↩</a></li>"
+            )
+            .unwrap();
+        }
+        html.push_str("</ol>\n</section>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+/// Builds HTML from content.
+struct HtmlExporter<'a, 'e> {
+    /// The engine used for realization and fallback layout.
+    engine: &'a mut Engine<'e>,
+    /// The finished block-level HTML.
+    html: String,
+    /// The inline HTML of the paragraph that is currently being built.
+    par: String,
+    /// The list whose items are currently being collected, if any.
+    list: Option<ListKind>,
+    /// The bodies of all footnotes so far.
+    footnotes: Vec<String>,
+    /// Maps labelled footnotes to their numbers.
+    footnote_labels: HashMap<Label, usize>,
+    /// Substitutes smart quotes in the current paragraph.
+    quoter: SmartQuoter,
+    /// A smart quote that is waiting for the character following it.
+    quote: Option<PendingQuote>,
+}
+
+/// A smart quote whose substitution depends on what follows it.
+struct PendingQuote {
+    /// Whether this is a double quote.
+    double: bool,
+    /// The opening quote for the quote's language and region.
+    open: EcoString,
+    /// The closing quote for the quote's language and region.
+    close: EcoString,
+}
+
+/// The kind of a list that is being built from consecutive items.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ListKind {
+    Bullet,
+    Numbered,
+    Terms,
+}
+
+impl ListKind {
+    /// The HTML tag for this kind of list.
+    fn tag(self) -> &'static str {
+        match self {
+            Self::Bullet => "ul",
+            Self::Numbered => "ol",
+            Self::Terms => "dl",
+        }
+    }
+}
+
+impl<'a, 'e> HtmlExporter<'a, 'e> {
+    /// Create a new exporter.
+    fn new(engine: &'a mut Engine<'e>) -> Self {
+        Self {
+            engine,
+            html: String::new(),
+            par: String::new(),
+            list: None,
+            footnotes: vec![],
+            footnote_labels: HashMap::new(),
+            quoter: SmartQuoter::new(),
+            quote: None,
+        }
+    }
+
+    /// Export a piece of content.
+    fn accept(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        if let Some(styled) = content.to_packed::<StyledElem>() {
+            let outer = styles;
+            let styles = outer.chain(styled.styles());
+
+            // Content linked through metadata, e.g. by references.
+            let local = StyleChain::new(styled.styles());
+            if let Some(dest) =
+                MetaElem::data_in(local).iter().find_map(|meta| match meta {
+                    Meta::Link(dest) => Some(dest.clone()),
+                    _ => None,
+                })
+            {
+                let body = self.nested(styled.child(), styles, false)?;
+                self.end_list();
+                self.write_link(self.href(&dest), &body);
+                return Ok(());
+            }
+
+            return self.accept(styled.child(), styles);
+        }
+
+        if let Some(sequence) = content.to_packed::<SequenceElem>() {
+            for child in sequence.children() {
+                self.accept(child, styles)?;
+            }
+            return Ok(());
+        }
+
+        // A pending smart quote is resolved by the character following it, just
+        // like in paragraph layout.
+        if self.quote.is_some() && !content.is::<MetaElem>() {
+            let peeked = if let Some(elem) = content.to_packed::<TextElem>() {
+                elem.text().chars().next()
+            } else if content.is::<SmartQuoteElem>() {
+                Some('"')
+            } else if content.is::<SpaceElem>()
+                || content.is::<HElem>()
+                || content.is::<LinebreakElem>()
+            {
+                Some(' ')
+            } else if content.is::<ParbreakElem>()
+                || content.is::<VElem>()
+                || content.is::<PagebreakElem>()
+            {
+                None
+            } else {
+                Some('\u{FFFC}')
+            };
+            self.resolve_quote(peeked);
+        }
+
+        // Spaces and paragraph breaks between list items don't end the list.
+        if self.list.is_some()
+            && (content.is::<SpaceElem>() || content.is::<ParbreakElem>())
+        {
+            return Ok(());
+        }
+
+        if let Some(item) = content.to_packed::<ListItem>() {
+            let body = self.nested(item.body(), styles, false)?;
+            self.start_list(ListKind::Bullet, styles);
+            writeln!(self.html, "<li>{body}</li>").unwrap();
+            return Ok(());
+        }
+
+        if let Some(item) = content.to_packed::<EnumItem>() {
+            let body = self.nested(item.body(), styles, false)?;
+            self.start_list(ListKind::Numbered, styles);
+            match item.number(styles) {
+                Some(n) => writeln!(self.html, "<li value=\"{n}\">{body}</li>"),
+                None => writeln!(self.html, "<li>{body}</li>"),
+            }
+            .unwrap();
+            return Ok(());
+        }
+
+        if let Some(item) = content.to_packed::<TermItem>() {
+            let term = self.nested(item.term(), styles, false)?;
+            let description = self.nested(item.description(), styles, false)?;
+            self.start_list(ListKind::Terms, styles);
+            writeln!(self.html, "<dt>{term}</dt>\n<dd>{description}</dd>").unwrap();
+            return Ok(());
+        }
+
+        self.end_list();
+
+        if let Some(elem) = content.to_packed::<TextElem>() {
+            self.par.push_str(&escape(elem.text()));
+            if let Some(c) = elem.text().chars().last() {
+                self.quoter.last(c, false);
+            }
+        } else if content.is::<SpaceElem>() {
+            self.par.push(' ');
+            self.quoter.last(' ', false);
+        } else if content.is::<LinebreakElem>() {
+            self.par.push_str("<br>");
+            self.quoter.last('\n', false);
+        } else if let Some(elem) = content.to_packed::<SmartQuoteElem>() {
+            self.smart_quote(elem, styles);
+        } else if content.is::<ParbreakElem>() {
+            self.finish_par();
+        } else if content.is::<HElem>()
+            || content.is::<VElem>()
+            || content.is::<PagebreakElem>()
+            || content.is::<MetaElem>()
+        {
+            // Spacing, page breaks, and metadata have no meaning in HTML.
+        } else if let Some(elem) = content.to_packed::<StrongElem>() {
+            let body = self.nested(elem.body(), styles, false)?;
+            write!(self.par, "<strong>{body}</strong>").unwrap();
+        } else if let Some(elem) = content.to_packed::<EmphElem>() {
+            let body = self.nested(elem.body(), styles, false)?;
+            write!(self.par, "<em>{body}</em>").unwrap();
+        } else if let Some(elem) = content.to_packed::<ParElem>() {
+            self.finish_par();
+            for child in elem.children() {
+                self.accept(child, styles)?;
+            }
+            self.finish_par();
+        } else if let Some(elem) = content.to_packed::<HeadingElem>() {
+            self.heading(elem, styles)?;
+        } else if let Some(elem) = content.to_packed::<TableElem>() {
+            self.table(elem, styles)?;
+        } else if let Some(elem) = content.to_packed::<FigureElem>() {
+            self.figure(elem, styles)?;
+        } else if let Some(elem) = content.to_packed::<RawElem>() {
+            self.raw(elem, styles);
+        } else if let Some(elem) = content.to_packed::<LinkElem>() {
+            self.link(elem, styles)?;
+        } else if let Some(elem) = content.to_packed::<FootnoteElem>() {
+            self.footnote(elem, styles)?;
+        } else if let Some(realized) = process(self.engine, content, styles)? {
+            self.engine.route.increase();
+            if !self.engine.route.within(Route::MAX_SHOW_RULE_DEPTH) {
+                bail!(
+                    content.span(), "maximum show rule depth exceeded";
+                    hint: "check whether the show rule matches its own output"
+                );
+            }
+            let result = self.accept(&realized, styles);
+            self.engine.route.decrease();
+            return result;
+        } else {
+            self.fallback(content, styles)?;
+        }
+
+        Ok(())
+    }
+
+    /// Export content into a separate buffer and return the resulting HTML.
+    ///
+    /// If `pars` is false and the content is purely inline, it is not wrapped
+    /// in a paragraph.
+    fn nested(
+        &mut self,
+        content: &Content,
+        styles: StyleChain,
+        pars: bool,
+    ) -> SourceResult<String> {
+        let html = mem::take(&mut self.html);
+        let par = mem::take(&mut self.par);
+        let list = self.list.take();
+
+        self.resolve_quote(Some('\u{FFFC}'));
+        let result = self.accept(content, styles);
+        self.resolve_quote(None);
+        self.end_list();
+        if pars || !self.html.is_empty() {
+            self.finish_par();
+        }
+
+        let output = mem::take(&mut self.html) + &mem::take(&mut self.par);
+        self.html = html;
+        self.par = par;
+        self.list = list;
+
+        result.map(|()| output.trim().into())
+    }
+
+    /// Open a list of the given kind, closing any list of a different kind.
+    fn start_list(&mut self, kind: ListKind, styles: StyleChain) {
+        if self.list == Some(kind) {
+            return;
+        }
+
+        self.finish_block();
+        match kind {
+            ListKind::Numbered if EnumElem::start_in(styles) != 1 => {
+                writeln!(self.html, "<ol start=\"{}\">", EnumElem::start_in(styles))
+                    .unwrap();
+            }
+            _ => writeln!(self.html, "<{}>", kind.tag()).unwrap(),
+        }
+        self.list = Some(kind);
+    }
+
+    /// Close the currently open list, if any.
+    fn end_list(&mut self) {
+        if let Some(kind) = self.list.take() {
+            writeln!(self.html, "</{}>", kind.tag()).unwrap();
+        }
+    }
+
+    /// Start a smart quote. Unless it opens a quotation, which quote it
+    /// becomes depends on the next character, so it is resolved later.
+    fn smart_quote(&mut self, elem: &Packed<SmartQuoteElem>, styles: StyleChain) {
+        let double = elem.double(styles);
+        if !elem.enabled(styles) {
+            self.par.push_str(if double { "&quot;" } else { "&#39;" });
+            self.quoter.last(if double { '"' } else { '\'' }, true);
+            return;
+        }
+
+        let quotes = SmartQuotes::new(
+            elem.quotes(styles),
+            TextElem::lang_in(styles),
+            TextElem::region_in(styles),
+            elem.alternative(styles),
+        );
+        self.quote = Some(PendingQuote {
+            double,
+            open: quotes.open(double).into(),
+            close: quotes.close(double).into(),
+        });
+    }
+
+    /// Substitute the pending smart quote, if any, given the character
+    /// following it.
+    fn resolve_quote(&mut self, peeked: Option<char>) {
+        let Some(PendingQuote { double, open, close }) = self.quote.take() else {
+            return;
+        };
+        let quotes = SmartQuotes {
+            single_open: &open,
+            single_close: &close,
+            double_open: &open,
+            double_close: &close,
+        };
+        let quote = self.quoter.quote(&quotes, double, peeked);
+        self.par.push_str(&escape(quote));
+        if let Some(c) = quote.chars().last() {
+            self.quoter.last(c, true);
+        }
+    }
+
+    /// Finish the current paragraph, if any.
+    fn finish_par(&mut self) {
+        self.resolve_quote(None);
+        self.quoter = SmartQuoter::new();
+        let par = mem::take(&mut self.par);
+        let trimmed = par.trim();
+        if !trimmed.is_empty() {
+            writeln!(self.html, "<p>{trimmed}</p>").unwrap();
+        }
+    }
+
+    /// Finish everything that is currently open in preparation for a block
+    /// element.
+    fn finish_block(&mut self) {
+        self.end_list();
+        self.finish_par();
+    }
+
+    /// Export a heading.
+    fn heading(
+        &mut self,
+        elem: &Packed<HeadingElem>,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        self.finish_block();
+        let level = elem.resolve_level(styles).get().min(6);
+        let body = self.nested(elem.body(), styles, false)?;
+        writeln!(self.html, "<h{level}{}>{body}</h{level}>", id(elem.label())).unwrap();
+        Ok(())
+    }
+
+    /// Export a table.
+    ///
+    /// Cells are placed in row-major order, skipping positions that are
+    /// occupied by earlier cells spanning multiple rows. Cells in the table's
+    /// header become header cells.
+    fn table(
+        &mut self,
+        elem: &Packed<TableElem>,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        self.finish_block();
+        writeln!(self.html, "<table{}>", id(elem.label())).unwrap();
+
+        let mut rows = TableRows::new(elem.columns(styles).0.len().max(1));
+        for child in elem.children() {
+            let (items, section, tag) = match child {
+                TableChild::Header(header) => {
+                    (header.children().as_slice(), Some("thead"), "th")
+                }
+                TableChild::Footer(footer) => {
+                    (footer.children().as_slice(), Some("tfoot"), "td")
+                }
+                TableChild::Item(item) => (std::slice::from_ref(item), None, "td"),
+            };
+
+            if let Some(section) = section {
+                rows.close(&mut self.html);
+                writeln!(self.html, "<{section}>").unwrap();
+            }
+
+            for item in items {
+                let TableItem::Cell(cell) = item else { continue };
+                rows.advance(&mut self.html);
+
+                let colspan = cell.colspan(styles).get().min(rows.columns - rows.x);
+                let rowspan = ResolvableCell::rowspan(cell, styles).get();
+                let body = self.nested(cell.body(), styles, false)?;
+                write!(self.html, "<{tag}").unwrap();
+                if colspan > 1 {
+                    write!(self.html, " colspan=\"{colspan}\"").unwrap();
+                }
+                if rowspan > 1 {
+                    write!(self.html, " rowspan=\"{rowspan}\"").unwrap();
+                }
+                write!(self.html, ">{body}</{tag}>").unwrap();
+                rows.place(colspan, rowspan);
+            }
+
+            if let Some(section) = section {
+                rows.close(&mut self.html);
+                writeln!(self.html, "</{section}>").unwrap();
+            }
+        }
+
+        rows.close(&mut self.html);
+        writeln!(self.html, "</table>").unwrap();
+        Ok(())
+    }
+
+    /// Export a figure and its caption.
+    fn figure(
+        &mut self,
+        elem: &Packed<FigureElem>,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        self.finish_block();
+        let body = self.nested(elem.body(), styles, false)?;
+        writeln!(self.html, "<figure{}>\n{body}", id(elem.label())).unwrap();
+        if let Some(caption) = elem.caption(styles) {
+            let caption = self.nested(caption.body(), styles, false)?;
+            writeln!(self.html, "<figcaption>{caption}</figcaption>").unwrap();
+        }
+        writeln!(self.html, "</figure>").unwrap();
+        Ok(())
+    }
+
+    /// Export raw text as inline code or a preformatted code block.
+    fn raw(&mut self, elem: &Packed<RawElem>, styles: StyleChain) {
+        let text: EcoString = match elem.text() {
+            RawContent::Text(text) => text.clone(),
+            RawContent::Lines(lines) => lines
+                .iter()
+                .map(|(line, _)| line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into(),
+        };
+
+        let class = match elem.lang(styles) {
+            Some(lang) => format!(" class=\"language-{}\"", escape(lang)),
+            None => String::new(),
+        };
+
+        if elem.block(styles) {
+            self.finish_block();
+            writeln!(self.html, "<pre><code{class}>{}</code></pre>", escape(&text))
+                .unwrap();
+        } else {
+            write!(self.par, "<code{class}>{}</code>", escape(&text)).unwrap();
+        }
+    }
+
+    /// Export a link to a URL or to a labelled element.
+    fn link(&mut self, elem: &Packed<LinkElem>, styles: StyleChain) -> SourceResult<()> {
+        let href = match elem.dest() {
+            LinkTarget::Dest(dest) => self.href(dest),
+            LinkTarget::Label(label) => Some(format!("#{}", escape(label.as_str()))),
+        };
+        let body = self.nested(elem.body(), styles, false)?;
+        self.write_link(href, &body);
+        Ok(())
+    }
+
+    /// Determine the `href` for a link destination.
+    ///
+    /// Links to locations only work if the target element has a label, which
+    /// is used as its `id`.
+    fn href(&self, dest: &Destination) -> Option<String> {
+        match dest {
+            Destination::Url(url) => Some(escape(url)),
+            Destination::Location(loc) => self
+                .engine
+                .introspector
+                .query_first(&Selector::Location(*loc))
+                .and_then(|target| target.label())
+                .map(|label| format!("#{}", escape(label.as_str()))),
+            Destination::Position(_) => None,
+        }
+    }
+
+    /// Write an inline link around already exported HTML.
+    fn write_link(&mut self, href: Option<String>, body: &str) {
+        match href {
+            Some(href) => write!(self.par, "<a href=\"{href}\">{body}</a>"),
+            None => write!(self.par, "<a>{body}</a>"),
+        }
+        .unwrap();
+    }
+
+    /// Export a footnote marker and remember the note for the end of the
+    /// document.
+    fn footnote(
+        &mut self,
+        elem: &Packed<FootnoteElem>,
+        styles: StyleChain,
+    ) -> SourceResult<()> {
+        let n = match elem.body() {
+            FootnoteBody::Content(body) => {
+                let note = self.nested(body, styles, false)?;
+                self.footnotes.push(note);
+                let n = self.footnotes.len();
+                if let Some(label) = elem.label() {
+                    self.footnote_labels.insert(label, n);
+                }
+                n
+            }
+            FootnoteBody::Reference(label) => match self.footnote_labels.get(label) {
+                Some(&n) => n,
+                None => return Ok(()),
+            },
+        };
+
+        write!(
+            self.par,
+            "<sup><a id=\"footnote-ref-{n}\" href=\"#footnote-{n}\">{n}</a></sup>"
+        )
+        .unwrap();
+        Ok(())
+    }
+
+    /// Lay out content that has no HTML counterpart and embed it as SVG.
+    fn fallback(&mut self, content: &Content, styles: StyleChain) -> SourceResult<()> {
+        let width = match PageElem::width_in(styles) {
+            Smart::Custom(width) => width,
+            Smart::Auto => Abs::inf(),
+        };
+        let regions = Regions::one(Size::new(width, Abs::inf()), Axes::splat(false));
+
+        let inline = content.is::<BoxElem>()
+            || content
+                .to_packed::<EquationElem>()
+                .is_some_and(|eq| !eq.block(styles));
+
+        if inline {
+            let boxed = BoxElem::new().with_body(Some(content.clone())).pack();
+            let boxed = boxed.to_packed::<BoxElem>().unwrap();
+            let frame = boxed.layout(self.engine, styles, regions)?;
+            if !is_empty(&frame) {
+                write!(
+                    self.par,
+                    "<span class=\"typst-frame\">{}</span>",
+                    typst_svg::svg(&frame)
+                )
+                .unwrap();
+            }
+        } else {
+            let fragment = content.layout(self.engine, styles, regions)?;
+            let frames: Vec<_> = fragment.into_iter().filter(|f| !is_empty(f)).collect();
+            if !frames.is_empty() {
+                self.finish_block();
+                for frame in frames {
+                    writeln!(
+                        self.html,
+                        "<div class=\"typst-frame\">{}</div>",
+                        typst_svg::svg(&frame)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tracks the placement of cells into the rows of an HTML table.
+struct TableRows {
+    /// The number of columns.
+    columns: usize,
+    /// The column of the next cell.
+    x: usize,
+    /// Whether a row is currently open.
+    open: bool,
+    /// For each column, how many more rows are blocked by a cell spanning
+    /// multiple rows.
+    occupied: Vec<usize>,
+}
+
+impl TableRows {
+    /// Start placing cells into a table with the given number of columns.
+    fn new(columns: usize) -> Self {
+        Self {
+            columns,
+            x: 0,
+            open: false,
+            occupied: vec![0; columns],
+        }
+    }
+
+    /// Move to the next free position, opening a new row if necessary.
+    fn advance(&mut self, html: &mut String) {
+        loop {
+            if self.x == self.columns {
+                self.close(html);
+            }
+            if !self.open {
+                html.push_str("<tr>");
+                self.open = true;
+            }
+            if self.occupied[self.x] == 0 {
+                break;
+            }
+            self.occupied[self.x] -= 1;
+            self.x += 1;
+        }
+    }
+
+    /// Mark a cell as placed at the current position.
+    fn place(&mut self, colspan: usize, rowspan: usize) {
+        for blocked in &mut self.occupied[self.x..self.x + colspan] {
+            *blocked = rowspan - 1;
+        }
+        self.x += colspan;
+    }
+
+    /// Close the current row if one is open.
+    fn close(&mut self, html: &mut String) {
+        if self.open {
+            // Columns we didn't reach in this row were still blocked by it.
+            for blocked in &mut self.occupied[self.x..] {
+                *blocked = blocked.saturating_sub(1);
+            }
+            html.push_str("</tr>\n");
+            self.open = false;
+        }
+        self.x = 0;
+    }
+}
+
+/// Whether a frame has no visible contents.
+fn is_empty(frame: &Frame) -> bool {
+    frame.size().is_zero()
+        || frame.items().all(|(_, item)| matches!(item, FrameItem::Meta(..)))
+}
+
+/// Format an `id` attribute for an element's label.
+fn id(label: Option<Label>) -> String {
+    match label {
+        Some(label) => format!(" id=\"{}\"", escape(label.as_str())),
+        None => String::new(),
+    }
+}
+
+/// Escape text for use in HTML text content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
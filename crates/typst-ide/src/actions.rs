@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+use typst::diag::SourceDiagnostic;
+use typst::syntax::{Fix, Source};
+
+use crate::TextEdit;
+
+/// Collect the fixes of diagnostics that touch the given byte range of a
+/// source file, ready to be offered as code actions.
+///
+/// Fixes that no longer fit the source text, for example because it was
+/// edited since the diagnostics were produced, are left out.
+pub fn code_actions(
+    source: &Source,
+    range: Range<usize>,
+    diagnostics: &[SourceDiagnostic],
+) -> Vec<CodeAction> {
+    let mut actions: Vec<CodeAction> = vec![];
+    for diagnostic in diagnostics {
+        if diagnostic.span.id() != Some(source.id()) {
+            continue;
+        }
+
+        let Some(target) = source.range(diagnostic.span) else { continue };
+        if target.end < range.start || target.start > range.end {
+            continue;
+        }
+
+        for fix in &diagnostic.fixes {
+            let Some(edit) = resolve(source, fix) else { continue };
+            let action = CodeAction { title: fix.title.clone(), edit };
+
+            // The same problem may be reported from multiple calls.
+            if !actions.contains(&action) {
+                actions.push(action);
+            }
+        }
+    }
+    actions
+}
+
+/// A change that can be applied to resolve a diagnostic.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CodeAction {
+    /// A short description of the change.
+    pub title: EcoString,
+    /// The edit to apply.
+    pub edit: TextEdit,
+}
+
+/// Turn a fix into an edit of the source file.
+fn resolve(source: &Source, fix: &Fix) -> Option<TextEdit> {
+    if fix.span.id() != Some(source.id()) {
+        return None;
+    }
+
+    let start = source.range(fix.span)?.start;
+    let range = start + fix.range.start..start + fix.range.end;
+    let text = source.text();
+    if range.start > range.end
+        || range.end > text.len()
+        || !text.is_char_boundary(range.start)
+        || !text.is_char_boundary(range.end)
+    {
+        return None;
+    }
+
+    Some(TextEdit { id: source.id(), range, text: fix.text.clone() })
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::eval::Tracer;
+
+    use super::code_actions;
+    use crate::tests::TestWorld;
+
+    /// The titles of the code actions for a text, with the text after the
+    /// first one is applied.
+    #[track_caller]
+    fn fix(text: &str) -> (Vec<String>, String) {
+        let world = TestWorld::new(text);
+        let mut tracer = Tracer::new();
+        let errors = typst::compile(&world, &mut tracer).unwrap_err();
+        let actions = code_actions(&world.main, 0..text.len(), &errors);
+        let mut fixed = text.to_string();
+        if let Some(action) = actions.first() {
+            fixed.replace_range(action.edit.range.clone(), &action.edit.text);
+        }
+        let titles = actions.into_iter().map(|action| action.title.into()).collect();
+        (titles, fixed)
+    }
+
+    #[test]
+    fn test_code_actions_syntax() {
+        assert_eq!(
+            fix("#calc.pow(2, 3"),
+            (vec!["add missing `)`".into()], "#calc.pow(2, 3)".into())
+        );
+        assert_eq!(
+            fix("#{\n  let x = 1\n  [A] "),
+            (vec!["add missing `}`".into()], "#{\n  let x = 1\n  [A]} ".into())
+        );
+        assert_eq!(fix("*strong"), (vec!["add missing `*`".into()], "*strong*".into()));
+    }
+
+    #[test]
+    fn test_code_actions_eval() {
+        assert_eq!(
+            fix("$x = none$"),
+            (vec!["add missing `#`".into()], "$x = #none$".into())
+        );
+        assert_eq!(
+            fix("#let a = 1\n#let b = 2\n#a-b"),
+            (
+                vec!["add spaces around the minus sign".into()],
+                "#let a = 1\n#let b = 2\n#(a - b)".into()
+            )
+        );
+        assert_eq!(
+            fix("#algin(center)[A]"),
+            (vec!["replace with `align`".into()], "#align(center)[A]".into())
+        );
+        assert_eq!(
+            fix("#arrow.r"),
+            (vec!["use `sym.arrow`".into()], "#sym.arrow.r".into())
+        );
+        assert_eq!(
+            fix("#let d = (a: 1)\n#d.a()"),
+            (vec!["access the field `a`".into()], "#let d = (a: 1)\n#d.a".into())
+        );
+    }
+}
@@ -0,0 +1,637 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use ecow::EcoString;
+use typst::foundations::{Label, Value};
+use typst::model::Document;
+use typst::syntax::ast::AstNode;
+use typst::syntax::package::PackageSpec;
+use typst::syntax::{ast, FileId, LinkedNode, Side, Source, Span, SyntaxKind};
+use typst::World;
+
+use crate::analyze::{analyze_expr, analyze_import};
+
+/// How many imports to follow at most when resolving a binding.
+const MAX_IMPORT_DEPTH: usize = 16;
+
+/// Find the definition of the symbol under the cursor.
+///
+/// Identifiers resolve to the `let` binding, parameter, loop variable, or
+/// imported module item that introduces them. Labels referenced through
+/// `@label`, `<label>`, or `label("label")` resolve to the labelled element.
+///
+/// Passing a `document` (from a previous compilation) is optional, but labels
+/// can only be resolved when the document is available.
+pub fn definition(
+    world: &dyn World,
+    document: Option<&Document>,
+    source: &Source,
+    cursor: usize,
+) -> Option<Definition> {
+    let (leaf, symbol) = symbol_at(source, cursor)?;
+    match symbol {
+        Symbol::Binding(name) => {
+            let span = match resolve(world, &leaf, true)? {
+                Target::Binding(span) => span,
+                Target::Module(id) => world.source(id).ok()?.root().span(),
+            };
+            Some(Definition { name, kind: DefinitionKind::Binding, span })
+        }
+        Symbol::Label(name) => {
+            let elem =
+                document?.introspector.query_label(Label::new(name.as_str())).ok()?;
+            Some(Definition {
+                name,
+                kind: DefinitionKind::Label,
+                span: elem.span(),
+            })
+        }
+    }
+}
+
+/// Find all references to the symbol under the cursor.
+///
+/// Searches the source file and all files that are statically reachable from
+/// it or from the main file through imports and includes. The returned spans
+/// point to the identifiers, `@label` references, `<label>` markers, and
+/// `label("label")` strings that refer to the symbol, including its
+/// definition.
+pub fn references(world: &dyn World, source: &Source, cursor: usize) -> Vec<Span> {
+    let Some((leaf, symbol)) = symbol_at(source, cursor) else { return vec![] };
+    let sources = reachable(world, source);
+    let mut spans = vec![];
+
+    match symbol {
+        Symbol::Binding(name) => {
+            let Some(target) = resolve(world, &leaf, true) else { return vec![] };
+
+            // Uses of renamed imports refer to the symbol under another name.
+            let mut names = HashSet::from([name]);
+            for source in &sources {
+                visit(&LinkedNode::new(source.root()), &mut |node| {
+                    let Some(item) = node.cast::<ast::RenamedImportItem>() else {
+                        return;
+                    };
+                    let original = node.find(item.original_name().span());
+                    if original.is_some_and(|original| {
+                        resolve(world, &original, false) == Some(target)
+                    }) {
+                        names.insert(item.new_name().get().clone());
+                    }
+                });
+            }
+
+            for source in &sources {
+                visit(&LinkedNode::new(source.root()), &mut |node| {
+                    if let Some(Symbol::Binding(name)) = symbol_of(node) {
+                        if names.contains(&name)
+                            && resolve(world, node, false) == Some(target)
+                        {
+                            spans.push(node.span());
+                        }
+                    }
+                });
+            }
+        }
+        Symbol::Label(name) => {
+            for source in &sources {
+                visit(&LinkedNode::new(source.root()), &mut |node| {
+                    if symbol_of(node) == Some(Symbol::Label(name.clone())) {
+                        spans.push(node.span());
+                    }
+                });
+            }
+        }
+    }
+
+    spans
+}
+
+/// The definition of a symbol.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Definition {
+    /// The name of the symbol.
+    pub name: EcoString,
+    /// The kind of symbol.
+    pub kind: DefinitionKind,
+    /// Where the symbol is defined.
+    ///
+    /// For a binding, this is the identifier that introduces it or, for a
+    /// module, the root of the module's file. For a label, this is the
+    /// labelled element.
+    pub span: Span,
+}
+
+/// A kind of symbol that can be defined.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DefinitionKind {
+    /// A variable, function, or module binding.
+    Binding,
+    /// A label.
+    Label,
+}
+
+/// A symbol referenced in source code.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Symbol {
+    /// A binding, referenced by its name.
+    Binding(EcoString),
+    /// A label, referenced by its name.
+    Label(EcoString),
+}
+
+/// Find the symbol under the cursor.
+pub(crate) fn symbol_at(
+    source: &Source,
+    cursor: usize,
+) -> Option<(LinkedNode<'_>, Symbol)> {
+    let root = LinkedNode::new(source.root());
+    [Side::After, Side::Before].into_iter().find_map(|side| {
+        let leaf = root.leaf_at(cursor, side)?;
+        let symbol = symbol_of(&leaf)?;
+        Some((leaf, symbol))
+    })
+}
+
+/// The symbol a node refers to, if any.
+pub(crate) fn symbol_of(node: &LinkedNode) -> Option<Symbol> {
+    match node.kind() {
+        SyntaxKind::Ident | SyntaxKind::MathIdent => {
+            // The names of named arguments and dictionary keys aren't
+            // bindings.
+            if let Some(parent) = node.parent() {
+                if parent.kind() == SyntaxKind::Named
+                    && node.index() == 0
+                    && matches!(
+                        parent.parent_kind(),
+                        Some(SyntaxKind::Args | SyntaxKind::Dict)
+                    )
+                {
+                    return None;
+                }
+            }
+            Some(Symbol::Binding(node.text().clone()))
+        }
+        SyntaxKind::RefMarker => {
+            Some(Symbol::Label(node.text().trim_start_matches('@').into()))
+        }
+        SyntaxKind::Label => Some(Symbol::Label(node.cast::<ast::Label>()?.get().into())),
+        SyntaxKind::Str => {
+            // A string that is the first argument to `label`.
+            let args = node.parent()?;
+            let call = args.parent()?.cast::<ast::FuncCall>()?;
+            let ast::Expr::Ident(callee) = call.callee() else { return None };
+            let first = call.args().items().next()?;
+            if callee.as_str() != "label" || first.to_untyped() != node.get() {
+                return None;
+            }
+            Some(Symbol::Label(node.cast::<ast::Str>()?.get()))
+        }
+        _ => None,
+    }
+}
+
+/// What a binding resolves to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum Target {
+    /// A binding introduced by the identifier with the given span.
+    Binding(Span),
+    /// A module that was imported from the file with the given id.
+    Module(FileId),
+}
+
+/// Resolve an identifier to what it is bound to, following imports to the
+/// files that define the imported items.
+///
+/// When `dynamic` is true, identifiers that can't be resolved syntactically
+/// are resolved by analyzing their value.
+pub(crate) fn resolve(
+    world: &dyn World,
+    node: &LinkedNode,
+    dynamic: bool,
+) -> Option<Target> {
+    let name = node.text();
+
+    let resolved = if let Some(target) = declaration(world, node) {
+        Some(target)
+    } else if let Some(access) = node
+        .parent()
+        .filter(|parent| parent.kind() == SyntaxKind::FieldAccess && node.index() > 0)
+    {
+        // A field access into an imported module: `utils.add`.
+        let target = access.children().next()?;
+        match resolve(world, &target, false) {
+            Some(Target::Module(id)) => exported(world, id, name, 0),
+            _ => None,
+        }
+    } else {
+        lookup(world, node, name, 0)
+    };
+
+    if resolved.is_some() || !dynamic {
+        return resolved;
+    }
+
+    // Closures know where they were defined.
+    analyze_expr(world, node).into_iter().find_map(|(value, _)| {
+        let Value::Func(func) = value else { return None };
+        let span = func.span();
+        let id = span.id()?;
+        let source = world.source(id).ok()?;
+        let node = source.find(span)?;
+        let closure = node.cast::<ast::Closure>()?;
+        Some(Target::Binding(closure.name().map_or(span, |name| name.span())))
+    })
+}
+
+/// Resolve an identifier that introduces a binding itself.
+fn declaration(world: &dyn World, node: &LinkedNode) -> Option<Target> {
+    let span = node.span();
+    let mut ancestor = node.parent()?;
+    loop {
+        let idents = match ancestor.kind() {
+            SyntaxKind::LetBinding => {
+                ancestor.cast::<ast::LetBinding>()?.kind().bindings()
+            }
+            SyntaxKind::ForLoop => ancestor.cast::<ast::ForLoop>()?.pattern().bindings(),
+            SyntaxKind::Closure => {
+                ancestor.cast::<ast::Closure>()?.name().into_iter().collect()
+            }
+            SyntaxKind::Params => ancestor
+                .cast::<ast::Params>()?
+                .children()
+                .flat_map(|param| match param {
+                    ast::Param::Pos(pattern) => pattern.bindings(),
+                    ast::Param::Named(named) => vec![named.name()],
+                    ast::Param::Spread(spread) => {
+                        spread.sink_ident().into_iter().collect()
+                    }
+                })
+                .collect(),
+            SyntaxKind::ModuleImport => {
+                let import = ancestor.cast::<ast::ModuleImport>()?;
+                if import.new_name().is_some_and(|name| name.span() == span) {
+                    return Some(module(world, ancestor));
+                }
+
+                let Some(ast::Imports::Items(items)) = import.imports() else {
+                    return None;
+                };
+
+                // Both the original and the bound name of an item refer to
+                // the item in the imported module.
+                let item = items.iter().find(|item| {
+                    item.original_name().span() == span
+                        || item.bound_name().span() == span
+                })?;
+
+                return import_item(world, ancestor, item, 0);
+            }
+            SyntaxKind::Destructuring
+            | SyntaxKind::Named
+            | SyntaxKind::Spread
+            | SyntaxKind::Parenthesized
+            | SyntaxKind::ImportItems
+            | SyntaxKind::RenamedImportItem => {
+                ancestor = ancestor.parent()?;
+                continue;
+            }
+            _ => return None,
+        };
+
+        return idents
+            .iter()
+            .any(|ident| ident.span() == span)
+            .then_some(Target::Binding(span));
+    }
+}
+
+/// Find the binding that is visible at the node under the given name by
+/// walking through the enclosing scopes.
+pub(crate) fn lookup(
+    world: &dyn World,
+    node: &LinkedNode,
+    name: &str,
+    depth: usize,
+) -> Option<Target> {
+    let mut current = node.clone();
+    loop {
+        // Bindings introduced earlier in the same block.
+        let mut sibling = current.prev_sibling();
+        while let Some(prev) = sibling {
+            if let Some(target) = declared_in(world, &prev, name, depth) {
+                return Some(target);
+            }
+            sibling = prev.prev_sibling();
+        }
+
+        let parent = current.parent()?.clone();
+        match parent.kind() {
+            // Parameters and the name of a closure are visible in its body.
+            SyntaxKind::Closure => {
+                let closure = parent.cast::<ast::Closure>()?;
+                if closure.body().span() == current.span() {
+                    let params =
+                        closure.params().children().flat_map(|param| match param {
+                            ast::Param::Pos(pattern) => pattern.bindings(),
+                            ast::Param::Named(named) => vec![named.name()],
+                            ast::Param::Spread(spread) => {
+                                spread.sink_ident().into_iter().collect()
+                            }
+                        });
+
+                    if let Some(ident) = params
+                        .chain(closure.name())
+                        .rev()
+                        .find(|ident| ident.as_str() == name)
+                    {
+                        return Some(Target::Binding(ident.span()));
+                    }
+                }
+            }
+            // Loop variables are visible in the loop's body.
+            SyntaxKind::ForLoop => {
+                let for_loop = parent.cast::<ast::ForLoop>()?;
+                if for_loop.body().span() == current.span() {
+                    if let Some(ident) = for_loop
+                        .pattern()
+                        .bindings()
+                        .into_iter()
+                        .rev()
+                        .find(|ident| ident.as_str() == name)
+                    {
+                        return Some(Target::Binding(ident.span()));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        current = parent;
+    }
+}
+
+/// Resolve the binding with the given name if the node introduces it.
+fn declared_in(
+    world: &dyn World,
+    node: &LinkedNode,
+    name: &str,
+    depth: usize,
+) -> Option<Target> {
+    match node.kind() {
+        SyntaxKind::LetBinding => {
+            let binding = node.cast::<ast::LetBinding>()?;
+            let ident = binding
+                .kind()
+                .bindings()
+                .into_iter()
+                .rev()
+                .find(|ident| ident.as_str() == name)?;
+            Some(Target::Binding(ident.span()))
+        }
+        SyntaxKind::ModuleImport => {
+            if depth >= MAX_IMPORT_DEPTH {
+                return None;
+            }
+
+            let import = node.cast::<ast::ModuleImport>()?;
+            match import.imports() {
+                Some(ast::Imports::Items(items)) => {
+                    if let Some(item) =
+                        items.iter().rev().find(|item| item.bound_name().as_str() == name)
+                    {
+                        return import_item(world, node, item, depth);
+                    }
+                }
+                Some(ast::Imports::Wildcard) => {
+                    let id = import_file(world, node)?;
+                    if let Some(target) = exported(world, id, name, depth + 1) {
+                        return Some(target);
+                    }
+                }
+                None => {}
+            }
+
+            // The module itself is bound to its new name or, if the import
+            // neither renames it nor imports items from it, to its name.
+            let bound = match import.new_name() {
+                Some(new_name) => Some(new_name.get().clone()),
+                None if import.imports().is_none() => module_name(import.source()),
+                None => None,
+            };
+
+            (bound.as_deref() == Some(name)).then(|| module(world, node))
+        }
+        _ => None,
+    }
+}
+
+/// Resolve an imported item to its binding in the imported module, falling
+/// back to the item itself if the module has no source file.
+fn import_item(
+    world: &dyn World,
+    import: &LinkedNode,
+    item: ast::ImportItem,
+    depth: usize,
+) -> Option<Target> {
+    let fallback = Target::Binding(item.bound_name().span());
+    let Some(id) = import_file(world, import) else { return Some(fallback) };
+    let name = item.original_name();
+    Some(exported(world, id, name.as_str(), depth + 1).unwrap_or(fallback))
+}
+
+/// Resolve an import of a whole module.
+fn module(world: &dyn World, import: &LinkedNode) -> Target {
+    match import_file(world, import) {
+        Some(id) => Target::Module(id),
+        None => Target::Binding(import.span()),
+    }
+}
+
+/// Resolve a top-level binding of the file with the given id.
+fn exported(world: &dyn World, id: FileId, name: &str, depth: usize) -> Option<Target> {
+    let source = world.source(id).ok()?;
+    let root = LinkedNode::new(source.root());
+    root.children()
+        .rev()
+        .find_map(|child| declared_in(world, &child, name, depth))
+}
+
+/// Determine the file from which a module import imports.
+fn import_file(world: &dyn World, import: &LinkedNode) -> Option<FileId> {
+    let expr = import.cast::<ast::ModuleImport>()?.source();
+    if let ast::Expr::Str(path) = expr {
+        let path = path.get();
+        if !path.starts_with('@') {
+            return import.span().resolve_path(&path).ok();
+        }
+    }
+
+    let node = import.find(expr.span())?;
+    match analyze_import(world, &node)? {
+        Value::Module(module) => module.file_id(),
+        _ => None,
+    }
+}
+
+/// The name a module is bound to when imported from a path without renaming.
+pub(crate) fn module_name(source: ast::Expr) -> Option<EcoString> {
+    let ast::Expr::Str(path) = source else { return None };
+    let path = path.get();
+    if path.starts_with('@') {
+        let spec: PackageSpec = path.parse().ok()?;
+        return Some(spec.name);
+    }
+
+    Some(Path::new(path.as_str()).file_stem()?.to_string_lossy().into())
+}
+
+/// Collect the source file and all files statically reachable from it or from
+/// the main file through imports and includes of paths.
+pub(crate) fn reachable(world: &dyn World, source: &Source) -> Vec<Source> {
+    let mut seen = HashSet::new();
+    let mut sources = vec![];
+    let mut queue = vec![source.clone(), world.main()];
+    while let Some(source) = queue.pop() {
+        if !seen.insert(source.id()) {
+            continue;
+        }
+
+        visit(&LinkedNode::new(source.root()), &mut |node| {
+            let path = match node.kind() {
+                SyntaxKind::ModuleImport => {
+                    node.cast::<ast::ModuleImport>().unwrap().source()
+                }
+                SyntaxKind::ModuleInclude => {
+                    node.cast::<ast::ModuleInclude>().unwrap().source()
+                }
+                _ => return,
+            };
+
+            let ast::Expr::Str(path) = path else { return };
+            let path = path.get();
+            if path.starts_with('@') {
+                return;
+            }
+
+            if let Some(source) = node
+                .span()
+                .resolve_path(&path)
+                .ok()
+                .and_then(|id| world.source(id).ok())
+            {
+                queue.push(source);
+            }
+        });
+
+        sources.push(source);
+    }
+
+    sources
+}
+
+/// Call a function for a node and all of its descendants.
+pub(crate) fn visit(node: &LinkedNode, f: &mut impl FnMut(&LinkedNode)) {
+    f(node);
+    for child in node.children() {
+        visit(&child, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::eval::Tracer;
+    use typst::syntax::{Source, Span};
+    use typst::{World, WorldExt};
+
+    use super::{definition, references};
+    use crate::tests::TestWorld;
+
+    /// Get the text at the definition of the symbol at the cursor.
+    #[track_caller]
+    fn def(world: &TestWorld, cursor: usize) -> Option<String> {
+        let doc = typst::compile(world, &mut Tracer::new()).ok();
+        let definition = definition(world, doc.as_ref(), &world.main, cursor)?;
+        text(world, definition.span)
+    }
+
+    /// Get the texts of all references to the symbol at the cursor.
+    #[track_caller]
+    fn refs(world: &TestWorld, source: &Source, cursor: usize) -> Vec<String> {
+        references(world, source, cursor)
+            .into_iter()
+            .filter_map(|span| text(world, span))
+            .collect()
+    }
+
+    /// Get the text at a span along with its offset.
+    fn text(world: &TestWorld, span: Span) -> Option<String> {
+        let source = world.source(span.id()?).ok()?;
+        let range = world.range(span)?;
+        Some(format!("{}@{}", &source.text()[range.clone()], range.start))
+    }
+
+    #[test]
+    fn test_definition_of_bindings() {
+        let world = TestWorld::new("#let x = 1\n#let f(x) = x + 1\n#x #f(x)");
+        assert_eq!(def(&world, 30).as_deref(), Some("x@5"));
+        assert_eq!(def(&world, 23).as_deref(), Some("x@18"));
+        assert_eq!(def(&world, 33).as_deref(), Some("f@16"));
+        assert_eq!(def(&world, 35).as_deref(), Some("x@5"));
+    }
+
+    #[test]
+    fn test_definition_of_shadowed_binding() {
+        let world = TestWorld::new("#let a = 1\n#{ let a = 2; a }\n#a");
+        assert_eq!(def(&world, 25).as_deref(), Some("a@18"));
+        assert_eq!(def(&world, 30).as_deref(), Some("a@5"));
+    }
+
+    #[test]
+    fn test_definition_of_loop_and_import() {
+        let world = TestWorld::new("#for (i, v) in (1, 2) { v }")
+            .with_source("utils.typ", "#let add(a, b) = a + b");
+        assert_eq!(def(&world, 24).as_deref(), Some("v@9"));
+
+        let world = TestWorld::new("#import \"utils.typ\": add\n#add(1, 2)")
+            .with_source("utils.typ", "#let add(a, b) = a + b");
+        assert_eq!(def(&world, 28).as_deref(), Some("add@5"));
+
+        let world = TestWorld::new("#import \"utils.typ\"\n#utils.add(1, 2)")
+            .with_source("utils.typ", "#let add(a, b) = a + b");
+        assert_eq!(def(&world, 28).as_deref(), Some("add@5"));
+    }
+
+    #[test]
+    fn test_definition_of_cyclic_import() {
+        let world = TestWorld::new("#import \"a.typ\": x\n#x")
+            .with_source("a.typ", "#import \"b.typ\": x")
+            .with_source("b.typ", "#import \"a.typ\": x");
+        assert_eq!(def(&world, 21).as_deref(), Some("x@17"));
+
+        let world = TestWorld::new("#import \"a.typ\": x\n#x")
+            .with_source("a.typ", "#import \"a.typ\": x");
+        assert_eq!(def(&world, 21).as_deref(), Some("x@17"));
+    }
+
+    #[test]
+    fn test_definition_of_label() {
+        let world = TestWorld::new("#set heading(numbering: \"1.\")\n= A <a>\n@a");
+        assert_eq!(def(&world, 39).as_deref(), Some("= A@30"));
+        assert_eq!(def(&world, 100), None);
+    }
+
+    #[test]
+    fn test_references() {
+        let world = TestWorld::new("#import \"utils.typ\": add as plus\n#plus(1, 2)")
+            .with_source("utils.typ", "#let add(a, b) = a + b\n#add(1, 2)");
+        let utils = world.source(world.id("utils.typ")).unwrap();
+        assert_eq!(
+            refs(&world, &utils, 6),
+            ["add@21", "plus@28", "plus@34", "add@5", "add@24"]
+        );
+
+        let world = TestWorld::new("= A <a>\n@a #ref(label(\"a\")) #ref(<b>)");
+        assert_eq!(refs(&world, &world.main, 9), ["<a>@4", "@a@8", "\"a\"@22"]);
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::HashSet;
+
+use ecow::EcoString;
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{FileId, Source, SyntaxKind, SyntaxNode};
+use typst::World;
+
+/// How many imports to follow at most when collecting re-exported items.
+const MAX_IMPORT_DEPTH: usize = 16;
+
+/// Extract the documentation of the items a package's entrypoint exports.
+///
+/// Items are the top-level `let` bindings whose names don't start with an
+/// underscore, documented by the `///` comments directly above them. Within
+/// these comments, lines of the form `- name (type): description` document
+/// a parameter and a line of the form `-> type` the return type. Items that
+/// the entrypoint imports from other files of the package are included, in
+/// the position of their import.
+pub fn package_docs(world: &dyn World, source: &Source) -> Vec<DocItem> {
+    let mut items = vec![];
+    let mut seen = HashSet::new();
+    collect(world, source, None, 0, &mut seen, &mut items);
+    items
+}
+
+/// A documented binding.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DocItem {
+    /// The name under which the item is exported.
+    pub name: EcoString,
+    /// What kind of item this is.
+    pub kind: DocItemKind,
+    /// The item's description, without the parameter and return type lines.
+    pub docs: EcoString,
+    /// The parameters, if the item is a function.
+    pub params: Vec<DocParam>,
+    /// The documented return type, if any.
+    pub returns: Option<EcoString>,
+    /// The source code of a variable's initializer, if it is short.
+    pub value: Option<EcoString>,
+    /// The file the item is defined in.
+    pub file: FileId,
+}
+
+/// The kind of a documented item.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DocItemKind {
+    /// A function defined through a closure.
+    Function,
+    /// Any other value.
+    Variable,
+}
+
+/// A parameter of a documented function.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DocParam {
+    /// The parameter's name, or the pattern of a destructuring parameter.
+    pub name: EcoString,
+    /// The documented types of the parameter, if any.
+    pub types: Option<EcoString>,
+    /// The parameter's description.
+    pub docs: EcoString,
+    /// The source code of the default value of a named parameter.
+    pub default: Option<EcoString>,
+    /// Whether the parameter is an argument sink.
+    pub variadic: bool,
+}
+
+impl DocParam {
+    /// Whether the parameter is named.
+    pub fn named(&self) -> bool {
+        self.default.is_some()
+    }
+}
+
+/// The maximum length of a variable's value in the documentation.
+const MAX_VALUE_LEN: usize = 80;
+
+/// Collect the documented items of a file. If `only` is given, just the
+/// items with the given original and bound names are collected.
+fn collect(
+    world: &dyn World,
+    source: &Source,
+    only: Option<&[(EcoString, EcoString)]>,
+    depth: usize,
+    seen: &mut HashSet<(FileId, EcoString)>,
+    items: &mut Vec<DocItem>,
+) {
+    let children: Vec<_> = source.root().children().collect();
+    for (i, child) in children.iter().enumerate() {
+        if let Some(binding) = child.cast::<ast::LetBinding>() {
+            let docs = doc_comment(&children[..i]);
+            for item in items_of(binding, &docs, source.id()) {
+                let name = match only {
+                    Some(only) => {
+                        let Some((_, bound)) =
+                            only.iter().find(|(original, _)| *original == item.name)
+                        else {
+                            continue;
+                        };
+                        bound.clone()
+                    }
+                    None => item.name.clone(),
+                };
+
+                if !name.starts_with('_') && seen.insert((item.file, name.clone())) {
+                    items.push(DocItem { name, ..item });
+                }
+            }
+        } else if let Some(import) = child.cast::<ast::ModuleImport>() {
+            if only.is_none() && depth < MAX_IMPORT_DEPTH {
+                reexports(world, child, import, depth, seen, items);
+            }
+        }
+    }
+}
+
+/// Collect the items a top-level import brings into scope.
+fn reexports(
+    world: &dyn World,
+    node: &SyntaxNode,
+    import: ast::ModuleImport,
+    depth: usize,
+    seen: &mut HashSet<(FileId, EcoString)>,
+    items: &mut Vec<DocItem>,
+) {
+    let ast::Expr::Str(path) = import.source() else { return };
+    let path = path.get();
+    if path.starts_with('@') {
+        return;
+    }
+
+    let Some(source) = node
+        .span()
+        .resolve_path(&path)
+        .ok()
+        .and_then(|id| world.source(id).ok())
+    else {
+        return;
+    };
+
+    match import.imports() {
+        Some(ast::Imports::Wildcard) => {
+            collect(world, &source, None, depth + 1, seen, items);
+        }
+        Some(ast::Imports::Items(imports)) => {
+            let names: Vec<_> = imports
+                .iter()
+                .map(|item| {
+                    (item.original_name().get().clone(), item.bound_name().get().clone())
+                })
+                .collect();
+            collect(world, &source, Some(&names), depth + 1, seen, items);
+        }
+        None => {}
+    }
+}
+
+/// The items a `let` binding defines.
+fn items_of(binding: ast::LetBinding, docs: &str, file: FileId) -> Vec<DocItem> {
+    let (description, param_docs, returns) = split_docs(docs);
+    let closure = match binding.init() {
+        Some(ast::Expr::Closure(closure)) => Some(closure),
+        _ => None,
+    };
+
+    binding
+        .kind()
+        .bindings()
+        .into_iter()
+        .map(|name| {
+            let mut item = DocItem {
+                name: name.get().clone(),
+                kind: DocItemKind::Variable,
+                docs: description.clone(),
+                params: vec![],
+                returns: returns.clone(),
+                value: None,
+                file,
+            };
+
+            match closure {
+                Some(closure) => {
+                    item.kind = DocItemKind::Function;
+                    item.params = params(closure, &param_docs);
+                }
+                None => {
+                    let init =
+                        binding.init().map(|init| init.to_untyped().clone().into_text());
+                    item.value = init.filter(|init| {
+                        init.len() <= MAX_VALUE_LEN && !init.contains('\n')
+                    });
+                }
+            }
+
+            item
+        })
+        .collect()
+}
+
+/// The parameters of a closure, with their documentation.
+fn params(
+    closure: ast::Closure,
+    docs: &[(EcoString, Option<EcoString>, EcoString)],
+) -> Vec<DocParam> {
+    closure
+        .params()
+        .children()
+        .map(|param| {
+            let (name, default, variadic) = match param {
+                ast::Param::Pos(pattern) => {
+                    (pattern.to_untyped().clone().into_text(), None, false)
+                }
+                ast::Param::Named(named) => (
+                    named.name().get().clone(),
+                    Some(named.expr().to_untyped().clone().into_text()),
+                    false,
+                ),
+                ast::Param::Spread(spread) => (
+                    spread
+                        .sink_ident()
+                        .map(|ident| ident.get().clone())
+                        .unwrap_or_default(),
+                    None,
+                    true,
+                ),
+            };
+
+            let (types, docs) = docs
+                .iter()
+                .find(|(documented, ..)| *documented == name)
+                .map(|(_, types, docs)| (types.clone(), docs.clone()))
+                .unwrap_or_default();
+
+            DocParam { name, types, docs, default, variadic }
+        })
+        .collect()
+}
+
+/// The text of the `///` comments directly above the last of the given
+/// nodes.
+fn doc_comment(before: &[&SyntaxNode]) -> EcoString {
+    let mut lines = vec![];
+    for node in before.iter().rev() {
+        match node.kind() {
+            SyntaxKind::Hash => {}
+            SyntaxKind::Space
+                if node.text().chars().filter(|&c| c == '\n').count() <= 1 => {}
+            SyntaxKind::LineComment if node.text().starts_with("///") => {
+                let line = &node.text()[3..];
+                lines.push(line.strip_prefix(' ').unwrap_or(line));
+            }
+            _ => break,
+        }
+    }
+
+    lines.reverse();
+    lines.join("\n").trim().into()
+}
+
+/// Split a doc comment into the description, the parameter documentation
+/// (name, types, and description), and the return type.
+#[allow(clippy::type_complexity)]
+fn split_docs(
+    docs: &str,
+) -> (EcoString, Vec<(EcoString, Option<EcoString>, EcoString)>, Option<EcoString>) {
+    let mut description = vec![];
+    let mut params: Vec<(EcoString, Option<EcoString>, EcoString)> = vec![];
+    let mut returns = None;
+    let mut in_param = false;
+
+    for line in docs.lines() {
+        if let Some(ty) = line.trim().strip_prefix("->") {
+            returns = Some(ty.trim().into());
+            in_param = false;
+        } else if let Some(param) = line.strip_prefix("- ").and_then(parse_param) {
+            params.push(param);
+            in_param = true;
+        } else if in_param
+            && line.starts_with(char::is_whitespace)
+            && !line.trim().is_empty()
+        {
+            let (_, _, docs) = params.last_mut().unwrap();
+            if !docs.is_empty() {
+                docs.push(' ');
+            }
+            docs.push_str(line.trim());
+        } else {
+            description.push(line);
+            in_param = false;
+        }
+    }
+
+    (description.join("\n").trim().into(), params, returns)
+}
+
+/// Parse the documentation of a parameter: `name (types): description`.
+fn parse_param(line: &str) -> Option<(EcoString, Option<EcoString>, EcoString)> {
+    let (head, docs) = line.split_once(':')?;
+    let (name, types) = match head.split_once('(') {
+        Some((name, types)) => {
+            (name, Some(types.trim().strip_suffix(')')?.trim().into()))
+        }
+        None => (head, None),
+    };
+
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((name.into(), types, docs.trim().into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{package_docs, split_docs, DocItemKind};
+    use crate::tests::TestWorld;
+
+    #[test]
+    fn test_package_docs() {
+        let world = TestWorld::new(
+            "#import \"util.typ\": helper as assist\n\
+             /// The golden ratio.\n\
+             #let ratio = 1.618\n\n\
+             /// Not attached.\n\n\
+             #let plain = 1\n\
+             #let _private = 2\n\
+             /// Draws a box.\n///\n\
+             /// - body (content): What to put\n///   into the box.\n\
+             /// - fill (color): The fill.\n\
+             /// -> content\n\
+             #let boxed(body, fill: red, ..rest) = box(fill: fill, body)",
+        )
+        .with_source("util.typ", "/// Helps.\n#let helper(x) = x\n#let other = 1");
+
+        let items = package_docs(&world, &world.main);
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["assist", "ratio", "plain", "boxed"]);
+
+        assert_eq!(items[0].docs, "Helps.");
+        assert_eq!(items[0].kind, DocItemKind::Function);
+        assert_eq!(items[1].docs, "The golden ratio.");
+        assert_eq!(items[1].value.as_deref(), Some("1.618"));
+        assert_eq!(items[2].docs, "");
+
+        let boxed = &items[3];
+        assert_eq!(boxed.docs, "Draws a box.");
+        assert_eq!(boxed.returns.as_deref(), Some("content"));
+        let params: Vec<_> = boxed
+            .params
+            .iter()
+            .map(|param| {
+                (
+                    param.name.as_str(),
+                    param.types.as_deref(),
+                    param.docs.as_str(),
+                    param.default.as_deref(),
+                    param.variadic,
+                )
+            })
+            .collect();
+        assert_eq!(
+            params,
+            [
+                ("body", Some("content"), "What to put into the box.", None, false),
+                ("fill", Some("color"), "The fill.", Some("red"), false),
+                ("rest", None, "", None, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_docs_reexports() {
+        let world = TestWorld::new(
+            "#import \"a.typ\": *\n#import \"@preview/pkg:0.1.0\": *\n#let own = 1",
+        )
+        .with_source("a.typ", "#import \"b.typ\": *\n#let a = 1")
+        .with_source("b.typ", "#import \"a.typ\": *\n#let b = 2\n#let a = 3");
+
+        let items = package_docs(&world, &world.main);
+        let names: Vec<_> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "a", "own"]);
+        assert_eq!(items[1].file, world.id("b.typ"));
+        assert_eq!(items[2].file, world.id("a.typ"));
+    }
+
+    #[test]
+    fn test_package_docs_values() {
+        let world = TestWorld::new(
+            "/// Both.\n#let (x, y) = (1, 2)\n\
+             #let long = \"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\"\n\
+             #let multi = (\n  1,\n)\n\
+             #let f = x => x",
+        );
+
+        let items = package_docs(&world, &world.main);
+        let summary: Vec<_> = items
+            .iter()
+            .map(|item| {
+                (item.name.as_str(), item.kind, item.docs.as_str(), item.value.as_deref())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("x", DocItemKind::Variable, "Both.", Some("(1, 2)")),
+                ("y", DocItemKind::Variable, "Both.", Some("(1, 2)")),
+                ("long", DocItemKind::Variable, "", None),
+                ("multi", DocItemKind::Variable, "", None),
+                ("f", DocItemKind::Function, "", None),
+            ]
+        );
+        assert_eq!(items[4].params[0].name, "x");
+    }
+
+    #[test]
+    fn test_split_docs() {
+        let (description, params, returns) = split_docs(
+            "Intro.\n\
+             - a list item\n\
+             - x: Untyped.\n\
+             - two words: Not a parameter.\n\
+             - y (int | none): Typed\n  and continued.\n\
+             \n\
+             Outro.\n\
+             -> str",
+        );
+
+        assert_eq!(
+            description,
+            "Intro.\n- a list item\n- two words: Not a parameter.\n\nOutro."
+        );
+        assert_eq!(returns.as_deref(), Some("str"));
+        assert_eq!(
+            params,
+            [
+                ("x".into(), None, "Untyped.".into()),
+                ("y".into(), Some("int | none".into()), "Typed and continued.".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use typst::diag::{bail, StrResult};
+use typst::syntax::{parse, LinkedNode, Source, SyntaxKind, SyntaxNode};
+
+/// Format a source file.
+///
+/// Only the whitespace in code is rearranged: Code blocks, argument lists,
+/// arrays, dictionaries, parameter lists, and destructuring patterns are
+/// indented consistently and broken up over multiple lines when they don't
+/// fit into the maximum width. Markup, math, strings, raw text, and comments
+/// are kept as they are, except that the indentation of markup and math in
+/// content blocks and equations follows the indentation of the surrounding
+/// code.
+///
+/// Fails if the source contains syntax errors.
+pub fn format(source: &Source, config: &FormatConfig) -> StrResult<String> {
+    if source.root().erroneous() {
+        bail!("cannot format a file with syntax errors");
+    }
+
+    let mut formatter = Formatter::new(config, source.text(), config.max_width);
+    formatter.markup(&LinkedNode::new(source.root()), 0);
+
+    // Rearranging whitespace must not change the syntax tree in any other
+    // way. This is a safety net against subtle whitespace sensitivities.
+    let output = formatter.out;
+    if !equivalent(source.root(), &parse(&output), true) {
+        bail!("formatting would change the meaning of the file");
+    }
+
+    Ok(output)
+}
+
+/// Configuration for the formatter.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FormatConfig {
+    /// The number of spaces per indentation level in code.
+    pub indent: usize,
+    /// The width up to which code is kept on a single line.
+    pub max_width: usize,
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self { indent: 2, max_width: 80 }
+    }
+}
+
+/// Writes the formatted text of a syntax tree.
+struct Formatter<'a> {
+    config: &'a FormatConfig,
+    /// The original text of the source file.
+    text: &'a str,
+    /// The width up to which code is kept on a single line.
+    width: usize,
+    /// The formatted text.
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(config: &'a FormatConfig, text: &'a str, width: usize) -> Self {
+        Self { config, text, width, out: String::new() }
+    }
+
+    /// Write markup or math, keeping everything but embedded code as it is.
+    ///
+    /// The indentation of lines is adjusted by `shift` columns so that it
+    /// stays consistent with the surrounding code.
+    fn markup(&mut self, node: &LinkedNode, shift: isize) {
+        let mut embedded = false;
+        for child in node.children() {
+            if embedded {
+                let indent = self.line_indent();
+                self.code(&child, indent);
+                embedded = false;
+                continue;
+            }
+
+            match child.kind() {
+                SyntaxKind::Hash => {
+                    self.out.push('#');
+                    embedded = true;
+                }
+                SyntaxKind::Space | SyntaxKind::Parbreak => {
+                    self.shifted(child.text(), shift);
+                }
+                SyntaxKind::Raw => self.verbatim(&child),
+                _ if child.children().len() == 0 => self.out.push_str(child.text()),
+                _ => self.markup(&child, shift),
+            }
+        }
+    }
+
+    /// Write code that starts on a line with the given indentation.
+    fn code(&mut self, node: &LinkedNode, indent: usize) {
+        match node.kind() {
+            SyntaxKind::Space => self.space(node, indent),
+            SyntaxKind::CodeBlock => self.block(node),
+            SyntaxKind::ContentBlock | SyntaxKind::Equation => {
+                if self.text[node.range()].contains('\n') {
+                    let shift =
+                        self.line_indent() as isize - self.original_indent(node) as isize;
+                    self.markup(node, shift);
+                } else {
+                    // Keep code embedded in single-line markup on one line.
+                    let mut formatter =
+                        Formatter::new(self.config, self.text, usize::MAX);
+                    formatter.markup(node, 0);
+                    self.out.push_str(&formatter.out);
+                }
+            }
+            SyntaxKind::Args
+            | SyntaxKind::Array
+            | SyntaxKind::Dict
+            | SyntaxKind::Params
+            | SyntaxKind::Destructuring => self.list(node, indent),
+            SyntaxKind::Raw => self.verbatim(node),
+            _ if node.children().len() == 0 => self.out.push_str(node.text()),
+            _ => {
+                for child in node.children() {
+                    self.code(&child, indent);
+                }
+            }
+        }
+    }
+
+    /// Write whitespace in code.
+    fn space(&mut self, node: &LinkedNode, indent: usize) {
+        if !node.text().contains('\n') {
+            self.out.push(' ');
+            return;
+        }
+
+        // A line break within an expression continues it on the next line,
+        // which is indented further unless it continues with `else`.
+        let continued =
+            node.next_sibling().map(|next| next.kind()) != Some(SyntaxKind::Else);
+        self.newline(indent + if continued { self.config.indent } else { 0 });
+    }
+
+    /// Write a code block, putting each statement on its own line unless the
+    /// whole block was written on a single line, in which case it is kept on
+    /// a single line.
+    fn block(&mut self, node: &LinkedNode) {
+        let indent = self.line_indent();
+        if !self.text[node.range()].contains('\n') {
+            let mut formatter = Formatter::new(self.config, self.text, usize::MAX);
+            for child in node.children() {
+                formatter.code(&child, indent);
+            }
+            self.out.push_str(&formatter.out);
+            return;
+        }
+
+        let mut statements = vec![];
+        for child in node.children() {
+            match child.kind() {
+                SyntaxKind::LeftBrace | SyntaxKind::RightBrace => {}
+                SyntaxKind::Code => statements.extend(child.children()),
+                _ => statements.push(child),
+            }
+        }
+
+        self.out.push('{');
+        self.lines(&statements, indent + self.config.indent, false, false);
+        self.newline(indent);
+        self.out.push('}');
+    }
+
+    /// Write a parenthesized list of arguments, items, or parameters.
+    ///
+    /// The list is kept on a single line if it fits and didn't start with a
+    /// line break. Otherwise, only the last argument may be broken up, or the
+    /// items are put on separate lines. Items that
+    /// were on the same line stay together if they fit, so that the rows of
+    /// tables and grids are kept intact.
+    fn list(&mut self, node: &LinkedNode, indent: usize) {
+        let children: Vec<_> = node.children().collect();
+        let (Some(open), Some(close)) = (
+            children
+                .iter()
+                .position(|child| child.kind() == SyntaxKind::LeftParen),
+            children
+                .iter()
+                .rposition(|child| child.kind() == SyntaxKind::RightParen),
+        ) else {
+            for child in &children {
+                self.code(child, indent);
+            }
+            return;
+        };
+
+        let inner = &children[open + 1..close];
+        let items: Vec<_> = inner
+            .iter()
+            .filter(|child| {
+                !matches!(
+                    child.kind(),
+                    SyntaxKind::Space | SyntaxKind::Comma | SyntaxKind::Colon
+                )
+            })
+            .collect();
+
+        let broken = inner.first().is_some_and(|child| {
+            child.kind() == SyntaxKind::Space && child.text().contains('\n')
+        });
+
+        if !items.iter().any(|item| item.kind().is_trivia()) {
+            if let Some(flat) = self.flat_list(node.kind(), inner, &items) {
+                if items.is_empty()
+                    || (!broken && self.column() + flat.chars().count() <= self.width)
+                {
+                    self.out.push_str(&flat);
+                    self.trailing(&children[close + 1..], indent);
+                    return;
+                }
+            }
+
+            if node.kind() == SyntaxKind::Args && !broken && self.overflow(&items, indent)
+            {
+                self.trailing(&children[close + 1..], indent);
+                return;
+            }
+        }
+
+        // Lists may start on a continuation line, so the items are indented
+        // relative to the current line.
+        let base = self.line_indent();
+        let group = items
+            .windows(2)
+            .any(|pair| self.text[pair[0].range().end..pair[1].offset()].contains('\n'));
+        self.out.push('(');
+        self.lines(inner, base + self.config.indent, true, group);
+        self.newline(base);
+        self.out.push(')');
+        self.trailing(&children[close + 1..], indent);
+    }
+
+    /// Render a list on a single line, if possible.
+    fn flat_list(
+        &self,
+        kind: SyntaxKind,
+        inner: &[LinkedNode],
+        items: &[&LinkedNode],
+    ) -> Option<String> {
+        let mut flat = String::from("(");
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                flat.push_str(", ");
+            }
+            flat.push_str(&self.flat(item)?);
+        }
+
+        // Arrays and destructuring patterns with a single item may need a
+        // trailing comma to not be parenthesized expressions.
+        if items.len() == 1
+            && matches!(kind, SyntaxKind::Array | SyntaxKind::Destructuring)
+            && inner.iter().any(|child| child.kind() == SyntaxKind::Comma)
+        {
+            flat.push(',');
+        }
+
+        if items.is_empty() && kind == SyntaxKind::Dict {
+            flat.push(':');
+        }
+
+        flat.push(')');
+        Some(flat)
+    }
+
+    /// Try to write an argument list on a single line up to its last
+    /// argument, which is broken up over multiple lines instead of the list.
+    ///
+    /// This only succeeds if the last argument's first line fits and ends
+    /// with an opening delimiter, as for a trailing closure, block, or call.
+    fn overflow(&mut self, items: &[&LinkedNode], indent: usize) -> bool {
+        let Some((last, rest)) = items.split_last() else { return false };
+
+        // A last argument that fits on a line of its own is better put there.
+        if self.flat(last).is_some_and(|flat| {
+            self.line_indent() + self.config.indent + flat.chars().count() < self.width
+        }) {
+            return false;
+        }
+
+        let mut prefix = String::from("(");
+        for item in rest {
+            let Some(flat) = self.flat(item) else { return false };
+            prefix.push_str(&flat);
+            prefix.push_str(", ");
+        }
+
+        let mark = self.out.len();
+        let start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out.push_str(&prefix);
+        self.code(last, indent);
+
+        let line = self.out[start..].split('\n').next().unwrap_or_default();
+        if self.out[mark..].contains('\n')
+            && line.chars().count() <= self.width
+            && line.trim_end().ends_with(['(', '[', '{'])
+        {
+            self.out.push(')');
+            return true;
+        }
+
+        self.out.truncate(mark);
+        false
+    }
+
+    /// Write the content blocks following the parenthesized arguments.
+    fn trailing(&mut self, children: &[LinkedNode], indent: usize) {
+        for child in children {
+            self.code(child, indent);
+        }
+    }
+
+    /// Write items on separate lines with the given indentation.
+    ///
+    /// Comments that followed an item on the same line stay there and a
+    /// single empty line between items is preserved. With `group`, items that
+    /// followed another item on the same line stay there if they fit.
+    fn lines(
+        &mut self,
+        children: &[LinkedNode],
+        indent: usize,
+        commas: bool,
+        group: bool,
+    ) {
+        let mut first = true;
+        let mut newlines = 0;
+        for child in children {
+            match child.kind() {
+                SyntaxKind::Space => {
+                    newlines += child.text().chars().filter(|&c| c == '\n').count();
+                    continue;
+                }
+                SyntaxKind::Comma | SyntaxKind::Semicolon | SyntaxKind::Colon => continue,
+                _ => {}
+            }
+
+            let comment = child.kind().is_trivia();
+            if comment && !first && newlines == 0 {
+                self.out.push(' ');
+                self.out.push_str(child.text());
+                continue;
+            }
+
+            if group && !comment && !first && newlines == 0 {
+                if let Some(flat) = self.flat(child) {
+                    if self.column() + flat.chars().count() + 2 <= self.width {
+                        self.out.push(' ');
+                        self.out.push_str(&flat);
+                        self.out.push(',');
+                        continue;
+                    }
+                }
+            }
+
+            if newlines > 1 && !first {
+                self.out.push('\n');
+            }
+
+            self.newline(indent);
+            self.code(child, indent);
+            if commas && !comment {
+                self.out.push(',');
+            }
+
+            first = false;
+            newlines = 0;
+        }
+    }
+
+    /// Render a node on a single line, if possible.
+    fn flat(&self, node: &LinkedNode) -> Option<String> {
+        let mut formatter = Formatter::new(self.config, self.text, usize::MAX);
+        formatter.code(node, 0);
+        (!formatter.out.contains('\n')).then_some(formatter.out)
+    }
+
+    /// Write a node's original text.
+    fn verbatim(&mut self, node: &LinkedNode) {
+        self.out.push_str(&self.text[node.range()]);
+    }
+
+    /// Write whitespace, adjusting the indentation of its last line.
+    fn shifted(&mut self, text: &str, shift: isize) {
+        match text.rfind('\n') {
+            Some(i) if shift != 0 && text[i + 1..].bytes().all(|b| b == b' ') => {
+                let width = (text.len() - i - 1) as isize + shift;
+                self.out.push_str(&text[..=i]);
+                self.out.push_str(&" ".repeat(width.max(0) as usize));
+            }
+            _ => self.out.push_str(text),
+        }
+    }
+
+    /// Start a new line with the given indentation.
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.out.push_str(&" ".repeat(indent));
+    }
+
+    /// The current line of the output.
+    fn line(&self) -> &str {
+        &self.out[self.out.rfind('\n').map_or(0, |i| i + 1)..]
+    }
+
+    /// The column at the end of the output.
+    fn column(&self) -> usize {
+        self.line().chars().count()
+    }
+
+    /// The indentation of the current line of the output.
+    fn line_indent(&self) -> usize {
+        let line = self.line();
+        line.len() - line.trim_start().len()
+    }
+
+    /// The indentation of the line on which a node originally started.
+    fn original_indent(&self, node: &LinkedNode) -> usize {
+        let start = self.text[..node.offset()].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.text[start..node.offset()];
+        line.len() - line.trim_start().len()
+    }
+}
+
+/// Whether two syntax trees are the same, ignoring whitespace, commas, and
+/// semicolons in code and only comparing the number of line breaks in markup
+/// whitespace.
+fn equivalent(a: &SyntaxNode, b: &SyntaxNode, markup: bool) -> bool {
+    if a.kind() != b.kind() {
+        return false;
+    }
+
+    if matches!(a.kind(), SyntaxKind::Space | SyntaxKind::Parbreak) {
+        let newlines = |node: &SyntaxNode| node.text().matches('\n').count();
+        return newlines(a) == newlines(b);
+    }
+
+    if a.children().len() == 0 || b.children().len() == 0 {
+        return a.text() == b.text();
+    }
+
+    let (a, b) = (significant(a, markup), significant(b, markup));
+    a.len() == b.len()
+        && a.iter()
+            .zip(&b)
+            .all(|((a, markup), (b, _))| equivalent(a, b, *markup))
+}
+
+/// The children of a node that matter for equivalence, along with whether
+/// they are in markup or math.
+fn significant(node: &SyntaxNode, markup: bool) -> Vec<(&SyntaxNode, bool)> {
+    let mut children = vec![];
+    let mut embedded = false;
+    for child in node.children() {
+        let markup = match node.kind() {
+            SyntaxKind::ContentBlock | SyntaxKind::Equation => true,
+            _ => markup && !embedded,
+        };
+
+        embedded = markup && child.kind() == SyntaxKind::Hash;
+        if markup
+            || !matches!(
+                child.kind(),
+                SyntaxKind::Space | SyntaxKind::Comma | SyntaxKind::Semicolon
+            )
+        {
+            children.push((child, markup));
+        }
+    }
+    children
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::Source;
+
+    use super::{format, FormatConfig};
+
+    #[track_caller]
+    fn test(text: &str, expected: &str) {
+        let config = FormatConfig::default();
+        let formatted = format(&Source::detached(text), &config).unwrap();
+        assert_eq!(formatted, expected);
+        let again = format(&Source::detached(&formatted), &config).unwrap();
+        assert_eq!(again, expected);
+    }
+
+    #[test]
+    fn test_format_code() {
+        test(
+            "#let f(x)={\n      let y = x\n\n\n    y\n}",
+            "#let f(x)={\n  let y = x\n\n  y\n}",
+        );
+        test("#f(a,\n  b\n)[c] #g( )", "#f(a, b)[c] #g()");
+        test("#f(\n  a, b,\n  c\n)", "#f(\n  a, b,\n  c,\n)");
+        test("#let (a,) = (1 ,)\n#let d = ( : )", "#let (a,) = (1,)\n#let d = (:)");
+        test(
+            "#figure(image(\"a-long-file-name.png\", width: 80%), caption: [A much longer caption])",
+            "#figure(\n  image(\"a-long-file-name.png\", width: 80%),\n  caption: [A much longer caption],\n)",
+        );
+        test(
+            "#show heading: it => {\n  it.body\n}\n#(1, 2).map(x => {\n x\n})",
+            "#show heading: it => {\n  it.body\n}\n#(1, 2).map(x => {\n  x\n})",
+        );
+    }
+
+    #[test]
+    fn test_format_comments() {
+        test(
+            "#let x = (\n  1, // one\n  // two\n  2\n)",
+            "#let x = (\n  1, // one\n  // two\n  2,\n)",
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_markup() {
+        test(
+            "= A  heading\n  - list\n    - nested #f( a ,b )\n",
+            "= A  heading\n  - list\n    - nested #f(a, b)\n",
+        );
+        test(
+            "#{\n    [\n      - a\n        - b\n    ]\n}",
+            "#{\n  [\n    - a\n      - b\n  ]\n}",
+        );
+        test(
+            "#let s = \"a\n     b\"\n```\n  raw\n```",
+            "#let s = \"a\n     b\"\n```\n  raw\n```",
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert!(format(&Source::detached("#f("), &FormatConfig::default()).is_err());
+    }
+}
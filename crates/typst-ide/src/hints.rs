@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use comemo::Track;
+use ecow::{eco_format, EcoString};
+use typst::eval::{eval_string, EvalMode};
+use typst::foundations::{Repr, Scope, Value};
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{LinkedNode, Source, Span, SyntaxKind};
+use typst::World;
+
+use crate::signature::{positional_param, signature};
+
+/// The maximum length of an inferred value in a hint.
+const MAX_VALUE_LEN: usize = 40;
+
+/// The maximum length of the representation of a binding's value for later
+/// bindings to build on it.
+const MAX_KNOWN_LEN: usize = 1024;
+
+/// Find hints to display inline in the given byte range of a source file.
+///
+/// Positional arguments are annotated with the names of the parameters they
+/// set, unless there is just one of them or the argument is a variable of
+/// the same name. Top-level `let` bindings whose
+/// value can be computed from literals and earlier such bindings are
+/// annotated with that value.
+pub fn inlay_hints(
+    world: &dyn World,
+    source: &Source,
+    range: Range<usize>,
+) -> Vec<InlayHint> {
+    let root = LinkedNode::new(source.root());
+    let mut hints = vec![];
+    param_hints(world, &root, &range, &mut hints);
+    value_hints(world, &root, &range, &mut hints);
+    hints.sort_by_key(|hint| hint.offset);
+    hints
+}
+
+/// A label to display inline at a position in a source file.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct InlayHint {
+    /// The byte offset at which to display the hint.
+    pub offset: usize,
+    /// The text of the hint.
+    pub label: EcoString,
+    /// What the hint describes.
+    pub kind: InlayHintKind,
+}
+
+/// What an inlay hint describes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InlayHintKind {
+    /// The name of the parameter an argument sets, placed before the
+    /// argument.
+    Parameter,
+    /// The value of a binding, placed after its initializer.
+    Value,
+}
+
+/// Annotate positional arguments in the range with parameter names.
+fn param_hints(
+    world: &dyn World,
+    node: &LinkedNode,
+    range: &Range<usize>,
+    hints: &mut Vec<InlayHint>,
+) {
+    for child in node.children() {
+        let child_range = child.range();
+        if child_range.end < range.start || child_range.start > range.end {
+            continue;
+        }
+
+        if let Some(call) = child.cast::<ast::FuncCall>() {
+            call_hints(world, &child, call, range, hints);
+        }
+
+        param_hints(world, &child, range, hints);
+    }
+}
+
+/// Annotate the positional arguments of one call.
+fn call_hints(
+    world: &dyn World,
+    node: &LinkedNode,
+    call: ast::FuncCall,
+    range: &Range<usize>,
+    hints: &mut Vec<InlayHint>,
+) {
+    let Some(callee) = node.find(call.callee().span()) else { return };
+    let Some(args) = node.find(call.args().span()) else { return };
+
+    // The meaning of a single argument is usually obvious.
+    let positional = args
+        .children()
+        .take_while(|child| child.kind() != SyntaxKind::RightParen)
+        .filter(|child| matches!(child.cast::<ast::Arg>(), Some(ast::Arg::Pos(_))))
+        .count();
+    if positional < 2 {
+        return;
+    }
+
+    let Some(signature) = signature(world, &callee, false) else { return };
+    let mut index = 0;
+    let mut trailing = false;
+    for child in args.children() {
+        // Content blocks after the parentheses are always the body.
+        trailing |= child.kind() == SyntaxKind::RightParen;
+        match child.cast::<ast::Arg>() {
+            Some(ast::Arg::Pos(expr)) if !trailing => {
+                index += 1;
+                let Some(i) = positional_param(&signature.params, index - 1) else {
+                    break;
+                };
+
+                let param = &signature.params[i];
+                let named_alike = matches!(
+                    expr,
+                    ast::Expr::Ident(ident) if ident.as_str() == param.name
+                );
+                if param.name.is_empty()
+                    || named_alike
+                    || !range.contains(&child.offset())
+                {
+                    continue;
+                }
+
+                hints.push(InlayHint {
+                    offset: child.offset(),
+                    label: eco_format!("{}:", param.name),
+                    kind: InlayHintKind::Parameter,
+                });
+            }
+            // Positions are unknown after a spread argument.
+            Some(ast::Arg::Spread(_)) => break,
+            _ => {}
+        }
+    }
+}
+
+/// Annotate simple top-level bindings in the range with their values.
+fn value_hints(
+    world: &dyn World,
+    root: &LinkedNode,
+    range: &Range<usize>,
+    hints: &mut Vec<InlayHint>,
+) {
+    // The values of the bindings so far that are known.
+    let mut known: HashMap<EcoString, Value> = HashMap::new();
+    for child in root.children() {
+        let Some(binding) = child.cast::<ast::LetBinding>() else { continue };
+        let names = binding.kind().bindings();
+        let value = match (names.as_slice(), binding.init()) {
+            ([_], Some(init)) if is_simple(init, &known) => {
+                let mut scope = Scope::new();
+                for (name, value) in &known {
+                    scope.define(name.clone(), value.clone());
+                }
+                eval_string(
+                    world.track(),
+                    &init.to_untyped().clone().into_text(),
+                    Span::detached(),
+                    EvalMode::Code,
+                    scope,
+                )
+                .ok()
+            }
+            _ => None,
+        };
+
+        // Later bindings can only build on those whose value is known.
+        let Some(value) = value else {
+            for name in names {
+                known.remove(name.as_str());
+            }
+            continue;
+        };
+
+        let init = binding.init().unwrap().to_untyped();
+        let end = child.find(init.span()).map(|init| init.range().end);
+        let repr = value.repr();
+        if let Some(end) = end {
+            if (range.start..=range.end).contains(&end)
+                && repr != init.clone().into_text()
+            {
+                hints.push(InlayHint {
+                    offset: end,
+                    label: eco_format!("= {}", truncate(&repr)),
+                    kind: InlayHintKind::Value,
+                });
+            }
+        }
+
+        // Don't build on large values, so that repeatedly concatenating a
+        // binding with itself can't blow up.
+        if repr.len() <= MAX_KNOWN_LEN {
+            known.insert(names[0].get().clone(), value);
+        } else {
+            known.remove(names[0].as_str());
+        }
+    }
+}
+
+/// Whether an expression can be evaluated without side effects or access to
+/// anything but literals and the known bindings.
+fn is_simple(expr: ast::Expr, known: &HashMap<EcoString, Value>) -> bool {
+    match expr {
+        ast::Expr::None(_)
+        | ast::Expr::Auto(_)
+        | ast::Expr::Bool(_)
+        | ast::Expr::Int(_)
+        | ast::Expr::Float(_)
+        | ast::Expr::Numeric(_)
+        | ast::Expr::Str(_) => true,
+        ast::Expr::Ident(ident) => known.contains_key(ident.as_str()),
+        ast::Expr::Parenthesized(paren) => is_simple(paren.expr(), known),
+        ast::Expr::Unary(unary) => is_simple(unary.expr(), known),
+        ast::Expr::Binary(binary) => {
+            let (lhs, rhs) = (binary.lhs(), binary.rhs());
+            match binary.op() {
+                ast::BinOp::Assign
+                | ast::BinOp::AddAssign
+                | ast::BinOp::SubAssign
+                | ast::BinOp::MulAssign
+                | ast::BinOp::DivAssign => false,
+                // Repeating a string or array can produce a huge value.
+                ast::BinOp::Mul if may_repeat(lhs, known) || may_repeat(rhs, known) => {
+                    false
+                }
+                _ => is_simple(lhs, known) && is_simple(rhs, known),
+            }
+        }
+        ast::Expr::Array(array) => array.items().all(|item| match item {
+            ast::ArrayItem::Pos(expr) => is_simple(expr, known),
+            ast::ArrayItem::Spread(spread) => is_simple(spread.expr(), known),
+        }),
+        ast::Expr::Dict(dict) => dict.items().all(|item| match item {
+            ast::DictItem::Named(named) => is_simple(named.expr(), known),
+            ast::DictItem::Keyed(keyed) => {
+                is_simple(keyed.key(), known) && is_simple(keyed.expr(), known)
+            }
+            ast::DictItem::Spread(spread) => is_simple(spread.expr(), known),
+        }),
+        _ => false,
+    }
+}
+
+/// Whether an expression may evaluate to a value that is repeated when
+/// multiplied with an integer, like a string or an array.
+fn may_repeat(expr: ast::Expr, known: &HashMap<EcoString, Value>) -> bool {
+    match expr {
+        ast::Expr::Str(_) | ast::Expr::Array(_) => true,
+        ast::Expr::Ident(ident) => matches!(
+            known.get(ident.as_str()),
+            Some(Value::Str(_) | Value::Array(_) | Value::Content(_))
+        ),
+        ast::Expr::Parenthesized(paren) => may_repeat(paren.expr(), known),
+        ast::Expr::Binary(binary) => {
+            may_repeat(binary.lhs(), known) || may_repeat(binary.rhs(), known)
+        }
+        _ => false,
+    }
+}
+
+/// Shorten the representation of a value to fit into a hint.
+fn truncate(repr: &str) -> EcoString {
+    if repr.chars().count() <= MAX_VALUE_LEN {
+        return repr.into();
+    }
+
+    let mut short: EcoString = repr.chars().take(MAX_VALUE_LEN - 1).collect();
+    short.push('…');
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inlay_hints, InlayHintKind};
+    use crate::tests::TestWorld;
+
+    /// The hints in a text, with the text they are attached to.
+    #[track_caller]
+    fn hints(text: &str) -> Vec<(String, &str, InlayHintKind)> {
+        let world = TestWorld::new(text);
+        inlay_hints(&world, &world.main, 0..text.len())
+            .into_iter()
+            .map(|hint| (hint.label.into(), &text[hint.offset..], hint.kind))
+            .map(|(label, rest, kind)| (label, rest.get(..3).unwrap_or(rest), kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_inlay_hints_params() {
+        use InlayHintKind::Parameter;
+        assert_eq!(
+            hints("#let f(a, b, c: 1) = a\n#let b = 2\n#f(1, b, c: 3)\n#calc.pow(2, 3)"),
+            [
+                ("a:".into(), "1, ", Parameter),
+                ("base:".into(), "2, ", Parameter),
+                ("exponent:".into(), "3)", Parameter),
+            ]
+        );
+        assert_eq!(hints("#image(\"a.png\") #text(red)[A] #range(..(1, 2))"), []);
+    }
+
+    #[test]
+    fn test_inlay_hints_values() {
+        use InlayHintKind::Value;
+        assert_eq!(
+            hints(
+                "#let a = 2 * 3\n#let b = (a, a * 1pt)\n#let c = 4\n\
+                 #let d = read(\"x\")\n#let e = d + 1\n#let f = c + 1"
+            ),
+            [
+                ("= 6".into(), "\n#l", Value),
+                ("= (6, 6pt)".into(), "\n#l", Value),
+                ("= 5".into(), "", Value),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inlay_hints_values_bounded() {
+        use InlayHintKind::Value;
+        assert_eq!(
+            hints("#let a = \"ab\" * 3\n#let b = 3 * (1, 2)\n#let c = (2 * 3) * 4"),
+            [("= 24".into(), "", Value)]
+        );
+
+        let long = "x".repeat(1000);
+        let text = format!("#let a = \"{long}\"\n#let b = a + a\n#let c = b + b");
+        let found = hints(&text);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].0.starts_with("= \"xxx"));
+        assert_eq!(found[0].1, "\n#l");
+    }
+}
@@ -1,13 +1,35 @@
 //! Capabilities for Typst IDE support.
 
+mod actions;
 mod analyze;
 mod complete;
+mod definition;
+mod docs;
+mod format;
+mod hints;
 mod jump;
+mod lint;
+mod rename;
+mod signature;
+mod symbols;
+mod tokens;
 mod tooltip;
 
+pub use self::actions::{code_actions, CodeAction};
 pub use self::analyze::analyze_labels;
 pub use self::complete::{autocomplete, Completion, CompletionKind};
+pub use self::definition::{definition, references, Definition, DefinitionKind};
+pub use self::docs::{package_docs, DocItem, DocItemKind, DocParam};
+pub use self::format::{format, FormatConfig};
+pub use self::hints::{inlay_hints, InlayHint, InlayHintKind};
 pub use self::jump::{jump_from_click, jump_from_cursor, Jump};
+pub use self::lint::{lint, Finding, Lint};
+pub use self::rename::{prepare_rename, rename, TextEdit};
+pub use self::signature::{signature_help, SignatureHelp, SignatureParam};
+pub use self::symbols::{document_symbols, DocumentSymbol, DocumentSymbolKind};
+pub use self::tokens::{
+    semantic_tokens, SemanticToken, SemanticTokenKind, SemanticTokenModifiers,
+};
 pub use self::tooltip::{tooltip, Tooltip};
 
 use std::fmt::Write;
@@ -97,13 +119,14 @@ mod tests {
     use once_cell::sync::Lazy;
     use typst::diag::{FileError, FileResult};
     use typst::foundations::{Bytes, Datetime};
-    use typst::syntax::{FileId, Source};
+    use typst::syntax::{FileId, Source, VirtualPath};
     use typst::text::{Font, FontBook};
     use typst::{Library, World};
 
     /// A world for IDE testing.
     pub struct TestWorld {
         pub main: Source,
+        sources: Vec<Source>,
         base: &'static TestBase,
     }
 
@@ -115,7 +138,24 @@ mod tests {
         pub fn new(text: &str) -> Self {
             static BASE: Lazy<TestBase> = Lazy::new(TestBase::default);
             let main = Source::detached(text);
-            Self { main, base: &*BASE }
+            Self { main, sources: vec![], base: &*BASE }
+        }
+
+        /// Place the main file at the given path.
+        pub fn with_main(mut self, path: &str) -> Self {
+            self.main = Source::new(self.id(path), self.main.text().into());
+            self
+        }
+
+        /// Add another source file to the world.
+        pub fn with_source(mut self, path: &str, text: &str) -> Self {
+            self.sources.push(Source::new(self.id(path), text.into()));
+            self
+        }
+
+        /// The id of the file at the given path.
+        pub fn id(&self, path: &str) -> FileId {
+            FileId::new(None, VirtualPath::new(path))
         }
     }
 
@@ -134,14 +174,18 @@ mod tests {
 
         fn source(&self, id: FileId) -> FileResult<Source> {
             if id == self.main.id() {
-                Ok(self.main.clone())
-            } else {
-                Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+                return Ok(self.main.clone());
             }
+
+            self.sources
+                .iter()
+                .find(|source| source.id() == id)
+                .cloned()
+                .ok_or_else(|| FileError::NotFound(id.vpath().as_rootless_path().into()))
         }
 
         fn file(&self, id: FileId) -> FileResult<Bytes> {
-            Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+            self.source(id).map(|source| source.text().as_bytes().into())
         }
 
         fn font(&self, index: usize) -> Option<Font> {
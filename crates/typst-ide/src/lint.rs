@@ -0,0 +1,742 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use ecow::{eco_format, EcoString};
+use typst::diag::{warning, SourceDiagnostic};
+use typst::foundations::Value;
+use typst::model::Document;
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{FileId, LinkedNode, Source, Span, SyntaxKind, VirtualPath};
+use typst::World;
+
+use crate::analyze::{analyze_expr, analyze_labels};
+use crate::definition::{module_name, reachable, visit};
+use crate::plain_docs_sentence;
+
+/// The marker with which the documentation of a function announces its
+/// deprecation.
+const DEPRECATION_MARKER: &str = "**Deprecation planned";
+
+/// Check a project for likely mistakes that are not errors.
+///
+/// All files that are statically reachable from the main file are checked.
+/// References to labels are only checked when a `document` from a previous
+/// compilation is passed. The diagnostics of the findings are identified by
+/// the lint's name, so findings can be allowed through comments.
+pub fn lint(world: &dyn World, document: Option<&Document>) -> Vec<Finding> {
+    let main = world.main();
+    let sources = reachable(world, &main);
+    let deprecated = deprecated_methods(world);
+    let entrypoint = is_entrypoint(world, &main);
+
+    let mut checked = vec![];
+    for source in &sources {
+        let mut checker = Checker {
+            world,
+            deprecated: &deprecated,
+            scopes: vec![vec![]],
+            declarations: HashSet::new(),
+            mentions: HashSet::new(),
+            findings: vec![],
+        };
+        checker.walk(&LinkedNode::new(source.root()));
+        let top = checker.scopes.pop().unwrap_or_default();
+        checked.push((top, checker.mentions, checker.findings));
+    }
+
+    let mut findings = vec![];
+    for (i, (top, _, found)) in checked.iter().enumerate() {
+        findings.extend(found.iter().cloned());
+
+        // Top-level bindings may be imported by other files and those of a
+        // package's entrypoint are imported by its users.
+        let exported = |binding: &Binding| {
+            (entrypoint && sources[i].id() == main.id())
+                || checked.iter().enumerate().any(|(j, (_, mentions, _))| {
+                    i != j && mentions.contains(&binding.name)
+                })
+        };
+        findings.extend(
+            top.iter()
+                .filter(|binding| !exported(binding))
+                .filter_map(Binding::finding),
+        );
+    }
+
+    labels(&sources, document, &mut findings);
+
+    for finding in &mut findings {
+        finding.diagnostic.id = Some(finding.lint.name().into());
+    }
+
+    findings.retain(|finding| !finding.diagnostic.is_allowed(world));
+    findings
+}
+
+/// Whether the main file is the entrypoint of a package, according to the
+/// `typst.toml` manifest next to it.
+fn is_entrypoint(world: &dyn World, main: &Source) -> bool {
+    let id = FileId::new(main.id().package().cloned(), VirtualPath::new("typst.toml"));
+    let Ok(bytes) = world.file(id) else { return false };
+    let Ok(text) = std::str::from_utf8(&bytes) else { return false };
+    let Ok(manifest) = toml::from_str::<toml::Table>(text) else { return false };
+    manifest
+        .get("package")
+        .and_then(|package| package.get("entrypoint"))
+        .and_then(|entrypoint| entrypoint.as_str())
+        .is_some_and(|entrypoint| main.id().vpath() == &VirtualPath::new(entrypoint))
+}
+
+/// A likely mistake found by [`lint`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Finding {
+    /// Which lint found the mistake.
+    pub lint: Lint,
+    /// A warning describing the mistake.
+    pub diagnostic: SourceDiagnostic,
+}
+
+/// A kind of mistake that [`lint`] looks for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Lint {
+    /// A `let` binding that is never used.
+    UnusedBinding,
+    /// An imported module or item that is never used.
+    UnusedImport,
+    /// A parameter that is shadowed by a binding before it is used.
+    ShadowedParameter,
+    /// A reference to a label that doesn't exist in the document.
+    UndefinedReference,
+    /// A set rule that is not followed by any content it could apply to.
+    IneffectiveSetRule,
+    /// A label that is defined or attached to elements more than once.
+    DuplicateLabel,
+    /// A call to a function whose deprecation is planned.
+    Deprecated,
+}
+
+impl Lint {
+    /// All lints.
+    pub const ALL: [Self; 7] = [
+        Self::UnusedBinding,
+        Self::UnusedImport,
+        Self::ShadowedParameter,
+        Self::UndefinedReference,
+        Self::IneffectiveSetRule,
+        Self::DuplicateLabel,
+        Self::Deprecated,
+    ];
+
+    /// The name with which the lint is configured.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnusedBinding => "unused-binding",
+            Self::UnusedImport => "unused-import",
+            Self::ShadowedParameter => "shadowed-parameter",
+            Self::UndefinedReference => "undefined-reference",
+            Self::IneffectiveSetRule => "ineffective-set-rule",
+            Self::DuplicateLabel => "duplicate-label",
+            Self::Deprecated => "deprecated",
+        }
+    }
+}
+
+impl FromStr for Lint {
+    type Err = EcoString;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|lint| lint.name() == s)
+            .ok_or_else(|| eco_format!("unknown lint `{s}`"))
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// Walks a source file and keeps track of the bindings in scope.
+struct Checker<'a> {
+    world: &'a dyn World,
+    /// The names of methods some of which are deprecated.
+    deprecated: &'a HashSet<EcoString>,
+    /// The bindings of the enclosing scopes, innermost last.
+    scopes: Vec<Vec<Binding>>,
+    /// The identifiers that introduce a binding rather than use one.
+    declarations: HashSet<Span>,
+    /// The names that are used or imported anywhere in the file.
+    mentions: HashSet<EcoString>,
+    findings: Vec<Finding>,
+}
+
+/// A binding in a scope.
+struct Binding {
+    name: EcoString,
+    span: Span,
+    kind: BindingKind,
+    uses: usize,
+    /// The binding that shadows this one, if any.
+    shadowed: Option<Span>,
+}
+
+/// How a binding was introduced.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum BindingKind {
+    Let,
+    Import,
+    Param,
+    /// Loop variables and a closure's name within the closure.
+    Other,
+}
+
+impl Binding {
+    /// The finding for the binding if it is never used.
+    fn finding(&self) -> Option<Finding> {
+        if self.uses > 0 || self.name.starts_with('_') {
+            return None;
+        }
+
+        let (lint, diagnostic) = match self.kind {
+            BindingKind::Let => (
+                Lint::UnusedBinding,
+                warning!(
+                    self.span, "unused binding `{}`", self.name;
+                    hint: "prefix the name with an underscore to silence this"
+                ),
+            ),
+            BindingKind::Import => {
+                (Lint::UnusedImport, warning!(self.span, "unused import `{}`", self.name))
+            }
+            BindingKind::Param => (
+                Lint::ShadowedParameter,
+                warning!(
+                    self.shadowed?,
+                    "this binding shadows the parameter `{}` before it is used",
+                    self.name,
+                ),
+            ),
+            BindingKind::Other => return None,
+        };
+
+        Some(Finding { lint, diagnostic })
+    }
+}
+
+impl Checker<'_> {
+    /// Check a node and its descendants.
+    fn walk(&mut self, node: &LinkedNode) {
+        let mut scoped = false;
+        let mut defined = vec![];
+        match node.kind() {
+            SyntaxKind::Ident | SyntaxKind::MathIdent => return self.ident(node),
+            SyntaxKind::CodeBlock | SyntaxKind::ContentBlock => {
+                scoped = true;
+                self.scopes.push(vec![]);
+            }
+            SyntaxKind::SetRule => self.set_rule(node),
+            SyntaxKind::FuncCall => self.call(node),
+            SyntaxKind::Closure => {
+                scoped = true;
+                self.scopes.push(vec![]);
+
+                // Recursive calls don't count as uses of the outer binding.
+                let closure = node.cast::<ast::Closure>().unwrap();
+                if let Some(name) = closure.name() {
+                    self.define(name, BindingKind::Other);
+                }
+
+                for param in closure.params().children() {
+                    let names = match param {
+                        ast::Param::Pos(pattern) => pattern.bindings(),
+                        ast::Param::Named(named) => vec![named.name()],
+                        ast::Param::Spread(spread) => {
+                            spread.sink_ident().into_iter().collect()
+                        }
+                    };
+                    for name in names {
+                        self.define(name, BindingKind::Param);
+                    }
+                }
+            }
+            SyntaxKind::ForLoop => {
+                scoped = true;
+                self.scopes.push(vec![]);
+                let for_loop = node.cast::<ast::ForLoop>().unwrap();
+                for name in for_loop.pattern().bindings() {
+                    self.define(name, BindingKind::Other);
+                }
+            }
+            SyntaxKind::LetBinding => {
+                let binding = node.cast::<ast::LetBinding>().unwrap();
+                for name in binding.kind().bindings() {
+                    self.declarations.insert(name.span());
+                    defined.push((name, BindingKind::Let));
+                }
+            }
+            SyntaxKind::ModuleImport => self.import(node, &mut defined),
+            _ => {}
+        }
+
+        for child in node.children() {
+            self.walk(&child);
+        }
+
+        if scoped {
+            let scope = self.scopes.pop().unwrap_or_default();
+            self.findings.extend(scope.iter().filter_map(Binding::finding));
+        }
+
+        // A binding is only in scope after its initializer.
+        for (name, kind) in defined {
+            self.define(name, kind);
+        }
+    }
+
+    /// Collect the bindings introduced by an import.
+    fn import<'a>(
+        &mut self,
+        node: &'a LinkedNode,
+        defined: &mut Vec<(ast::Ident<'a>, BindingKind)>,
+    ) {
+        let import = node.cast::<ast::ModuleImport>().unwrap();
+        if let Some(name) = import.new_name() {
+            self.declarations.insert(name.span());
+            defined.push((name, BindingKind::Import));
+        }
+
+        match import.imports() {
+            Some(ast::Imports::Items(items)) => {
+                for item in items.iter() {
+                    let original = item.original_name();
+                    self.declarations.insert(original.span());
+                    self.mentions.insert(original.get().clone());
+
+                    let bound = item.bound_name();
+                    self.declarations.insert(bound.span());
+                    defined.push((bound, BindingKind::Import));
+                }
+            }
+            Some(ast::Imports::Wildcard) => {}
+            None if import.new_name().is_none() => {
+                // The module is bound to a name derived from its path.
+                if let Some(name) = module_name(import.source()) {
+                    let span = import.source().span();
+                    self.scope().push(Binding {
+                        name,
+                        span,
+                        kind: BindingKind::Import,
+                        uses: 0,
+                        shadowed: None,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Add a binding to the innermost scope.
+    fn define(&mut self, name: ast::Ident, kind: BindingKind) {
+        self.declarations.insert(name.span());
+        if matches!(kind, BindingKind::Let | BindingKind::Other) {
+            if let Some(param) = self.lookup(&name) {
+                if param.kind == BindingKind::Param && param.shadowed.is_none() {
+                    param.shadowed = Some(name.span());
+                }
+            }
+        }
+
+        self.scope().push(Binding {
+            name: name.get().clone(),
+            span: name.span(),
+            kind,
+            uses: 0,
+            shadowed: None,
+        });
+    }
+
+    /// The innermost scope.
+    fn scope(&mut self) -> &mut Vec<Binding> {
+        self.scopes.last_mut().unwrap()
+    }
+
+    /// Find the binding a name refers to.
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name))
+    }
+
+    /// Count an identifier as a use of the binding it refers to.
+    fn ident(&mut self, node: &LinkedNode) {
+        if self.declarations.contains(&node.span()) {
+            return;
+        }
+
+        let name: EcoString = node.text().clone();
+        if let Some(parent) = node.parent() {
+            match parent.kind() {
+                // The field of a field access, which may be defined by an
+                // imported module.
+                SyntaxKind::FieldAccess if node.index() > 0 => {
+                    self.mentions.insert(name);
+                    return;
+                }
+                // The key of a named argument or pair.
+                SyntaxKind::Named if node.index() == 0 => return,
+                _ => {}
+            }
+        }
+
+        if let Some(binding) = self.lookup(&name) {
+            binding.uses += 1;
+        }
+        self.mentions.insert(name);
+    }
+
+    /// Check whether a set rule applies to anything.
+    fn set_rule(&mut self, node: &LinkedNode) {
+        if !matches!(node.parent_kind(), Some(SyntaxKind::Markup | SyntaxKind::Code)) {
+            return;
+        }
+
+        let mut next = node.next_sibling();
+        while let Some(sibling) = next {
+            if !matches!(
+                sibling.kind(),
+                SyntaxKind::Hash
+                    | SyntaxKind::Semicolon
+                    | SyntaxKind::SetRule
+                    | SyntaxKind::ShowRule
+                    | SyntaxKind::LetBinding
+                    | SyntaxKind::ModuleImport
+            ) {
+                return;
+            }
+            next = sibling.next_sibling();
+        }
+
+        self.findings.push(Finding {
+            lint: Lint::IneffectiveSetRule,
+            diagnostic: warning!(
+                node.span(), "set rule has no effect";
+                hint: "set rules only apply to content that follows them in the same block"
+            ),
+        });
+    }
+
+    /// Check whether a call is to a function whose deprecation is planned.
+    fn call(&mut self, node: &LinkedNode) {
+        let call = node.cast::<ast::FuncCall>().unwrap();
+        let (span, name, func) = match call.callee() {
+            ast::Expr::Ident(ident) => {
+                if self.lookup(&ident).is_some() {
+                    return;
+                }
+                let global = self.world.library().global.scope();
+                let Some(Value::Func(func)) = global.get(&ident) else { return };
+
+                if ident.as_str() == "locate"
+                    && matches!(
+                        call.args().items().next(),
+                        Some(ast::Arg::Pos(ast::Expr::Closure(_)))
+                    )
+                {
+                    self.findings.push(Finding {
+                        lint: Lint::Deprecated,
+                        diagnostic: warning!(
+                            ident.span(),
+                            "calling `locate` with a function will be deprecated";
+                            hint: "use a `context` expression and `here()` instead"
+                        ),
+                    });
+                    return;
+                }
+
+                (ident.span(), ident.get().clone(), func.clone())
+            }
+            ast::Expr::FieldAccess(access) => {
+                let field = access.field();
+                if !self.deprecated.contains(field.as_str()) {
+                    return;
+                }
+                let Some(target) = node.find(access.target().span()) else { return };
+                let Some((Value::Func(func), ty)) = analyze_expr(self.world, &target)
+                    .into_iter()
+                    .find_map(|(value, _)| {
+                        let ty = value.ty();
+                        Some((ty.scope().get(&field)?.clone(), ty))
+                    })
+                else {
+                    return;
+                };
+                (
+                    field.span(),
+                    eco_format!("{}.{}", ty.short_name(), field.as_str()),
+                    func,
+                )
+            }
+            _ => return,
+        };
+
+        let Some(docs) = func.docs() else { return };
+        let Some((_, rest)) = docs.split_once(DEPRECATION_MARKER) else { return };
+
+        let mut diagnostic = warning!(span, "`{name}` will be deprecated");
+        let advice = plain_docs_sentence(rest.trim_start_matches([':', '.', '*']).trim());
+        let advice = advice.trim_end_matches('.');
+        if let Some(first) = advice.chars().next() {
+            diagnostic.hint(eco_format!(
+                "{}{}",
+                first.to_lowercase(),
+                &advice[first.len_utf8()..]
+            ));
+        }
+
+        self.findings.push(Finding { lint: Lint::Deprecated, diagnostic });
+    }
+}
+
+/// The names of the methods whose deprecation is planned on any type.
+fn deprecated_methods(world: &dyn World) -> HashSet<EcoString> {
+    let mut names = HashSet::new();
+    for (_, value) in world.library().global.scope().iter() {
+        let Value::Type(ty) = value else { continue };
+        for (name, method) in ty.scope().iter() {
+            if let Value::Func(func) = method {
+                if func.docs().is_some_and(|docs| docs.contains(DEPRECATION_MARKER)) {
+                    names.insert(name.clone());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Check the labels defined and referenced in the sources.
+fn labels(sources: &[Source], document: Option<&Document>, findings: &mut Vec<Finding>) {
+    let mut defined: Vec<(EcoString, Span)> = vec![];
+    let mut references = vec![];
+    for source in sources {
+        visit(&LinkedNode::new(source.root()), &mut |node| match node.kind() {
+            SyntaxKind::Label if node.parent_kind() == Some(SyntaxKind::Markup) => {
+                let label = node.cast::<ast::Label>().unwrap();
+                defined.push((label.get().into(), node.span()));
+            }
+            SyntaxKind::Ref => {
+                let reference = node.cast::<ast::Ref>().unwrap();
+                references.push((EcoString::from(reference.target()), node.span()));
+            }
+            _ => {}
+        });
+    }
+
+    let mut counts = HashMap::new();
+    for (name, span) in &defined {
+        let count = counts.entry(name.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            findings.push(Finding {
+                lint: Lint::DuplicateLabel,
+                diagnostic: warning!(*span, "label `<{name}>` is defined multiple times"),
+            });
+        }
+    }
+
+    let Some(document) = document else { return };
+    let (labels, split) = analyze_labels(document);
+    let mut attached: HashMap<EcoString, usize> = HashMap::new();
+    for (label, _) in &labels[..split] {
+        *attached.entry(label.as_str().into()).or_default() += 1;
+    }
+
+    for (name, span) in &defined {
+        let times = attached.get(name).copied().unwrap_or_default();
+        if counts[name] == 1 && times > 1 {
+            findings.push(Finding {
+                lint: Lint::DuplicateLabel,
+                diagnostic: warning!(
+                    *span,
+                    "label `<{name}>` is attached to {times} elements in the document";
+                    hint: "references to it will fail"
+                ),
+            });
+        }
+    }
+
+    for (name, span) in references {
+        if !labels.iter().any(|(label, _)| label.as_str() == name) {
+            findings.push(Finding {
+                lint: Lint::UndefinedReference,
+                diagnostic: warning!(
+                    span,
+                    "label `<{name}>` does not exist in the document"
+                ),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::eval::Tracer;
+
+    use super::lint;
+    use crate::tests::TestWorld;
+
+    /// The names of the lints with their messages and the text they point
+    /// at.
+    #[track_caller]
+    fn findings(world: &TestWorld) -> Vec<(&'static str, String, String)> {
+        let document = typst::compile(world, &mut Tracer::new()).ok();
+        lint(world, document.as_ref())
+            .into_iter()
+            .map(|finding| {
+                let span = finding.diagnostic.span;
+                let source = if span.id() == Some(world.main.id()) {
+                    world.main.clone()
+                } else {
+                    typst::World::source(world, span.id().unwrap()).unwrap()
+                };
+                let text = source.find(span).unwrap().get().clone().into_text();
+                (finding.lint.name(), finding.diagnostic.message.into(), text.into())
+            })
+            .collect()
+    }
+
+    #[track_caller]
+    fn test(text: &str, expected: &[(&str, &str, &str)]) {
+        let found = findings(&TestWorld::new(text));
+        let found: Vec<_> = found
+            .iter()
+            .map(|(lint, message, text)| (*lint, message.as_str(), text.as_str()))
+            .collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_lint_bindings() {
+        test(
+            "#let a = 1\n#let _b = 2\n#let f(x) = { let y = x; let x = 2; x }\n\
+             #let g(z) = { let z = 3; z }\n#f(a) #g(1)",
+            &[
+                ("unused-binding", "unused binding `y`", "y"),
+                (
+                    "shadowed-parameter",
+                    "this binding shadows the parameter `z` before it is used",
+                    "z",
+                ),
+            ],
+        );
+        test(
+            "#let fact(n) = if n > 0 { n * fact(n - 1) } else { 1 }\n\
+             #import \"@preview/example:0.1.0\"\n#import calc: pow, abs as magnitude\n\
+             #pow(2, 3) #(abs: 1)",
+            &[
+                ("unused-binding", "unused binding `fact`", "fact"),
+                (
+                    "unused-import",
+                    "unused import `example`",
+                    "\"@preview/example:0.1.0\"",
+                ),
+                ("unused-import", "unused import `magnitude`", "magnitude"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_modules() {
+        let world = TestWorld::new("#import \"lib.typ\": used\n#used").with_source(
+            "lib.typ",
+            "#let used = 1\n#let helper = 2\n#let unused = helper",
+        );
+        let found = findings(&world);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "unused binding `unused`");
+    }
+
+    #[test]
+    fn test_lint_package_entrypoint() {
+        let text = "#import \"util.typ\": helper\n#let api = 1\n#let _private = 2";
+        let manifest = "[package]\nname = \"pkg\"\nversion = \"0.1.0\"\n\
+                        entrypoint = \"lib.typ\"";
+
+        // The top-level bindings of the entrypoint are its exports.
+        let world = TestWorld::new(text)
+            .with_main("lib.typ")
+            .with_source("typst.toml", manifest)
+            .with_source("util.typ", "#let helper = 1\n#let unused = 2");
+        let found = findings(&world);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "unused binding `unused`");
+
+        // Other files of the package are checked as usual.
+        let world = TestWorld::new(text)
+            .with_main("main.typ")
+            .with_source("typst.toml", manifest)
+            .with_source("util.typ", "#let helper = 1");
+        let found = findings(&world);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].1, "unused import `helper`");
+        assert_eq!(found[1].1, "unused binding `api`");
+    }
+
+    #[test]
+    fn test_lint_rules_and_labels() {
+        test(
+            "#set text(red)\n= A <a>\n#[#set text(blue)]\nB <a>\n\
+             #if false [See @b.]\n#for i in range(2) [#figure[] <fig>]\n\
+             #set par(justify: true)",
+            &[
+                ("ineffective-set-rule", "set rule has no effect", "set text(blue)"),
+                (
+                    "ineffective-set-rule",
+                    "set rule has no effect",
+                    "set par(justify: true)",
+                ),
+                ("duplicate-label", "label `<a>` is defined multiple times", "<a>"),
+                (
+                    "duplicate-label",
+                    "label `<fig>` is attached to 2 elements in the document",
+                    "<fig>",
+                ),
+                (
+                    "undefined-reference",
+                    "label `<b>` does not exist in the document",
+                    "@b",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_deprecated() {
+        test(
+            "#style(styles => [])\n#let s = state(\"x\", 1)\n#s.display()\n\
+             #locate(loc => [])",
+            &[
+                ("deprecated", "`style` will be deprecated", "style"),
+                ("deprecated", "`state.display` will be deprecated", "display"),
+                (
+                    "deprecated",
+                    "calling `locate` with a function will be deprecated",
+                    "locate",
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_lint_allow() {
+        test(
+            "#let a = 1 // typst: allow(unused-binding)\n\
+             // typst: allow(unused-binding, deprecated)\n\
+             #let b = style(s => [])\n#let c = 3",
+            &[("unused-binding", "unused binding `c`", "c")],
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::ops::Range;
+
+use ecow::{eco_format, EcoString};
+use typst::diag::{bail, StrResult};
+use typst::foundations::Repr;
+use typst::syntax::ast::AstNode;
+use typst::syntax::{ast, is_id_continue, is_ident, FileId, LinkedNode, Source, Span};
+use typst::syntax::{SyntaxKind, SyntaxNode};
+use typst::World;
+
+use crate::definition::{
+    lookup, reachable, references, resolve, symbol_at, visit, Symbol, Target,
+};
+
+/// Check whether the symbol under the cursor can be renamed.
+///
+/// Returns the range of the symbol's name in the source file, which editors
+/// can highlight and use as the initial value of the new name.
+pub fn prepare_rename(
+    world: &dyn World,
+    source: &Source,
+    cursor: usize,
+) -> StrResult<Range<usize>> {
+    let (leaf, symbol) = renamable(world, source, cursor)?;
+    Ok(name_range(&leaf, &symbol))
+}
+
+/// Rename the symbol under the cursor.
+///
+/// Bindings are renamed at their definition and at all of their uses,
+/// including named arguments that refer to a renamed parameter. Labels are
+/// renamed at their `<label>` definitions, `@label` references, and
+/// `label("label")` strings. All files that are statically reachable from the
+/// source file or the main file are searched.
+///
+/// Returns an error if the symbol can't be renamed, if the new name isn't
+/// valid for it, or if a binding is renamed to a name that another binding
+/// visible at one of its references already has.
+pub fn rename(
+    world: &dyn World,
+    source: &Source,
+    cursor: usize,
+    new_name: &str,
+) -> StrResult<Vec<TextEdit>> {
+    let (leaf, symbol) = renamable(world, source, cursor)?;
+    let mut edits = vec![];
+
+    match &symbol {
+        Symbol::Binding(name) => {
+            if !is_ident(new_name) {
+                bail!("`{new_name}` is not a valid identifier");
+            }
+
+            if world.library().global.scope().get(new_name).is_some() {
+                bail!("`{new_name}` would shadow the global `{new_name}`");
+            }
+
+            let target = resolve(world, &leaf, true);
+            for span in references(world, source, cursor) {
+                let Some((id, node)) = find(world, source, span) else { continue };
+
+                // The new name must not refer to another binding at any of
+                // the references, neither before nor after renaming.
+                if new_name != name.as_str()
+                    && visible(world, source, span, new_name)
+                        .is_some_and(|other| Some(other) != target)
+                {
+                    bail!("`{new_name}` would collide with another binding of that name");
+                }
+
+                // Uses of renamed imports keep their new name.
+                if node.text() != name {
+                    continue;
+                }
+
+                if node.kind() == SyntaxKind::MathIdent && !is_math_ident(new_name) {
+                    bail!("`{new_name}` cannot be used as an identifier in math");
+                }
+
+                edits.push(TextEdit::new(id, span_range(world, source, span)?, new_name));
+            }
+
+            if let Some(Target::Binding(span)) = target {
+                for (id, range) in named_args(world, source, span, name) {
+                    edits.push(TextEdit::new(id, range, new_name));
+                }
+            }
+        }
+        Symbol::Label(_) => {
+            if !is_label(new_name) {
+                bail!("`{new_name}` is not a valid label name");
+            }
+
+            for span in references(world, source, cursor) {
+                let Some((id, node)) = find(world, source, span) else { continue };
+                let range = span_range(world, source, span)?;
+                let edit = match node.kind() {
+                    SyntaxKind::RefMarker => {
+                        TextEdit::new(id, range.start + 1..range.end, new_name)
+                    }
+                    SyntaxKind::Label => {
+                        TextEdit::new(id, range.start + 1..range.end - 1, new_name)
+                    }
+                    _ => TextEdit::new(id, range, &new_name.repr()),
+                };
+                edits.push(edit);
+            }
+        }
+    }
+
+    edits.sort_by_key(|edit| (edit.id, edit.range.start));
+    edits.dedup();
+    Ok(edits)
+}
+
+/// A replacement of a range of text in a file.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TextEdit {
+    /// The file to edit.
+    pub id: FileId,
+    /// The byte range to replace.
+    pub range: Range<usize>,
+    /// The text to insert in place of the range.
+    pub text: EcoString,
+}
+
+impl TextEdit {
+    fn new(id: FileId, range: Range<usize>, text: &str) -> Self {
+        Self { id, range, text: text.into() }
+    }
+}
+
+/// Find the symbol under the cursor and ensure that it can be renamed.
+fn renamable<'a>(
+    world: &dyn World,
+    source: &'a Source,
+    cursor: usize,
+) -> StrResult<(LinkedNode<'a>, Symbol)> {
+    let Some((leaf, symbol)) = symbol_at(source, cursor) else {
+        bail!("there is no binding or label at the cursor");
+    };
+
+    if let Symbol::Binding(name) = &symbol {
+        match resolve(world, &leaf, true) {
+            Some(Target::Binding(span)) => {
+                if span.id().is_some_and(|id| id.package().is_some()) {
+                    bail!("cannot rename `{name}` because it is defined in a package");
+                }
+            }
+            Some(Target::Module(_)) => bail!("cannot rename the module `{name}`"),
+            None => bail!("cannot rename `{name}` because its definition is unknown"),
+        }
+    }
+
+    Ok((leaf, symbol))
+}
+
+/// The range of just the name in a node that refers to a symbol.
+fn name_range(node: &LinkedNode, symbol: &Symbol) -> Range<usize> {
+    let range = node.range();
+    match (symbol, node.kind()) {
+        (Symbol::Label(_), SyntaxKind::RefMarker) => range.start + 1..range.end,
+        (Symbol::Label(_), SyntaxKind::Label | SyntaxKind::Str) => {
+            range.start + 1..range.end - 1
+        }
+        _ => range,
+    }
+}
+
+/// Find the node with the given span, preferring the given source over the
+/// world's version of the same file.
+fn find(world: &dyn World, source: &Source, span: Span) -> Option<(FileId, SyntaxNode)> {
+    let id = span.id()?;
+    let node = if id == source.id() {
+        source.find(span)?.get().clone()
+    } else {
+        world.source(id).ok()?.find(span)?.get().clone()
+    };
+    Some((id, node))
+}
+
+/// Find the binding visible under the given name at the node with the given
+/// span, preferring the given source over the world's version of the same
+/// file.
+fn visible(world: &dyn World, source: &Source, span: Span, name: &str) -> Option<Target> {
+    let id = span.id()?;
+    let source =
+        if id == source.id() { source.clone() } else { world.source(id).ok()? };
+    let node = source.find(span)?;
+    lookup(world, &node, name, 0)
+}
+
+/// The byte range of a span, preferring the given source over the world's
+/// version of the same file.
+fn span_range(world: &dyn World, source: &Source, span: Span) -> StrResult<Range<usize>> {
+    let range = match span.id() {
+        Some(id) if id == source.id() => source.range(span),
+        Some(id) => world.source(id).ok().and_then(|source| source.range(span)),
+        None => None,
+    };
+    range.ok_or_else(|| eco_format!("failed to locate a reference to the symbol"))
+}
+
+/// Find the names of named arguments in calls that set the parameter
+/// introduced by the identifier with the given span.
+fn named_args(
+    world: &dyn World,
+    source: &Source,
+    param: Span,
+    name: &str,
+) -> Vec<(FileId, Range<usize>)> {
+    let Some(function) = param.id().and_then(|id| {
+        let source =
+            if id == source.id() { source.clone() } else { world.source(id).ok()? };
+        function_of(&source.find(param)?)
+    }) else {
+        return vec![];
+    };
+
+    let mut found = vec![];
+    for source in reachable(world, source) {
+        visit(&LinkedNode::new(source.root()), &mut |node| {
+            let Some(call) = node.cast::<ast::FuncCall>() else { return };
+            let Some(callee) = node.find(call.callee().span()) else { return };
+            let callee = match callee.kind() {
+                SyntaxKind::FieldAccess => match callee.children().last() {
+                    Some(field) => field,
+                    None => return,
+                },
+                _ => callee,
+            };
+
+            if resolve(world, &callee, false) != Some(Target::Binding(function)) {
+                return;
+            }
+
+            for arg in call.args().items() {
+                let ast::Arg::Named(named) = arg else { continue };
+                if named.name().as_str() == name {
+                    if let Some(range) = source.range(named.name().span()) {
+                        found.push((source.id(), range));
+                    }
+                }
+            }
+        });
+    }
+
+    found
+}
+
+/// If the identifier is the name of a named parameter, find the span of the
+/// identifier the function with the parameter is bound to.
+fn function_of(ident: &LinkedNode) -> Option<Span> {
+    let named = ident.parent().filter(|parent| parent.kind() == SyntaxKind::Named)?;
+    let params = named.parent().filter(|parent| parent.kind() == SyntaxKind::Params)?;
+    let node = params.parent()?;
+    let closure = node.cast::<ast::Closure>()?;
+    if let Some(name) = closure.name() {
+        return Some(name.span());
+    }
+
+    // An unnamed closure that is bound directly: `let f = (x: 1) => x`.
+    let binding = node.parent()?.cast::<ast::LetBinding>()?;
+    match binding.kind().bindings().as_slice() {
+        [name] => Some(name.span()),
+        _ => None,
+    }
+}
+
+/// Whether a name can be used as a label in `<label>` and `@label`.
+fn is_label(name: &str) -> bool {
+    !name.is_empty()
+        && !name.ends_with(['.', ':'])
+        && name.chars().all(|c| is_id_continue(c) || matches!(c, '.' | ':'))
+}
+
+/// Whether a name can be used as an identifier in math, where single letters
+/// are plain text and underscores and hyphens aren't part of identifiers.
+fn is_math_ident(name: &str) -> bool {
+    name.chars().count() > 1 && is_ident(name) && !name.contains(['_', '-'])
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::World;
+
+    use super::{prepare_rename, rename};
+    use crate::tests::TestWorld;
+
+    /// Rename the symbol at the cursor and apply the edits to all files.
+    #[track_caller]
+    fn apply(world: &TestWorld, cursor: usize, new_name: &str) -> Vec<String> {
+        let edits = rename(world, &world.main, cursor, new_name).unwrap();
+        let mut ids: Vec<_> = edits.iter().map(|edit| edit.id).collect();
+        ids.dedup();
+        ids.into_iter()
+            .map(|id| {
+                let mut text = world.source(id).unwrap().text().to_string();
+                for edit in edits.iter().rev().filter(|edit| edit.id == id) {
+                    text.replace_range(edit.range.clone(), &edit.text);
+                }
+                text
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_binding() {
+        let world = TestWorld::new("#let x = 1\n#let f(x) = x + 1\n#x #f(x) $x + xy$");
+        assert_eq!(
+            apply(&world, 5, "y"),
+            ["#let y = 1\n#let f(x) = x + 1\n#y #f(y) $x + xy$"]
+        );
+        assert_eq!(
+            apply(&world, 18, "value"),
+            ["#let x = 1\n#let f(value) = value + 1\n#x #f(x) $x + xy$"]
+        );
+    }
+
+    #[test]
+    fn test_rename_across_files() {
+        let world = TestWorld::new(
+            "#import \"utils.typ\": add, add as plus\n#add(1, 2) #plus(3, 4)",
+        )
+        .with_source("utils.typ", "#let add(a, b: 0) = a + b\n#add(1, b: 2)");
+        assert_eq!(
+            apply(&world, 39, "sum"),
+            [
+                "#import \"utils.typ\": sum, sum as plus\n#sum(1, 2) #plus(3, 4)",
+                "#let sum(a, b: 0) = a + b\n#sum(1, b: 2)",
+            ]
+        );
+
+        let world = TestWorld::new("#let f(a, b: 0) = a + b\n#f(1, b: 2) #f(b: 3, 1)");
+        assert_eq!(
+            apply(&world, 10, "c"),
+            ["#let f(a, c: 0) = a + c\n#f(1, c: 2) #f(c: 3, 1)"]
+        );
+    }
+
+    #[test]
+    fn test_rename_label() {
+        let world = TestWorld::new("= A <a>\n@a #ref(label(\"a\")) #ref(<b>)");
+        assert_eq!(prepare_rename(&world, &world.main, 9), Ok(9..10));
+        assert_eq!(
+            apply(&world, 9, "sec:intro"),
+            ["= A <sec:intro>\n@sec:intro #ref(label(\"sec:intro\")) #ref(<b>)"]
+        );
+    }
+
+    #[test]
+    fn test_rename_collision() {
+        // Another binding is visible at the definition.
+        let world = TestWorld::new("#let a = 1\n#let b = 2\n#(a + b)");
+        assert!(rename(&world, &world.main, 17, "a").is_err());
+
+        // A binding in an inner scope would capture a reference.
+        let world = TestWorld::new("#let x = 1\n#{ let y = 2; x + y }");
+        assert!(rename(&world, &world.main, 5, "y").is_err());
+        assert!(rename(&world, &world.main, 5, "z").is_ok());
+
+        // Bindings of the same name elsewhere don't matter.
+        let world = TestWorld::new("#{ let y = 2; y }\n#let x = 1\n#x");
+        assert_eq!(apply(&world, 24, "y"), ["#{ let y = 2; y }\n#let y = 1\n#y"]);
+
+        // Globals would be shadowed.
+        let world = TestWorld::new("#let x = 1\n#x");
+        assert!(rename(&world, &world.main, 5, "text").is_err());
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        let world = TestWorld::new("#let xy = 1\n$xy + 1$ #text(red)[<a>]");
+        assert!(rename(&world, &world.main, 5, "1x").is_err());
+        assert!(rename(&world, &world.main, 5, "a-b").is_err());
+        assert!(rename(&world, &world.main, 5, "ab").is_ok());
+        assert!(rename(&world, &world.main, 27, "blue").is_err());
+        assert!(rename(&world, &world.main, 33, "a.").is_err());
+        assert!(prepare_rename(&world, &world.main, 0).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use std::ops::Range;
+
+use ecow::{eco_format, EcoString};
+use typst::foundations::{CastInfo, Func, Value};
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{LinkedNode, Side, Source, SyntaxKind};
+use typst::World;
+
+use crate::analyze::analyze_expr;
+use crate::definition::{resolve, Target};
+use crate::plain_docs_sentence;
+
+/// Describe the signature of the function whose argument list the cursor is
+/// in.
+///
+/// Works for native functions, elements, and closures. The active parameter
+/// is the one that the argument under the cursor sets, if it can be
+/// determined.
+pub fn signature_help(
+    world: &dyn World,
+    source: &Source,
+    cursor: usize,
+) -> Option<SignatureHelp> {
+    let leaf = LinkedNode::new(source.root()).leaf_at(cursor, Side::Before)?;
+    let args = args_at(&leaf, cursor)?;
+    let parent = args.parent()?;
+    let (callee, set) = match parent.cast::<ast::Expr>()? {
+        ast::Expr::FuncCall(call) => (parent.find(call.callee().span())?, false),
+        ast::Expr::Set(rule) => (parent.find(rule.target().span())?, true),
+        _ => return None,
+    };
+
+    let mut signature = signature(world, &callee, true)?;
+    if set {
+        signature.params.retain(|param| param.settable);
+    }
+
+    let mut label = eco_format!("{}(", signature.name);
+    let mut params = vec![];
+    for (i, param) in signature.params.iter().enumerate() {
+        if i > 0 {
+            label.push_str(", ");
+        }
+        let start = label.len();
+        label.push_str(&param.label);
+        params.push(SignatureParam {
+            range: start..label.len(),
+            docs: param.docs.clone(),
+        });
+    }
+    label.push(')');
+
+    let active = active_param(&signature.params, &args, cursor);
+    Some(SignatureHelp { label, docs: signature.docs, params, active })
+}
+
+/// The signature of a function, with the parameter that is being set.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SignatureHelp {
+    /// The signature as it is displayed, e.g. `pow(base: int | float,
+    /// exponent: int | float)`.
+    pub label: EcoString,
+    /// The first sentence of the function's documentation.
+    pub docs: Option<EcoString>,
+    /// The function's parameters.
+    pub params: Vec<SignatureParam>,
+    /// The index of the parameter that the argument under the cursor sets.
+    pub active: Option<usize>,
+}
+
+/// A parameter in a signature.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SignatureParam {
+    /// The byte range of the parameter in the signature's label.
+    pub range: Range<usize>,
+    /// The first sentence of the parameter's documentation.
+    pub docs: Option<EcoString>,
+}
+
+/// What is known about a function's parameters.
+pub(crate) struct Signature {
+    /// The function's name.
+    pub name: EcoString,
+    /// The first sentence of the function's documentation.
+    pub docs: Option<EcoString>,
+    /// The parameters, in order.
+    pub params: Vec<Param>,
+}
+
+/// A parameter of a native function or closure.
+pub(crate) struct Param {
+    /// The parameter's name.
+    pub name: EcoString,
+    /// How the parameter is displayed in a signature.
+    pub label: EcoString,
+    /// The first sentence of the parameter's documentation.
+    pub docs: Option<EcoString>,
+    /// Whether the parameter can be given positionally.
+    pub positional: bool,
+    /// Whether the parameter can be given any number of times.
+    pub variadic: bool,
+    /// Whether the parameter is settable with a set rule.
+    pub settable: bool,
+}
+
+/// Find the signature of the function a callee expression refers to.
+///
+/// Bindings are resolved syntactically first. When `dynamic` is true, callees
+/// that can't be resolved like this are resolved by analyzing their value.
+pub(crate) fn signature(
+    world: &dyn World,
+    callee: &LinkedNode,
+    dynamic: bool,
+) -> Option<Signature> {
+    let ident = match callee.kind() {
+        SyntaxKind::Ident => Some(callee.clone()),
+        SyntaxKind::FieldAccess => callee.children().last(),
+        _ => None,
+    };
+
+    if let Some(ident) = &ident {
+        match resolve(world, ident, false) {
+            Some(Target::Binding(span)) => {
+                let source = world.source(span.id()?).ok()?;
+                let node = source.find(span)?;
+                if let Some(signature) = closure_of(&node)
+                    .and_then(|closure| closure_signature(ident.text(), closure))
+                {
+                    return Some(signature);
+                }
+            }
+            Some(Target::Module(_)) => return None,
+            None => {
+                if let Some(func) = global(world, callee) {
+                    return func_signature(world, func);
+                }
+            }
+        }
+    }
+
+    if !dynamic {
+        return None;
+    }
+
+    analyze_expr(world, callee)
+        .into_iter()
+        .find_map(|(value, _)| match value {
+            Value::Func(func) => func_signature(world, &func),
+            _ => None,
+        })
+}
+
+/// Find the argument list that contains the cursor, not looking into
+/// blocks.
+fn args_at<'a>(leaf: &LinkedNode<'a>, cursor: usize) -> Option<LinkedNode<'a>> {
+    // Whitespace after an unclosed argument list isn't part of it.
+    args_around(leaf, cursor).or_else(|| args_around(&leaf.prev_leaf()?, cursor))
+}
+
+/// Find the argument list that contains a leaf and the cursor.
+fn args_around<'a>(leaf: &LinkedNode<'a>, cursor: usize) -> Option<LinkedNode<'a>> {
+    let mut node = leaf.clone();
+    while node.kind() != SyntaxKind::Args {
+        if matches!(node.kind(), SyntaxKind::CodeBlock | SyntaxKind::ContentBlock) {
+            return None;
+        }
+        node = node.parent()?.clone();
+    }
+
+    // The cursor must be within the parentheses, which may be unclosed
+    // while typing.
+    let mut children = node.children();
+    let open = children.find(|child| {
+        child.kind() == SyntaxKind::LeftParen
+            || (child.kind() == SyntaxKind::Error && child.text() == "(")
+    })?;
+    let close = children.find(|child| child.kind() == SyntaxKind::RightParen);
+    (cursor > open.offset() && close.map_or(true, |close| cursor <= close.offset()))
+        .then_some(node)
+}
+
+/// Determine the parameter that is set by the argument under the cursor.
+fn active_param(params: &[Param], args: &LinkedNode, cursor: usize) -> Option<usize> {
+    // The number of positional arguments before the current one, which is the
+    // last argument before the cursor that isn't followed by a comma yet.
+    let mut positional = 0;
+    let mut current = None;
+    for child in args.children() {
+        if child.offset() >= cursor {
+            break;
+        }
+
+        if child.kind() == SyntaxKind::Comma {
+            match current.take() {
+                Some(ast::Arg::Pos(_)) => positional += 1,
+                Some(ast::Arg::Spread(_)) => return None,
+                _ => {}
+            }
+        } else if let Some(arg) = child.get().cast::<ast::Arg>() {
+            current = Some(arg);
+        }
+    }
+
+    match current {
+        Some(ast::Arg::Named(named)) => {
+            params.iter().position(|param| param.name == *named.name().get())
+        }
+        Some(ast::Arg::Spread(_)) => None,
+        _ => positional_param(params, positional),
+    }
+}
+
+/// Find the parameter that the positional argument with the given index
+/// sets.
+pub(crate) fn positional_param(params: &[Param], index: usize) -> Option<usize> {
+    let mut positional = params.iter().enumerate().filter(|(_, param)| param.positional);
+    match positional.clone().nth(index) {
+        Some((i, _)) => Some(i),
+        None => positional
+            .next_back()
+            .filter(|(_, param)| param.variadic)
+            .map(|(i, _)| i),
+    }
+}
+
+/// Find the closure that an identifier names.
+fn closure_of<'a>(ident: &LinkedNode<'a>) -> Option<ast::Closure<'a>> {
+    let parent = ident.parent()?;
+    if let Some(closure) = parent.get().cast::<ast::Closure>() {
+        return Some(closure);
+    }
+
+    // An unnamed closure that is bound directly: `let f = (x) => x`.
+    let binding = parent.get().cast::<ast::LetBinding>()?;
+    match binding.init()? {
+        ast::Expr::Closure(closure) => Some(closure),
+        _ => None,
+    }
+}
+
+/// Resolve a callee to a function in the global scope of the standard
+/// library.
+fn global<'a>(world: &'a dyn World, callee: &LinkedNode) -> Option<&'a Func> {
+    let global = world.library().global.scope();
+    let value = match callee.cast::<ast::Expr>()? {
+        ast::Expr::Ident(ident) => global.get(&ident)?,
+        ast::Expr::FieldAccess(access) => match access.target() {
+            ast::Expr::Ident(target) => match global.get(&target)? {
+                Value::Module(module) => module.field(&access.field()).ok()?,
+                Value::Func(func) => func.field(&access.field()).ok()?,
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    match value {
+        Value::Func(func) => Some(func),
+        _ => None,
+    }
+}
+
+/// The signature of a function value.
+fn func_signature(world: &dyn World, func: &Func) -> Option<Signature> {
+    let name: EcoString = func.name().unwrap_or("function").into();
+    let Some(infos) = func.params() else {
+        // A closure, whose parameters are only known from its syntax.
+        let span = func.span();
+        let source = world.source(span.id()?).ok()?;
+        let node = source.find(span)?;
+        return closure_signature(&name, node.cast::<ast::Closure>()?);
+    };
+
+    let params = infos
+        .iter()
+        .map(|info| Param {
+            name: info.name.into(),
+            label: eco_format!(
+                "{}{}: {}",
+                if info.variadic { ".." } else { "" },
+                info.name,
+                describe(&info.input)
+            ),
+            docs: Some(plain_docs_sentence(info.docs)).filter(|docs| !docs.is_empty()),
+            positional: info.positional,
+            variadic: info.variadic,
+            settable: info.settable,
+        })
+        .collect();
+
+    Some(Signature {
+        name,
+        docs: func.docs().map(plain_docs_sentence),
+        params,
+    })
+}
+
+/// The signature of a closure, from its syntax.
+fn closure_signature(name: &str, closure: ast::Closure) -> Option<Signature> {
+    let params = closure
+        .params()
+        .children()
+        .map(|param| {
+            let label = param.to_untyped().clone().into_text();
+            let (name, positional, variadic) = match param {
+                ast::Param::Pos(pattern) => {
+                    (pattern.to_untyped().clone().into_text(), true, false)
+                }
+                ast::Param::Named(named) => (named.name().get().clone(), false, false),
+                ast::Param::Spread(spread) => (
+                    spread
+                        .sink_ident()
+                        .map(|ident| ident.get().clone())
+                        .unwrap_or_default(),
+                    true,
+                    true,
+                ),
+            };
+            Param {
+                name,
+                label,
+                docs: None,
+                positional,
+                variadic,
+                settable: false,
+            }
+        })
+        .collect();
+
+    Some(Signature { name: name.into(), docs: None, params })
+}
+
+/// Describe the values a parameter accepts, e.g. `none | content`.
+fn describe(info: &CastInfo) -> EcoString {
+    let mut types: Vec<EcoString> = vec![];
+    info.walk(|info| {
+        let ty = match info {
+            CastInfo::Any => "any".into(),
+            CastInfo::Value(value, _) => value.ty().short_name().into(),
+            CastInfo::Type(ty) => ty.short_name().into(),
+            CastInfo::Union(_) => return,
+        };
+        if !types.contains(&ty) {
+            types.push(ty);
+        }
+    });
+
+    let mut description = EcoString::new();
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            description.push_str(" | ");
+        }
+        description.push_str(ty);
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::signature_help;
+    use crate::tests::TestWorld;
+
+    /// The signature, its parameters, and the active one at the end of the
+    /// text.
+    #[track_caller]
+    fn help(text: &str) -> (String, Vec<String>, Option<usize>) {
+        let world = TestWorld::new(text);
+        let help = signature_help(&world, &world.main, text.len()).unwrap();
+        let params = help
+            .params
+            .iter()
+            .map(|param| help.label[param.range.clone()].into())
+            .collect();
+        (help.label.into(), params, help.active)
+    }
+
+    #[test]
+    fn test_signature_help_native() {
+        let (label, params, active) = help("#calc.pow(2, ");
+        assert_eq!(label, "pow(base: int | float, exponent: int | float)");
+        assert_eq!(params, ["base: int | float", "exponent: int | float"]);
+        assert_eq!(active, Some(1));
+
+        let (label, params, active) = help("#figure([], caption: ");
+        assert!(label.starts_with("figure(body: content, placement: "));
+        assert_eq!(params[active.unwrap()], "caption: content | none");
+
+        let (_, params, active) = help("#set text(");
+        assert!(!params.iter().any(|param| param.starts_with("body")));
+        assert_eq!(active, None);
+    }
+
+    #[test]
+    fn test_signature_help_closure() {
+        let text = "#let f(x, (a, b), y: 1, ..rest) = x\n#f(1, (2, 3), 4, ";
+        let (label, params, active) = help(text);
+        assert_eq!(label, "f(x, (a, b), y: 1, ..rest)");
+        assert_eq!(params[active.unwrap()], "..rest");
+        assert_eq!(help("#let f(x, y: 1) = x\n#f(1, y: ").2, Some(1));
+
+        let (label, _, active) = help("#let g = (a, b) => a\n#g(1, ");
+        assert_eq!(label, "g(a, b)");
+        assert_eq!(active, Some(1));
+
+        let world = TestWorld::new("#f(1)[]");
+        assert_eq!(signature_help(&world, &world.main, 6), None);
+    }
+}
@@ -0,0 +1,226 @@
+use std::ops::Range;
+
+use ecow::EcoString;
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{LinkedNode, Source, SyntaxKind, SyntaxNode};
+
+/// Build an outline of a source file.
+///
+/// Headings are nested by their level and each spans its whole section, up
+/// to the next heading of the same or a higher level. Top-level `let`
+/// bindings are placed into the section they are defined in.
+pub fn document_symbols(source: &Source) -> Vec<DocumentSymbol> {
+    let mut items = vec![];
+    collect(&LinkedNode::new(source.root()), &mut items);
+
+    let mut outline = Outline { roots: vec![], open: vec![] };
+    for (depth, symbol) in items {
+        match depth {
+            Some(depth) => {
+                outline.close(depth, symbol.range.start);
+                outline.open.push((depth, symbol));
+            }
+            None => outline.push(symbol),
+        }
+    }
+
+    outline.close(1, source.len_bytes());
+    outline.roots
+}
+
+/// An entry in the outline of a source file.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DocumentSymbol {
+    /// The name of the symbol.
+    pub name: EcoString,
+    /// What kind of symbol this is.
+    pub kind: DocumentSymbolKind,
+    /// The byte range of the whole symbol, including a heading's section.
+    pub range: Range<usize>,
+    /// The byte range of just the symbol's name.
+    pub selection: Range<usize>,
+    /// The symbols nested in this one.
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The kind of a document symbol.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DocumentSymbolKind {
+    /// A section heading.
+    Heading,
+    /// A binding of a function.
+    Function,
+    /// A binding of any other value.
+    Variable,
+}
+
+/// Nests symbols into an outline.
+struct Outline {
+    /// The finished top-level symbols.
+    roots: Vec<DocumentSymbol>,
+    /// The headings whose section hasn't ended yet, with their depth.
+    open: Vec<(usize, DocumentSymbol)>,
+}
+
+impl Outline {
+    /// Add a symbol to the innermost open section.
+    fn push(&mut self, symbol: DocumentSymbol) {
+        match self.open.last_mut() {
+            Some((_, parent)) => parent.children.push(symbol),
+            None => self.roots.push(symbol),
+        }
+    }
+
+    /// End the sections of all open headings that are at least as deep as
+    /// the given depth.
+    fn close(&mut self, depth: usize, end: usize) {
+        while self.open.last().is_some_and(|&(open, _)| open >= depth) {
+            let (_, mut symbol) = self.open.pop().unwrap();
+            symbol.range.end = end;
+            self.push(symbol);
+        }
+    }
+}
+
+/// Collect headings and top-level bindings in document order. Headings come
+/// with their depth.
+fn collect(node: &LinkedNode, items: &mut Vec<(Option<usize>, DocumentSymbol)>) {
+    for child in node.children() {
+        match child.kind() {
+            SyntaxKind::Heading => {
+                let heading = child.cast::<ast::Heading>().unwrap();
+                let body = heading.body();
+                let name = plain(body.to_untyped());
+                if name.is_empty() {
+                    continue;
+                }
+
+                items.push((
+                    Some(heading.depth().get()),
+                    DocumentSymbol {
+                        name,
+                        kind: DocumentSymbolKind::Heading,
+                        range: child.range(),
+                        selection: child.find(body.span()).unwrap_or(child).range(),
+                        children: vec![],
+                    },
+                ));
+            }
+            SyntaxKind::LetBinding if node.parent().is_none() => {
+                let binding = child.cast::<ast::LetBinding>().unwrap();
+                let kind = match binding.init() {
+                    Some(ast::Expr::Closure(_)) => DocumentSymbolKind::Function,
+                    _ => DocumentSymbolKind::Variable,
+                };
+
+                for name in binding.kind().bindings() {
+                    let Some(ident) = child.find(name.span()) else { continue };
+                    items.push((
+                        None,
+                        DocumentSymbol {
+                            name: name.get().clone(),
+                            kind,
+                            range: child.range(),
+                            selection: ident.range(),
+                            children: vec![],
+                        },
+                    ));
+                }
+            }
+            // Headings in functions belong to the documents the function is
+            // used in.
+            SyntaxKind::Closure => {}
+            _ => collect(&child, items),
+        }
+    }
+}
+
+/// The plain text of a heading's body, with whitespace collapsed.
+fn plain(node: &SyntaxNode) -> EcoString {
+    fn walk(node: &SyntaxNode, buf: &mut String) {
+        match node.kind() {
+            SyntaxKind::Label
+            | SyntaxKind::LineComment
+            | SyntaxKind::BlockComment
+            | SyntaxKind::Hash
+            | SyntaxKind::RawDelim
+            | SyntaxKind::RawLang => {}
+            SyntaxKind::Space | SyntaxKind::Parbreak | SyntaxKind::Linebreak => {
+                buf.push(' ')
+            }
+            SyntaxKind::Escape => {
+                buf.extend(node.cast::<ast::Escape>().map(|escape| escape.get()))
+            }
+            SyntaxKind::Shorthand => {
+                buf.extend(node.cast::<ast::Shorthand>().map(|shorthand| shorthand.get()))
+            }
+            SyntaxKind::Strong | SyntaxKind::Emph => {
+                for child in node.children() {
+                    if child.kind() == SyntaxKind::Markup {
+                        walk(child, buf);
+                    }
+                }
+            }
+            _ if node.children().len() == 0 => buf.push_str(node.text()),
+            _ => {
+                for child in node.children() {
+                    walk(child, buf);
+                }
+            }
+        }
+    }
+
+    let mut buf = String::new();
+    walk(node, &mut buf);
+    buf.split_whitespace().collect::<Vec<_>>().join(" ").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::Source;
+
+    use super::{document_symbols, DocumentSymbol, DocumentSymbolKind};
+
+    /// Flatten an outline into its names, kinds and depths.
+    fn flatten(
+        symbols: &[DocumentSymbol],
+        depth: usize,
+    ) -> Vec<(usize, &str, DocumentSymbolKind)> {
+        symbols
+            .iter()
+            .flat_map(|symbol| {
+                std::iter::once((depth, symbol.name.as_str(), symbol.kind))
+                    .chain(flatten(&symbol.children, depth + 1))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        use DocumentSymbolKind::*;
+
+        let source = Source::detached(
+            "#let title = [A]\n= Intro <intro>\n#let f(x) = x\n== *Bold* -- it\n\
+             #let (a, b) = (1, 2)\n#{ let hidden = 1 }\n= End\n#let g = x => x",
+        );
+        let symbols = document_symbols(&source);
+        assert_eq!(
+            flatten(&symbols, 0),
+            [
+                (0, "title", Variable),
+                (0, "Intro", Heading),
+                (1, "f", Function),
+                (1, "Bold – it", Heading),
+                (2, "a", Variable),
+                (2, "b", Variable),
+                (0, "End", Heading),
+                (1, "g", Function),
+            ]
+        );
+
+        let intro = &symbols[1];
+        assert_eq!(intro.range, 17..source.text().find("= End").unwrap());
+        assert_eq!(intro.selection, 19..24);
+        assert_eq!(symbols[2].range.end, source.len_bytes());
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use ecow::EcoString;
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::{highlight, LinkedNode, Source, Span, SyntaxKind, Tag};
+
+/// Split a source file into semantic tokens.
+///
+/// In contrast to [`highlight`], which tags each syntax node on its own, this
+/// produces one token per leaf, taking the surrounding markup into account
+/// and resolving identifiers to the functions, parameters and variables they
+/// refer to. The tokens are sorted and don't overlap. Whitespace and leaves
+/// that carry no meaning of their own are skipped.
+pub fn semantic_tokens(source: &Source) -> Vec<SemanticToken> {
+    let mut tokenizer = Tokenizer {
+        tokens: vec![],
+        scopes: vec![vec![]],
+        declarations: HashMap::new(),
+    };
+    tokenizer.walk(
+        &LinkedNode::new(source.root()),
+        None,
+        SemanticTokenModifiers::empty(),
+    );
+    tokenizer.tokens
+}
+
+/// A classified range of a source file.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SemanticToken {
+    /// The byte range of the token.
+    pub range: Range<usize>,
+    /// What kind of token this is.
+    pub kind: SemanticTokenKind,
+    /// Additional properties of the token.
+    pub modifiers: SemanticTokenModifiers,
+}
+
+/// The kind of a semantic token.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SemanticTokenKind {
+    /// A line or block comment.
+    Comment,
+    /// Punctuation in code or math.
+    Punctuation,
+    /// An escape sequence or shorthand.
+    Escape,
+    /// Strong markup.
+    Strong,
+    /// Emphasized markup.
+    Emph,
+    /// A hyperlink.
+    Link,
+    /// Raw text.
+    Raw,
+    /// A label.
+    Label,
+    /// A reference to a label.
+    Ref,
+    /// A section heading.
+    Heading,
+    /// A marker of a list, enumeration, or term list.
+    ListMarker,
+    /// A term in a term list.
+    ListTerm,
+    /// A keyword.
+    Keyword,
+    /// An operator in code or math.
+    Operator,
+    /// A numeric literal.
+    Number,
+    /// A string literal.
+    String,
+    /// A function, either where it is defined or where it is called.
+    Function,
+    /// A parameter of a function or the name of a named argument.
+    Parameter,
+    /// Any other variable.
+    Variable,
+}
+
+impl SemanticTokenKind {
+    /// The list of all kinds, in the same order as they are defined.
+    ///
+    /// Can be used as the counter-part to `kind as usize`.
+    pub const LIST: &'static [Self] = &[
+        Self::Comment,
+        Self::Punctuation,
+        Self::Escape,
+        Self::Strong,
+        Self::Emph,
+        Self::Link,
+        Self::Raw,
+        Self::Label,
+        Self::Ref,
+        Self::Heading,
+        Self::ListMarker,
+        Self::ListTerm,
+        Self::Keyword,
+        Self::Operator,
+        Self::Number,
+        Self::String,
+        Self::Function,
+        Self::Parameter,
+        Self::Variable,
+    ];
+}
+
+bitflags::bitflags! {
+    /// Additional properties of a semantic token.
+    #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+    pub struct SemanticTokenModifiers: u8 {
+        /// The token is the name of a newly defined binding.
+        const DECLARATION = 1 << 0;
+        /// The token is part of an equation.
+        const MATH = 1 << 1;
+        /// The token is within strong markup.
+        const STRONG = 1 << 2;
+        /// The token is within emphasized markup.
+        const EMPH = 1 << 3;
+    }
+}
+
+/// Walks a syntax tree and collects its tokens.
+struct Tokenizer {
+    /// The tokens collected so far.
+    tokens: Vec<SemanticToken>,
+    /// The bindings that are in scope, innermost last.
+    scopes: Vec<Vec<(EcoString, SemanticTokenKind)>>,
+    /// The identifiers that define a binding, by span.
+    declarations: HashMap<Span, SemanticTokenKind>,
+}
+
+impl Tokenizer {
+    /// Collect the tokens of a node and its descendants.
+    ///
+    /// The tag is the innermost highlighting tag of an ancestor, which leaves
+    /// without a tag of their own inherit.
+    fn walk(
+        &mut self,
+        node: &LinkedNode,
+        tag: Option<Tag>,
+        mut modifiers: SemanticTokenModifiers,
+    ) {
+        let tag = highlight(node).or(tag);
+        match node.kind() {
+            SyntaxKind::Strong => modifiers |= SemanticTokenModifiers::STRONG,
+            SyntaxKind::Emph => modifiers |= SemanticTokenModifiers::EMPH,
+            SyntaxKind::Equation => modifiers |= SemanticTokenModifiers::MATH,
+            _ => {}
+        }
+
+        if node.children().len() == 0 {
+            self.leaf(node, tag, modifiers);
+            return;
+        }
+
+        // The names defined by a node that only come into scope after it.
+        let mut defined = vec![];
+        let mut scoped = false;
+        match node.kind() {
+            SyntaxKind::CodeBlock | SyntaxKind::ContentBlock => {
+                scoped = true;
+                self.scopes.push(vec![]);
+            }
+            SyntaxKind::Closure => {
+                let closure = node.cast::<ast::Closure>().unwrap();
+                // The name is in scope in the body to allow for recursion.
+                if let Some(name) = closure.name() {
+                    self.declare(name, SemanticTokenKind::Function);
+                    self.define(name, SemanticTokenKind::Function);
+                }
+
+                scoped = true;
+                self.scopes.push(vec![]);
+                for param in closure.params().children() {
+                    let names = match param {
+                        ast::Param::Pos(pattern) => pattern.bindings(),
+                        ast::Param::Named(named) => vec![named.name()],
+                        ast::Param::Spread(spread) => {
+                            spread.sink_ident().into_iter().collect()
+                        }
+                    };
+                    for name in names {
+                        self.declare(name, SemanticTokenKind::Parameter);
+                        self.define(name, SemanticTokenKind::Parameter);
+                    }
+                }
+            }
+            SyntaxKind::LetBinding => {
+                let binding = node.cast::<ast::LetBinding>().unwrap();
+                let kind = match binding.init() {
+                    Some(ast::Expr::Closure(_)) => SemanticTokenKind::Function,
+                    _ => SemanticTokenKind::Variable,
+                };
+                for name in binding.kind().bindings() {
+                    self.declare(name, kind);
+                    defined.push((name, kind));
+                }
+            }
+            SyntaxKind::ForLoop => {
+                let for_loop = node.cast::<ast::ForLoop>().unwrap();
+                scoped = true;
+                self.scopes.push(vec![]);
+                for name in for_loop.pattern().bindings() {
+                    self.declare(name, SemanticTokenKind::Variable);
+                    self.define(name, SemanticTokenKind::Variable);
+                }
+            }
+            SyntaxKind::ModuleImport => {
+                let import = node.cast::<ast::ModuleImport>().unwrap();
+                let mut names: Vec<_> = import.new_name().into_iter().collect();
+                if let Some(ast::Imports::Items(items)) = import.imports() {
+                    names.extend(items.iter().map(ast::ImportItem::bound_name));
+                }
+                for name in names {
+                    self.declare(name, SemanticTokenKind::Variable);
+                    defined.push((name, SemanticTokenKind::Variable));
+                }
+            }
+            _ => {}
+        }
+
+        for child in node.children() {
+            self.walk(&child, tag, modifiers);
+        }
+
+        if scoped {
+            self.scopes.pop();
+        }
+
+        for (name, kind) in defined {
+            self.define(name, kind);
+        }
+    }
+
+    /// Collect the token of a leaf.
+    fn leaf(
+        &mut self,
+        node: &LinkedNode,
+        tag: Option<Tag>,
+        modifiers: SemanticTokenModifiers,
+    ) {
+        if node.text().trim().is_empty() {
+            return;
+        }
+
+        let token = match node.kind() {
+            SyntaxKind::Ident | SyntaxKind::MathIdent => self.ident(node),
+            SyntaxKind::Hash => self.hash(node, tag),
+            _ => tag.and_then(kind).map(|kind| (kind, SemanticTokenModifiers::empty())),
+        };
+
+        if let Some((kind, extra)) = token {
+            self.tokens.push(SemanticToken {
+                range: node.range(),
+                kind,
+                modifiers: modifiers | extra,
+            });
+        }
+    }
+
+    /// Classify an identifier.
+    fn ident(
+        &self,
+        node: &LinkedNode,
+    ) -> Option<(SemanticTokenKind, SemanticTokenModifiers)> {
+        if let Some(&kind) = self.declarations.get(&node.span()) {
+            return Some((kind, SemanticTokenModifiers::DECLARATION));
+        }
+
+        let function = highlight(node) == Some(Tag::Function);
+        let parent = node.parent()?;
+        let first = node.prev_sibling().is_none();
+        let kind = match parent.kind() {
+            // The field of a field access doesn't refer to a binding in scope.
+            SyntaxKind::FieldAccess if !first => {
+                if function {
+                    SemanticTokenKind::Function
+                } else {
+                    SemanticTokenKind::Variable
+                }
+            }
+            // Named arguments set parameters, but dictionary keys are just
+            // strings.
+            SyntaxKind::Named if first => match parent.parent_kind() {
+                Some(SyntaxKind::Args) => SemanticTokenKind::Parameter,
+                _ => return None,
+            },
+            _ if function => SemanticTokenKind::Function,
+            _ => self.lookup(node.text()).unwrap_or(SemanticTokenKind::Variable),
+        };
+
+        Some((kind, SemanticTokenModifiers::empty()))
+    }
+
+    /// Classify a hash like the expression it introduces.
+    fn hash(
+        &self,
+        node: &LinkedNode,
+        tag: Option<Tag>,
+    ) -> Option<(SemanticTokenKind, SemanticTokenModifiers)> {
+        let leaf = node.next_sibling().and_then(|next| next.leftmost_leaf());
+        match leaf {
+            Some(leaf) if leaf.kind() == SyntaxKind::Ident => {
+                let (kind, _) = self.ident(&leaf)?;
+                Some((kind, SemanticTokenModifiers::empty()))
+            }
+            _ => tag.and_then(kind).map(|kind| (kind, SemanticTokenModifiers::empty())),
+        }
+    }
+
+    /// Mark an identifier as the definition of a binding.
+    fn declare(&mut self, name: ast::Ident, kind: SemanticTokenKind) {
+        self.declarations.insert(name.span(), kind);
+    }
+
+    /// Bring a binding into the innermost scope.
+    fn define(&mut self, name: ast::Ident, kind: SemanticTokenKind) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push((name.get().clone(), kind));
+        }
+    }
+
+    /// Find the kind of the innermost binding with the given name.
+    fn lookup(&self, name: &str) -> Option<SemanticTokenKind> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(binding, _)| binding == name)
+            .map(|&(_, kind)| kind)
+    }
+}
+
+/// The semantic token kind for a highlighting tag.
+fn kind(tag: Tag) -> Option<SemanticTokenKind> {
+    Some(match tag {
+        Tag::Comment => SemanticTokenKind::Comment,
+        Tag::Punctuation | Tag::MathDelimiter => SemanticTokenKind::Punctuation,
+        Tag::Escape => SemanticTokenKind::Escape,
+        Tag::Strong => SemanticTokenKind::Strong,
+        Tag::Emph => SemanticTokenKind::Emph,
+        Tag::Link => SemanticTokenKind::Link,
+        Tag::Raw => SemanticTokenKind::Raw,
+        Tag::Label => SemanticTokenKind::Label,
+        Tag::Ref => SemanticTokenKind::Ref,
+        Tag::Heading => SemanticTokenKind::Heading,
+        Tag::ListMarker => SemanticTokenKind::ListMarker,
+        Tag::ListTerm => SemanticTokenKind::ListTerm,
+        Tag::Keyword => SemanticTokenKind::Keyword,
+        Tag::Operator | Tag::MathOperator => SemanticTokenKind::Operator,
+        Tag::Number => SemanticTokenKind::Number,
+        Tag::String => SemanticTokenKind::String,
+        Tag::Function => SemanticTokenKind::Function,
+        Tag::Interpolated => SemanticTokenKind::Variable,
+        Tag::Error => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::Source;
+
+    use super::{semantic_tokens, SemanticTokenKind, SemanticTokenModifiers};
+
+    /// The text, kind and modifiers of all tokens.
+    fn tokens(text: &str) -> Vec<(String, SemanticTokenKind, SemanticTokenModifiers)> {
+        let source = Source::detached(text);
+        semantic_tokens(&source)
+            .into_iter()
+            .map(|token| (text[token.range].to_string(), token.kind, token.modifiers))
+            .collect()
+    }
+
+    /// The kinds of the tokens with the given text.
+    #[track_caller]
+    fn kinds_of(
+        text: &str,
+        token: &str,
+    ) -> Vec<(SemanticTokenKind, SemanticTokenModifiers)> {
+        tokens(text)
+            .into_iter()
+            .filter(|(text, _, _)| text == token)
+            .map(|(_, kind, modifiers)| (kind, modifiers))
+            .collect()
+    }
+
+    #[test]
+    fn test_semantic_tokens_bindings() {
+        use SemanticTokenKind::*;
+        const DECL: SemanticTokenModifiers = SemanticTokenModifiers::DECLARATION;
+        const NONE: SemanticTokenModifiers = SemanticTokenModifiers::empty();
+
+        let text = "#let f(x, y: 1) = x + y\n#let z = f(2, y: 3)\n#f(z) #z #x";
+        assert_eq!(
+            kinds_of(text, "f"),
+            [(Function, DECL), (Function, NONE), (Function, NONE)]
+        );
+        assert_eq!(
+            kinds_of(text, "x"),
+            [(Parameter, DECL), (Parameter, NONE), (Variable, NONE)]
+        );
+        assert_eq!(
+            kinds_of(text, "y"),
+            [(Parameter, DECL), (Parameter, NONE), (Parameter, NONE)]
+        );
+        assert_eq!(
+            kinds_of(text, "z"),
+            [(Variable, DECL), (Variable, NONE), (Variable, NONE)]
+        );
+        assert_eq!(
+            kinds_of(text, "#"),
+            [
+                (Keyword, NONE),
+                (Keyword, NONE),
+                (Function, NONE),
+                (Variable, NONE),
+                (Variable, NONE)
+            ]
+        );
+
+        let text = "#for x in (1, 2) { x.abs() + (a: x).a }";
+        assert_eq!(
+            kinds_of(text, "x"),
+            [(Variable, DECL), (Variable, NONE), (Variable, NONE)]
+        );
+        assert_eq!(kinds_of(text, "abs"), [(Function, NONE)]);
+        assert_eq!(kinds_of(text, "a"), [(Variable, NONE)]);
+    }
+
+    #[test]
+    fn test_semantic_tokens_markup() {
+        use SemanticTokenKind::*;
+        const MATH: SemanticTokenModifiers = SemanticTokenModifiers::MATH;
+
+        assert_eq!(
+            tokens("= A *b _c_* <l>\n@l $pi^a sin(x)$"),
+            [
+                ("=".into(), Heading, SemanticTokenModifiers::empty()),
+                ("A".into(), Heading, SemanticTokenModifiers::empty()),
+                ("*".into(), Strong, SemanticTokenModifiers::STRONG),
+                ("b".into(), Strong, SemanticTokenModifiers::STRONG),
+                (
+                    "_".into(),
+                    Emph,
+                    SemanticTokenModifiers::STRONG | SemanticTokenModifiers::EMPH
+                ),
+                (
+                    "c".into(),
+                    Emph,
+                    SemanticTokenModifiers::STRONG | SemanticTokenModifiers::EMPH
+                ),
+                (
+                    "_".into(),
+                    Emph,
+                    SemanticTokenModifiers::STRONG | SemanticTokenModifiers::EMPH
+                ),
+                ("*".into(), Strong, SemanticTokenModifiers::STRONG),
+                ("<l>".into(), Label, SemanticTokenModifiers::empty()),
+                ("@l".into(), Ref, SemanticTokenModifiers::empty()),
+                ("$".into(), Punctuation, MATH),
+                ("pi".into(), Variable, MATH),
+                ("^".into(), Operator, MATH),
+                ("sin".into(), Function, MATH),
+                ("(".into(), Punctuation, MATH),
+                (")".into(), Punctuation, MATH),
+                ("$".into(), Punctuation, MATH),
+            ]
+        );
+    }
+}
@@ -36,6 +36,16 @@ pub struct ColorSpaces {
     use_linear_rgb: bool,
 }
 
+/// The color spaces used by some content, which must be present in the
+/// document it is part of.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct UsedColorSpaces {
+    pub oklab: bool,
+    pub srgb: bool,
+    pub d65_gray: bool,
+    pub linear_rgb: bool,
+}
+
 impl ColorSpaces {
     /// Get a reference to the oklab color space.
     ///
@@ -61,6 +71,22 @@ impl ColorSpaces {
         self.use_linear_rgb = true;
     }
 
+    /// Add the color spaces used by some content.
+    pub fn merge(&mut self, used: UsedColorSpaces, alloc: &mut Ref) {
+        if used.oklab {
+            self.oklab(alloc);
+        }
+        if used.srgb {
+            self.srgb(alloc);
+        }
+        if used.d65_gray {
+            self.d65_gray(alloc);
+        }
+        if used.linear_rgb {
+            self.linear_rgb();
+        }
+    }
+
     /// Write the color space on usage.
     pub fn write(
         &mut self,
@@ -235,29 +261,37 @@ impl PaintEncode for Color {
     fn set_as_fill(&self, ctx: &mut PageContext, _: bool, _: Transforms) {
         match self {
             Color::Luma(_) => {
-                ctx.parent.colors.d65_gray(&mut ctx.parent.alloc);
+                ctx.resources.colors.d65_gray = true;
                 ctx.set_fill_color_space(D65_GRAY);
 
                 let [l, _, _, _] = ColorSpace::D65Gray.encode(*self);
                 ctx.content.set_fill_color([l]);
             }
-            // Oklch is converted to Oklab.
-            Color::Oklab(_) | Color::Oklch(_) | Color::Hsl(_) | Color::Hsv(_) => {
-                ctx.parent.colors.oklab(&mut ctx.parent.alloc);
+            // Oklch is converted to Oklab. PDF/A forbids the Oklab color space
+            // as its components aren't registered colorants, so we then fall
+            // back to its alternate space, linear RGB.
+            Color::Oklab(_) | Color::Oklch(_) | Color::Hsl(_) | Color::Hsv(_)
+                if !ctx.pdfa =>
+            {
+                ctx.resources.colors.oklab = true;
                 ctx.set_fill_color_space(OKLAB);
 
                 let [l, a, b, _] = ColorSpace::Oklab.encode(*self);
                 ctx.content.set_fill_color([l, a, b]);
             }
-            Color::LinearRgb(_) => {
-                ctx.parent.colors.linear_rgb();
+            Color::LinearRgb(_)
+            | Color::Oklab(_)
+            | Color::Oklch(_)
+            | Color::Hsl(_)
+            | Color::Hsv(_) => {
+                ctx.resources.colors.linear_rgb = true;
                 ctx.set_fill_color_space(LINEAR_SRGB);
 
                 let [r, g, b, _] = ColorSpace::LinearRgb.encode(*self);
                 ctx.content.set_fill_color([r, g, b]);
             }
             Color::Rgb(_) => {
-                ctx.parent.colors.srgb(&mut ctx.parent.alloc);
+                ctx.resources.colors.srgb = true;
                 ctx.set_fill_color_space(SRGB);
 
                 let [r, g, b, _] = ColorSpace::Srgb.encode(*self);
@@ -275,29 +309,37 @@ impl PaintEncode for Color {
     fn set_as_stroke(&self, ctx: &mut PageContext, _: bool, _: Transforms) {
         match self {
             Color::Luma(_) => {
-                ctx.parent.colors.d65_gray(&mut ctx.parent.alloc);
+                ctx.resources.colors.d65_gray = true;
                 ctx.set_stroke_color_space(D65_GRAY);
 
                 let [l, _, _, _] = ColorSpace::D65Gray.encode(*self);
                 ctx.content.set_stroke_color([l]);
             }
-            // Oklch is converted to Oklab.
-            Color::Oklab(_) | Color::Oklch(_) | Color::Hsl(_) | Color::Hsv(_) => {
-                ctx.parent.colors.oklab(&mut ctx.parent.alloc);
+            // Oklch is converted to Oklab. PDF/A forbids the Oklab color space
+            // as its components aren't registered colorants, so we then fall
+            // back to its alternate space, linear RGB.
+            Color::Oklab(_) | Color::Oklch(_) | Color::Hsl(_) | Color::Hsv(_)
+                if !ctx.pdfa =>
+            {
+                ctx.resources.colors.oklab = true;
                 ctx.set_stroke_color_space(OKLAB);
 
                 let [l, a, b, _] = ColorSpace::Oklab.encode(*self);
                 ctx.content.set_stroke_color([l, a, b]);
             }
-            Color::LinearRgb(_) => {
-                ctx.parent.colors.linear_rgb();
+            Color::LinearRgb(_)
+            | Color::Oklab(_)
+            | Color::Oklch(_)
+            | Color::Hsl(_)
+            | Color::Hsv(_) => {
+                ctx.resources.colors.linear_rgb = true;
                 ctx.set_stroke_color_space(LINEAR_SRGB);
 
                 let [r, g, b, _] = ColorSpace::LinearRgb.encode(*self);
                 ctx.content.set_stroke_color([r, g, b]);
             }
             Color::Rgb(_) => {
-                ctx.parent.colors.srgb(&mut ctx.parent.alloc);
+                ctx.resources.colors.srgb = true;
                 ctx.set_stroke_color_space(SRGB);
 
                 let [r, g, b, _] = ColorSpace::Srgb.encode(*self);
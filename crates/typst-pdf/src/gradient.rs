@@ -1,10 +1,9 @@
 use std::f32::consts::{PI, TAU};
 use std::sync::Arc;
 
-use ecow::eco_format;
 use pdf_writer::types::{ColorSpaceOperand, FunctionShadingType};
 use pdf_writer::writers::StreamShadingType;
-use pdf_writer::{Filter, Finish, Name, Ref};
+use pdf_writer::{Filter, Finish, Ref};
 use typst::layout::{Abs, Angle, Point, Quadrant, Ratio, Transform};
 use typst::util::Numeric;
 use typst::visualize::{
@@ -39,12 +38,18 @@ pub(crate) fn write_gradients(ctx: &mut PdfContext) {
         let shading = ctx.alloc.bump();
         ctx.gradient_refs.push(shading);
 
-        let color_space = if gradient.space().hue_index().is_some() {
+        let mut color_space = if gradient.space().hue_index().is_some() {
             ColorSpace::Oklab
         } else {
             gradient.space()
         };
 
+        // PDF/A forbids the Oklab color space, so we interpolate in its
+        // alternate space instead.
+        if ctx.standards.pdfa.is_some() && color_space == ColorSpace::Oklab {
+            color_space = ColorSpace::LinearRgb;
+        }
+
         let mut shading_pattern = match &gradient {
             Gradient::Linear(_) => {
                 let shading_function = shading_function(ctx, &gradient, color_space);
@@ -104,7 +109,8 @@ pub(crate) fn write_gradients(ctx: &mut PdfContext) {
                 shading_pattern
             }
             Gradient::Conic(_) => {
-                let vertices = compute_vertex_stream(&gradient, aspect_ratio);
+                let vertices =
+                    compute_vertex_stream(&gradient, aspect_ratio, color_space);
 
                 let stream_shading_id = ctx.alloc.bump();
                 let mut stream_shading =
@@ -156,11 +162,11 @@ fn shading_function(
     for window in gradient.stops_ref().windows(2) {
         let (first, second) = (window[0], window[1]);
 
-        // If we have a hue index, we will create several stops in-between
-        // to make the gradient smoother without interpolation issues with
-        // native color spaces.
+        // If we have a hue index or write the gradient in a different color
+        // space, we will create several stops in-between to make the gradient
+        // smoother without interpolation issues with native color spaces.
         let mut last_c = first.0;
-        if gradient.space().hue_index().is_some() {
+        if color_space != gradient.space() {
             for i in 0..=32 {
                 let t = i as f64 / 32.0;
                 let real_t = first.1.get() * (1.0 - t) + second.1.get() * t;
@@ -224,13 +230,10 @@ impl PaintEncode for Gradient {
         ctx.reset_fill_color_space();
 
         let index = register_gradient(ctx, self, on_text, transforms);
-        let id = eco_format!("Gr{index}");
-        let name = Name(id.as_bytes());
+        let resource = PageResource::new(ResourceKind::Gradient, index);
 
         ctx.content.set_fill_color_space(ColorSpaceOperand::Pattern);
-        ctx.content.set_fill_pattern(None, name);
-        ctx.resources
-            .insert(PageResource::new(ResourceKind::Gradient, id), index);
+        ctx.content.set_fill_pattern(None, resource.name());
     }
 
     fn set_as_stroke(
@@ -242,13 +245,10 @@ impl PaintEncode for Gradient {
         ctx.reset_stroke_color_space();
 
         let index = register_gradient(ctx, self, on_text, transforms);
-        let id = eco_format!("Gr{index}");
-        let name = Name(id.as_bytes());
+        let resource = PageResource::new(ResourceKind::Gradient, index);
 
         ctx.content.set_stroke_color_space(ColorSpaceOperand::Pattern);
-        ctx.content.set_stroke_pattern(None, name);
-        ctx.resources
-            .insert(PageResource::new(ResourceKind::Gradient, id), index);
+        ctx.content.set_stroke_pattern(None, resource.name());
     }
 }
 
@@ -307,7 +307,7 @@ fn register_gradient(
         angle: Gradient::correct_aspect_ratio(rotation, size.aspect_ratio()),
     };
 
-    ctx.parent.gradient_map.insert(pdf_gradient)
+    ctx.resources.gradients.insert(pdf_gradient)
 }
 
 /// Writes a single Coons Patch as defined in the PDF specification
@@ -387,7 +387,11 @@ fn control_point(c: Point, r: f32, angle_start: f32, angle_end: f32) -> (Point,
 }
 
 #[comemo::memoize]
-fn compute_vertex_stream(gradient: &Gradient, aspect_ratio: Ratio) -> Arc<Vec<u8>> {
+fn compute_vertex_stream(
+    gradient: &Gradient,
+    aspect_ratio: Ratio,
+    encode_space: ColorSpace,
+) -> Arc<Vec<u8>> {
     let Gradient::Conic(conic) = gradient else { unreachable!() };
 
     // Generated vertices for the Coons patches
@@ -410,11 +414,6 @@ fn compute_vertex_stream(gradient: &Gradient, aspect_ratio: Ratio) -> Arc<Vec<u8
         } else {
             0.05
         };
-        let encode_space = conic
-            .space
-            .hue_index()
-            .map(|_| ColorSpace::Oklab)
-            .unwrap_or(conic.space);
         let mut t_x = t0.get();
         let dt = (t1.get() - t0.get()).min(max_dt);
 
@@ -8,24 +8,28 @@ mod image;
 mod outline;
 mod page;
 mod pattern;
+mod pdfa;
+mod tag;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 use base64::Engine;
 use ecow::{eco_format, EcoString};
-use pdf_writer::types::Direction;
-use pdf_writer::writers::Destination;
+use pdf_writer::types::{Direction, OutputIntentSubtype};
+use pdf_writer::writers::{Destination, OutputIntent};
 use pdf_writer::{Finish, Name, Pdf, Ref, Str, TextStr};
+use typst::diag::{bail, SourceResult, StrResult};
 use typst::foundations::{Datetime, Label, NativeElement, Smart};
 use typst::introspection::Location;
-use typst::layout::{Abs, Dir, Em, Transform};
+use typst::layout::{Abs, Dir, Em, PageRanges, Transform};
 use typst::model::{Document, HeadingElem};
 use typst::text::{Font, Lang};
 use typst::util::Deferred;
 use typst::visualize::Image;
-use xmp_writer::{DateTime, LangId, RenditionClass, Timezone, XmpWriter};
+use xmp_writer::{DateTime, LangId, Namespace, RenditionClass, Timezone, XmpWriter};
 
 use crate::color::ColorSpaces;
 use crate::extg::ExtGState;
@@ -33,34 +37,37 @@ use crate::gradient::PdfGradient;
 use crate::image::EncodedImage;
 use crate::page::EncodedPage;
 use crate::pattern::PdfPattern;
+use crate::tag::Tags;
 
 /// Export a document into a PDF file.
 ///
 /// Returns the raw bytes making up the PDF file.
 ///
-/// The `ident` parameter, if given, shall be a string that uniquely and stably
-/// identifies the document. It should not change between compilations of the
-/// same document.  **If you cannot provide such a stable identifier, just pass
-/// `Smart::Auto` rather than trying to come up with one.** The CLI, for
-/// example, does not have a well-defined notion of a long-lived project and as
-/// such just passes `Smart::Auto`.
-///
-/// If an `ident` is given, the hash of it will be used to create a PDF document
-/// identifier (the identifier itself is not leaked). If `ident` is `Auto`, a
-/// hash of the document's title and author is used instead (which is reasonably
-/// unique and stable).
-///
-/// The `timestamp`, if given, is expected to be the creation date of the
-/// document as a UTC datetime. It will only be used if `set document(date: ..)`
-/// is `auto`.
-#[typst_macros::time(name = "pdf")]
+/// See [`PdfOptions`] for the meaning of `ident` and `timestamp`.
+#[deprecated = "use `pdf_with_options` instead"]
 pub fn pdf(
     document: &Document,
     ident: Smart<&str>,
     timestamp: Option<Datetime>,
 ) -> Vec<u8> {
-    let mut ctx = PdfContext::new(document);
-    page::construct_pages(&mut ctx, &document.pages);
+    let options = PdfOptions { ident, timestamp, ..PdfOptions::default() };
+    pdf_with_options(document, &options)
+        .expect("export without standards should not fail")
+}
+
+/// Export a document into a PDF file with the given options.
+///
+/// Returns the raw bytes making up the PDF file or errors if the document
+/// doesn't conform to the requested [standards](PdfOptions::standards).
+#[typst_macros::time(name = "pdf")]
+pub fn pdf_with_options(
+    document: &Document,
+    options: &PdfOptions,
+) -> SourceResult<Vec<u8>> {
+    let mut ctx = PdfContext::new(document, options.standards.clone());
+    page::construct_pages(&mut ctx, &document.pages, options.page_ranges.as_ref());
+    tag::validate(&ctx)?;
+    pdfa::validate(&ctx)?;
     font::write_fonts(&mut ctx);
     image::write_images(&mut ctx);
     gradient::write_gradients(&mut ctx);
@@ -68,8 +75,89 @@ pub fn pdf(
     pattern::write_patterns(&mut ctx);
     write_named_destinations(&mut ctx);
     page::write_page_tree(&mut ctx);
-    write_catalog(&mut ctx, ident, timestamp);
-    ctx.pdf.finish()
+    write_catalog(&mut ctx, options.ident, options.timestamp);
+    Ok(ctx.pdf.finish())
+}
+
+/// Settings for PDF export.
+#[derive(Debug, Default)]
+pub struct PdfOptions<'a> {
+    /// If given, shall be a string that uniquely and stably identifies the
+    /// document. It should not change between compilations of the same
+    /// document.  **If you cannot provide such a stable identifier, just pass
+    /// `Smart::Auto` rather than trying to come up with one.** The CLI, for
+    /// example, does not have a well-defined notion of a long-lived project and
+    /// as such just passes `Smart::Auto`.
+    ///
+    /// If an `ident` is given, the hash of it will be used to create a PDF
+    /// document identifier (the identifier itself is not leaked). If `ident` is
+    /// `Auto`, a hash of the document's title and author is used instead (which
+    /// is reasonably unique and stable).
+    pub ident: Smart<&'a str>,
+    /// If given, is expected to be the creation date of the document as a UTC
+    /// datetime. It will only be used if `set document(date: ..)` is `auto`.
+    pub timestamp: Option<Datetime>,
+    /// The standards the PDF should conform to.
+    pub standards: PdfStandards,
+    /// Which pages to export. When `None`, all pages are exported.
+    ///
+    /// Named destinations and outline entries for pages that are not exported
+    /// are left out and links to such pages don't navigate anywhere.
+    pub page_ranges: Option<PageRanges>,
+}
+
+/// A set of PDF standards a document should conform to.
+///
+/// Conformance with a standard writes the structures and metadata it requires
+/// and turns violations of its rules into export errors. PDF/UA-1, for
+/// instance, adds the document's logical structure.
+#[derive(Debug, Default, Clone)]
+pub struct PdfStandards {
+    /// Whether to conform to PDF/UA-1.
+    ua: bool,
+    /// The part of PDF/A to conform to at conformance level B, if any.
+    pdfa: Option<i32>,
+}
+
+impl PdfStandards {
+    /// Validates a list of PDF standards for compatibility and returns their
+    /// combination.
+    pub fn new(list: &[PdfStandard]) -> StrResult<Self> {
+        let mut standards = Self::default();
+        for standard in list {
+            match standard {
+                PdfStandard::V_1_7 => {}
+                PdfStandard::A_2b => standards.set_pdfa(2)?,
+                PdfStandard::A_3b => standards.set_pdfa(3)?,
+                PdfStandard::Ua_1 => standards.ua = true,
+            }
+        }
+        Ok(standards)
+    }
+
+    /// Selects a part of PDF/A, which must not conflict with a previous one.
+    fn set_pdfa(&mut self, part: i32) -> StrResult<()> {
+        if let Some(previous) = self.pdfa.filter(|&previous| previous != part) {
+            bail!("PDF/A-{previous}b and PDF/A-{part}b cannot be combined");
+        }
+        self.pdfa = Some(part);
+        Ok(())
+    }
+}
+
+/// A PDF standard that Typst can enforce conformance with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[allow(non_camel_case_types)]
+#[non_exhaustive]
+pub enum PdfStandard {
+    /// PDF 1.7.
+    V_1_7,
+    /// PDF/A-2b, the archival standard based on PDF 1.7.
+    A_2b,
+    /// PDF/A-3b, which is like PDF/A-2b, but permits arbitrary embedded files.
+    A_3b,
+    /// PDF/UA-1, the accessibility standard.
+    Ua_1,
 }
 
 /// Context for exporting a whole PDF document.
@@ -80,6 +168,9 @@ struct PdfContext<'a> {
     pdf: Pdf,
     /// Content of exported pages.
     pages: Vec<EncodedPage>,
+    /// For each page of the document, the index of its content in `pages` if
+    /// it is exported.
+    page_indices: Vec<Option<usize>>,
     /// For each font a mapping from used glyphs to their text representation.
     /// May contain multiple chars in case of ligatures or similar things. The
     /// same glyph can have a different text representation within one document,
@@ -128,16 +219,30 @@ struct PdfContext<'a> {
     dests: Vec<(Label, Ref)>,
     /// Maps from locations to named destinations that point to them.
     loc_to_dest: HashMap<Location, Label>,
+
+    /// The logical structure of the document.
+    tags: Tags,
+    /// The standards the document should conform to.
+    standards: PdfStandards,
 }
 
 impl<'a> PdfContext<'a> {
-    fn new(document: &'a Document) -> Self {
+    fn new(document: &'a Document, standards: PdfStandards) -> Self {
         let mut alloc = Ref::new(1);
         let page_tree_ref = alloc.bump();
+
+        // The output intent required by PDF/A refers to the sRGB profile, so it
+        // must be written even if no color uses it.
+        let mut colors = ColorSpaces::default();
+        if standards.pdfa.is_some() {
+            colors.srgb(&mut alloc);
+        }
+
         Self {
             document,
             pdf: Pdf::new(),
             pages: vec![],
+            page_indices: vec![],
             glyph_sets: HashMap::new(),
             languages: BTreeMap::new(),
             alloc,
@@ -148,7 +253,7 @@ impl<'a> PdfContext<'a> {
             gradient_refs: vec![],
             pattern_refs: vec![],
             ext_gs_refs: vec![],
-            colors: ColorSpaces::default(),
+            colors,
             font_map: Remapper::new(),
             image_map: Remapper::new(),
             image_deferred_map: HashMap::default(),
@@ -157,8 +262,16 @@ impl<'a> PdfContext<'a> {
             extg_map: Remapper::new(),
             dests: vec![],
             loc_to_dest: HashMap::new(),
+            tags: Tags::default(),
+            standards,
         }
     }
+
+    /// The index in `pages` of the page with the given one-indexed physical
+    /// page number, if that page is exported.
+    fn page_index(&self, number: NonZeroUsize) -> Option<usize> {
+        self.page_indices.get(number.get() - 1).copied().flatten()
+    }
 }
 
 /// Write the document catalog.
@@ -177,6 +290,9 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     // Write the page labels.
     let page_labels = page::write_page_labels(ctx);
 
+    // Write the logical structure.
+    let struct_tree_root = ctx.standards.ua.then(|| tag::write_structure(ctx));
+
     // Write the document information.
     let mut info = ctx.pdf.document_info(ctx.alloc.bump());
     let mut xmp = XmpWriter::new();
@@ -232,7 +348,7 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     }
 
     info.finish();
-    xmp.num_pages(ctx.document.pages.len() as u32);
+    xmp.num_pages(ctx.pages.len() as u32);
     xmp.format("application/pdf");
     xmp.language(ctx.languages.keys().map(|lang| LangId(lang.as_str())));
 
@@ -263,6 +379,18 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     xmp.rendition_class(RenditionClass::Proof);
     xmp.pdf_version("1.7");
 
+    if ctx.standards.ua {
+        xmp.element("part", PDFUA_ID).value(1);
+    }
+
+    if let Some(part) = ctx.standards.pdfa {
+        xmp.pdfa_part(&part.to_string());
+        xmp.pdfa_conformance("B");
+        if ctx.standards.ua {
+            pdfa::write_pdfua_extension_schema(&mut xmp);
+        }
+    }
+
     let xmp_buf = xmp.finish(None);
     let meta_ref = ctx.alloc.bump();
     ctx.pdf
@@ -270,11 +398,35 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
         .pair(Name(b"Type"), Name(b"Metadata"))
         .pair(Name(b"Subtype"), Name(b"XML"));
 
+    // PDF/A documents declare sRGB as the color space of their output device.
+    let output_profile = ctx.standards.pdfa.map(|_| ctx.colors.srgb(&mut ctx.alloc));
+
     // Write the document catalog.
     let mut catalog = ctx.pdf.catalog(ctx.alloc.bump());
     catalog.pages(ctx.page_tree_ref);
-    catalog.viewer_preferences().direction(dir);
+    let mut preferences = catalog.viewer_preferences();
+    preferences.direction(dir);
+    if ctx.standards.ua {
+        preferences.pair(Name(b"DisplayDocTitle"), true);
+    }
+    preferences.finish();
     catalog.metadata(meta_ref);
+    if let Some(struct_tree_root) = struct_tree_root {
+        catalog.pair(Name(b"StructTreeRoot"), struct_tree_root);
+        catalog.mark_info().marked(true);
+    }
+
+    if let Some(profile) = output_profile {
+        catalog
+            .insert(Name(b"OutputIntents"))
+            .array()
+            .push()
+            .start::<OutputIntent>()
+            .subtype(OutputIntentSubtype::PDFA)
+            .output_condition_identifier(TextStr("sRGB"))
+            .info(TextStr("sRGB IEC61966-2.1"))
+            .dest_output_profile(profile);
+    }
 
     // Write the named destination tree.
     let mut name_dict = catalog.names();
@@ -307,6 +459,10 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     catalog.finish();
 }
 
+/// The XMP namespace of the PDF/UA identification schema.
+const PDFUA_ID: Namespace =
+    Namespace::Custom(("pdfuaid", "http://www.aiim.org/pdfua/ns/id/"));
+
 /// Fills in the map and vector for named destinations and writes the indirect
 /// destination objects.
 fn write_named_destinations(ctx: &mut PdfContext) {
@@ -328,10 +484,10 @@ fn write_named_destinations(ctx: &mut PdfContext) {
 
     for (loc, label) in matches {
         let pos = ctx.document.introspector.position(loc);
-        let index = pos.page.get() - 1;
         let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
 
-        if let Some(page) = ctx.pages.get(index) {
+        if let Some(index) = ctx.page_index(pos.page) {
+            let page = &ctx.pages[index];
             let dest_ref = ctx.alloc.bump();
             let x = pos.point.x.to_f32();
             let y = (page.size.y - y).to_f32();
@@ -443,13 +599,6 @@ where
         })
     }
 
-    fn pdf_indices<'a>(
-        &'a self,
-        refs: &'a [Ref],
-    ) -> impl Iterator<Item = (Ref, usize)> + 'a {
-        refs.iter().copied().zip(0..self.to_pdf.len())
-    }
-
     fn items(&self) -> impl Iterator<Item = &T> + '_ {
         self.to_items.iter()
     }
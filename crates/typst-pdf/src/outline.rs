@@ -19,6 +19,13 @@ pub(crate) fn write_outline(ctx: &mut PdfContext) -> Option<Ref> {
     let mut last_skipped_level = None;
     let elements = ctx.document.introspector.query(&HeadingElem::elem().select());
     for elem in elements.iter() {
+        // Headings on pages that are not exported are left out entirely, as
+        // if they didn't exist.
+        let page = ctx.document.introspector.page(elem.location().unwrap());
+        if ctx.page_index(page).is_none() {
+            continue;
+        }
+
         let heading = elem.to_packed::<HeadingElem>().unwrap();
         let leaf = HeadingNode::leaf(heading);
 
@@ -143,6 +150,10 @@ fn write_outline_item(
     let id = ctx.alloc.bump();
     let next_ref = Ref::new(id.get() + node.len() as i32);
 
+    let loc = node.element.location().unwrap();
+    let pos = ctx.document.introspector.position(loc);
+    let index = ctx.page_index(pos.page);
+
     let mut outline = ctx.pdf.outline_item(id);
     outline.parent(parent_ref);
 
@@ -163,12 +174,10 @@ fn write_outline_item(
     let body = node.element.body();
     outline.title(TextStr(body.plain_text().trim()));
 
-    let loc = node.element.location().unwrap();
-    let pos = ctx.document.introspector.position(loc);
-    let index = pos.page.get() - 1;
-    if let Some(page) = ctx.pages.get(index) {
+    if let Some(index) = index {
+        let page = &ctx.pages[index];
         let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
-        outline.dest().page(ctx.page_refs[index]).xyz(
+        outline.dest().page(page.id).xyz(
             pos.point.x.to_f32(),
             (page.size.y - y).to_f32(),
             None,
@@ -1,59 +1,128 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::num::NonZeroUsize;
+use std::sync::Arc;
 
 use ecow::{eco_format, EcoString};
 use pdf_writer::types::{
     ActionType, AnnotationFlags, AnnotationType, ColorSpaceOperand, LineCapStyle,
-    LineJoinStyle, NumberingStyle, TextRenderingMode,
+    LineJoinStyle, NumberingStyle, TabOrder, TextRenderingMode,
 };
-use pdf_writer::writers::{PageLabel, Resources};
+use pdf_writer::writers::{Annotation, PageLabel, Resources};
 use pdf_writer::{Content, Filter, Finish, Name, Rect, Ref, Str, TextStr};
+use typst::foundations::Label;
 use typst::introspection::Meta;
 use typst::layout::{
-    Abs, Em, Frame, FrameItem, GroupItem, Page, Point, Ratio, Size, Transform,
+    Abs, Em, Frame, FrameItem, GroupItem, Page, PageRanges, Point, Ratio, Size, Transform,
 };
 use typst::model::{Destination, Numbering};
-use typst::text::{Case, Font, TextItem};
+use typst::text::{Case, Font, Lang, TextItem};
 use typst::util::{Deferred, Numeric};
 use typst::visualize::{
-    FixedStroke, Geometry, Image, LineCap, LineJoin, Paint, Path, PathItem, Shape,
+    FixedStroke, Geometry, Image, LineCap, LineJoin, Paint, Path, PathItem, Pattern,
+    Shape,
 };
 
-use crate::color::PaintEncode;
+use crate::color::{PaintEncode, UsedColorSpaces};
 use crate::extg::ExtGState;
+use crate::gradient::PdfGradient;
 use crate::image::deferred_image;
-use crate::{deflate_deferred, AbsExt, EmExt, PdfContext};
+use crate::pattern::add_pattern;
+use crate::tag::{PageTagger, PageTags};
+use crate::{deflate_deferred, AbsExt, EmExt, PdfContext, Remapper};
 
 /// Construct page objects.
 #[typst_macros::time(name = "construct pages")]
-pub(crate) fn construct_pages(ctx: &mut PdfContext, pages: &[Page]) {
-    for page in pages {
-        let (page_ref, mut encoded) = construct_page(ctx, &page.frame);
+pub(crate) fn construct_pages(
+    ctx: &mut PdfContext,
+    pages: &[Page],
+    page_ranges: Option<&PageRanges>,
+) {
+    for (i, page) in pages.iter().enumerate() {
+        if page_ranges.is_some_and(|ranges| !ranges.includes_page_index(i)) {
+            ctx.page_indices.push(None);
+            continue;
+        }
+
+        ctx.page_indices.push(Some(ctx.pages.len()));
+        let mut encoded = construct_page(ctx, &page.frame);
         encoded.label = page
             .numbering
             .as_ref()
             .and_then(|num| PdfPageLabel::generate(num, page.number));
-        ctx.page_refs.push(page_ref);
+        ctx.page_refs.push(encoded.id);
         ctx.pages.push(encoded);
     }
 }
 
 /// Construct a page object.
+///
+/// If the document is tagged, the page's contents become part of its logical
+/// structure.
 #[typst_macros::time(name = "construct page")]
-pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, EncodedPage) {
+fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> EncodedPage {
     let page_ref = ctx.alloc.bump();
+    let content = encode_content(frame, ctx.standards.pdfa.is_some(), ctx.standards.ua);
+    let resources = add_resources(ctx, &content);
+
+    // Tagged documents refer to the link annotations from the structure tree,
+    // so they are written as indirect objects.
+    let tagged = content.tags.is_some();
+    let mut links: Vec<_> = content
+        .links
+        .iter()
+        .map(|(dest, rect)| EncodedLink {
+            id: tagged.then(|| ctx.alloc.bump()),
+            dest: dest.clone(),
+            rect: *rect,
+            struct_parent: None,
+        })
+        .collect();
+
+    let struct_parents = content
+        .tags
+        .as_ref()
+        .map(|tags| ctx.tags.add_page(ctx.pages.len(), tags, &mut links));
+
+    EncodedPage {
+        id: page_ref,
+        size: frame.size(),
+        content: content.content.clone(),
+        uses_opacities: content.uses_opacities,
+        links,
+        resources,
+        label: None,
+        struct_parents,
+    }
+}
 
+/// Encode a frame into a content stream.
+///
+/// The encoding doesn't depend on the rest of the document: Resources are
+/// named by their index among the content's own resources and only resolved
+/// to PDF objects when the content is added to the document. This way, the
+/// encoding of an unchanged page is reused when the document is exported
+/// again.
+///
+/// If `tagged` is true, the content is marked up for the document's logical
+/// structure.
+#[comemo::memoize]
+#[typst_macros::time(name = "encode content")]
+pub(crate) fn encode_content(
+    frame: &Frame,
+    pdfa: bool,
+    tagged: bool,
+) -> Arc<EncodedContent> {
     let size = frame.size();
     let mut ctx = PageContext {
-        parent: ctx,
-        page_ref,
-        uses_opacities: false,
+        pdfa,
         content: Content::new(),
         state: State::new(size),
         saves: vec![],
         bottom: 0.0,
+        uses_opacities: false,
         links: vec![],
-        resources: HashMap::default(),
+        resources: ContentResources::default(),
+        tags: tagged.then(PageTagger::new),
     };
 
     // Make the coordinate system start at the top-left.
@@ -70,87 +139,143 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> (Ref, Encod
     // Encode the page into the content stream.
     write_frame(&mut ctx, frame);
 
-    let page = EncodedPage {
-        size,
+    Arc::new(EncodedContent {
         content: deflate_deferred(ctx.content.finish()),
-        id: ctx.page_ref,
         uses_opacities: ctx.uses_opacities,
-        links: ctx.links,
-        label: None,
         resources: ctx.resources,
-    };
-
-    (page_ref, page)
-}
-
-/// Write the page tree.
-pub(crate) fn write_page_tree(ctx: &mut PdfContext) {
-    let resources_ref = write_global_resources(ctx);
-
-    for i in 0..ctx.pages.len() {
-        write_page(ctx, i, resources_ref);
-    }
-
-    ctx.pdf
-        .pages(ctx.page_tree_ref)
-        .count(ctx.page_refs.len() as i32)
-        .kids(ctx.page_refs.iter().copied());
+        links: ctx.links,
+        tags: ctx.tags.map(PageTagger::finish),
+    })
 }
 
-/// Write the global resource dictionary that will be referenced by all pages.
+/// Add the resources used by encoded content to the document.
 ///
-/// We add a reference to this dictionary to each page individually instead of
-/// to the root node of the page tree because using the resource inheritance
-/// feature breaks PDF merging with Apple Preview.
-fn write_global_resources(ctx: &mut PdfContext) -> Ref {
-    let resource_ref = ctx.alloc.bump();
-
-    let mut resources = ctx.pdf.indirect(resource_ref).start::<Resources>();
-    ctx.colors
-        .write_color_spaces(resources.color_spaces(), &mut ctx.alloc);
+/// Returns the names of the resources along with their indices among the
+/// document's resources of the same kind.
+pub(crate) fn add_resources(
+    ctx: &mut PdfContext,
+    content: &EncodedContent,
+) -> Vec<(PageResource, usize)> {
+    let ContentResources {
+        fonts,
+        images,
+        gradients,
+        patterns,
+        ext_gs,
+        colors,
+        glyph_sets,
+        languages,
+    } = &content.resources;
+
+    ctx.colors.merge(*colors, &mut ctx.alloc);
+
+    for (lang, count) in languages {
+        *ctx.languages.entry(*lang).or_insert(0) += count;
+    }
 
-    let mut fonts = resources.fonts();
-    for (font_ref, f) in ctx.font_map.pdf_indices(&ctx.font_refs) {
-        let name = eco_format!("F{}", f);
-        fonts.pair(Name(name.as_bytes()), font_ref);
+    for (font, glyphs) in glyph_sets {
+        let glyph_set = ctx.glyph_sets.entry(font.clone()).or_default();
+        for (glyph, text) in glyphs {
+            glyph_set.entry(*glyph).or_insert_with(|| text.clone());
+        }
     }
 
-    fonts.finish();
+    let mut resources = vec![];
+    for (i, font) in fonts.items().enumerate() {
+        let index = ctx.font_map.insert(font.clone());
+        resources.push((PageResource::new(ResourceKind::Font, i), index));
+    }
 
-    let mut images = resources.x_objects();
-    for (image_ref, im) in ctx.image_map.pdf_indices(&ctx.image_refs) {
-        let name = eco_format!("Im{}", im);
-        images.pair(Name(name.as_bytes()), image_ref);
+    for (i, image) in images.items().enumerate() {
+        let index = ctx.image_map.insert(image.clone());
+        ctx.image_deferred_map
+            .entry(index)
+            .or_insert_with(|| deferred_image(image.clone()));
+        resources.push((PageResource::new(ResourceKind::XObject, i), index));
     }
 
-    images.finish();
+    for (i, gradient) in gradients.items().enumerate() {
+        let index = ctx.gradient_map.insert(gradient.clone());
+        resources.push((PageResource::new(ResourceKind::Gradient, i), index));
+    }
 
-    let mut patterns = resources.patterns();
-    for (gradient_ref, gr) in ctx.gradient_map.pdf_indices(&ctx.gradient_refs) {
-        let name = eco_format!("Gr{}", gr);
-        patterns.pair(Name(name.as_bytes()), gradient_ref);
+    for (i, (transform, pattern)) in patterns.items().enumerate() {
+        let index = add_pattern(ctx, *transform, pattern);
+        resources.push((PageResource::new(ResourceKind::Pattern, i), index));
     }
 
-    for (pattern_ref, p) in ctx.pattern_map.pdf_indices(&ctx.pattern_refs) {
-        let name = eco_format!("P{}", p);
-        patterns.pair(Name(name.as_bytes()), pattern_ref);
+    for (i, state) in ext_gs.items().enumerate() {
+        let index = ctx.extg_map.insert(*state);
+        resources.push((PageResource::new(ResourceKind::ExtGState, i), index));
     }
 
-    patterns.finish();
+    resources
+}
 
-    let mut ext_gs_states = resources.ext_g_states();
-    for (gs_ref, gs) in ctx.extg_map.pdf_indices(&ctx.ext_gs_refs) {
-        let name = eco_format!("Gs{}", gs);
-        ext_gs_states.pair(Name(name.as_bytes()), gs_ref);
-    }
-    ext_gs_states.finish();
+/// Write the page tree.
+pub(crate) fn write_page_tree(ctx: &mut PdfContext) {
+    // Pages with the same resources share a resource dictionary. We add a
+    // reference to it to each page individually instead of to the root node
+    // of the page tree because using the resource inheritance feature breaks
+    // PDF merging with Apple Preview.
+    let mut dictionaries = HashMap::new();
+    for i in 0..ctx.pages.len() {
+        let resources = &ctx.pages[i].resources;
+        let resources_ref = match dictionaries.get(resources) {
+            Some(&resources_ref) => resources_ref,
+            None => {
+                let resources = resources.clone();
+                let resources_ref = write_resources(ctx, &resources);
+                dictionaries.insert(resources, resources_ref);
+                resources_ref
+            }
+        };
 
-    resources.finish();
+        write_page(ctx, i, resources_ref);
+    }
 
     // Write all of the functions used by the document.
     ctx.colors.write_functions(&mut ctx.pdf);
 
-    resource_ref
+    ctx.pdf
+        .pages(ctx.page_tree_ref)
+        .count(ctx.page_refs.len() as i32)
+        .kids(ctx.page_refs.iter().copied());
+}
+
+/// Write a resource dictionary that maps the names of the given resources to
+/// the document's resources of the same kind.
+pub(crate) fn write_resources(
+    ctx: &mut PdfContext,
+    resources: &[(PageResource, usize)],
+) -> Ref {
+    let resources_ref = ctx.alloc.bump();
+    let mut dict = ctx.pdf.indirect(resources_ref).start::<Resources>();
+    ctx.colors.write_color_spaces(dict.color_spaces(), &mut ctx.alloc);
+
+    let refs = |kind: ResourceKind| {
+        let refs = match kind {
+            ResourceKind::XObject => &ctx.image_refs,
+            ResourceKind::Font => &ctx.font_refs,
+            ResourceKind::Gradient => &ctx.gradient_refs,
+            ResourceKind::Pattern => &ctx.pattern_refs,
+            ResourceKind::ExtGState => &ctx.ext_gs_refs,
+        };
+        resources
+            .iter()
+            .filter(move |(resource, _)| resource.kind == kind)
+            .map(|(resource, index)| (resource.name(), refs[*index]))
+    };
+
+    dict.fonts().pairs(refs(ResourceKind::Font));
+    dict.x_objects().pairs(refs(ResourceKind::XObject));
+    dict.patterns()
+        .pairs(refs(ResourceKind::Gradient))
+        .pairs(refs(ResourceKind::Pattern));
+    dict.ext_g_states().pairs(refs(ResourceKind::ExtGState));
+    dict.finish();
+
+    resources_ref
 }
 
 /// Write a page tree node.
@@ -158,6 +283,10 @@ fn write_page(ctx: &mut PdfContext, i: usize, resources_ref: Ref) {
     let page = &ctx.pages[i];
     let content_id = ctx.alloc.bump();
 
+    // The actions are resolved up front as the page writer borrows the context.
+    let actions: Vec<_> =
+        page.links.iter().map(|link| resolve_link(ctx, &link.dest)).collect();
+
     let mut page_writer = ctx.pdf.page(page.id);
     page_writer.parent(ctx.page_tree_ref);
 
@@ -177,54 +306,136 @@ fn write_page(ctx: &mut PdfContext, i: usize, resources_ref: Ref) {
             .srgb();
     }
 
-    let mut annotations = page_writer.annotations();
-    for (dest, rect) in &page.links {
-        let mut annotation = annotations.push();
-        annotation.subtype(AnnotationType::Link).rect(*rect);
-        annotation.border(0.0, 0.0, 0.0, None).flags(AnnotationFlags::PRINT);
+    if let Some(key) = page.struct_parents {
+        page_writer.struct_parents(key);
+        if !page.links.is_empty() {
+            page_writer.tab_order(TabOrder::StructureOrder);
+        }
+    }
 
-        let pos = match dest {
-            Destination::Url(uri) => {
-                annotation
-                    .action()
-                    .action_type(ActionType::Uri)
-                    .uri(Str(uri.as_bytes()));
-                continue;
+    if page.struct_parents.is_some() {
+        if !page.links.is_empty() {
+            page_writer
+                .insert(Name(b"Annots"))
+                .array()
+                .items(page.links.iter().filter_map(|link| link.id));
+        }
+        page_writer.finish();
+
+        for (link, action) in page.links.iter().zip(&actions) {
+            let Some(id) = link.id else { continue };
+            let mut annotation = ctx.pdf.indirect(id).start::<Annotation>();
+            write_link_annotation(&mut annotation, link, action);
+            if let Some(key) = link.struct_parent {
+                annotation.struct_parent(key);
             }
-            Destination::Position(pos) => *pos,
-            Destination::Location(loc) => {
-                if let Some(key) = ctx.loc_to_dest.get(loc) {
-                    annotation
-                        .action()
-                        .action_type(ActionType::GoTo)
-                        // `key` must be a `Str`, not a `Name`.
-                        .pair(Name(b"D"), Str(key.as_str().as_bytes()));
-                    continue;
-                } else {
-                    ctx.document.introspector.position(*loc)
-                }
+
+            // Link annotations need a textual description for accessibility.
+            annotation.contents(TextStr(&action.description));
+        }
+    } else {
+        let mut annotations = page_writer.annotations();
+        for (link, action) in page.links.iter().zip(&actions) {
+            write_link_annotation(&mut annotations.push(), link, action);
+        }
+        annotations.finish();
+        page_writer.finish();
+    }
+
+    ctx.pdf
+        .stream(content_id, page.content.wait())
+        .filter(Filter::FlateDecode);
+}
+
+/// What a link annotation does when it is activated.
+struct LinkAction {
+    /// Where the link leads, if its target is exported.
+    target: Option<LinkTarget>,
+    /// A textual description of the link.
+    description: EcoString,
+}
+
+/// The target of a link annotation.
+enum LinkTarget {
+    /// A URI.
+    Uri(EcoString),
+    /// A named destination.
+    Named(Label),
+    /// A position on an exported page.
+    Position { page: Ref, x: f32, y: f32 },
+}
+
+/// Resolve the action of a link annotation.
+fn resolve_link(ctx: &PdfContext, dest: &Destination) -> LinkAction {
+    let pos = match dest {
+        Destination::Url(uri) => {
+            return LinkAction {
+                target: Some(LinkTarget::Uri(uri.clone())),
+                description: uri.clone(),
+            };
+        }
+        Destination::Position(pos) => *pos,
+        Destination::Location(loc) => {
+            let pos = ctx.document.introspector.position(*loc);
+            if let Some(&key) = ctx.loc_to_dest.get(loc) {
+                return LinkAction {
+                    target: Some(LinkTarget::Named(key)),
+                    description: eco_format!("Go to page {}", pos.page),
+                };
             }
-        };
+            pos
+        }
+    };
 
-        let index = pos.page.get() - 1;
+    let target = ctx.page_index(pos.page).map(|index| {
+        let page = &ctx.pages[index];
         let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
+        LinkTarget::Position {
+            page: page.id,
+            x: pos.point.x.to_f32(),
+            y: (page.size.y - y).to_f32(),
+        }
+    });
 
-        if let Some(page) = ctx.pages.get(index) {
+    LinkAction {
+        target,
+        description: eco_format!("Go to page {}", pos.page),
+    }
+}
+
+/// Write a link annotation.
+fn write_link_annotation(
+    annotation: &mut Annotation,
+    link: &EncodedLink,
+    action: &LinkAction,
+) {
+    annotation.subtype(AnnotationType::Link).rect(link.rect);
+    annotation.border(0.0, 0.0, 0.0, None).flags(AnnotationFlags::PRINT);
+
+    match &action.target {
+        Some(LinkTarget::Uri(uri)) => {
+            annotation
+                .action()
+                .action_type(ActionType::Uri)
+                .uri(Str(uri.as_bytes()));
+        }
+        Some(LinkTarget::Named(key)) => {
+            annotation
+                .action()
+                .action_type(ActionType::GoTo)
+                // `key` must be a `Str`, not a `Name`.
+                .pair(Name(b"D"), Str(key.as_str().as_bytes()));
+        }
+        Some(LinkTarget::Position { page, x, y }) => {
             annotation
                 .action()
                 .action_type(ActionType::GoTo)
                 .destination()
-                .page(page.id)
-                .xyz(pos.point.x.to_f32(), (page.size.y - y).to_f32(), None);
+                .page(*page)
+                .xyz(*x, *y, None);
         }
+        None => {}
     }
-
-    annotations.finish();
-    page_writer.finish();
-
-    ctx.pdf
-        .stream(content_id, page.content.wait())
-        .filter(Filter::FlateDecode);
 }
 
 /// Write the page labels.
@@ -368,12 +579,73 @@ pub struct EncodedPage {
     pub content: Deferred<Vec<u8>>,
     /// Whether the page uses opacities.
     pub uses_opacities: bool,
-    /// Links in the PDF coordinate system.
-    pub links: Vec<(Destination, Rect)>,
+    /// The page's link annotations.
+    pub links: Vec<EncodedLink>,
     /// The page's used resources
-    pub resources: HashMap<PageResource, usize>,
+    pub resources: Vec<(PageResource, usize)>,
     /// The page's PDF label.
     label: Option<PdfPageLabel>,
+    /// The page's key in the structure parent tree, if it is tagged.
+    pub struct_parents: Option<i32>,
+}
+
+/// Data for a link annotation on an exported page.
+pub struct EncodedLink {
+    /// The indirect object id of the annotation, if it is tagged.
+    pub id: Option<Ref>,
+    /// Where the link leads.
+    pub dest: Destination,
+    /// The link's area in the PDF coordinate system.
+    pub rect: Rect,
+    /// The annotation's key in the structure parent tree, if it is tagged.
+    pub struct_parent: Option<i32>,
+}
+
+/// A content stream along with everything it refers to.
+pub struct EncodedContent {
+    /// The compressed content stream.
+    pub content: Deferred<Vec<u8>>,
+    /// Whether the content uses opacities.
+    pub uses_opacities: bool,
+    /// The resources used by the content.
+    pub resources: ContentResources,
+    /// The content's links with their areas in the PDF coordinate system.
+    pub links: Vec<(Destination, Rect)>,
+    /// The logical structure of the content, if it is tagged.
+    pub tags: Option<PageTags>,
+}
+
+/// The resources used by a content stream, in the order of their first use.
+///
+/// The content refers to a resource by the [name](PageResource) derived from
+/// its index here.
+pub struct ContentResources {
+    pub fonts: Remapper<Font>,
+    pub images: Remapper<Image>,
+    pub gradients: Remapper<PdfGradient>,
+    /// The patterns along with the transforms they are painted with.
+    pub patterns: Remapper<(Transform, Pattern)>,
+    pub ext_gs: Remapper<ExtGState>,
+    pub colors: UsedColorSpaces,
+    /// For each font a mapping from used glyphs to their text representation.
+    pub glyph_sets: HashMap<Font, BTreeMap<u16, EcoString>>,
+    /// The number of glyphs for each language.
+    pub languages: BTreeMap<Lang, usize>,
+}
+
+impl Default for ContentResources {
+    fn default() -> Self {
+        Self {
+            fonts: Remapper::new(),
+            images: Remapper::new(),
+            gradients: Remapper::new(),
+            patterns: Remapper::new(),
+            ext_gs: Remapper::new(),
+            colors: UsedColorSpaces::default(),
+            glyph_sets: HashMap::new(),
+            languages: BTreeMap::new(),
+        }
+    }
 }
 
 /// Represents a resource being used in a PDF page by its name.
@@ -384,13 +656,26 @@ pub struct PageResource {
 }
 
 impl PageResource {
-    pub fn new(kind: ResourceKind, name: EcoString) -> Self {
-        Self { kind, name }
+    /// The resource with the given index among the resources of its kind.
+    pub fn new(kind: ResourceKind, index: usize) -> Self {
+        let prefix = match kind {
+            ResourceKind::XObject => "Im",
+            ResourceKind::Font => "F",
+            ResourceKind::Gradient => "Gr",
+            ResourceKind::Pattern => "P",
+            ResourceKind::ExtGState => "Gs",
+        };
+        Self { kind, name: eco_format!("{prefix}{index}") }
+    }
+
+    /// Returns the name of the resource.
+    pub fn name(&self) -> Name<'_> {
+        Name(self.name.as_bytes())
     }
 }
 
 /// A kind of resource being used in a PDF page.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum ResourceKind {
     XObject,
     Font,
@@ -399,42 +684,10 @@ pub enum ResourceKind {
     ExtGState,
 }
 
-impl PageResource {
-    /// Returns the name of the resource.
-    pub fn name(&self) -> Name<'_> {
-        Name(self.name.as_bytes())
-    }
-
-    /// Returns whether the resource is an XObject.
-    pub fn is_x_object(&self) -> bool {
-        matches!(self.kind, ResourceKind::XObject)
-    }
-
-    /// Returns whether the resource is a font.
-    pub fn is_font(&self) -> bool {
-        matches!(self.kind, ResourceKind::Font)
-    }
-
-    /// Returns whether the resource is a gradient.
-    pub fn is_gradient(&self) -> bool {
-        matches!(self.kind, ResourceKind::Gradient)
-    }
-
-    /// Returns whether the resource is a pattern.
-    pub fn is_pattern(&self) -> bool {
-        matches!(self.kind, ResourceKind::Pattern)
-    }
-
-    /// Returns whether the resource is an external graphics state.
-    pub fn is_ext_g_state(&self) -> bool {
-        matches!(self.kind, ResourceKind::ExtGState)
-    }
-}
-
 /// An exporter for the contents of a single PDF page.
-pub struct PageContext<'a, 'b> {
-    pub(crate) parent: &'a mut PdfContext<'b>,
-    page_ref: Ref,
+pub struct PageContext {
+    /// Whether the content must conform to PDF/A.
+    pub(crate) pdfa: bool,
     pub content: Content,
     state: State,
     saves: Vec<State>,
@@ -442,7 +695,9 @@ pub struct PageContext<'a, 'b> {
     uses_opacities: bool,
     links: Vec<(Destination, Rect)>,
     /// Keep track of the resources being used in the page.
-    pub resources: HashMap<PageResource, usize>,
+    pub resources: ContentResources,
+    /// The tagging state, if the page is part of the logical structure.
+    tags: Option<PageTagger>,
 }
 
 /// A simulated graphics state used to deduplicate graphics state changes and
@@ -505,7 +760,7 @@ pub(super) struct Transforms {
     pub size: Size,
 }
 
-impl PageContext<'_, '_> {
+impl PageContext {
     fn save_state(&mut self) {
         self.saves.push(self.state.clone());
         self.content.save_state();
@@ -519,11 +774,9 @@ impl PageContext<'_, '_> {
     fn set_external_graphics_state(&mut self, graphics_state: &ExtGState) {
         let current_state = self.state.external_graphics_state.as_ref();
         if current_state != Some(graphics_state) {
-            let index = self.parent.extg_map.insert(*graphics_state);
-            let name = eco_format!("Gs{index}");
-            self.content.set_parameters(Name(name.as_bytes()));
-            self.resources
-                .insert(PageResource::new(ResourceKind::ExtGState, name), index);
+            let index = self.resources.ext_gs.insert(*graphics_state);
+            let resource = PageResource::new(ResourceKind::ExtGState, index);
+            self.content.set_parameters(resource.name());
 
             if graphics_state.uses_opacities() {
                 self.uses_opacities = true;
@@ -578,11 +831,9 @@ impl PageContext<'_, '_> {
 
     fn set_font(&mut self, font: &Font, size: Abs) {
         if self.state.font.as_ref().map(|(f, s)| (f, *s)) != Some((font, size)) {
-            let index = self.parent.font_map.insert(font.clone());
-            let name = eco_format!("F{index}");
-            self.content.set_font(Name(name.as_bytes()), size.to_f32());
-            self.resources
-                .insert(PageResource::new(ResourceKind::Font, name), index);
+            let index = self.resources.fonts.insert(font.clone());
+            let resource = PageResource::new(ResourceKind::Font, index);
+            self.content.set_font(resource.name(), size.to_f32());
             self.state.font = Some((font.clone(), size));
         }
     }
@@ -671,21 +922,86 @@ impl PageContext<'_, '_> {
 
 /// Encode a frame into the content stream.
 fn write_frame(ctx: &mut PageContext, frame: &Frame) {
+    if let Some(tags) = &mut ctx.tags {
+        tags.enter(frame.kind().is_hard());
+    }
+
     for &(pos, ref item) in frame.items() {
         let x = pos.x.to_f32();
         let y = pos.y.to_f32();
+        if let Some(tags) = &mut ctx.tags {
+            tags.advance(pos);
+        }
+
         match item {
             FrameItem::Group(group) => write_group(ctx, pos, group),
-            FrameItem::Text(text) => write_text(ctx, pos, text),
-            FrameItem::Shape(shape, _) => write_shape(ctx, pos, shape),
-            FrameItem::Image(image, size, _) => write_image(ctx, x, y, image, *size),
+            FrameItem::Text(text) => {
+                let marked = begin_marked(ctx, PageTagger::mark_text);
+                write_text(ctx, pos, text);
+                end_marked(ctx, marked);
+            }
+            FrameItem::Shape(shape, _) => {
+                // Shapes are decorative, so they are always artifacts.
+                let marked = begin_artifact(ctx);
+                write_shape(ctx, pos, shape);
+                end_marked(ctx, marked);
+            }
+            FrameItem::Image(image, size, span) => {
+                let marked = begin_marked(ctx, |tags| tags.mark_image(image, *span));
+                write_image(ctx, x, y, image, *size);
+                end_marked(ctx, marked);
+            }
             FrameItem::Meta(meta, size) => match meta {
                 Meta::Link(dest) => write_link(ctx, pos, dest, *size),
-                Meta::Elem(_) => {}
+                Meta::Elem(elem) => {
+                    if let Some(tags) = &mut ctx.tags {
+                        tags.open(elem, pos, *size);
+                    }
+                }
                 Meta::Hide => {}
             },
         }
     }
+
+    if let Some(tags) = &mut ctx.tags {
+        tags.leave();
+    }
+}
+
+/// Start a marked-content sequence for an item that belongs to the structure
+/// element determined by `mark`, if the content is tagged.
+///
+/// Returns whether a sequence was started.
+fn begin_marked(
+    ctx: &mut PageContext,
+    mark: impl FnOnce(&mut PageTagger) -> (Name<'static>, i32),
+) -> bool {
+    let Some(tags) = &mut ctx.tags else { return false };
+    let (tag, mcid) = mark(tags);
+    ctx.content
+        .begin_marked_content_with_properties(tag)
+        .properties()
+        .identify(mcid);
+    true
+}
+
+/// Start a marked-content sequence for an artifact, if the content is tagged.
+///
+/// Returns whether a sequence was started.
+fn begin_artifact(ctx: &mut PageContext) -> bool {
+    if ctx.tags.is_none() {
+        return false;
+    }
+    ctx.content.begin_marked_content(Name(b"Artifact"));
+    true
+}
+
+/// End a marked-content sequence started by [`begin_marked`] or
+/// [`begin_artifact`].
+fn end_marked(ctx: &mut PageContext, marked: bool) {
+    if marked {
+        ctx.content.end_marked_content();
+    }
 }
 
 /// Encode a group into the content stream.
@@ -713,6 +1029,7 @@ fn write_group(ctx: &mut PageContext, pos: Point, group: &GroupItem) {
     }
 
     write_frame(ctx, &group.frame);
+
     ctx.restore_state();
 }
 
@@ -721,9 +1038,9 @@ fn write_text(ctx: &mut PageContext, pos: Point, text: &TextItem) {
     let x = pos.x.to_f32();
     let y = pos.y.to_f32();
 
-    *ctx.parent.languages.entry(text.lang).or_insert(0) += text.glyphs.len();
+    *ctx.resources.languages.entry(text.lang).or_insert(0) += text.glyphs.len();
 
-    let glyph_set = ctx.parent.glyph_sets.entry(text.font.clone()).or_default();
+    let glyph_set = ctx.resources.glyph_sets.entry(text.font.clone()).or_default();
     for g in &text.glyphs {
         let segment = &text.text[g.range()];
         glyph_set.entry(g.id).or_insert_with(|| segment.into());
@@ -876,13 +1193,8 @@ fn write_path(ctx: &mut PageContext, x: f32, y: f32, path: &Path) {
 
 /// Encode a vector or raster image into the content stream.
 fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size) {
-    let index = ctx.parent.image_map.insert(image.clone());
-    ctx.parent
-        .image_deferred_map
-        .entry(index)
-        .or_insert_with(|| deferred_image(image.clone()));
-
-    let name = eco_format!("Im{index}");
+    let index = ctx.resources.images.insert(image.clone());
+    let resource = PageResource::new(ResourceKind::XObject, index);
     let w = size.x.to_f32();
     let h = size.y.to_f32();
     ctx.content.save_state();
@@ -896,14 +1208,12 @@ fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size)
         image_alt.finish();
         image_span.finish();
 
-        ctx.content.x_object(Name(name.as_bytes()));
+        ctx.content.x_object(resource.name());
         ctx.content.end_marked_content();
     } else {
-        ctx.content.x_object(Name(name.as_bytes()));
+        ctx.content.x_object(resource.name());
     }
 
-    ctx.resources
-        .insert(PageResource::new(ResourceKind::XObject, name.clone()), index);
     ctx.content.restore_state();
 }
 
@@ -934,6 +1244,11 @@ fn write_link(ctx: &mut PageContext, pos: Point, dest: &Destination, size: Size)
     let y2 = min_y.to_f32();
     let rect = Rect::new(x1, y1, x2, y2);
 
+    if let Some(tags) = &mut ctx.tags {
+        tags.open_link(dest, pos, size);
+        tags.annotate(ctx.links.len());
+    }
+
     ctx.links.push((dest.clone(), rect));
 }
 
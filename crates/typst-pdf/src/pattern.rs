@@ -1,22 +1,27 @@
-use ecow::eco_format;
 use pdf_writer::types::{ColorSpaceOperand, PaintType, TilingType};
-use pdf_writer::{Filter, Finish, Name, Rect};
+use pdf_writer::{Filter, Name, Rect};
 use typst::layout::{Abs, Ratio, Transform};
 use typst::util::Numeric;
 use typst::visualize::{Pattern, RelativeTo};
 
 use crate::color::PaintEncode;
-use crate::page::{construct_page, PageContext, PageResource, ResourceKind, Transforms};
+use crate::page::{
+    add_resources, encode_content, write_resources, PageContext, PageResource,
+    ResourceKind, Transforms,
+};
 use crate::{transform_to_array, PdfContext};
 
 /// Writes the actual patterns (tiling patterns) to the PDF.
 /// This is performed once after writing all pages.
 pub(crate) fn write_patterns(ctx: &mut PdfContext) {
-    for PdfPattern { transform, pattern, content, resources } in ctx.pattern_map.items() {
+    for PdfPattern { transform, pattern, content, resources } in
+        ctx.pattern_map.items().cloned().collect::<Vec<_>>()
+    {
         let tiling = ctx.alloc.bump();
         ctx.pattern_refs.push(tiling);
+        let resources_ref = write_resources(ctx, &resources);
 
-        let mut tiling_pattern = ctx.pdf.tiling_pattern(tiling, content);
+        let mut tiling_pattern = ctx.pdf.tiling_pattern(tiling, &content);
         tiling_pattern
             .tiling_type(TilingType::ConstantSpacing)
             .paint_type(PaintType::Colored)
@@ -27,50 +32,9 @@ pub(crate) fn write_patterns(ctx: &mut PdfContext) {
                 pattern.size().y.to_pt() as _,
             ))
             .x_step((pattern.size().x + pattern.spacing().x).to_pt() as _)
-            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _);
-
-        let mut resources_map = tiling_pattern.resources();
-
-        resources_map.x_objects().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_x_object())
-                .map(|(res, ref_)| (res.name(), ctx.image_refs[*ref_])),
-        );
-
-        resources_map.fonts().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_font())
-                .map(|(res, ref_)| (res.name(), ctx.font_refs[*ref_])),
-        );
-
-        ctx.colors
-            .write_color_spaces(resources_map.color_spaces(), &mut ctx.alloc);
-
-        resources_map
-            .patterns()
-            .pairs(
-                resources
-                    .iter()
-                    .filter(|(res, _)| res.is_pattern())
-                    .map(|(res, ref_)| (res.name(), ctx.pattern_refs[*ref_])),
-            )
-            .pairs(
-                resources
-                    .iter()
-                    .filter(|(res, _)| res.is_gradient())
-                    .map(|(res, ref_)| (res.name(), ctx.gradient_refs[*ref_])),
-            );
-
-        resources_map.ext_g_states().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_ext_g_state())
-                .map(|(res, ref_)| (res.name(), ctx.ext_gs_refs[*ref_])),
-        );
-
-        resources_map.finish();
+            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _)
+            .pair(Name(b"Resources"), resources_ref);
+
         tiling_pattern
             .matrix(transform_to_array(
                 transform
@@ -94,7 +58,7 @@ pub struct PdfPattern {
     pub resources: Vec<(PageResource, usize)>,
 }
 
-/// Registers a pattern with the PDF.
+/// Deduplicates a pattern to a named resource of the content.
 fn register_pattern(
     ctx: &mut PageContext,
     pattern: &Pattern,
@@ -115,19 +79,27 @@ fn register_pattern(
         RelativeTo::Parent => transforms.container_transform,
     };
 
+    ctx.resources.patterns.insert((transform, pattern.clone()))
+}
+
+/// Adds a pattern that is painted with the given transform to the document.
+///
+/// Returns the index of the pattern among the document's patterns.
+pub(crate) fn add_pattern(
+    ctx: &mut PdfContext,
+    transform: Transform,
+    pattern: &Pattern,
+) -> usize {
     // Render the body.
-    let (_, content) = construct_page(ctx.parent, pattern.frame());
+    let content = encode_content(pattern.frame(), ctx.standards.pdfa.is_some(), false);
+    let resources = add_resources(ctx, &content);
 
-    let mut pdf_pattern = PdfPattern {
+    ctx.pattern_map.insert(PdfPattern {
         transform,
         pattern: pattern.clone(),
         content: content.content.wait().clone(),
-        resources: content.resources.into_iter().collect(),
-    };
-
-    pdf_pattern.resources.sort();
-
-    ctx.parent.pattern_map.insert(pdf_pattern)
+        resources,
+    })
 }
 
 impl PaintEncode for Pattern {
@@ -135,13 +107,10 @@ impl PaintEncode for Pattern {
         ctx.reset_fill_color_space();
 
         let index = register_pattern(ctx, self, on_text, transforms);
-        let id = eco_format!("P{index}");
-        let name = Name(id.as_bytes());
+        let resource = PageResource::new(ResourceKind::Pattern, index);
 
         ctx.content.set_fill_color_space(ColorSpaceOperand::Pattern);
-        ctx.content.set_fill_pattern(None, name);
-        ctx.resources
-            .insert(PageResource::new(ResourceKind::Pattern, id), index);
+        ctx.content.set_fill_pattern(None, resource.name());
     }
 
     fn set_as_stroke(
@@ -153,12 +122,9 @@ impl PaintEncode for Pattern {
         ctx.reset_stroke_color_space();
 
         let index = register_pattern(ctx, self, on_text, transforms);
-        let id = eco_format!("P{index}");
-        let name = Name(id.as_bytes());
+        let resource = PageResource::new(ResourceKind::Pattern, index);
 
         ctx.content.set_stroke_color_space(ColorSpaceOperand::Pattern);
-        ctx.content.set_stroke_pattern(None, name);
-        ctx.resources
-            .insert(PageResource::new(ResourceKind::Pattern, id), index);
+        ctx.content.set_stroke_pattern(None, resource.name());
     }
 }
@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+
+use ecow::{EcoString, EcoVec};
+use typst::diag::{error, SourceDiagnostic, SourceResult};
+use typst::layout::{Frame, FrameItem};
+use typst::syntax::Span;
+use typst::text::TextItem;
+use typst::visualize::{Color, ColorSpace, Paint};
+use xmp_writer::{Namespace, RdfCollectionType, XmpWriter};
+
+use crate::PdfContext;
+
+/// The XMP namespaces of the PDF/A extension schema container schema.
+const PDFA_EXTENSION: Namespace =
+    Namespace::Custom(("pdfaExtension", "http://www.aiim.org/pdfa/ns/extension/"));
+const PDFA_SCHEMA: Namespace =
+    Namespace::Custom(("pdfaSchema", "http://www.aiim.org/pdfa/ns/schema#"));
+const PDFA_PROPERTY: Namespace =
+    Namespace::Custom(("pdfaProperty", "http://www.aiim.org/pdfa/ns/property#"));
+
+/// Check the document's content for conformance with PDF/A.
+///
+/// The remaining requirements are met by construction: Fonts are always
+/// embedded, the metadata is mirrored into XMP and all colors are written in
+/// device-independent color spaces or in sRGB, which the output intent
+/// describes. Transparency is limited to constant opacities and soft-masked
+/// images, both of which PDF/A-2 and PDF/A-3 permit as long as the blending
+/// color space is known, which the output intent ensures.
+pub(crate) fn validate(ctx: &PdfContext) -> SourceResult<()> {
+    let Some(part) = ctx.standards.pdfa else { return Ok(()) };
+
+    let mut validator = Validator { part, errors: EcoVec::new(), seen: HashSet::new() };
+    for (page, index) in ctx.document.pages.iter().zip(&ctx.page_indices) {
+        if index.is_some() {
+            validator.frame(&page.frame);
+        }
+    }
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+/// Collects the PDF/A violations in a document's frames.
+struct Validator {
+    /// The part of PDF/A to conform to.
+    part: i32,
+    /// The violations found so far.
+    errors: EcoVec<SourceDiagnostic>,
+    /// The spans for which violations were already reported.
+    seen: HashSet<(Span, Violation)>,
+}
+
+/// A kind of PDF/A violation.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+enum Violation {
+    Cmyk,
+    MissingGlyph,
+}
+
+impl Validator {
+    /// Check a frame and its descendants.
+    fn frame(&mut self, frame: &Frame) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame),
+                FrameItem::Text(text) => self.text(text),
+                FrameItem::Shape(shape, span) => {
+                    if let Some(paint) = &shape.fill {
+                        self.paint(paint, *span);
+                    }
+                    if let Some(stroke) = &shape.stroke {
+                        self.paint(&stroke.paint, *span);
+                    }
+                }
+                FrameItem::Image(..) | FrameItem::Meta(..) => {}
+            }
+        }
+    }
+
+    /// Check a text run.
+    fn text(&mut self, text: &TextItem) {
+        let span = text.glyphs.first().map_or(Span::detached(), |glyph| glyph.span.0);
+        self.paint(&text.fill, span);
+        if let Some(stroke) = &text.stroke {
+            self.paint(&stroke.paint, span);
+        }
+
+        // PDF/A forbids references to the `.notdef` glyph, which is used for
+        // characters that no font covers.
+        for glyph in text.glyphs.iter().filter(|glyph| glyph.id == 0) {
+            let (span, part) = (glyph.span.0, self.part);
+            let unrepresentable: EcoString = text.text[glyph.range()].into();
+            self.report(
+                Violation::MissingGlyph,
+                error!(
+                    span,
+                    "PDF/A-{part}b error: the text {unrepresentable:?} could not be \
+                     displayed with any font";
+                    hint: "choose a font that covers this text with `set text(font: ..)`"
+                ),
+            );
+        }
+    }
+
+    /// Check a fill or stroke paint.
+    fn paint(&mut self, paint: &Paint, span: Span) {
+        let cmyk = match paint {
+            Paint::Solid(color) => matches!(color, Color::Cmyk(_)),
+            Paint::Gradient(gradient) => gradient.space() == ColorSpace::Cmyk,
+            Paint::Pattern(pattern) => {
+                self.frame(pattern.frame());
+                false
+            }
+        };
+
+        // The output intent describes an RGB device, which rules out
+        // device-dependent CMYK colors.
+        if cmyk {
+            let part = self.part;
+            self.report(
+                Violation::Cmyk,
+                error!(
+                    span,
+                    "PDF/A-{part}b error: CMYK colors are not supported";
+                    hint: "convert the color to RGB with `rgb(..)`"
+                ),
+            );
+        }
+    }
+
+    /// Report a violation unless it was already reported for the same span.
+    fn report(&mut self, violation: Violation, diagnostic: SourceDiagnostic) {
+        if self.seen.insert((diagnostic.span, violation)) {
+            self.errors.push(diagnostic);
+        }
+    }
+}
+
+/// Declare the PDF/UA identification schema, which PDF/A does not predefine,
+/// in the XMP metadata.
+pub(crate) fn write_pdfua_extension_schema(xmp: &mut XmpWriter) {
+    let mut schemas =
+        xmp.element("schemas", PDFA_EXTENSION).array(RdfCollectionType::Bag);
+    let mut schema = schemas.element().obj();
+    schema
+        .element("schema", PDFA_SCHEMA)
+        .value("PDF/UA Universal Accessibility Schema");
+    schema
+        .element("namespaceURI", PDFA_SCHEMA)
+        .value("http://www.aiim.org/pdfua/ns/id/");
+    schema.element("prefix", PDFA_SCHEMA).value("pdfuaid");
+
+    let mut properties =
+        schema.element("property", PDFA_SCHEMA).array(RdfCollectionType::Seq);
+    let mut property = properties.element().obj();
+    property.element("name", PDFA_PROPERTY).value("part");
+    property.element("valueType", PDFA_PROPERTY).value("Integer");
+    property.element("category", PDFA_PROPERTY).value("internal");
+    property
+        .element("description", PDFA_PROPERTY)
+        .value("Indicates which part of ISO 14289 is followed");
+}
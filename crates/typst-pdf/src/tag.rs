@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+
+use ecow::{EcoString, EcoVec};
+use pdf_writer::types::{StructRole, TableHeaderScope};
+use pdf_writer::writers::StructElement;
+use pdf_writer::{Finish, Name, Ref, TextStr};
+use typst::diag::{error, SourceDiagnostic, SourceResult};
+use typst::foundations::{Content, Smart, StyleChain};
+use typst::introspection::Location;
+use typst::layout::{Abs, Point, ResolvableCell, Size};
+use typst::math::EquationElem;
+use typst::model::{
+    Destination, EnumElem, EnumItem, FigureElem, HeadingElem, ListElem, ListItem,
+    TableCell, TableChild, TableElem, TableItem, TermItem, TermsElem,
+};
+use typst::syntax::Span;
+use typst::visualize::Image;
+
+use crate::page::EncodedLink;
+use crate::PdfContext;
+
+/// The logical structure of the document.
+///
+/// The structure of each page is recorded while its content stream is written
+/// and then [added](Tags::add_page) to the document's structure.
+#[derive(Default)]
+pub struct Tags {
+    /// The structure elements, in the order of their first appearance.
+    elems: Vec<TagElem>,
+    /// Maps from the keys of structure elements to their indices, so that
+    /// elements continuing across pages are only written once.
+    keys: HashMap<(Location, Role), usize>,
+    /// The entries of the structure parent tree, indexed by their keys.
+    parent_tree: Vec<ParentTreeEntry>,
+}
+
+/// A structure element of the document.
+struct TagElem {
+    /// The element's structure type.
+    role: Role,
+    /// The element's parent. Top-level elements are children of the document
+    /// element.
+    parent: Option<usize>,
+    /// The element's children, in logical order.
+    kids: Vec<TagKid>,
+    /// The alternative text and span of the image the element describes, if
+    /// any.
+    image: Option<(Option<EcoString>, Span)>,
+}
+
+/// A child of a structure element.
+enum TagKid {
+    /// Another structure element.
+    Elem(usize),
+    /// A marked-content sequence on a page.
+    Content { page: usize, mcid: i32 },
+    /// An annotation on a page.
+    Annotation { page: usize, id: Ref },
+}
+
+/// An entry in the structure parent tree.
+enum ParentTreeEntry {
+    /// The structure elements of the marked-content sequences on a page,
+    /// indexed by MCID.
+    Page(Vec<usize>),
+    /// The structure element of an annotation.
+    Annotation(usize),
+}
+
+impl Tags {
+    /// Add the structure of the page with the given index to the document's
+    /// structure. The page's link annotations receive their keys in the
+    /// structure parent tree.
+    ///
+    /// Returns the page's key in the structure parent tree.
+    pub fn add_page(
+        &mut self,
+        index: usize,
+        page: &PageTags,
+        links: &mut [EncodedLink],
+    ) -> i32 {
+        // Elements that continue from a previous page are extended, all others
+        // are added unless they end up empty, like a table row whose cells
+        // all continue from the previous page.
+        let continued: Vec<_> = page
+            .elems
+            .iter()
+            .map(|elem| elem.key.and_then(|key| self.keys.get(&key)).copied())
+            .collect();
+        let mut filled = vec![false; page.elems.len()];
+        for (i, elem) in page.elems.iter().enumerate().rev() {
+            filled[i] = elem.kids.iter().any(|kid| match *kid {
+                PageKid::Elem(child) => continued[child].is_none() && filled[child],
+                PageKid::Content(_) | PageKid::Link(_) => true,
+            });
+        }
+
+        let mut mapped = vec![usize::MAX; page.elems.len()];
+        let mut added = vec![false; page.elems.len()];
+        for (i, elem) in page.elems.iter().enumerate() {
+            if let Some(target) = continued[i] {
+                mapped[i] = target;
+                continue;
+            } else if !filled[i] {
+                continue;
+            }
+
+            let target = self.elems.len();
+            self.elems.push(TagElem {
+                role: elem.role,
+                parent: elem.parent.map(|parent| mapped[parent]),
+                kids: vec![],
+                image: elem.image.clone(),
+            });
+            if let Some(key) = elem.key {
+                self.keys.insert(key, target);
+            }
+            mapped[i] = target;
+            added[i] = true;
+        }
+
+        let mut mcids = vec![0; page.mcids];
+        for (elem, &target) in page.elems.iter().zip(&mapped) {
+            if target == usize::MAX {
+                continue;
+            }
+
+            for kid in &elem.kids {
+                let kid = match *kid {
+                    PageKid::Elem(child) if added[child] => TagKid::Elem(mapped[child]),
+                    PageKid::Elem(_) => continue,
+                    PageKid::Content(mcid) => {
+                        mcids[mcid as usize] = target;
+                        TagKid::Content { page: index, mcid }
+                    }
+                    PageKid::Link(link) => {
+                        let link = &mut links[link];
+                        let Some(id) = link.id else { continue };
+                        self.parent_tree.push(ParentTreeEntry::Annotation(target));
+                        link.struct_parent = Some(self.parent_tree.len() as i32 - 1);
+                        TagKid::Annotation { page: index, id }
+                    }
+                };
+                self.elems[target].kids.push(kid);
+            }
+        }
+
+        self.parent_tree.push(ParentTreeEntry::Page(mcids));
+        self.parent_tree.len() as i32 - 1
+    }
+}
+
+/// The logical structure of a single page.
+///
+/// It doesn't depend on the rest of the document, so that it can be reused
+/// along with the page's content stream.
+#[derive(Default)]
+pub struct PageTags {
+    /// The structure elements on the page. Parents precede their children.
+    elems: Vec<PageElem>,
+    /// The number of marked-content sequences on the page.
+    mcids: usize,
+}
+
+/// A structure element on a page.
+struct PageElem {
+    /// The element's structure type.
+    role: Role,
+    /// The location of the element the structure element originates from
+    /// along with its structure type, if any.
+    key: Option<(Location, Role)>,
+    /// The element's parent on the page.
+    parent: Option<usize>,
+    /// The element's children, in logical order.
+    kids: Vec<PageKid>,
+    /// The alternative text and span of the image the element describes, if
+    /// any.
+    image: Option<(Option<EcoString>, Span)>,
+}
+
+/// A child of a structure element on a page.
+enum PageKid {
+    /// Another structure element on the page.
+    Elem(usize),
+    /// The marked-content sequence with the given MCID.
+    Content(i32),
+    /// The link annotation with the given index.
+    Link(usize),
+}
+
+/// Records the logical structure of a page while its content stream is
+/// written.
+///
+/// The structure follows the frame hierarchy: The metadata at the start of a
+/// run of frame items names the elements the run belongs to. An element stays
+/// open for the following items of the same frame that lie within its
+/// metadata's area and for everything nested in them.
+pub struct PageTagger {
+    /// The page's structure.
+    tags: PageTags,
+    /// The structure elements that are currently open, from the outermost to
+    /// the innermost one.
+    stack: Vec<Scope>,
+    /// The nesting depth of the frame that is currently written.
+    depth: usize,
+    /// The depths of the hard frames that are currently written.
+    blocks: Vec<usize>,
+    /// The number of header rows of the tables on the page.
+    headers: HashMap<usize, usize>,
+    /// The rows of the tables on the page, by table and row index.
+    rows: HashMap<(usize, usize), usize>,
+}
+
+/// An open structure element.
+struct Scope {
+    /// The structure element on the page.
+    elem: usize,
+    /// The depth of the frame in which the scope was opened.
+    depth: usize,
+    /// The area of the frame the element covers, if it is restricted.
+    area: Option<(Point, Size)>,
+    /// The destination, if the element is a link.
+    dest: Option<Destination>,
+}
+
+impl PageTagger {
+    /// Create a tagger for an empty page.
+    pub fn new() -> Self {
+        Self {
+            tags: PageTags::default(),
+            stack: vec![],
+            depth: 0,
+            blocks: vec![],
+            headers: HashMap::new(),
+            rows: HashMap::new(),
+        }
+    }
+
+    /// Start writing the items of a nested frame, which is hard if it
+    /// originates from a block-level element.
+    pub fn enter(&mut self, hard: bool) {
+        self.depth += 1;
+        if hard {
+            self.blocks.push(self.depth);
+        }
+    }
+
+    /// Finish writing the items of a nested frame, closing all structure
+    /// elements that were opened within it.
+    pub fn leave(&mut self) {
+        while self.stack.last().is_some_and(|scope| scope.depth >= self.depth) {
+            self.stack.pop();
+        }
+        if self.blocks.last() == Some(&self.depth) {
+            self.blocks.pop();
+        }
+        self.depth -= 1;
+    }
+
+    /// Close the structure elements of the current frame whose area the item
+    /// at `pos` leaves, along with everything opened after them.
+    pub fn advance(&mut self, pos: Point) {
+        let depth = self.depth;
+        if let Some(i) = self.stack.iter().position(|scope| {
+            scope.depth == depth && scope.area.is_some_and(|area| !contains(area, pos))
+        }) {
+            self.stack.truncate(i);
+        }
+    }
+
+    /// Open the structure element for the element of a [`Meta::Elem`] marker
+    /// spanning an area of the given `size` at `pos`.
+    ///
+    /// [`Meta::Elem`]: typst::introspection::Meta::Elem
+    pub fn open(&mut self, elem: &Content, pos: Point, size: Size) {
+        if size.x.approx_eq(Abs::zero()) || size.y.approx_eq(Abs::zero()) {
+            return;
+        }
+
+        let Some(loc) = elem.location() else { return };
+
+        // Fields are materialized during preparation, so the default style
+        // chain suffices to access them.
+        let styles = StyleChain::default();
+        let role = if let Some(heading) = elem.to_packed::<HeadingElem>() {
+            Role::H(heading.resolve_level(styles).get().min(6) as u8)
+        } else if elem.is::<FigureElem>() {
+            Role::Figure
+        } else if let Some(equation) = elem.to_packed::<EquationElem>() {
+            // Inline equations are part of their paragraph.
+            if !equation.block(styles) {
+                return;
+            }
+            Role::P
+        } else if elem.is::<ListElem>() || elem.is::<EnumElem>() || elem.is::<TermsElem>()
+        {
+            Role::L
+        } else if elem.is::<ListItem>() || elem.is::<EnumItem>() || elem.is::<TermItem>()
+        {
+            return self.open_item(loc, Role::LBody);
+        } else if loc.variant == 1 && elem.is_empty() {
+            // The marker of a list item is marked with a variant of the item's
+            // location.
+            return self.open_item(loc.variant(0), Role::Lbl);
+        } else if elem.is::<TableElem>() {
+            Role::Table
+        } else if let Some(cell) = elem.to_packed::<TableCell>() {
+            let y = match cell.y(styles) {
+                Smart::Custom(y) => y,
+                Smart::Auto => 0,
+            };
+            return self.open_cell(loc, y);
+        } else {
+            return;
+        };
+
+        if self.is_open((loc, role)) {
+            return;
+        }
+
+        // Block-level elements end the current paragraph.
+        if let Some(i) = self.stack.iter().rposition(|scope| self.role(scope) == Role::P)
+        {
+            self.stack.truncate(i);
+        }
+
+        let parent = self.stack.last().map(|s| s.elem);
+        let index = self.find_or_push(role, loc, parent);
+        if let Some(table) = elem.to_packed::<TableElem>() {
+            self.headers.insert(index, header_rows(table, styles));
+        }
+
+        self.stack.push(Scope {
+            elem: index,
+            depth: self.depth,
+            area: Some((pos, size)),
+            dest: None,
+        });
+    }
+
+    /// Open the label or body of the list item with the given location.
+    ///
+    /// Both are marked repeatedly within their cell or paragraph, so they stay
+    /// open until the next part of the list opens or the list is closed.
+    fn open_item(&mut self, item: Location, role: Role) {
+        if self.is_open((item, role)) {
+            return;
+        }
+
+        let Some(list) = self.stack.iter().rposition(|scope| self.role(scope) == Role::L)
+        else {
+            return;
+        };
+
+        self.stack.truncate(list + 1);
+        let Scope { elem: list, depth, .. } = self.stack[list];
+        let parent = self.find_or_push(Role::LI, item, Some(list));
+        let index = self.find_or_push(role, item, Some(parent));
+
+        // The label precedes the body, also in right-to-left lists, where the
+        // body is written first.
+        let kids = &mut self.tags.elems[parent].kids;
+        if role == Role::Lbl && kids.len() > 1 {
+            if let Some(i) = kids
+                .iter()
+                .position(|kid| matches!(kid, PageKid::Elem(i) if *i == index))
+            {
+                let kid = kids.remove(i);
+                kids.insert(0, kid);
+            }
+        }
+
+        self.stack.push(Scope { elem: index, depth, area: None, dest: None });
+    }
+
+    /// Open the table cell with the given location in the row with index `y`.
+    ///
+    /// A cell stays open until the next cell opens or the table is closed.
+    fn open_cell(&mut self, loc: Location, y: usize) {
+        let Some(i) =
+            self.stack.iter().rposition(|scope| self.role(scope) == Role::Table)
+        else {
+            return;
+        };
+
+        let Scope { elem: table, depth, .. } = self.stack[i];
+        let header = self.headers.get(&table).copied().unwrap_or(0);
+        let role = if y < header { Role::TH } else { Role::TD };
+        if self.is_open((loc, role)) {
+            return;
+        }
+
+        self.stack.truncate(i + 1);
+        let row = match self.rows.get(&(table, y)) {
+            Some(&row) => row,
+            None => {
+                let row = self.push(Role::TR, None, Some(table));
+                self.rows.insert((table, y), row);
+                row
+            }
+        };
+
+        let index = self.find_or_push(role, loc, Some(row));
+        self.stack.push(Scope { elem: index, depth, area: None, dest: None });
+    }
+
+    /// Open a link structure element for a [`Meta::Link`] marker spanning an
+    /// area of the given `size` at `pos`.
+    ///
+    /// [`Meta::Link`]: typst::introspection::Meta::Link
+    pub fn open_link(&mut self, dest: &Destination, pos: Point, size: Size) {
+        if self.stack.iter().any(|scope| scope.dest.as_ref() == Some(dest)) {
+            return;
+        }
+
+        let parent = self.inline();
+        let index = self.push(Role::Link, None, Some(parent));
+        self.stack.push(Scope {
+            elem: index,
+            depth: self.depth,
+            area: Some((pos, size)),
+            dest: Some(dest.clone()),
+        });
+    }
+
+    /// Attach the link annotation with the given index to the innermost
+    /// link.
+    pub fn annotate(&mut self, link: usize) {
+        if let Some(scope) = self.stack.iter().rev().find(|scope| scope.dest.is_some()) {
+            self.tags.elems[scope.elem].kids.push(PageKid::Link(link));
+        }
+    }
+
+    /// Start a marked-content sequence for a text run.
+    ///
+    /// Returns the tag and MCID of the sequence.
+    pub fn mark_text(&mut self) -> (Name<'static>, i32) {
+        let elem = self.inline();
+        self.mark(elem)
+    }
+
+    /// Start a marked-content sequence for an image. An image in a figure is
+    /// described by the figure and any other image becomes a figure of its
+    /// own.
+    ///
+    /// Returns the tag and MCID of the sequence.
+    pub fn mark_image(&mut self, image: &Image, span: Span) -> (Name<'static>, i32) {
+        let figure = match self.stack.last() {
+            Some(scope) if self.role(scope) == Role::Figure => scope.elem,
+            parent => {
+                let parent = parent.map(|scope| scope.elem);
+                self.push(Role::Figure, None, parent)
+            }
+        };
+
+        let elem = &mut self.tags.elems[figure];
+        if elem.image.is_none() {
+            elem.image = Some((image.alt().map(Into::into), span));
+        }
+
+        self.mark(figure)
+    }
+
+    /// Finish tagging the page.
+    pub fn finish(self) -> PageTags {
+        self.tags
+    }
+
+    /// The structure element that receives inline content, opening a new
+    /// paragraph if necessary.
+    fn inline(&mut self) -> usize {
+        let (parent, depth) = match self.stack.last() {
+            Some(scope) if self.role(scope).is_inline_container() => return scope.elem,
+            Some(scope) => (Some(scope.elem), scope.depth),
+            None => (None, 0),
+        };
+
+        // The paragraph spans all lines of the innermost block, but doesn't
+        // outlive its parent.
+        let depth = depth.max(self.blocks.last().copied().unwrap_or(0));
+        let index = self.push(Role::P, None, parent);
+        self.stack.push(Scope { elem: index, depth, area: None, dest: None });
+        index
+    }
+
+    /// Add a marked-content sequence to a structure element.
+    fn mark(&mut self, elem: usize) -> (Name<'static>, i32) {
+        let mcid = self.tags.mcids as i32;
+        self.tags.mcids += 1;
+        let elem = &mut self.tags.elems[elem];
+        elem.kids.push(PageKid::Content(mcid));
+        (elem.role.name(), mcid)
+    }
+
+    /// Whether the structure element with the given key is open.
+    fn is_open(&self, key: (Location, Role)) -> bool {
+        self.stack
+            .iter()
+            .any(|scope| self.tags.elems[scope.elem].key == Some(key))
+    }
+
+    /// The structure element on the page that originates from the element at
+    /// `loc`, which is added if the element didn't appear on the page yet.
+    fn find_or_push(
+        &mut self,
+        role: Role,
+        loc: Location,
+        parent: Option<usize>,
+    ) -> usize {
+        let key = Some((loc, role));
+        match self.tags.elems.iter().position(|elem| elem.key == key) {
+            Some(index) => index,
+            None => self.push(role, key, parent),
+        }
+    }
+
+    /// Push a new structure element.
+    fn push(
+        &mut self,
+        role: Role,
+        key: Option<(Location, Role)>,
+        parent: Option<usize>,
+    ) -> usize {
+        let index = self.tags.elems.len();
+        self.tags
+            .elems
+            .push(PageElem { role, key, parent, kids: vec![], image: None });
+        if let Some(parent) = parent {
+            self.tags.elems[parent].kids.push(PageKid::Elem(index));
+        }
+        index
+    }
+
+    /// The structure type of an open element.
+    fn role(&self, scope: &Scope) -> Role {
+        self.tags.elems[scope.elem].role
+    }
+}
+
+/// Whether an item at `pos` lies within the area of a frame that starts at
+/// the given point and has the given size.
+///
+/// The area is open towards the end of the inline axis, as the text run
+/// following a marked run starts exactly where the marked run ends. Along the
+/// block axis, it allows for a bit of slack, as the baseline of the last line
+/// may end up just below the frame due to rounding.
+fn contains((start, size): (Point, Size), pos: Point) -> bool {
+    pos.x.fits(start.x)
+        && pos.x < start.x + size.x
+        && pos.y.fits(start.y)
+        && (start.y + size.y).fits(pos.y)
+}
+
+/// The number of header rows of a table.
+///
+/// The header starts at the first row, so it spans as many rows as its cells
+/// fill or as its explicitly positioned cells reach.
+fn header_rows(table: &TableElem, styles: StyleChain) -> usize {
+    let Some(TableChild::Header(header)) = table.children().first() else { return 0 };
+    let columns = table.columns(styles).0.len().max(1);
+    let mut area = 0;
+    let mut rows = 0;
+    for item in header.children() {
+        let TableItem::Cell(cell) = item else { continue };
+        let colspan = cell.colspan(styles).get();
+        let rowspan = cell.rowspan(styles).get();
+        area += colspan * rowspan;
+        if let Smart::Custom(y) = cell.y(styles) {
+            rows = rows.max(y + rowspan);
+        }
+    }
+    rows.max(area.div_ceil(columns))
+}
+
+/// A structure type.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+enum Role {
+    P,
+    H(u8),
+    /// A figure. Figures without an image are written as divisions.
+    Figure,
+    L,
+    LI,
+    Lbl,
+    LBody,
+    Table,
+    TR,
+    TH,
+    TD,
+    Link,
+}
+
+impl Role {
+    /// The standard structure type.
+    fn to_struct_role(self, has_image: bool) -> StructRole {
+        match self {
+            Self::P => StructRole::P,
+            Self::H(1) => StructRole::H1,
+            Self::H(2) => StructRole::H2,
+            Self::H(3) => StructRole::H3,
+            Self::H(4) => StructRole::H4,
+            Self::H(5) => StructRole::H5,
+            Self::H(_) => StructRole::H6,
+            Self::Figure if has_image => StructRole::Figure,
+            Self::Figure => StructRole::Div,
+            Self::L => StructRole::L,
+            Self::LI => StructRole::LI,
+            Self::Lbl => StructRole::Lbl,
+            Self::LBody => StructRole::LBody,
+            Self::Table => StructRole::Table,
+            Self::TR => StructRole::TR,
+            Self::TH => StructRole::TH,
+            Self::TD => StructRole::TD,
+            Self::Link => StructRole::Link,
+        }
+    }
+
+    /// The name of the structure type, used as the tag of marked-content
+    /// sequences.
+    fn name(self) -> Name<'static> {
+        Name(match self {
+            Self::P => b"P",
+            Self::H(1) => b"H1",
+            Self::H(2) => b"H2",
+            Self::H(3) => b"H3",
+            Self::H(4) => b"H4",
+            Self::H(5) => b"H5",
+            Self::H(_) => b"H6",
+            Self::Figure => b"Figure",
+            Self::L => b"L",
+            Self::LI => b"LI",
+            Self::Lbl => b"Lbl",
+            Self::LBody => b"LBody",
+            Self::Table => b"Table",
+            Self::TR => b"TR",
+            Self::TH => b"TH",
+            Self::TD => b"TD",
+            Self::Link => b"Link",
+        })
+    }
+
+    /// Whether text directly belongs to elements of this type. Text in other
+    /// elements forms paragraphs.
+    fn is_inline_container(self) -> bool {
+        matches!(
+            self,
+            Self::P | Self::H(_) | Self::Lbl | Self::TH | Self::TD | Self::Link
+        )
+    }
+}
+
+/// Check the structure tree for conformance with PDF/UA-1.
+pub(crate) fn validate(ctx: &PdfContext) -> SourceResult<()> {
+    if !ctx.standards.ua {
+        return Ok(());
+    }
+
+    let mut errors: EcoVec<SourceDiagnostic> = EcoVec::new();
+    if ctx.document.title.is_none() {
+        errors.push(error!(
+            Span::detached(),
+            "PDF/UA-1 error: document title is missing";
+            hint: "set the title with `set document(title: ..)`"
+        ));
+    }
+
+    if ctx.languages.is_empty() {
+        errors.push(error!(
+            Span::detached(),
+            "PDF/UA-1 error: document language is missing";
+            hint: "set the language with `set text(lang: ..)`"
+        ));
+    }
+
+    for elem in &ctx.tags.elems {
+        if let Some((None, span)) = elem.image {
+            errors.push(error!(
+                span,
+                "PDF/UA-1 error: image is missing alternative text";
+                hint: "describe the image with the `alt` parameter"
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Write the structure tree.
+///
+/// Returns the reference of the structure tree root.
+pub(crate) fn write_structure(ctx: &mut PdfContext) -> Ref {
+    let root_ref = ctx.alloc.bump();
+    let document_ref = ctx.alloc.bump();
+    let refs: Vec<Ref> = ctx.tags.elems.iter().map(|_| ctx.alloc.bump()).collect();
+
+    let mut root = ctx
+        .pdf
+        .indirect(root_ref)
+        .start::<pdf_writer::writers::StructTreeRoot>();
+    root.child(document_ref);
+
+    // The parent tree can't be written with the typed number tree as its values
+    // are either single references or arrays of references.
+    let mut parent_tree = root.insert(Name(b"ParentTree")).dict();
+    let mut nums = parent_tree.insert(Name(b"Nums")).array();
+    for (key, entry) in ctx.tags.parent_tree.iter().enumerate() {
+        nums.item(key as i32);
+        match entry {
+            ParentTreeEntry::Page(elems) => {
+                nums.push().array().items(elems.iter().map(|&i| refs[i]));
+            }
+            ParentTreeEntry::Annotation(elem) => {
+                nums.item(refs[*elem]);
+            }
+        }
+    }
+    nums.finish();
+    parent_tree.finish();
+    root.parent_tree_next_key(ctx.tags.parent_tree.len() as i32);
+    root.finish();
+
+    let mut document = ctx.pdf.indirect(document_ref).start::<StructElement>();
+    document.kind(StructRole::Document).parent(root_ref);
+    if let Some(title) = &ctx.document.title {
+        document.title(TextStr(title));
+    }
+    document.children().items(
+        ctx.tags
+            .elems
+            .iter()
+            .zip(&refs)
+            .filter(|(elem, _)| elem.parent.is_none())
+            .map(|(_, &id)| id),
+    );
+    document.finish();
+
+    for (elem, &id) in ctx.tags.elems.iter().zip(&refs) {
+        let mut writer = ctx.pdf.indirect(id).start::<StructElement>();
+        writer.kind(elem.role.to_struct_role(elem.image.is_some()));
+        writer.parent(elem.parent.map_or(document_ref, |parent| refs[parent]));
+        if let Some((Some(alt), _)) = &elem.image {
+            writer.alt(TextStr(alt));
+        }
+        if elem.role == Role::TH {
+            writer.attributes().push().table().scope(TableHeaderScope::Column);
+        }
+
+        let mut kids = writer.children();
+        for kid in &elem.kids {
+            match *kid {
+                TagKid::Elem(index) => {
+                    kids.struct_element(refs[index]);
+                }
+                TagKid::Content { page, mcid } => {
+                    kids.marked_content_ref()
+                        .page(ctx.pages[page].id)
+                        .marked_content_id(mcid);
+                }
+                TagKid::Annotation { page, id } => {
+                    kids.object_ref().page(ctx.pages[page].id).object(id);
+                }
+            }
+        }
+    }
+
+    root_ref
+}
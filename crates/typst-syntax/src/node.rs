@@ -156,12 +156,27 @@ impl SyntaxNode {
         }
     }
 
+    /// Add a machine-applicable fix if this is an error node.
+    ///
+    /// The `range` is relative to the start of this node.
+    pub fn fix(
+        &mut self,
+        title: impl Into<EcoString>,
+        range: Range<usize>,
+        text: impl Into<EcoString>,
+    ) {
+        if let Repr::Error(node) = &mut self.0 {
+            let fix = Fix::replace(title, node.error.span, range, text);
+            Arc::make_mut(node).error.fixes.push(fix);
+        }
+    }
+
     /// Set a synthetic span for the node and all its descendants.
     pub fn synthesize(&mut self, span: Span) {
         match &mut self.0 {
             Repr::Leaf(leaf) => leaf.span = span,
             Repr::Inner(inner) => Arc::make_mut(inner).synthesize(span),
-            Repr::Error(node) => Arc::make_mut(node).error.span = span,
+            Repr::Error(node) => Arc::make_mut(node).set_span(span),
         }
     }
 
@@ -230,7 +245,7 @@ impl SyntaxNode {
         match &mut self.0 {
             Repr::Leaf(leaf) => leaf.span = mid,
             Repr::Inner(inner) => Arc::make_mut(inner).numberize(id, None, within)?,
-            Repr::Error(node) => Arc::make_mut(node).error.span = mid,
+            Repr::Error(node) => Arc::make_mut(node).set_span(mid),
         }
 
         Ok(())
@@ -631,6 +646,7 @@ impl ErrorNode {
                 span: Span::detached(),
                 message: message.into(),
                 hints: eco_vec![],
+                fixes: eco_vec![],
             },
         }
     }
@@ -645,6 +661,14 @@ impl ErrorNode {
         self.error.hints.push(hint.into());
     }
 
+    /// Set the span of the node and of its fixes.
+    fn set_span(&mut self, span: Span) {
+        self.error.span = span;
+        for fix in self.error.fixes.make_mut() {
+            fix.span = span;
+        }
+    }
+
     /// Whether the two leaf nodes are the same apart from spans.
     fn spanless_eq(&self, other: &Self) -> bool {
         self.text == other.text && self.error.spanless_eq(&other.error)
@@ -667,12 +691,65 @@ pub struct SyntaxError {
     /// Additional hints to the user, indicating how this error could be avoided
     /// or worked around.
     pub hints: EcoVec<EcoString>,
+    /// Machine-applicable changes that resolve the error.
+    pub fixes: EcoVec<Fix>,
 }
 
 impl SyntaxError {
     /// Whether the two errors are the same apart from spans.
     fn spanless_eq(&self, other: &Self) -> bool {
-        self.message == other.message && self.hints == other.hints
+        self.message == other.message
+            && self.hints == other.hints
+            && self.fixes.len() == other.fixes.len()
+            && self.fixes.iter().zip(&other.fixes).all(|(a, b)| a.spanless_eq(b))
+    }
+}
+
+/// A machine-applicable change to the source text that resolves a diagnostic,
+/// for example the insertion of a missing closing delimiter.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Fix {
+    /// A short description of the change, like "add missing `#`".
+    pub title: EcoString,
+    /// The span of the node the change is positioned relative to.
+    pub span: Span,
+    /// The byte range to replace, relative to the start of the node. It may
+    /// extend past the end of the node.
+    pub range: Range<usize>,
+    /// The text to replace the range with.
+    pub text: EcoString,
+}
+
+impl Fix {
+    /// Create a fix that replaces a range relative to the node with the span.
+    pub fn replace(
+        title: impl Into<EcoString>,
+        span: Span,
+        range: Range<usize>,
+        text: impl Into<EcoString>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            span,
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Create a fix that inserts text at an offset relative to the node with
+    /// the span.
+    pub fn insert(
+        title: impl Into<EcoString>,
+        span: Span,
+        offset: usize,
+        text: impl Into<EcoString>,
+    ) -> Self {
+        Self::replace(title, span, offset..offset, text)
+    }
+
+    /// Whether the two fixes are the same apart from spans.
+    fn spanless_eq(&self, other: &Self) -> bool {
+        self.title == other.title && self.range == other.range && self.text == other.text
     }
 }
 
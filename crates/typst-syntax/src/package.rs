@@ -26,6 +26,98 @@ pub struct TemplateInfo {
     pub path: EcoString,
     /// The path of the entrypoint relative to the starting point's `path`.
     pub entrypoint: EcoString,
+    /// Parameters that are substituted into the entrypoint when a project is
+    /// created from the template, in the order in which they are declared.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "serialize_params",
+        deserialize_with = "deserialize_params"
+    )]
+    pub params: Vec<TemplateParam>,
+}
+
+/// A parameter of a template, declared in the `[template.params]` table.
+///
+/// A parameter is declared either with just its default value, as in
+/// `title = "Untitled"`, or with a table that additionally describes it, as
+/// in `author = { default = "", description = "Your name" }`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TemplateParam {
+    /// The parameter's name, which appears as `{{name}}` in the entrypoint.
+    pub name: EcoString,
+    /// The value used if no other value is given.
+    pub default: EcoString,
+    /// A description of the parameter, shown when asking for its value.
+    pub description: Option<EcoString>,
+}
+
+/// The declaration of a template parameter in the manifest.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum TemplateParamDecl {
+    Default(EcoString),
+    Full {
+        #[serde(default)]
+        default: EcoString,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<EcoString>,
+    },
+}
+
+fn serialize_params<S: Serializer>(
+    params: &[TemplateParam],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(params.iter().map(|param| {
+        let decl = match &param.description {
+            Some(description) => TemplateParamDecl::Full {
+                default: param.default.clone(),
+                description: Some(description.clone()),
+            },
+            None => TemplateParamDecl::Default(param.default.clone()),
+        };
+        (&param.name, decl)
+    }))
+}
+
+fn deserialize_params<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TemplateParam>, D::Error> {
+    struct Visitor;
+
+    impl<'de> serde::de::Visitor<'de> for Visitor {
+        type Value = Vec<TemplateParam>;
+
+        fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+            f.write_str("a table of template parameters")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut params = vec![];
+            while let Some((name, decl)) = map.next_entry::<EcoString, _>()? {
+                if !is_ident(&name) {
+                    return Err(serde::de::Error::custom(eco_format!(
+                        "`{name}` is not a valid template parameter name"
+                    )));
+                }
+
+                let (default, description) = match decl {
+                    TemplateParamDecl::Default(default) => (default, None),
+                    TemplateParamDecl::Full { default, description } => {
+                        (default, description)
+                    }
+                };
+                params.push(TemplateParam { name, default, description });
+            }
+            Ok(params)
+        }
+    }
+
+    deserializer.deserialize_map(Visitor)
 }
 
 /// The `[package]` key in the manifest.
@@ -265,3 +357,63 @@ impl<'de> Deserialize<'de> for PackageVersion {
         string.parse().map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::de::value::{Error, MapDeserializer};
+    use serde::de::IntoDeserializer;
+
+    use super::*;
+
+    fn params<V>(decls: Vec<(&'static str, V)>) -> Result<Vec<TemplateParam>, Error>
+    where
+        V: IntoDeserializer<'static, Error>,
+    {
+        deserialize_params(MapDeserializer::new(decls.into_iter()))
+    }
+
+    fn param(name: &str, default: &str, description: Option<&str>) -> TemplateParam {
+        TemplateParam {
+            name: name.into(),
+            default: default.into(),
+            description: description.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_params_default() {
+        assert_eq!(
+            params(vec![("title", "Untitled"), ("author", "")]).unwrap(),
+            [param("title", "Untitled", None), param("author", "", None)]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_params_full() {
+        let decls = vec![
+            ("author", BTreeMap::from([("default", ""), ("description", "Your name")])),
+            ("date", BTreeMap::from([("description", "The date")])),
+            ("title", BTreeMap::from([("default", "Untitled")])),
+        ];
+        assert_eq!(
+            params(decls).unwrap(),
+            [
+                param("author", "", Some("Your name")),
+                param("date", "", Some("The date")),
+                param("title", "Untitled", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_params_invalid_name() {
+        for name in ["", "1st", "first name", "a.b"] {
+            assert_eq!(
+                params(vec![(name, "")]).unwrap_err().to_string(),
+                format!("`{name}` is not a valid template parameter name")
+            );
+        }
+    }
+}
@@ -1800,8 +1800,22 @@ impl<'s> Parser<'s> {
     /// opening delimiter at `open`.
     #[track_caller]
     fn expect_closing_delimiter(&mut self, open: Marker, kind: SyntaxKind) {
-        if !self.eat_if(kind) {
-            self.nodes[open.0].convert_to_error("unclosed delimiter");
+        if !self.eat_if(kind) && !self.nodes[open.0].kind().is_error() {
+            // The delimiter is missing after the last non-trivia node.
+            let end = self.before_trivia();
+            let len = self.nodes[open.0..end.0].iter().map(SyntaxNode::len).sum();
+            let node = &mut self.nodes[open.0];
+            node.convert_to_error("unclosed delimiter");
+            let delim = match kind {
+                SyntaxKind::RightParen => ")",
+                SyntaxKind::RightBracket => "]",
+                SyntaxKind::RightBrace => "}",
+                SyntaxKind::Star => "*",
+                SyntaxKind::Underscore => "_",
+                SyntaxKind::Dollar => "$",
+                _ => return,
+            };
+            node.fix(eco_format!("add missing `{delim}`"), len..len, delim);
         }
     }
 
@@ -2,6 +2,7 @@
 
 use std::fmt::{self, Display, Formatter};
 use std::io;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
@@ -10,7 +11,7 @@ use comemo::Tracked;
 use ecow::{eco_vec, EcoVec};
 
 use crate::syntax::package::PackageSpec;
-use crate::syntax::{Span, Spanned, SyntaxError};
+use crate::syntax::{Fix, LinkedNode, Source, Span, Spanned, SyntaxError, SyntaxKind};
 use crate::{World, WorldExt};
 
 /// Early-return with a [`StrResult`] or [`SourceResult`].
@@ -138,6 +139,11 @@ pub struct SourceDiagnostic {
     /// Additional hints to the user, indicating how this problem could be avoided
     /// or worked around.
     pub hints: EcoVec<EcoString>,
+    /// Machine-applicable changes to the source code that resolve the problem.
+    pub fixes: EcoVec<Fix>,
+    /// An identifier for the kind of warning, with which it can be allowed
+    /// through a `// typst: allow(id)` comment or denied.
+    pub id: Option<EcoString>,
 }
 
 /// The severity of a [`SourceDiagnostic`].
@@ -158,6 +164,8 @@ impl SourceDiagnostic {
             trace: eco_vec![],
             message: message.into(),
             hints: eco_vec![],
+            fixes: eco_vec![],
+            id: None,
         }
     }
 
@@ -169,6 +177,8 @@ impl SourceDiagnostic {
             trace: eco_vec![],
             message: message.into(),
             hints: eco_vec![],
+            fixes: eco_vec![],
+            id: None,
         }
     }
 
@@ -188,6 +198,92 @@ impl SourceDiagnostic {
         self.hints.extend(hints);
         self
     }
+
+    /// Adds a machine-applicable fix to the diagnostic.
+    pub fn fix(&mut self, fix: Fix) {
+        self.fixes.push(fix);
+    }
+
+    /// Adds a machine-applicable fix to the diagnostic.
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix(fix);
+        self
+    }
+
+    /// Sets the identifier of the diagnostic.
+    pub fn with_id(mut self, id: impl Into<EcoString>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Whether the diagnostic is allowed through a `// typst: allow(id)`
+    /// comment.
+    ///
+    /// A comment after code applies to its own line, while a comment on a
+    /// line of its own applies to the markup or code that follows it. The
+    /// comment may also apply to one of the diagnostic's tracepoints.
+    pub fn is_allowed(&self, world: &dyn World) -> bool {
+        std::iter::once(self.span)
+            .chain(self.trace.iter().map(|point| point.span))
+            .any(|span| self.is_allowed_at(world, span))
+    }
+
+    /// Whether the diagnostic is allowed through a comment that applies to
+    /// the given span.
+    fn is_allowed_at(&self, world: &dyn World, span: Span) -> bool {
+        let Some(id) = &self.id else { return false };
+        let Some(source) = span.id().and_then(|id| world.source(id).ok()) else {
+            return false;
+        };
+        let Some(offset) = source.range(span).map(|range| range.start) else {
+            return false;
+        };
+        allowances(&source)
+            .iter()
+            .any(|(range, ids)| range.contains(&offset) && ids.contains(id))
+    }
+}
+
+/// Find the byte ranges of a source file in which diagnostics are allowed
+/// through comments, with the allowed identifiers.
+#[comemo::memoize]
+fn allowances(source: &Source) -> EcoVec<(Range<usize>, EcoVec<EcoString>)> {
+    let text = source.text();
+    let mut allowances = EcoVec::new();
+    let mut nodes = vec![LinkedNode::new(source.root())];
+    while let Some(node) = nodes.pop() {
+        nodes.extend(node.children());
+        if node.kind() != SyntaxKind::LineComment {
+            continue;
+        }
+
+        let Some(ids) = node
+            .text()
+            .trim_start_matches('/')
+            .trim()
+            .strip_prefix("typst:")
+            .and_then(|rest| rest.trim().strip_prefix("allow("))
+            .and_then(|rest| rest.trim_end().strip_suffix(')'))
+        else {
+            continue;
+        };
+
+        let line_start = text[..node.offset()].rfind('\n').map_or(0, |i| i + 1);
+        let range = if text[line_start..node.offset()].trim().is_empty() {
+            let mut next = node.next_sibling();
+            if next.as_ref().is_some_and(|next| next.kind() == SyntaxKind::Hash) {
+                next = next.and_then(|next| next.next_sibling());
+            }
+            let Some(next) = next else { continue };
+            next.range()
+        } else {
+            line_start..node.offset()
+        };
+
+        let ids = ids.split(',').map(|id| id.trim().into()).collect();
+        allowances.push((range, ids));
+    }
+    allowances
 }
 
 impl From<SyntaxError> for SourceDiagnostic {
@@ -198,6 +294,8 @@ impl From<SyntaxError> for SourceDiagnostic {
             message: error.message,
             trace: eco_vec![],
             hints: error.hints,
+            fixes: error.fixes,
+            id: None,
         }
     }
 }
@@ -303,6 +401,9 @@ pub struct HintedString {
     /// Additional hints to the user, indicating how this error could be avoided
     /// or worked around.
     pub hints: Vec<EcoString>,
+    /// Machine-applicable changes that resolve the error. Fixes with a
+    /// detached span apply to the node the error is reported at.
+    pub fixes: Vec<Fix>,
 }
 
 impl<S> From<S> for HintedString
@@ -310,14 +411,26 @@ where
     S: Into<EcoString>,
 {
     fn from(value: S) -> Self {
-        Self { message: value.into(), hints: vec![] }
+        Self {
+            message: value.into(),
+            hints: vec![],
+            fixes: vec![],
+        }
     }
 }
 
 impl<T> At<T> for Result<T, HintedString> {
     fn at(self, span: Span) -> SourceResult<T> {
         self.map_err(|diags| {
-            eco_vec![SourceDiagnostic::error(span, diags.message).with_hints(diags.hints)]
+            let mut diagnostic =
+                SourceDiagnostic::error(span, diags.message).with_hints(diags.hints);
+            for mut fix in diags.fixes {
+                if fix.span.is_detached() {
+                    fix.span = span;
+                }
+                diagnostic.fix(fix);
+            }
+            eco_vec![diagnostic]
         })
     }
 }
@@ -336,6 +449,7 @@ where
         self.map_err(|message| HintedString {
             message: message.into(),
             hints: vec![hint.into()],
+            fixes: vec![],
         })
     }
 }
@@ -12,7 +12,7 @@ use crate::introspection::{Introspector, Locator};
 use crate::math::{Accent, AccentElem, LrElem};
 use crate::symbols::Symbol;
 use crate::syntax::ast::{self, AstNode};
-use crate::syntax::{Span, Spanned, SyntaxNode};
+use crate::syntax::{Fix, Span, Spanned, SyntaxNode};
 use crate::text::TextElem;
 use crate::util::LazyHash;
 use crate::World;
@@ -105,6 +105,18 @@ impl Eval for ast::FuncCall<'_> {
                             "did you mean to access the field `{}`?",
                             field.as_str()
                         ));
+
+                        // Dropping the parentheses is only safe if they are empty.
+                        let parens = self.args();
+                        if parens.items().next().is_none() {
+                            let len = field.as_str().len();
+                            error.fix(Fix::replace(
+                                eco_format!("access the field `{}`", field.as_str()),
+                                field_span,
+                                len..len + parens.to_untyped().len(),
+                                "",
+                            ));
+                        }
                     }
                 };
 
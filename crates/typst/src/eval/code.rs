@@ -147,7 +147,11 @@ impl Eval for ast::Ident<'_> {
     type Output = Value;
 
     fn eval(self, vm: &mut Vm) -> SourceResult<Self::Output> {
-        vm.scopes.get(&self).cloned().at(self.span())
+        vm.scopes
+            .get(&self)
+            .cloned()
+            .map_err(|err| vm.scopes.suggest(&self, false, err))
+            .at(self.span())
     }
 }
 
@@ -135,10 +135,13 @@ impl Eval for ast::Strong<'_> {
         if body.exprs().next().is_none() {
             vm.engine
                 .tracer
-                .warn(warning!(
-                    self.span(), "no text within stars";
-                    hint: "using multiple consecutive stars (e.g. **) has no additional effect",
-                ));
+                .warn(
+                    warning!(
+                        self.span(), "no text within stars";
+                        hint: "using multiple consecutive stars (e.g. **) has no additional effect",
+                    )
+                    .with_id("empty-strong"),
+                );
         }
 
         Ok(StrongElem::new(body.eval(vm)?).pack())
@@ -153,10 +156,13 @@ impl Eval for ast::Emph<'_> {
         if body.exprs().next().is_none() {
             vm.engine
                 .tracer
-                .warn(warning!(
-                    self.span(), "no text within underscores";
-                    hint: "using multiple consecutive underscores (e.g. __) has no additional effect"
-                ));
+                .warn(
+                    warning!(
+                        self.span(), "no text within underscores";
+                        hint: "using multiple consecutive underscores (e.g. __) has no additional effect"
+                    )
+                    .with_id("empty-emph"),
+                );
         }
 
         Ok(EmphElem::new(body.eval(vm)?).pack())
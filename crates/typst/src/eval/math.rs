@@ -22,7 +22,11 @@ impl Eval for ast::MathIdent<'_> {
     type Output = Value;
 
     fn eval(self, vm: &mut Vm) -> SourceResult<Self::Output> {
-        vm.scopes.get_in_math(&self).cloned().at(self.span())
+        vm.scopes
+            .get_in_math(&self)
+            .cloned()
+            .map_err(|err| vm.scopes.suggest(&self, true, err))
+            .at(self.span())
     }
 }
 
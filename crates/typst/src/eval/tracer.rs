@@ -6,6 +6,7 @@ use crate::diag::SourceDiagnostic;
 use crate::foundations::{Styles, Value};
 use crate::syntax::{FileId, Span};
 use crate::util::hash128;
+use crate::World;
 
 /// Traces warnings and which values existed for an expression at a span.
 #[derive(Default, Clone)]
@@ -36,6 +37,25 @@ impl Tracer {
         self.warnings
     }
 
+    /// Remove the warnings that are allowed through comments in the source
+    /// code at their span or one of their tracepoints.
+    ///
+    /// Warnings that only differ in their trace are kept apart until now, so
+    /// that each can be allowed on its own. Of the remaining ones, only the
+    /// first is kept.
+    pub(crate) fn drop_allowed(&mut self, world: &dyn World) {
+        let mut seen = HashSet::new();
+        let mut kept = EcoVec::new();
+        for warning in &self.warnings {
+            if !warning.is_allowed(world)
+                && seen.insert(hash128(&(&warning.span, &warning.message)))
+            {
+                kept.push(warning.clone());
+            }
+        }
+        self.warnings = kept;
+    }
+
     /// Mark a span as inspected. All values observed for this span can be
     /// retrieved via `values` later.
     pub fn inspect(&mut self, span: Span) {
@@ -58,7 +78,7 @@ impl Tracer {
     /// Add a warning.
     pub fn warn(&mut self, warning: SourceDiagnostic) {
         // Check if warning is a duplicate.
-        let hash = hash128(&(&warning.span, &warning.message));
+        let hash = hash128(&(&warning.span, &warning.message, &warning.trace));
         if self.warnings_set.insert(hash) {
             self.warnings.push(warning);
         }
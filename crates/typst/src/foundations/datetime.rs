@@ -162,6 +162,12 @@ impl Datetime {
             return None;
         };
 
+        Self::from_iso(string)
+    }
+
+    /// Try to parse an ISO 8601 date (`2024-03-15`), time (`12:30:00`), or
+    /// date and time (`2024-03-15T12:30:00`, optionally followed by `Z`).
+    pub fn from_iso(string: &str) -> Option<Self> {
         if let Ok(d) = time::PrimitiveDateTime::parse(
             string,
             &format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]Z"),
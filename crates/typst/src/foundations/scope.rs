@@ -9,6 +9,7 @@ use crate::foundations::{
     Element, Func, IntoValue, Module, NativeElement, NativeFunc, NativeFuncData,
     NativeType, Type, Value,
 };
+use crate::syntax::{Fix, Span};
 use crate::util::Static;
 use crate::Library;
 
@@ -76,22 +77,132 @@ impl<'a> Scopes<'a> {
     }
 }
 
+impl Scopes<'_> {
+    /// Extend the error for an unknown variable with a suggestion of a
+    /// standard library module defining it or of a similarly named variable.
+    #[cold]
+    pub fn suggest(
+        &self,
+        var: &str,
+        math: bool,
+        mut error: HintedString,
+    ) -> HintedString {
+        // Single letters are almost always meant to be local variables.
+        if !error.fixes.is_empty() || var.chars().count() < 2 {
+            return error;
+        }
+
+        let base =
+            self.base
+                .map(|base| if math { base.math.scope() } else { base.global.scope() });
+
+        // Prefer the most specific module, as some re-export others' items.
+        let module = base
+            .into_iter()
+            .flat_map(Scope::iter)
+            .filter_map(|(name, value)| match value {
+                Value::Module(module) if module.scope().get(var).is_some() => {
+                    Some((name, module.scope().iter().count()))
+                }
+                _ => None,
+            })
+            .min_by_key(|&(_, count)| count)
+            .map(|(name, _)| name);
+
+        if let Some(module) = module {
+            error
+                .hints
+                .push(eco_format!("`{var}` is defined in the `{module}` module"));
+            error.fixes.push(Fix::replace(
+                eco_format!("use `{module}.{var}`"),
+                Span::detached(),
+                0..var.len(),
+                eco_format!("{module}.{var}"),
+            ));
+            return error;
+        }
+
+        // Allow one typo per three characters.
+        let limit = var.chars().count() / 3;
+        let similar = std::iter::once(&self.top)
+            .chain(self.scopes.iter().rev())
+            .chain(base)
+            .flat_map(Scope::iter)
+            .map(|(name, _)| (distance(var, name), name))
+            .filter(|&(distance, _)| distance <= limit)
+            .min_by_key(|&(distance, _)| distance);
+
+        if let Some((_, name)) = similar {
+            error.hints.push(eco_format!("did you mean `{name}`?"));
+            error.fixes.push(Fix::replace(
+                eco_format!("replace with `{name}`"),
+                Span::detached(),
+                0..var.len(),
+                name.clone(),
+            ));
+        }
+
+        error
+    }
+}
+
+/// The number of single-character insertions, deletions, substitutions and
+/// transpositions needed to turn one string into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // The distances between all prefixes of `a` and `b`.
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1)
+                .min(table[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(table[i - 2][j - 2] + 1);
+            }
+            table[i][j] = best;
+        }
+    }
+
+    table[a.len()][b.len()]
+}
+
 /// The error message when a variable is not found.
 #[cold]
 fn unknown_variable(var: &str) -> HintedString {
     let mut res = HintedString {
         message: eco_format!("unknown variable: {}", var),
         hints: vec![],
+        fixes: vec![],
     };
 
     if matches!(var, "none" | "auto" | "false" | "true") {
         res.hints.push(eco_format!(
             "if you meant to use a literal, try adding a hash before it"
         ));
+        res.fixes
+            .push(Fix::insert("add missing `#`", Span::detached(), 0, "#"));
     } else if var.contains('-') {
         res.hints.push(eco_format!(
             "if you meant to use subtraction, try adding spaces around the minus sign",
         ));
+        // Parenthesized, so that the fix also works in embedded expressions.
+        res.fixes.push(Fix::replace(
+            "add spaces around the minus sign",
+            Span::detached(),
+            0..var.len(),
+            eco_format!("({})", var.replace('-', " - ")),
+        ));
     }
 
     res
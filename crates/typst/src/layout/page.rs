@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::ptr;
 use std::str::FromStr;
 
@@ -527,6 +528,38 @@ pub struct Page {
     pub number: usize,
 }
 
+/// A selection of pages to export, given as a list of page ranges.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PageRanges(Vec<PageRange>);
+
+/// A range of physical page numbers.
+///
+/// The range is one-indexed and inclusive. An unbounded start or end extends
+/// the range to the first or last page, respectively. For example,
+/// `Some(3)..=None` includes the third page and all pages after it.
+pub type PageRange = RangeInclusive<Option<NonZeroUsize>>;
+
+impl PageRanges {
+    /// Create a selection from a list of page ranges.
+    pub fn new(ranges: Vec<PageRange>) -> Self {
+        Self(ranges)
+    }
+
+    /// Whether the page with the given one-indexed physical number is
+    /// selected.
+    pub fn includes_page(&self, page: NonZeroUsize) -> bool {
+        self.0.iter().any(|range| {
+            range.start().map_or(true, |start| start <= page)
+                && range.end().map_or(true, |end| page <= end)
+        })
+    }
+
+    /// Whether the page with the given zero-based index is selected.
+    pub fn includes_page_index(&self, index: usize) -> bool {
+        self.includes_page(NonZeroUsize::new(index + 1).unwrap())
+    }
+}
+
 /// Specification of the page's margins.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Margin {
@@ -975,3 +1008,30 @@ papers! {
     (PRESENTATION_16_9:    297.0, 167.0625, "presentation-16-9")
     (PRESENTATION_4_3:     280.0,    210.0, "presentation-4-3")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_ranges_includes_page() {
+        let page = |n| NonZeroUsize::new(n);
+        let ranges =
+            PageRanges::new(vec![page(1)..=page(1), page(3)..=page(5), page(10)..=None]);
+        let included: Vec<_> =
+            (1..=12).filter(|&n| ranges.includes_page(page(n).unwrap())).collect();
+        assert_eq!(included, [1, 3, 4, 5, 10, 11, 12]);
+        assert!(ranges.includes_page_index(0));
+        assert!(!ranges.includes_page_index(1));
+
+        let ranges = PageRanges::new(vec![None..=page(3)]);
+        assert!(ranges.includes_page(page(1).unwrap()));
+        assert!(ranges.includes_page(page(3).unwrap()));
+        assert!(!ranges.includes_page(page(4).unwrap()));
+
+        // A selection that is out of range for a document includes no page.
+        let ranges = PageRanges::new(vec![page(6)..=page(8)]);
+        assert!(!(0..5).any(|i| ranges.includes_page_index(i)));
+        assert!(!PageRanges::new(vec![]).includes_page_index(0));
+    }
+}
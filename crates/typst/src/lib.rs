@@ -88,19 +88,22 @@ use crate::visualize::Color;
 #[typst_macros::time(name = "compile")]
 pub fn compile(world: &dyn World, tracer: &mut Tracer) -> SourceResult<Document> {
     // Call `track` on the world just once to keep comemo's ID stable.
-    let world = world.track();
+    let tracked = world.track();
 
-    // Try to evaluate the source file into a module.
-    let module = crate::eval::eval(
-        world,
+    // Try to evaluate the source file into a module and typeset its content,
+    // relayouting until convergence.
+    let result = crate::eval::eval(
+        tracked,
         Route::default().track(),
         tracer.track_mut(),
-        &world.main(),
+        &tracked.main(),
     )
-    .map_err(deduplicate)?;
+    .and_then(|module| typeset(tracked, tracer, &module.content()));
 
-    // Typeset the module's content, relayouting until convergence.
-    typeset(world, tracer, &module.content()).map_err(deduplicate)
+    // Drop the warnings that are allowed through comments.
+    tracer.drop_allowed(world);
+
+    result.map_err(deduplicate)
 }
 
 /// Relayout until introspection converges.
@@ -147,10 +150,13 @@ fn typeset(
         }
 
         if iter >= 5 {
-            tracer.warn(warning!(
-                Span::detached(), "layout did not converge within 5 attempts";
-                hint: "check if any states or queries are updating themselves"
-            ));
+            tracer.warn(
+                warning!(
+                    Span::detached(), "layout did not converge within 5 attempts";
+                    hint: "check if any states or queries are updating themselves"
+                )
+                .with_id("layout-convergence"),
+            );
             break;
         }
     }
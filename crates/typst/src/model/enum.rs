@@ -8,11 +8,12 @@ use crate::engine::Engine;
 use crate::foundations::{
     cast, elem, scope, Array, Content, Context, Packed, Smart, StyleChain,
 };
+use crate::introspection::Locatable;
 use crate::layout::{
     Alignment, Axes, BlockElem, Cell, CellGrid, Em, Fragment, GridLayouter, HAlignment,
     LayoutMultiple, Length, Regions, Sizing, Spacing, VAlignment,
 };
-use crate::model::{Numbering, NumberingPattern, ParElem};
+use crate::model::{mark_item, Numbering, NumberingPattern, ParElem};
 use crate::text::TextElem;
 
 /// A numbered list.
@@ -71,7 +72,7 @@ use crate::text::TextElem;
 /// Enumeration items can contain multiple paragraphs and other block-level
 /// content. All content that is indented more than an item's marker becomes
 /// part of that item.
-#[elem(scope, title = "Numbered List", LayoutMultiple)]
+#[elem(scope, title = "Numbered List", Locatable, LayoutMultiple)]
 pub struct EnumElem {
     /// If this is `{false}`, the items are spaced apart with
     /// [enum spacing]($enum.spacing). If it is `{true}`, they use normal
@@ -266,12 +267,16 @@ impl LayoutMultiple for Packed<EnumElem> {
             let resolved =
                 resolved.aligned(number_align).styled(TextElem::set_overhang(false));
 
+            let (resolved, body) = mark_item(
+                engine,
+                item.clone().pack(),
+                resolved,
+                item.body().clone().styled(EnumElem::set_parents(smallvec![number])),
+            );
             cells.push(Cell::from(Content::empty()));
             cells.push(Cell::from(resolved));
             cells.push(Cell::from(Content::empty()));
-            cells.push(Cell::from(
-                item.body().clone().styled(EnumElem::set_parents(smallvec![number])),
-            ));
+            cells.push(Cell::from(body));
             number = number.saturating_add(1);
         }
 
@@ -1,4 +1,5 @@
 use comemo::Track;
+use smallvec::smallvec;
 
 use crate::diag::{bail, SourceResult};
 use crate::engine::Engine;
@@ -6,12 +7,14 @@ use crate::foundations::{
     cast, elem, scope, Array, Content, Context, Depth, Func, Packed, Smart, StyleChain,
     Value,
 };
+use crate::introspection::{Locatable, Meta, MetaElem};
 use crate::layout::{
     Axes, BlockElem, Cell, CellGrid, Em, Fragment, GridLayouter, HAlignment,
     LayoutMultiple, Length, Regions, Sizing, Spacing, VAlignment,
 };
 use crate::model::ParElem;
 use crate::text::TextElem;
+use crate::util::hash128;
 
 /// A bullet list.
 ///
@@ -44,7 +47,7 @@ use crate::text::TextElem;
 /// followed by a space to create a list item. A list item can contain multiple
 /// paragraphs and other block-level content. All content that is indented
 /// more than an item's marker becomes part of that item.
-#[elem(scope, title = "Bullet List", LayoutMultiple)]
+#[elem(scope, title = "Bullet List", Locatable, LayoutMultiple)]
 pub struct ListElem {
     /// If this is `{false}`, the items are spaced apart with
     /// [list spacing]($list.spacing). If it is `{true}`, they use normal
@@ -163,12 +166,16 @@ impl LayoutMultiple for Packed<ListElem> {
 
         let mut cells = vec![];
         for item in self.children() {
+            let (marker, body) = mark_item(
+                engine,
+                item.clone().pack(),
+                marker.clone(),
+                item.body().clone().styled(ListElem::set_depth(Depth(1))),
+            );
             cells.push(Cell::from(Content::empty()));
-            cells.push(Cell::from(marker.clone()));
+            cells.push(Cell::from(marker));
             cells.push(Cell::from(Content::empty()));
-            cells.push(Cell::from(
-                item.body().clone().styled(ListElem::set_depth(Depth(1))),
-            ));
+            cells.push(Cell::from(body));
         }
 
         let grid = CellGrid::new(
@@ -187,6 +194,25 @@ impl LayoutMultiple for Packed<ListElem> {
     }
 }
 
+/// Mark the marker and body of a list, enumeration or term list item, so that
+/// the structure of the list can be recovered from the frames.
+///
+/// The body is marked with the item itself and the marker with a variant of
+/// the item's location.
+pub(crate) fn mark_item(
+    engine: &mut Engine,
+    mut item: Content,
+    marker: Content,
+    body: Content,
+) -> (Content, Content) {
+    let location = engine.locator.locate(hash128(&item));
+    item.set_location(location);
+    (
+        marker.backlinked(location.variant(1)),
+        body.styled(MetaElem::set_data(smallvec![Meta::Elem(item)])),
+    )
+}
+
 /// A bullet list item.
 #[elem(name = "item", title = "Bullet List Item")]
 pub struct ListItem {
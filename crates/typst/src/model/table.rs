@@ -2,12 +2,14 @@ use std::num::NonZeroUsize;
 use std::sync::Arc;
 
 use ecow::{eco_format, EcoString};
+use smallvec::smallvec;
 
 use crate::diag::{bail, SourceResult, StrResult, Trace, Tracepoint};
 use crate::engine::Engine;
 use crate::foundations::{
     cast, elem, scope, Content, Fold, Packed, Show, Smart, StyleChain,
 };
+use crate::introspection::{Locatable, Meta, MetaElem};
 use crate::layout::{
     show_grid_cell, Abs, Alignment, Axes, Cell, CellGrid, Celled, Dir, Fragment,
     GridCell, GridFooter, GridHLine, GridHeader, GridLayouter, GridVLine, LayoutMultiple,
@@ -17,7 +19,7 @@ use crate::layout::{
 use crate::model::Figurable;
 use crate::syntax::Span;
 use crate::text::{Lang, LocalName, Region, TextElem};
-use crate::util::NonZeroExt;
+use crate::util::{hash128, NonZeroExt};
 use crate::visualize::{Paint, Stroke};
 
 /// A table of items.
@@ -120,7 +122,7 @@ use crate::visualize::{Paint, Stroke};
 ///   [Robert], b, a, b,
 /// )
 /// ```
-#[elem(scope, LayoutMultiple, LocalName, Figurable)]
+#[elem(scope, Locatable, LayoutMultiple, LocalName, Figurable)]
 pub struct TableElem {
     /// The column sizes. See the [grid documentation]($grid) for more
     /// information on track sizing.
@@ -912,8 +914,18 @@ impl ResolvableCell for Packed<TableCell> {
 }
 
 impl Show for Packed<TableCell> {
-    fn show(&self, _engine: &mut Engine, styles: StyleChain) -> SourceResult<Content> {
-        show_grid_cell(self.body().clone(), self.inset(styles), self.align(styles))
+    fn show(&self, engine: &mut Engine, styles: StyleChain) -> SourceResult<Content> {
+        let mut body = self.body().clone();
+
+        // Mark the body with the cell, so that the structure of the table can
+        // be recovered from the frames. Labelled cells are already marked.
+        if self.location().is_none() {
+            let mut cell = self.clone().pack();
+            cell.set_location(engine.locator.locate(hash128(&cell)));
+            body = body.styled(MetaElem::set_data(smallvec![Meta::Elem(cell)]));
+        }
+
+        show_grid_cell(body, self.inset(styles), self.align(styles))
     }
 }
 
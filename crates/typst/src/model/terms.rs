@@ -3,11 +3,12 @@ use crate::engine::Engine;
 use crate::foundations::{
     cast, elem, scope, Array, Content, NativeElement, Packed, Smart, StyleChain,
 };
+use crate::introspection::Locatable;
 use crate::layout::{
     BlockElem, Dir, Em, Fragment, HElem, LayoutMultiple, Length, Regions, Sides, Spacing,
     StackChild, StackElem,
 };
-use crate::model::ParElem;
+use crate::model::{mark_item, ParElem};
 use crate::text::TextElem;
 use crate::util::Numeric;
 
@@ -27,7 +28,7 @@ use crate::util::Numeric;
 /// # Syntax
 /// This function also has dedicated syntax: Starting a line with a slash,
 /// followed by a term, a colon and a description creates a term list item.
-#[elem(scope, title = "Term List", LayoutMultiple)]
+#[elem(scope, title = "Term List", Locatable, LayoutMultiple)]
 pub struct TermsElem {
     /// If this is `{false}`, the items are spaced apart with
     /// [term list spacing]($terms.spacing). If it is `{true}`, they use normal
@@ -133,11 +134,16 @@ impl LayoutMultiple for Packed<TermsElem> {
 
         let mut children = vec![];
         for child in self.children().iter() {
+            let (term, description) = mark_item(
+                engine,
+                child.clone().pack(),
+                child.term().clone().strong() + (*separator).clone(),
+                child.description().clone(),
+            );
             let mut seq = vec![];
             seq.extend(unpad.clone());
-            seq.push(child.term().clone().strong());
-            seq.push((*separator).clone());
-            seq.push(child.description().clone());
+            seq.push(term);
+            seq.push(description);
             children.push(StackChild::Block(Content::sequence(seq)));
         }
 
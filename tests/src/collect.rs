@@ -7,6 +7,7 @@ use std::str::FromStr;
 use ecow::{eco_format, EcoString};
 use typst::syntax::package::PackageVersion;
 use typst::syntax::{is_id_continue, is_ident, is_newline, FileId, Source, VirtualPath};
+use typst_pdf::PdfStandard;
 use unscanny::Scanner;
 
 /// Collects all tests from all files.
@@ -25,6 +26,11 @@ pub struct Test {
     pub source: Source,
     pub notes: Vec<Note>,
     pub large: bool,
+    /// The PDF standards the test is additionally exported with, marked by
+    /// `// PDF/UA`, `// PDF/A-2b`, or `// PDF/A-3b`.
+    pub pdf: Vec<PdfStandard>,
+    /// Whether the test is exported as HTML, marked by `// HTML`.
+    pub html: bool,
 }
 
 impl Display for Test {
@@ -252,7 +258,17 @@ impl<'a> Parser<'a> {
             }
 
             let text = self.s.from(start);
-            let large = text.starts_with("// LARGE");
+            let large = flagged(text, "LARGE");
+            let pdf = [
+                ("PDF/UA", PdfStandard::Ua_1),
+                ("PDF/A-2b", PdfStandard::A_2b),
+                ("PDF/A-3b", PdfStandard::A_3b),
+            ]
+            .into_iter()
+            .filter(|(flag, _)| flagged(text, flag))
+            .map(|(_, standard)| standard)
+            .collect();
+            let html = flagged(text, "HTML");
             if large {
                 self.collector.large.insert(name.clone());
             }
@@ -280,7 +296,15 @@ impl<'a> Parser<'a> {
                 }
             }
 
-            self.collector.tests.push(Test { pos, name, source, notes, large });
+            self.collector.tests.push(Test {
+                pos,
+                name,
+                source,
+                notes,
+                large,
+                pdf,
+                html,
+            });
         }
     }
 
@@ -383,6 +407,13 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Whether the leading comment lines of a test contain a flag like `// LARGE`.
+fn flagged(text: &str, flag: &str) -> bool {
+    text.lines()
+        .take_while(|line| line.starts_with("//"))
+        .any(|line| line.strip_prefix("// ") == Some(flag))
+}
+
 /// Whether a test is within the filtered set.
 fn filtered(name: &str) -> bool {
     let exact = crate::ARGS.exact;
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::ops::Range;
 use std::path::Path;
@@ -6,12 +7,14 @@ use ecow::eco_vec;
 use tiny_skia as sk;
 use typst::diag::SourceDiagnostic;
 use typst::eval::Tracer;
-use typst::foundations::Smart;
 use typst::introspection::Meta;
 use typst::layout::{Abs, Frame, FrameItem, Page, Transform};
 use typst::model::Document;
+use typst::syntax::Span;
 use typst::visualize::Color;
 use typst::WorldExt;
+use typst_pdf::{PdfOptions, PdfStandard, PdfStandards};
+use unscanny::Scanner;
 
 use crate::collect::{FileSize, NoteKind, Test};
 use crate::world::TestWorld;
@@ -93,6 +96,12 @@ impl<'a> Runner<'a> {
         }
 
         self.check_document(doc.as_ref());
+        if let Some(doc) = doc.as_ref().filter(|_| !self.test.pdf.is_empty()) {
+            self.check_pdf(doc);
+        }
+        if let Some(doc) = doc.as_ref().filter(|_| self.test.html) {
+            self.check_html(doc);
+        }
 
         for error in &errors {
             self.check_diagnostic(NoteKind::Error, error);
@@ -176,7 +185,8 @@ impl<'a> Runner<'a> {
         // Write PDF if requested.
         if crate::ARGS.pdf {
             let pdf_path = format!("{}/pdf/{}.pdf", crate::STORE_PATH, self.test.name);
-            let pdf = typst_pdf::pdf(document, Smart::Auto, None);
+            let pdf =
+                typst_pdf::pdf_with_options(document, &PdfOptions::default()).unwrap();
             std::fs::write(pdf_path, pdf).unwrap();
         }
 
@@ -238,6 +248,125 @@ impl<'a> Runner<'a> {
         }
     }
 
+    /// Check that the document is exported with the test's PDF standards or
+    /// fails with the annotated errors.
+    ///
+    /// A PDF/UA-1 file must be tagged and its structure tree must match the
+    /// reference snapshot, while a plain export stays untagged. A PDF/A file
+    /// must identify its part and conformance level and have an output
+    /// intent.
+    fn check_pdf(&mut self, document: &Document) {
+        let contains = |pdf: &[u8], pattern: &str| {
+            pdf.windows(pattern.len()).any(|w| w == pattern.as_bytes())
+        };
+
+        let ua = self.test.pdf.contains(&PdfStandard::Ua_1);
+        if ua {
+            let plain =
+                typst_pdf::pdf_with_options(document, &PdfOptions::default()).unwrap();
+            if contains(&plain, "/StructTreeRoot") {
+                log!(self, "PDF without standards is tagged");
+            }
+        }
+
+        let standards = match PdfStandards::new(&self.test.pdf) {
+            Ok(standards) => standards,
+            Err(message) => {
+                let error = SourceDiagnostic::error(Span::detached(), message);
+                self.check_diagnostic(NoteKind::Error, &error);
+                return;
+            }
+        };
+
+        let options = PdfOptions { standards, ..PdfOptions::default() };
+        let pdf = match typst_pdf::pdf_with_options(document, &options) {
+            Ok(pdf) => pdf,
+            Err(errors) => {
+                for error in &errors {
+                    self.check_diagnostic(NoteKind::Error, error);
+                }
+                return;
+            }
+        };
+
+        if ua {
+            if contains(&pdf, "/StructTreeRoot") {
+                self.compare_snapshot(
+                    "tags",
+                    "txt",
+                    "structure tree",
+                    &struct_tree(&pdf),
+                );
+            } else {
+                log!(self, "PDF/UA-1 export is not tagged");
+            }
+        }
+
+        let part = self.test.pdf.iter().find_map(|standard| match standard {
+            PdfStandard::A_2b => Some(2),
+            PdfStandard::A_3b => Some(3),
+            _ => None,
+        });
+
+        if let Some(part) = part {
+            let part_xmp = format!("<pdfaid:part>{part}</pdfaid:part>");
+            for (pattern, what) in [
+                ("/OutputIntents", "output intent"),
+                ("/S /GTS_PDFA1", "PDF/A output intent subtype"),
+                ("/DestOutputProfile", "output profile"),
+                (part_xmp.as_str(), "PDF/A part"),
+                ("<pdfaid:conformance>B</pdfaid:conformance>", "PDF/A conformance"),
+            ] {
+                if !contains(&pdf, pattern) {
+                    log!(self, "PDF/A-{part}b export is missing the {what}");
+                }
+            }
+        }
+    }
+
+    /// Check that the document is exported to the HTML of the reference
+    /// snapshot or fails with the annotated errors.
+    fn check_html(&mut self, document: &Document) {
+        let html = match typst_html::html(&self.world, document, &mut Tracer::new()) {
+            Ok(html) => html,
+            Err(errors) => {
+                for error in &errors {
+                    self.check_diagnostic(NoteKind::Error, error);
+                }
+                return;
+            }
+        };
+
+        self.compare_snapshot("html", "html", "HTML", &html);
+    }
+
+    /// Compare a textual export with its reference snapshot in the given
+    /// directory, updating the snapshot if requested.
+    fn compare_snapshot(&mut self, dir: &str, ext: &str, what: &str, live: &str) {
+        let name = &self.test.name;
+        let live_path = format!("{}/{dir}/{name}.{ext}", crate::STORE_PATH);
+        let ref_path = format!("{}/{dir}/{name}.{ext}", crate::REF_PATH);
+
+        std::fs::write(&live_path, live).unwrap();
+        let reference = std::fs::read_to_string(&ref_path).ok();
+        if reference.as_deref() == Some(live) {
+            return;
+        }
+
+        if crate::ARGS.update {
+            std::fs::create_dir_all(Path::new(&ref_path).parent().unwrap()).unwrap();
+            std::fs::write(&ref_path, live).unwrap();
+            log!(into: self.result.infos, "Updated reference {what} ({ref_path})");
+        } else if reference.is_some() {
+            log!(self, "mismatched {what}");
+            log!(self, "  live      | {live_path}");
+            log!(self, "  ref       | {ref_path}");
+        } else {
+            log!(self, "missing reference {what}");
+            log!(self, "  live      | {live_path}");
+        }
+    }
+
     /// Compare a subset of notes with a given kind against diagnostics of
     /// that same kind.
     fn check_diagnostic(&mut self, kind: NoteKind, diag: &SourceDiagnostic) {
@@ -421,6 +550,90 @@ fn skippable_frame(frame: &Frame) -> bool {
     })
 }
 
+/// Render the structure tree of a tagged PDF as an outline of its elements
+/// with their marked content sequences and annotations.
+fn struct_tree(pdf: &[u8]) -> String {
+    // Structure elements are uncompressed, so a textual view of the file
+    // suffices to find them.
+    let text = String::from_utf8_lossy(pdf);
+    let objects: HashMap<&str, &str> = text
+        .split("endobj")
+        .filter_map(|chunk| {
+            let (head, body) = chunk.rsplit_once(" 0 obj")?;
+            let id = head.rsplit(char::is_whitespace).next()?;
+            Some((id, body))
+        })
+        .collect();
+
+    let mut out = String::new();
+    if let Some(root) =
+        objects.values().find(|body| body.contains("/Type /StructTreeRoot"))
+    {
+        write_struct_kids(&mut out, &objects, root, 0);
+    }
+    out
+}
+
+/// Write the kids of a structure element or the structure tree root.
+fn write_struct_kids(
+    out: &mut String,
+    objects: &HashMap<&str, &str>,
+    body: &str,
+    depth: usize,
+) {
+    let Some((_, kids)) = body.split_once("/K ") else { return };
+    let mut s = Scanner::new(kids);
+    loop {
+        s.eat_while(|c: char| c.is_whitespace() || c == '[');
+        if s.eat_if("<<") {
+            let dict = s.eat_until(">>");
+            s.eat_if(">>");
+            let kid = if dict.contains("/OBJR") {
+                "annotation".into()
+            } else {
+                let mcid = dict.split_once("/MCID ").map_or("", |(_, rest)| rest);
+                format!("mcid {}", mcid.trim())
+            };
+            writeln!(out, "{}{kid}", "  ".repeat(depth)).unwrap();
+        } else if s.at(char::is_numeric) {
+            let number = s.eat_while(char::is_numeric);
+            if s.eat_if(" 0 R") {
+                write_struct_elem(out, objects, number, depth);
+            } else {
+                writeln!(out, "{}mcid {number}", "  ".repeat(depth)).unwrap();
+            }
+        } else {
+            break;
+        }
+    }
+}
+
+/// Write a structure element along with its alternative text and table
+/// header scope.
+fn write_struct_elem(
+    out: &mut String,
+    objects: &HashMap<&str, &str>,
+    id: &str,
+    depth: usize,
+) {
+    let Some(body) = objects.get(id) else { return };
+    let value = |key: &str, end: char| {
+        let (_, rest) = body.split_once(key)?;
+        rest.split(end).next()
+    };
+
+    write!(out, "{}{}", "  ".repeat(depth), value("/S /", '\n').unwrap_or("?")).unwrap();
+    if let Some(alt) = value("/Alt (", ')') {
+        write!(out, " alt=\"{alt}\"").unwrap();
+    }
+    if let Some(scope) = value("/Scope /", '\n') {
+        write!(out, " scope={scope}").unwrap();
+    }
+    out.push('\n');
+
+    write_struct_kids(out, objects, body, depth + 1);
+}
+
 /// Whether to pixel images are approximately equal.
 fn approx_equal(a: &sk::Pixmap, b: &sk::Pixmap) -> bool {
     a.width() == b.width()
@@ -0,0 +1,43 @@
+--- html-basic ---
+// HTML
+#set page(width: 120pt, height: auto)
+#set document(title: "Basics & more")
+#set heading(numbering: "1.")
+= Introduction <intro>
+Some *strong* and _emphasized_ text \
+with a #link("https://typst.app")[link] and a reference to @intro.
+
+Escaped: `<a>` & 1 < 2
+
+--- html-smart-quotes ---
+// HTML
+#set page(width: 120pt, height: auto)
+"Double" and 'single' quotes, it's the 90's.
+
+#set text(lang: "de")
+"Anführungszeichen" und 'halbe'
+
+#set smartquote(enabled: false)
+"Straight" and 'plain'
+
+--- html-lists ---
+// HTML
+#set page(width: 120pt, height: auto)
+- First
+- Second
+
++ One
++ Two
+
+/ Term: Description
+
+--- html-table-and-footnote ---
+// HTML
+#set page(width: 120pt, height: auto)
+#table(
+  columns: 2,
+  table.header[A][B],
+  table.cell(rowspan: 2)[1], [2],
+  [3],
+)
+Note#footnote[A footnote.]
@@ -0,0 +1,87 @@
+--- pdf-ua-tagged ---
+// PDF/UA
+#set page(width: 120pt, height: auto)
+#set document(title: "Tagged")
+= Introduction
+See #link("https://typst.app")[the website] for more.
+
+#figure(
+  image.decode(
+    "<svg xmlns='http://www.w3.org/2000/svg' width='20' height='10'><rect width='20' height='10'/></svg>",
+    alt: "A black rectangle",
+  ),
+  caption: [A rectangle],
+)
+
+- Lists
+  - Nested
++ Enums
+/ Terms: Too
+
+#table(
+  columns: 2,
+  table.header[Key][Value],
+  [A], [1],
+)
+
+--- pdf-ua-missing-title ---
+// PDF/UA
+// Error: PDF/UA-1 error: document title is missing
+// Hint: set the title with `set document(title: ..)`
+Hello
+
+--- pdf-ua-missing-language ---
+// PDF/UA
+// Error: PDF/UA-1 error: document language is missing
+// Hint: set the language with `set text(lang: ..)`
+#set document(title: "Empty")
+#metadata(none)
+
+--- pdf-ua-missing-alt ---
+// PDF/UA
+#set page(width: auto, height: auto, margin: 0pt)
+#set document(title: "Images")
+#let svg = "<svg xmlns='http://www.w3.org/2000/svg' width='20' height='10'><rect width='20' height='10'/></svg>"
+// Error: 2:3-2:20 PDF/UA-1 error: image is missing alternative text
+// Hint: 2:3-2:20 describe the image with the `alt` parameter
+#figure(
+  image.decode(svg),
+  caption: [Unlabelled],
+)
+
+--- pdf-a-metadata ---
+// PDF/A-2b
+#set page(width: 80pt, height: auto)
+#set document(title: "Archived")
+Hello
+
+--- pdf-a-with-ua ---
+// PDF/UA
+// PDF/A-3b
+#set page(width: 80pt, height: auto)
+#set document(title: "Archived")
+#set text(lang: "en")
+Hello
+
+--- pdf-a-cmyk ---
+// PDF/A-2b
+#set page(width: 80pt, height: auto)
+// Error: 37-42 PDF/A-2b error: CMYK colors are not supported
+// Hint: 37-42 convert the color to RGB with `rgb(..)`
+#text(fill: cmyk(0%, 50%, 50%, 0%))[Hello]
+// Error: 2-49 PDF/A-2b error: CMYK colors are not supported
+// Hint: 2-49 convert the color to RGB with `rgb(..)`
+#rect(fill: cmyk(0%, 0%, 0%, 50%), height: 10pt)
+
+--- pdf-a-notdef ---
+// PDF/A-3b
+#set page(width: 80pt, height: auto)
+// Error: 1-5 PDF/A-3b error: the text "𞤀" could not be displayed with any font
+// Hint: 1-5 choose a font that covers this text with `set text(font: ..)`
+Hi 𞤀
+
+--- pdf-a-conflict ---
+// PDF/A-2b
+// PDF/A-3b
+// Error: PDF/A-2b and PDF/A-3b cannot be combined
+#metadata(none)
@@ -35,6 +35,12 @@ __
 // Hint: 13-15 using multiple consecutive underscores (e.g. __) has no additional effect
 __not italic__
 
+--- emph-empty-allowed ---
+// typst: allow(empty-emph)
+__
+
+__ // typst: allow(empty-emph)
+
 --- emph-unclosed ---
 // Error: 6-7 unclosed delimiter
 #box[_Scoped] to body.